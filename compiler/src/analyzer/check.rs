@@ -3,6 +3,7 @@ mod decl;
 mod expr;
 mod helpers;
 mod stmt;
+mod suggest;
 
 use crate::analyzer::errors::SemanticErrorKind;
 use crate::analyzer::{Analyzer, SymbolKind, TableInfo, Type};
@@ -32,8 +33,14 @@ impl<'a> Analyzer<'a> {
                 }
 
                 // Use 语句在 Collect 阶段已经处理完了，Check 阶段不需要管
-                TopLevelItem::Use(_) => {}
+                // [New] 枚举同样只有声明，没有需要检查的表达式
+                TopLevelItem::Use(_) | TopLevelItem::Enum(_) => {}
             }
         }
+
+        // [New] 所有类都已收集，记录类型标注引用的定义位置
+        self.record_type_refs();
+        // [New] 同样要等所有类都收集完，才能检查泛型实参是否满足约束
+        self.check_generic_uses();
     }
 }
@@ -1,9 +1,37 @@
 use super::*;
 use crate::analyzer::TableId;
+use crate::utils::NodeId;
 
 impl<'a> Analyzer<'a> {
-    pub(super) fn check_access_expr(&mut self, target: &Expression, field: Symbol) -> Type {
-        let target_ty = self.check_expression(target);
+    pub(super) fn check_access_expr(
+        &mut self,
+        site: NodeId,
+        target: &Expression,
+        field: Symbol,
+    ) -> Type {
+        let target_ty = match self.check_expression(target) {
+            // [New] 有约束的泛型参数 (T: Animal) 按约束类型查找成员
+            Type::GenericParam(sym) => self
+                .generic_bounds
+                .get(&sym)
+                .cloned()
+                .unwrap_or(Type::GenericParam(sym)),
+            ty => ty,
+        };
+        self.record_receiver(site, &target_ty);
+
+        // [New] Shape.Circle / lib.Shape.Empty：枚举类型名后面跟的是变体
+        // (枚举类型的值本身没有成员，走下面的 check_field_access 报错)
+        if let Type::Enum(id) = target_ty
+            && self.is_type_path(target)
+        {
+            return self.check_variant_access(site, id, field, target.span);
+        }
+
+        self.record_field_slot(site, &target_ty, field);
+        if let Some(loc) = self.member_location(&target_ty, field) {
+            self.record_def(site, field, loc.file_id, loc.span);
+        }
 
         if let Some(builtin_ty) = self.check_builtin_member_access(&target_ty, field) {
             return builtin_ty;
@@ -13,9 +41,26 @@ impl<'a> Analyzer<'a> {
     }
     pub(super) fn check_index_expr(&mut self, target: &Expression, index: &Expression) -> Type {
         let target_ty = self.check_expression(target);
+
+        // [New] 切片: arr[1..3] / s[2..] / s[..-1]，结果是新的数组 / 字符串
+        // 省略端点的范围只能出现在这里，所以不走 check_range_expr
+        if let ExpressionData::Range { start, end, .. } = &index.data {
+            self.check_slice_bounds(index, start, end);
+            return match target_ty {
+                Type::Array(_) | Type::Str | Type::Error => target_ty,
+                _ => {
+                    let ty_str = target_ty.display(self.ctx).to_string();
+                    self.report(target.span, SemanticErrorKind::TypeNotIndexable(ty_str));
+                    Type::Error
+                }
+            };
+        }
+
         let index_ty = self.check_expression(index);
 
         match target_ty {
+            // 保存在变量里的范围也可以用来切片
+            Type::Array(_) | Type::Str if matches!(index_ty, Type::Range(_)) => target_ty,
             Type::Array(inner) => {
                 if index_ty != Type::Int {
                     let ty_str = index_ty.display(self.ctx).to_string();
@@ -36,8 +81,37 @@ impl<'a> Analyzer<'a> {
                         SemanticErrorKind::InvalidIndexType("String index must be int".into()),
                     );
                 }
-                Type::Str
+                // [Changed] 按字符 (而不是字节) 索引
+                Type::Char
+            }
+            // [New] 元组的下标必须是整数字面量，这样每个位置的类型和越界都能静态确定
+            Type::Tuple(items) => {
+                let Some(i) = const_index(index) else {
+                    self.report(
+                        index.span,
+                        SemanticErrorKind::InvalidIndexType(
+                            "Tuple index must be an integer literal".into(),
+                        ),
+                    );
+                    return Type::Error;
+                };
+                // 负数下标从末尾数起: t[-1] 是最后一个元素
+                let pos = if i < 0 { i + items.len() as i64 } else { i };
+                match usize::try_from(pos).ok().and_then(|pos| items.get(pos)) {
+                    Some(ty) => ty.clone(),
+                    None => {
+                        self.report(
+                            index.span,
+                            SemanticErrorKind::TupleIndexOutOfBounds {
+                                index: i,
+                                len: items.len(),
+                            },
+                        );
+                        Type::Error
+                    }
+                }
             }
+            Type::Error => Type::Error,
             _ => {
                 let ty_str = target_ty.display(self.ctx).to_string();
                 self.report(target.span, SemanticErrorKind::TypeNotIndexable(ty_str));
@@ -46,6 +120,29 @@ impl<'a> Analyzer<'a> {
         }
     }
 
+    /// [New] 切片的两个端点 (都可以省略) 必须是整数
+    fn check_slice_bounds(
+        &mut self,
+        index: &Expression,
+        start: &Option<Box<Expression>>,
+        end: &Option<Box<Expression>>,
+    ) {
+        for bound in [start, end].into_iter().flatten() {
+            let ty = self.check_expression(bound);
+            if ty != Type::Int && ty != Type::Error {
+                let ty_str = ty.display(self.ctx).to_string();
+                self.report(
+                    bound.span,
+                    SemanticErrorKind::InvalidIndexType(format!(
+                        "Slice bounds must be int, got {}",
+                        ty_str
+                    )),
+                );
+            }
+        }
+        self.record_node(index.id, index.span, Type::Range(Box::new(Type::Int)));
+    }
+
     pub(super) fn check_call_expr(
         &mut self,
         callee: &Expression,
@@ -85,6 +182,13 @@ impl<'a> Analyzer<'a> {
                     }
                 };
 
+                // [New] trait 只有方法签名，不能实例化
+                if table_info.is_trait {
+                    let t_name = self.ctx.resolve_symbol(sym.symbol()).to_string();
+                    self.report(callee.span, SemanticErrorKind::TraitInstantiation(t_name));
+                    return Type::Error;
+                }
+
                 // A2. 检查泛型参数数量
                 // 定义: table Box<T>
                 // 调用: Box<int>() -> OK
@@ -102,6 +206,14 @@ impl<'a> Analyzer<'a> {
                     return Type::Error;
                 }
 
+                // [New] 泛型实参必须满足约束 (Box<Dog>() 而 T: Animal)
+                self.check_generic_bounds(
+                    callee.span,
+                    &table_info.generic_params,
+                    &table_info.generic_bounds,
+                    &resolved_generics,
+                );
+
                 // A3. 构建“实例类型” (Instance Type)
                 // 这是这个调用最终返回的类型
                 let instance_type = if resolved_generics.is_empty() {
@@ -170,6 +282,15 @@ impl<'a> Analyzer<'a> {
                         return Type::Error;
                     }
 
+                    // [New] 检查泛型实参是否满足函数 / 方法声明的约束
+                    let bounds = self.callee_generic_bounds(callee);
+                    self.check_generic_bounds(
+                        callee.span,
+                        &generic_params,
+                        &bounds,
+                        &resolved_generics,
+                    );
+
                     // 1.2 构建替换表 { T -> int }
                     let mut type_mapping = std::collections::HashMap::new();
                     for (i, param_sym) in generic_params.iter().enumerate() {
@@ -197,12 +318,12 @@ impl<'a> Analyzer<'a> {
                 // 如果函数本身是泛型的 (generic_params 不为空)，这通常是不允许的（除非支持推导）
                 if !generic_params.is_empty() {
                     // 简单起见，v0.2 要求显式泛型
-                    self.report(
-                        callee.span,
-                        SemanticErrorKind::Custom(
-                            "Generic function requires explicit type arguments (e.g. func<int>(...))".into()
-                        )
-                     );
+                    let names: Vec<&str> = generic_params
+                        .iter()
+                        .map(|p| self.ctx.resolve_symbol(*p))
+                        .collect();
+                    let params = names.join(", ");
+                    self.report(callee.span, SemanticErrorKind::MissingTypeArguments(params));
                     return Type::Error;
                 }
 
@@ -216,7 +337,13 @@ impl<'a> Analyzer<'a> {
             }
 
             // === Case C: 错误传播 ===
-            Type::Error => Type::Error,
+            // 参数仍然要检查一遍，保证其中的局部变量都被解析到槽位
+            Type::Error => {
+                for arg in args {
+                    self.check_expression(&arg.value);
+                }
+                Type::Error
+            }
 
             // === Case D: 其他类型不可调用 ===
             _ => {
@@ -262,6 +389,21 @@ impl<'a> Analyzer<'a> {
                     _ => None,
                 }
             }
+            // [New] 范围的内置方法
+            Type::Range(inner) => {
+                let (params, ret) = match field_name {
+                    "len" => (vec![], Type::Int),
+                    "contains" => (vec![*inner.clone()], Type::Bool),
+                    "rev" => (vec![], target_ty.clone()),
+                    "step" => (vec![Type::Int], target_ty.clone()),
+                    _ => return None,
+                };
+                Some(Type::Function {
+                    generic_params: vec![],
+                    params,
+                    ret: Box::new(ret),
+                })
+            }
             _ => None,
         }
     }
@@ -292,10 +434,8 @@ impl<'a> Analyzer<'a> {
             // 4. 不支持的类型
             _ => {
                 let ty_str = target_ty.display(self.ctx).to_string();
-                self.report(
-                    span,
-                    SemanticErrorKind::Custom(format!("Type '{}' does not have fields", ty_str)),
-                );
+                let help = self.suggest_for_builtin_member(&target_ty, field);
+                self.report_with_help(span, SemanticErrorKind::NoFields(ty_str), help);
                 Type::Error
             }
         }
@@ -362,6 +502,11 @@ impl<'a> Analyzer<'a> {
             };
         }
 
+        // [New] 导出的枚举
+        if module_info.enums.contains_key(&target_id) {
+            return Type::Enum(target_id);
+        }
+
         // C. 查找导出的顶层变量 (Globals)
         if let Some(global_info) = module_info.globals.get(&field) {
             return global_info.ty.clone();
@@ -373,13 +518,15 @@ impl<'a> Analyzer<'a> {
             .and_then(|s| s.to_str())
             .unwrap_or("module");
         let f_name = self.ctx.resolve_symbol(field).to_string();
+        let help = self.suggest_for_export(module_info, field);
 
-        self.report(
+        self.report_with_help(
             span,
             SemanticErrorKind::UndefinedSymbol(format!(
                 "Export '{}' not found in module '{}'",
                 f_name, mod_name
             )),
+            help,
         );
         Type::Error
     }
@@ -451,11 +598,10 @@ impl<'a> Analyzer<'a> {
         }
 
         // 4. 没找到
-        let f_name = self.ctx.resolve_symbol(field).to_string();
-        self.report(
-            span,
-            SemanticErrorKind::Custom(format!("Member '{}' not found", f_name)),
-        );
+        let member = self.ctx.resolve_symbol(field).to_string();
+        let ty = self.ctx.resolve_symbol(table_info.name).to_string();
+        let help = self.suggest_for_member(&table_info, field);
+        self.report_with_help(span, SemanticErrorKind::MemberNotFound { ty, member }, help);
         Type::Error
     }
 
@@ -488,7 +634,7 @@ impl<'a> Analyzer<'a> {
                     let expected_ty = &params[i];
 
                     // [核心修改]
-                    // 旧代码: if !expected_ty.is_assignable_from(&arg_ty) {
+                    // 旧代码: if !expected_ty.is_assignable_from(&arg_ty, self) {
                     // 新代码: 调用 Analyzer 的增强检查（支持继承查表）
                     if !self.check_type_compatibility(expected_ty, &arg_ty) {
                         // 使用参数自己的 span 报错
@@ -510,3 +656,18 @@ impl<'a> Analyzer<'a> {
         }
     }
 }
+
+/// 整数字面量下标 (允许负号): t.0 / t[1] / t[-1]
+fn const_index(index: &Expression) -> Option<i64> {
+    match &index.data {
+        ExpressionData::Literal(Literal::Int(i)) => Some(*i),
+        ExpressionData::Unary {
+            op: UnaryOp::Neg,
+            expr,
+        } => match expr.data {
+            ExpressionData::Literal(Literal::Int(i)) => Some(-i),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -13,6 +13,10 @@ impl<'a> Analyzer<'a> {
             None => return, // Should trigger internal error
         };
 
+        // [New] 类的泛型约束在字段初始值和方法体里都可见 (T: Animal 时 t.make_sound())
+        let prev_bounds =
+            std::mem::replace(&mut self.generic_bounds, table_info.generic_bounds.clone());
+
         // 2. 检查继承约束 (Override)
         self.check_inheritance_rules(&table_info);
 
@@ -28,7 +32,115 @@ impl<'a> Analyzer<'a> {
         }
 
         // 5. 完整性检查 (Abstract Implementation)
-        self.check_abstract_implementation(&table_info, def.span);
+        // [New] trait 的方法本来就都是抽象的，由实现它的类来检查
+        if !table_info.is_trait {
+            self.check_abstract_implementation(&table_info, def.span);
+            self.check_trait_conformance(&table_info, def.span);
+        }
+
+        self.generic_bounds = prev_bounds;
+    }
+
+    /// [New] 检查类实现了声明的每个 trait 的全部方法 (自己定义的或继承来的都算)
+    /// 签名规则和覆写父类方法一样：参数个数相同，参数逆变，返回值协变
+    fn check_trait_conformance(&mut self, info: &TableInfo, span: crate::utils::Span) {
+        for trait_ty in &info.traits {
+            let (trait_id, args) = match trait_ty {
+                Type::Table(id) => (*id, &[][..]),
+                Type::GenericInstance { base, args } => (*base, &args[..]),
+                _ => continue,
+            };
+            let Some(trait_info) = self.find_table_info(trait_id) else {
+                continue;
+            };
+            let type_mapping: HashMap<Symbol, Type> = trait_info
+                .generic_params
+                .iter()
+                .copied()
+                .zip(args.iter().cloned())
+                .collect();
+            let trait_name = trait_ty.display(self.ctx).to_string();
+
+            // 按 trait 里的声明顺序报错
+            let mut required: Vec<_> = trait_info.methods.iter().collect();
+            required.sort_by_key(|(_, m)| m.span.start);
+
+            for (name, required_info) in required {
+                let m_name = self.ctx.resolve_symbol(*name).to_string();
+                let Some(method) = info.methods.get(name) else {
+                    self.report(
+                        span,
+                        SemanticErrorKind::MissingTraitMethod {
+                            table: self.ctx.resolve_symbol(info.name).to_string(),
+                            trait_name: trait_name.clone(),
+                            method: m_name,
+                        },
+                    );
+                    continue;
+                };
+                // 继承来的方法报在类定义上，自己的方法报在方法上
+                let own = TableId(info.file_id, info.name);
+                let method_span = if info
+                    .vtable
+                    .iter()
+                    .any(|e| e.name == *name && e.owner == own)
+                {
+                    method.span
+                } else {
+                    span
+                };
+                let mismatch = |analyzer: &mut Self, reason: String| {
+                    analyzer.report(
+                        method_span,
+                        SemanticErrorKind::TraitMethodMismatch {
+                            trait_name: trait_name.clone(),
+                            method: m_name.clone(),
+                            reason,
+                        },
+                    );
+                };
+
+                let expected = &required_info.signature;
+                if method.signature.params.len() != expected.params.len() {
+                    mismatch(
+                        self,
+                        format!(
+                            "parameter count mismatch: expected {}, found {}",
+                            expected.params.len(),
+                            method.signature.params.len()
+                        ),
+                    );
+                    continue;
+                }
+                for (i, ((_, actual), (_, required))) in method
+                    .signature
+                    .params
+                    .iter()
+                    .zip(&expected.params)
+                    .enumerate()
+                {
+                    let required = required.substitute(&type_mapping);
+                    if !self.check_type_compatibility(actual, &required) {
+                        let reason = format!(
+                            "parameter {} has type '{}', which does not accept the trait's '{}'",
+                            i + 1,
+                            actual.display(self.ctx),
+                            required.display(self.ctx)
+                        );
+                        mismatch(self, reason);
+                    }
+                }
+                let required_ret = expected.ret.substitute(&type_mapping);
+                if !self.check_type_compatibility(&required_ret, &method.signature.ret) {
+                    let reason = format!(
+                        "returns '{}', but the trait expects '{}'",
+                        method.signature.ret.display(self.ctx),
+                        required_ret.display(self.ctx)
+                    );
+                    mismatch(self, reason);
+                }
+            }
+        }
     }
 
     /// 辅助：检查继承规则
@@ -59,7 +171,8 @@ impl<'a> Analyzer<'a> {
 
         for item in &def.items {
             if let TableItem::Field(field_def) = item {
-                self.scopes.enter_scope();
+                // 每个字段初值在独立的帧里求值 (解释器同样如此)
+                self.scopes.enter_function();
 
                 if let Some(init_expr) = &field_def.value {
                     let expr_type = self.check_expression(init_expr);
@@ -91,7 +204,7 @@ impl<'a> Analyzer<'a> {
                         }
                     }
                 }
-                self.scopes.exit_scope();
+                self.scopes.exit_function();
             }
         }
 
@@ -105,13 +218,33 @@ impl<'a> Analyzer<'a> {
                 }
             }
         }
+
+        // [LSP] 字段声明处的名字 (推导之后的类型)
+        for item in &def.items {
+            if let TableItem::Field(field_def) = item
+                && let Some(field_info) = self
+                    .tables
+                    .get(&table_id)
+                    .and_then(|info| info.fields.get(&field_def.name))
+            {
+                let ty = field_info.ty.clone();
+                self.record_binding(field_def.id, field_def.name, field_def.span, ty);
+            }
+        }
     }
 
     // [New] 检查顶层变量定义 (例如: count: int = 10)
     pub(super) fn check_top_level_field(&mut self, def: &FieldDefinition) {
         // 1. 如果有初始值，先检查表达式的类型
         if let Some(expr) = &def.value {
+            if def.is_const {
+                self.check_const_initializer(def.name, expr);
+            }
+
+            // 初值表达式里的块可能定义局部变量，放进独立的帧
+            self.scopes.enter_function();
             let expr_ty = self.check_expression(expr);
+            self.scopes.exit_function();
 
             // 2. 获取该变量在 Global 表中的信息
             // 注意：我们要修改它，所以需要 get_mut
@@ -131,24 +264,36 @@ impl<'a> Analyzer<'a> {
                             self.current_file_id,
                             true,
                         );
+                        if !def.is_mut {
+                            self.scopes.mark_immutable(def.name, def.is_const);
+                        }
                     }
                 }
                 // Case B: 变量有显式标注 (count: int = "hello")
-                else if !global_info.ty.is_assignable_from(&expr_ty) {
-                    let expected_str = global_info.ty.display(self.ctx).to_string();
-                    let got_str = expr_ty.display(self.ctx).to_string();
+                // [Changed] 先拿出标注的类型，子类型检查要借用整个 Analyzer
+                else {
+                    let declared = global_info.ty.clone();
+                    if !self.check_type_compatibility(&declared, &expr_ty) {
+                        let expected_str = declared.display(self.ctx).to_string();
+                        let got_str = expr_ty.display(self.ctx).to_string();
 
-                    self.report(
-                        def.span,
-                        SemanticErrorKind::FieldTypeMismatch {
-                            field: self.ctx.resolve_symbol(def.name).to_string(),
-                            expected: expected_str,
-                            found: got_str,
-                        },
-                    );
+                        self.report(
+                            def.span,
+                            SemanticErrorKind::FieldTypeMismatch {
+                                field: self.ctx.resolve_symbol(def.name).to_string(),
+                                expected: expected_str,
+                                found: got_str,
+                            },
+                        );
+                    }
                 }
             }
         }
+
+        if let Some(global_info) = self.globals.get(&def.name) {
+            let ty = global_info.ty.clone();
+            self.record_binding(def.id, def.name, def.span, ty);
+        }
     }
 
     /// 检查是否遗留了未实现的抽象方法
@@ -198,7 +343,7 @@ impl<'a> Analyzer<'a> {
                 // 检查：子类字段必须能够“装下”父类字段的要求
                 // 通常字段类型必须是不变的 (Invariant) 或者是协变的 (Covariant，如果是只读)
                 // 这里我们使用 is_assignable_from，意味着允许协变 (Parent = Child 是合法的)
-                if !expected_ty.is_assignable_from(&child_info.ty) {
+                if !expected_ty.is_assignable_from(&child_info.ty, self) {
                     let f_name = self.ctx.resolve_symbol(*name).to_string();
                     let child_ty_str = child_info.ty.display(self.ctx).to_string();
                     let parent_ty_str = expected_ty.display(self.ctx).to_string();
@@ -250,11 +395,11 @@ impl<'a> Analyzer<'a> {
 
                 // B. 参数类型检查：逆变 (Contravariance)
                 // 规则：子类参数必须比父类“更宽泛”或相同
-                // ChildParam.is_assignable_from(ParentParam) => True
+                // ChildParam.is_assignable_from(ParentParam, self) => True
                 for (i, (_, c_p_ty)) in child_info.signature.params.iter().enumerate() {
                     let e_p_ty = &expected_params[i]; // Parent (Expected) Param
 
-                    if !c_p_ty.is_assignable_from(e_p_ty) {
+                    if !c_p_ty.is_assignable_from(e_p_ty, self) {
                         let m_name = self.ctx.resolve_symbol(*name).to_string();
                         let c_str = c_p_ty.display(self.ctx).to_string();
                         let e_str = e_p_ty.display(self.ctx).to_string();
@@ -275,8 +420,8 @@ impl<'a> Analyzer<'a> {
 
                 // C. 返回值类型检查：协变 (Covariance)
                 // 规则：子类返回值必须比父类“更具体”或相同
-                // ParentRet.is_assignable_from(ChildRet) => True
-                if !expected_ret.is_assignable_from(&child_info.signature.ret) {
+                // ParentRet.is_assignable_from(ChildRet, self) => True
+                if !expected_ret.is_assignable_from(&child_info.signature.ret, self) {
                     let m_name = self.ctx.resolve_symbol(*name).to_string();
                     let c_str = child_info.signature.ret.display(self.ctx).to_string();
                     let e_str = expected_ret.display(self.ctx).to_string();
@@ -312,13 +457,22 @@ impl<'a> Analyzer<'a> {
         // [Fix] 获取方法签名信息
         // 注意：这里 sig_info 是 &MethodInfo (包含 span 和 signature)
         // 我们借用 signature 而不是 move 它
-        let sig_info = current_table.methods.get(&method.name).unwrap();
+        // [Fix] 重名的方法在收集阶段已报 DuplicateDefinition，这里直接跳过
+        let Some(sig_info) = current_table.methods.get(&method.name) else {
+            return;
+        };
         let sig = &sig_info.signature;
 
         let expected_ret = sig.ret.clone();
         self.current_return_type = Some(expected_ret.clone());
+        let prev_in_initializer = self.in_initializer;
+        self.in_initializer = self.ctx.resolve_symbol(method.name) == "init";
+        // [New] 方法自己的泛型约束 (fn pick<U: Animal>)，叠加在类的约束上
+        let mut bounds = self.generic_bounds.clone();
+        bounds.extend(sig.generic_bounds.clone());
+        let prev_bounds = std::mem::replace(&mut self.generic_bounds, bounds);
 
-        self.scopes.enter_scope();
+        self.scopes.enter_function();
 
         // 1. 定义 `self`
         // ---------------------------------------------------------
@@ -343,29 +497,38 @@ impl<'a> Analyzer<'a> {
 
         // [New] 定义 self
         // span: 使用 method.span。这意味着在 IDE 里如果你 hover `self`，它可能会高亮整个方法定义或方法名，这是合理的。
-        let _ = self.scopes.define(
-            self.ctx.intern("self"),
+        // self 是帧内第一个定义的符号，槽位记在方法节点上
+        let self_sym = self.ctx.intern("self");
+        if let Ok(Some(slot)) = self.scopes.define(
+            self_sym,
             self_type,
             SymbolKind::Variable, // 或者你可以加一个 SymbolKind::Self
             method.span,          // <--- 1. 定义位置：当前方法的 Span
             self.current_file_id, // <--- 2. 定义文件
             false,
-        );
+        ) {
+            self.record_slot(method.id, slot);
+        }
+        // [New] self 本身不能被重新赋值 (字段可以)
+        self.scopes.mark_immutable(self_sym, false);
 
         // 2. 定义参数
         // ---------------------------------------------------------
         for param in &method.params {
             // 从签名中查找已经 Resolve 好的类型
             if let Some((_, p_ty)) = sig.params.iter().find(|(n, _)| *n == param.name) {
+                self.record_binding(param.id, param.name, param.span, p_ty.clone());
                 // [New] 定义参数符号
-                let _ = self.scopes.define(
+                if let Ok(Some(slot)) = self.scopes.define(
                     param.name,
                     p_ty.clone(),
                     SymbolKind::Parameter,
                     param.span,           // <--- 1. 定义位置：参数节点本身的 Span (x: int)
                     self.current_file_id, // <--- 2. 定义文件
                     false,
-                );
+                ) {
+                    self.record_slot(param.id, slot);
+                }
             }
         }
 
@@ -373,7 +536,7 @@ impl<'a> Analyzer<'a> {
         let body_type = self.check_block(body_block);
 
         // 4. 检查返回值
-        if !expected_ret.is_assignable_from(&body_type) {
+        if !expected_ret.is_assignable_from(&body_type, self) {
             // 如果期望 Unit，允许隐式返回
             if expected_ret != Type::Unit {
                 self.error_type_mismatch(
@@ -384,8 +547,10 @@ impl<'a> Analyzer<'a> {
             }
         }
 
-        self.scopes.exit_scope();
+        self.scopes.exit_function();
         self.current_return_type = prev_return_type;
+        self.in_initializer = prev_in_initializer;
+        self.generic_bounds = prev_bounds;
     }
 
     // [Refactor] 这是一个通用的函数体检查器
@@ -401,21 +566,38 @@ impl<'a> Analyzer<'a> {
         };
 
         // 1. 获取期望的返回值类型 & 参数列表
-        let (expected_ret, params_info) = if let Some(table) = parent_table {
+        // [New] 同时取出泛型约束 (方法还要加上类的约束)
+        let (expected_ret, params_info, bounds) = if let Some(table) = parent_table {
             // Case A: 是方法 -> 去 TableInfo 里找
-            let m_info = table.methods.get(&func_def.name).unwrap();
-            (m_info.signature.ret.clone(), &m_info.signature.params)
+            let Some(m_info) = table.methods.get(&func_def.name) else {
+                return;
+            };
+            let mut bounds = table.generic_bounds.clone();
+            bounds.extend(m_info.signature.generic_bounds.clone());
+            (
+                m_info.signature.ret.clone(),
+                &m_info.signature.params,
+                bounds,
+            )
         } else {
             // Case B: 是顶层函数 -> 去 FunctionInfo 里找
-            let f_info = self.functions.get(&func_def.name).unwrap();
-            (f_info.signature.ret.clone(), &f_info.signature.params)
+            // [Fix] 与类/枚举/内置函数同名的函数没有进 functions 表 (收集阶段已报错)
+            let Some(f_info) = self.functions.get(&func_def.name) else {
+                return;
+            };
+            (
+                f_info.signature.ret.clone(),
+                &f_info.signature.params,
+                f_info.signature.generic_bounds.clone(),
+            )
         };
 
         // 2. 设置上下文 (用于 check_return)
         let prev_return_type = self.current_return_type.clone();
         self.current_return_type = Some(expected_ret.clone());
+        let prev_bounds = std::mem::replace(&mut self.generic_bounds, bounds);
 
-        self.scopes.enter_scope();
+        self.scopes.enter_function();
 
         // 3. 如果是方法，定义 `self`
         if let Some(table) = parent_table {
@@ -436,28 +618,40 @@ impl<'a> Analyzer<'a> {
                 Type::Table(table_id)
             };
 
-            let _ = self.scopes.define(
-                self.ctx.intern("self"),
+            // params_info 还借用着 self.functions，槽位直接写入 db
+            let self_sym = self.ctx.intern("self");
+            if let Ok(Some(slot)) = self.scopes.define(
+                self_sym,
                 self_type,
                 SymbolKind::Variable,
                 func_def.span,
                 self.current_file_id,
                 false,
-            );
+            ) {
+                self.ctx.db.record_slot(func_def.id, slot);
+            }
+            self.scopes.mark_immutable(self_sym, false);
         }
 
         // 4. 定义参数 (通用逻辑)
         // 遍历 AST 的参数，从 Info 里拿到已经解析好的 Type
         for param in &func_def.params {
-            if let Some((_, p_ty)) = params_info.iter().find(|(n, _)| *n == param.name) {
-                let _ = self.scopes.define(
-                    param.name,
-                    p_ty.clone(),
-                    SymbolKind::Parameter,
-                    param.span,
-                    self.current_file_id,
-                    false,
-                );
+            let Some((_, p_ty)) = params_info.iter().find(|(n, _)| *n == param.name) else {
+                continue;
+            };
+            self.ctx
+                .db
+                .bindings
+                .insert(param.id, (param.name, param.span, p_ty.clone()));
+            if let Ok(Some(slot)) = self.scopes.define(
+                param.name,
+                p_ty.clone(),
+                SymbolKind::Parameter,
+                param.span,
+                self.current_file_id,
+                false,
+            ) {
+                self.ctx.db.record_slot(param.id, slot);
             }
         }
 
@@ -465,7 +659,7 @@ impl<'a> Analyzer<'a> {
         let body_type = self.check_block(body_block);
 
         // 6. 检查隐式返回值 (Block 的最后一个表达式)
-        if !expected_ret.is_assignable_from(&body_type) {
+        if !expected_ret.is_assignable_from(&body_type, self) {
             // 除非期望 Unit，否则必须匹配
             if expected_ret != Type::Unit {
                 // 如果 body 是 Never (比如里面全是 return)，那也是合法的
@@ -475,7 +669,8 @@ impl<'a> Analyzer<'a> {
             }
         }
 
-        self.scopes.exit_scope();
+        self.scopes.exit_function();
         self.current_return_type = prev_return_type;
+        self.generic_bounds = prev_bounds;
     }
 }
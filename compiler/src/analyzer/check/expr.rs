@@ -1,10 +1,15 @@
 mod assignment;
+mod exhaustive;
+mod lambda;
+mod matching;
+mod pattern;
 
 use std::collections::HashSet;
 
 use super::*;
 use crate::analyzer::Span;
 use crate::analyzer::errors::SemanticErrorKind;
+use crate::source::FileId;
 
 impl<'a> Analyzer<'a> {
     // === 1. 字面量与原子 ===
@@ -20,13 +25,25 @@ impl<'a> Analyzer<'a> {
         }
     }
 
-    pub(super) fn check_identifier_expr(&mut self, sym: Symbol, span: crate::utils::Span) -> Type {
+    pub(super) fn check_identifier_expr(&mut self, expr: &Expression, sym: Symbol) -> Type {
+        let span = expr.span;
         if let Some(info) = self.scopes.resolve(sym) {
-            info.ty.clone()
+            let ty = info.ty.clone();
+            // [New] Goto Definition (内置函数没有源码位置)
+            if info.defined_file != FileId::BUILTIN {
+                let (def_file, def_span) = (info.defined_file, info.defined_span);
+                self.record_def(expr.id, sym, def_file, def_span);
+            }
+            // [New] 局部变量：记录槽位地址
+            if let Some(slot) = self.scopes.resolve_slot(sym) {
+                self.record_slot(expr.id, slot);
+            }
+            ty
         } else {
             let name = self.ctx.resolve_symbol(sym).to_string();
             // 现在有了 Span，可以精准报错了
-            self.report(span, SemanticErrorKind::UndefinedSymbol(name));
+            let help = self.suggest_for_identifier(sym);
+            self.report_with_help(span, SemanticErrorKind::UndefinedSymbol(name), help);
             Type::Error
         }
     }
@@ -38,15 +55,17 @@ impl<'a> Analyzer<'a> {
             return Type::Array(Box::new(Type::Infer));
         }
 
-        let first_ty = self.check_expression(&elements[0]);
+        let mut elem_ty = self.check_expression(&elements[0]);
 
         for (i, expr) in elements.iter().enumerate().skip(1) {
             let ty = self.check_expression(expr);
 
-            // 使用 check_type_compatibility 以支持继承关系的数组元素
-            // 例如: [Animal, Dog] -> Animal 兼容 Dog
-            if !self.check_type_compatibility(&first_ty, &ty) {
-                let expected_str = first_ty.display(self.ctx).to_string();
+            // [Changed] 元素类型取所有元素的公共父类型
+            // 例如: [Dog(), Cat()] -> [Animal]，[Dog(), Animal()] -> [Animal]
+            if let Some(joined) = self.common_supertype(&elem_ty, &ty) {
+                elem_ty = joined;
+            } else {
+                let expected_str = elem_ty.display(self.ctx).to_string();
                 let found_str = ty.display(self.ctx).to_string();
 
                 self.report(
@@ -61,7 +80,7 @@ impl<'a> Analyzer<'a> {
             }
         }
 
-        Type::Array(Box::new(first_ty))
+        Type::Array(Box::new(elem_ty))
     }
 
     pub(super) fn check_tuple_expr(&mut self, elements: &[Expression]) -> Type {
@@ -214,7 +233,10 @@ impl<'a> Analyzer<'a> {
             }
 
             BinaryOp::Lt | BinaryOp::Gt | BinaryOp::Lte | BinaryOp::Gte => {
-                if left.is_numeric() && right.is_numeric() {
+                // [New] 字符按码位比较 ('a' < 'b')
+                if (left.is_numeric() && right.is_numeric())
+                    || (left == Type::Char && right == Type::Char)
+                {
                     Type::Bool
                 } else {
                     let l_str = left.display(self.ctx).to_string();
@@ -277,10 +299,17 @@ impl<'a> Analyzer<'a> {
             // 2. 数值转换
             (Type::Int, Type::Float) => target_ty,
             (Type::Float, Type::Int) => target_ty,
+            // [New] 字符和它的码位互转 (int as char 在运行时检查码位是否合法)
+            (Type::Char, Type::Int) | (Type::Int, Type::Char) => target_ty,
 
             // 3. 继承体系转换 (Upcast/Downcast)
+            // [New] 涉及 trait 的转换留给运行时检查：子类可能实现了父类没有实现的 trait
             (Type::Table(s_id), Type::Table(t_id)) => {
-                if self.is_subtype(*s_id, *t_id) || self.is_subtype(*t_id, *s_id) {
+                if self.is_subtype(*s_id, *t_id)
+                    || self.is_subtype(*t_id, *s_id)
+                    || self.is_trait(*s_id)
+                    || self.is_trait(*t_id)
+                {
                     return target_ty;
                 }
                 self.report_cast_error(span, &source_ty, &target_ty);
@@ -326,13 +355,8 @@ impl<'a> Analyzer<'a> {
 
             // 5. 数组转换 ([Dog] as [Animal])
             // 既然泛型都支持了，数组也应该支持
-            (Type::Array(s_inner), Type::Array(t_inner)) => {
-                if self.is_castable(s_inner, t_inner) {
-                    target_ty
-                } else {
-                    self.report_cast_error(span, &source_ty, &target_ty);
-                    Type::Error
-                }
+            (Type::Array(s_inner), Type::Array(t_inner)) if self.is_castable(s_inner, t_inner) => {
+                target_ty
             }
 
             _ => {
@@ -351,9 +375,13 @@ impl<'a> Analyzer<'a> {
 
         match (src, target) {
             (Type::Int, Type::Float) | (Type::Float, Type::Int) => true,
+            (Type::Char, Type::Int) | (Type::Int, Type::Char) => true,
 
             (Type::Table(s_id), Type::Table(t_id)) => {
-                self.is_subtype(*s_id, *t_id) || self.is_subtype(*t_id, *s_id)
+                self.is_subtype(*s_id, *t_id)
+                    || self.is_subtype(*t_id, *s_id)
+                    || self.is_trait(*s_id)
+                    || self.is_trait(*t_id)
             }
 
             (
@@ -399,33 +427,52 @@ impl<'a> Analyzer<'a> {
 
     pub(super) fn check_range_expr(
         &mut self,
-        start: &Expression,
-        end: &Expression,
+        start: &Option<Box<Expression>>,
+        end: &Option<Box<Expression>>,
         span: Span,
     ) -> Type {
+        // [Changed] 省略端点的范围 (2.. / ..3) 只能用作切片下标 (见 check_index_expr)
+        let (Some(start), Some(end)) = (start, end) else {
+            if let Some(bound) = start.as_ref().or(end.as_ref()) {
+                self.check_expression(bound);
+            }
+            self.report(span, SemanticErrorKind::OpenEndedRange);
+            return Type::Error;
+        };
         let start_ty = self.check_expression(start);
         let end_ty = self.check_expression(end);
 
-        if start_ty == Type::Int && end_ty == Type::Int {
-            Type::Range(Box::new(Type::Int))
-        } else {
-            self.report(
-                span,
-                SemanticErrorKind::Custom("Range bounds must be integers".into()),
-            );
-            Type::Error
+        // [Changed] 报出第一个不是 int 的端点类型 (Error 已经报过了，不再重复)
+        match [&start_ty, &end_ty]
+            .into_iter()
+            .find(|ty| !matches!(ty, Type::Int))
+        {
+            None => Type::Range(Box::new(Type::Int)),
+            Some(Type::Error) => Type::Error,
+            Some(bad) => {
+                let found = bad.display(self.ctx).to_string();
+                self.report(span, SemanticErrorKind::InvalidRangeElement(found));
+                Type::Error
+            }
         }
     }
 
     /// 核心入口：检查表达式
     /// 这是一个干净的 Dispatcher，负责将 Expression 节点分发给具体的检查逻辑
     pub fn check_expression(&mut self, expr: &Expression) -> Type {
+        let ty = self.check_expression_data(expr);
+        // [New] 每个表达式的类型和区间都记下来 (LSP Hover 按光标位置反查)
+        self.record_node(expr.id, expr.span, ty.clone());
+        ty
+    }
+
+    fn check_expression_data(&mut self, expr: &Expression) -> Type {
         match &expr.data {
             // 字面量
             ExpressionData::Literal(lit) => self.check_literal_expr(lit),
 
             // [Fix] 这里的 Identifier 现在传入 span，以便在变量未定义时准确报错
-            ExpressionData::Identifier(sym) => self.check_identifier_expr(*sym, expr.span),
+            ExpressionData::Identifier(sym) => self.check_identifier_expr(expr, *sym),
 
             // 复合结构
             ExpressionData::Array(elements) => self.check_array_expr(elements),
@@ -443,7 +490,7 @@ impl<'a> Analyzer<'a> {
             // 访问与调用 (传入 span)
             ExpressionData::FieldAccess { target, field } => {
                 // check_access_expr 内部会处理 target 的检查，但若 access 失败需要 span
-                self.check_access_expr(target, *field)
+                self.check_access_expr(expr.id, target, *field)
             }
             ExpressionData::Index { target, index } => self.check_index_expr(target, index),
             ExpressionData::Call {
@@ -462,10 +509,10 @@ impl<'a> Analyzer<'a> {
 
             ExpressionData::While { condition, body } => self.check_while_expr(condition, body),
             ExpressionData::For {
-                iterator,
+                pattern,
                 iterable,
                 body,
-            } => self.check_for_expr(*iterator, iterable, body),
+            } => self.check_for_expr(pattern, iterable, body),
 
             // Return / Break / Continue
             ExpressionData::Return(val) => self.check_return_expr(val, expr.span),
@@ -480,14 +527,37 @@ impl<'a> Analyzer<'a> {
             ExpressionData::Assign { op, target, value } => {
                 self.check_assignment_expr(*op, target, value, expr.span)
             }
+            ExpressionData::Destructure {
+                pattern,
+                value,
+                is_mut,
+            } => self.check_destructure_expr(pattern, value, *is_mut),
 
             ExpressionData::Cast { expr, target_type } => {
                 self.check_cast_expr(expr, target_type, expr.span)
             }
 
+            // [New] 枚举与模式匹配 (见 matching.rs)
+            ExpressionData::Match { scrutinee, arms } => self.check_match_expr(scrutinee, arms),
+            ExpressionData::VariantLiteral { target, fields } => {
+                self.check_variant_literal(target, fields)
+            }
+
+            // [New] 匿名函数 (见 lambda.rs)
+            ExpressionData::Lambda(def) => self.check_lambda_expr(def),
+
             // 变量定义 (let a: int = 1)
-            ExpressionData::VariableDefinition { name, ty, init, .. } => {
+            ExpressionData::VariableDefinition {
+                is_mut,
+                is_const,
+                name,
+                ty,
+                init,
+            } => {
                 let init_ty = self.check_expression(init);
+                if *is_const {
+                    self.check_const_initializer(*name, init);
+                }
 
                 let final_ty = if let Some(t_ref) = ty {
                     // 解析显式类型标注
@@ -504,14 +574,20 @@ impl<'a> Analyzer<'a> {
                 };
 
                 // 定义变量
-                let _ = self.scopes.define(
+                self.record_binding(expr.id, *name, expr.span, final_ty.clone());
+                if let Ok(Some(slot)) = self.scopes.define(
                     *name,
                     final_ty,
                     SymbolKind::Variable,
                     expr.span,            // <--- 1. 使用整个表达式的 Span
                     self.current_file_id, // <--- 2. 当前文件 ID
                     true,                 // allow_shadow (Loom 允许遮蔽)
-                );
+                ) {
+                    self.record_slot(expr.id, slot);
+                }
+                if !is_mut {
+                    self.scopes.mark_immutable(*name, *is_const);
+                }
 
                 Type::Unit
             }
@@ -1,7 +1,8 @@
-use crate::analyzer::{Analyzer, SemanticErrorKind, SymbolKind, Type};
+use crate::analyzer::{Analyzer, SemanticErrorKind, SymbolInfo, SymbolKind, Type};
 use crate::ast::{AssignOp, BinaryOp, Expression, ExpressionData};
+use crate::diagnostic::Label;
 use crate::source::FileId;
-use crate::utils::{Span, Symbol};
+use crate::utils::{NodeId, Span, Symbol};
 
 impl<'a> Analyzer<'a> {
     /// [入口] 赋值表达式检查
@@ -28,10 +29,10 @@ impl<'a> Analyzer<'a> {
         // 根据左值的类型分发给不同的处理函数
         match &left.data {
             ExpressionData::Identifier(sym) => {
-                self.check_variable_assignment(*sym, left, rhs_ty, right.span)
+                self.check_variable_assignment(*sym, left.id, left.span, rhs_ty, right.span)
             }
             ExpressionData::FieldAccess { target, field } => {
-                self.check_field_assignment(target, *field, rhs_ty, right.span)
+                self.check_field_assignment(left, target, *field, rhs_ty, right.span)
             }
             ExpressionData::Index { target, index } => {
                 self.check_index_assignment(target, index, rhs_ty, right.span)
@@ -61,6 +62,21 @@ impl<'a> Analyzer<'a> {
             return Type::Error;
         }
 
+        // [New] x += 1 同样是写入，左值必须可变
+        match &left.data {
+            ExpressionData::Identifier(sym) => {
+                if let Some(info) = self.scopes.resolve(*sym).cloned() {
+                    self.check_binding_mutability(&info, left.span);
+                }
+            }
+            ExpressionData::FieldAccess { target, field } => {
+                if let Some(target_ty) = self.ctx.db.receivers.get(&left.id).cloned() {
+                    self.check_field_mutability(target, &target_ty, *field, left.span);
+                }
+            }
+            _ => {}
+        }
+
         let bin_op = match op {
             AssignOp::PlusAssign => BinaryOp::Add,
             AssignOp::MinusAssign => BinaryOp::Sub,
@@ -77,34 +93,33 @@ impl<'a> Analyzer<'a> {
         }
 
         // 2. 检查结果是否能写回左值 (例如 int += float -> float, 但左边是 int，报错)
-        if !lhs_ty.is_assignable_from(&result_ty) {
+        if !lhs_ty.is_assignable_from(&result_ty, self) {
             self.error_type_mismatch(span, &lhs_ty, &result_ty);
         }
         Type::Unit
     }
 
     /// 处理普通变量赋值 (Logic for `x = val`)
-    /// 处理普通变量赋值 (Logic for `x = val`)
-    fn check_variable_assignment(
+    /// [Changed] 传入左值的节点和位置，解构模式里的每个名字也走这里
+    pub(super) fn check_variable_assignment(
         &mut self,
         sym: Symbol,
-        left_expr: &Expression,
+        left_id: NodeId,
+        left_span: Span,
         rhs_ty: Type,
         rhs_span: Span,
     ) -> Type {
         // --- 第一阶段：只读查询 & 克隆数据 ---
-        // 我们先解析符号，如果有值，就把需要的数据 clone 出来。
-        // map 结束后，临时借用的 info 就会被销毁，self 也就自由了。
-        let resolved_data = self.scopes.resolve(sym).map(|info| {
-            (
-                info.ty.clone(),   // 需要 Clone 类型
-                info.defined_file, // Copy (FileId 是 usize)
-                info.defined_span, // Copy (Span 是两个 usize)
-            )
-        });
+        // 我们先解析符号，如果有值，就把整个 info clone 出来。
+        // 临时借用结束后，self 也就自由了。
+        let resolved_data = self.scopes.resolve(sym).cloned();
 
         // --- 第二阶段：可变操作 ---
-        if let Some((var_ty, def_file, def_span)) = resolved_data {
+        if let Some(info) = resolved_data {
+            // [New] 参数、let / const 等不可变的绑定不能再赋值
+            self.check_binding_mutability(&info, left_span);
+            let (var_ty, def_file, def_span) = (info.ty, info.defined_file, info.defined_span);
+
             // Case A: 变量已存在 -> 检查类型兼容性
             // 注意：这里传的是 &var_ty (我们 clone 出来的)，而不是 &info.ty
             if !self.check_type_compatibility(&var_ty, &rhs_ty) {
@@ -124,18 +139,25 @@ impl<'a> Analyzer<'a> {
             }
 
             // [LSP] 记录变量的写引用 (Usage)
-            self.record_def(left_expr.id, def_file, def_span);
-            self.record_type(left_expr.id, var_ty);
+            self.record_def(left_id, sym, def_file, def_span);
+            self.record_node(left_id, left_span, var_ty);
+
+            if let Some(slot) = self.scopes.resolve_slot(sym) {
+                self.record_slot(left_id, slot);
+            }
         } else {
             // Case B: 变量不存在 -> 定义新变量
-            let _ = self.scopes.define(
+            self.record_binding(left_id, sym, left_span, rhs_ty.clone());
+            if let Ok(Some(slot)) = self.scopes.define(
                 sym,
                 rhs_ty,
                 SymbolKind::Variable,
-                left_expr.span,
+                left_span,
                 self.current_file_id,
                 false,
-            );
+            ) {
+                self.record_slot(left_id, slot);
+            }
         }
 
         Type::Unit
@@ -145,24 +167,33 @@ impl<'a> Analyzer<'a> {
     /// 包含解决 Borrow Checker 冲突的 LookUpResult 模式
     fn check_field_assignment(
         &mut self,
+        site: &Expression,
         target: &Expression,
         field: Symbol,
         rhs_ty: Type,
         rhs_span: Span,
     ) -> Type {
         let target_ty = self.check_expression(target);
+        self.record_receiver(site.id, &target_ty);
+        self.record_field_slot(site.id, &target_ty, field);
+        // [Fix] 定义位置记在整个 `obj.field` 节点上 (原来记在 target 上，会覆盖 obj 自己的定义)
+        if let Some(loc) = self.member_location(&target_ty, field) {
+            self.record_def(site.id, field, loc.file_id, loc.span);
+        }
+
+        self.check_field_mutability(target, &target_ty, field, site.span);
 
         let expected_ty = match target_ty {
             Type::Table(table_id) | Type::GenericInstance { base: table_id, .. } => {
                 let lookup_result = if let Some(info) = self.find_table_info(table_id) {
                     if let Some(field_info) = info.fields.get(&field) {
                         LookupResult::Found {
-                            file_id: info.file_id,
-                            span: field_info.span,
                             ty: field_info.ty.clone(), // Clone 类型以断开引用
                         }
                     } else {
-                        LookupResult::FieldMissing
+                        LookupResult::FieldMissing {
+                            help: self.suggest_for_field(&info, field),
+                        }
                     }
                 } else {
                     LookupResult::TableMissing
@@ -171,20 +202,17 @@ impl<'a> Analyzer<'a> {
                 // --- Phase 2: 写入操作 (Mutable Borrow) ---
                 // 此时 self 借用已释放，可以安全调用 record_def 或 report
                 match lookup_result {
-                    LookupResult::Found { file_id, span, ty } => {
-                        // [LSP] 记录定义跳转
-                        // 当用户按住 Ctrl 点击 `target` (如 `user`) 时，通常由 check_expression 处理了
-                        // 这里我们记录 `target` 指向了字段定义？
-                        // *纠正*：理想情况下这里应该记录 `field` 的位置，但 AST 里 `field` 只是 Symbol。
-                        // 这里我们暂时把 target 的引用记录再次指向字段定义，或者不做操作。
-                        // 原代码逻辑：self.record_def(target.id, ...)
-                        self.record_def(target.id, file_id, span);
-                        self.record_type(target.id, ty.clone());
+                    LookupResult::Found { ty } => {
+                        self.record_node(site.id, site.span, ty.clone());
                         ty
                     }
-                    LookupResult::FieldMissing => {
+                    LookupResult::FieldMissing { help } => {
                         let f_name = self.ctx.resolve_symbol(field).to_string();
-                        self.report(target.span, SemanticErrorKind::UndefinedSymbol(f_name));
+                        self.report_with_help(
+                            target.span,
+                            SemanticErrorKind::UndefinedSymbol(f_name),
+                            help,
+                        );
                         Type::Error
                     }
                     LookupResult::TableMissing => Type::Error,
@@ -210,6 +238,105 @@ impl<'a> Analyzer<'a> {
         Type::Unit
     }
 
+    /// [New] 检查名字绑定能否被重新赋值
+    fn check_binding_mutability(&mut self, info: &SymbolInfo, span: Span) {
+        if info.is_mut {
+            return;
+        }
+        let name = self.ctx.resolve_symbol(info.name).to_string();
+        let (kind, help) = match info.kind {
+            // self 定义在整个方法上，不用标出定义位置
+            SymbolKind::Variable if name == "self" => {
+                return self.report_immutable(span, "immutable variable", name, None, None);
+            }
+            SymbolKind::Parameter => (
+                "parameter",
+                Some("copy it into a local variable declared with 'var'"),
+            ),
+            SymbolKind::Variable if info.is_const => ("constant", None),
+            SymbolKind::Variable if matches!(info.ty, Type::Module(_)) => ("module", None),
+            SymbolKind::Variable => (
+                "immutable variable",
+                Some("declare it with 'var' to make it mutable"),
+            ),
+            SymbolKind::Table => ("class", None),
+            SymbolKind::Field => ("field", None),
+            SymbolKind::Function | SymbolKind::Method => ("function", None),
+        };
+        let defined = (info.defined_file != FileId::BUILTIN).then(|| {
+            (
+                info.defined_file,
+                info.defined_span,
+                format!("'{}' is declared here", name),
+            )
+        });
+        self.report_immutable(span, kind, name, help, defined);
+    }
+
+    /// [New] 只读 (let) 字段只能在 init 里通过 self 赋值
+    fn check_field_mutability(
+        &mut self,
+        target: &Expression,
+        target_ty: &Type,
+        field: Symbol,
+        span: Span,
+    ) {
+        let table_id = match target_ty {
+            Type::Table(id) | Type::GenericInstance { base: id, .. } => *id,
+            _ => return,
+        };
+        let is_mut = self
+            .find_table_info(table_id)
+            .and_then(|info| info.fields.get(&field).map(|f| f.is_mut));
+        if is_mut != Some(false) {
+            return;
+        }
+        let on_self = matches!(
+            target.data,
+            ExpressionData::Identifier(sym) if self.ctx.resolve_symbol(sym) == "self"
+        );
+        if self.in_initializer && on_self {
+            return;
+        }
+
+        let name = self.ctx.resolve_symbol(field).to_string();
+        let defined = self.member_location(target_ty, field).map(|loc| {
+            (
+                loc.file_id,
+                loc.span,
+                format!("'{}' is declared with 'let' here", name),
+            )
+        });
+        self.report_immutable(
+            span,
+            "read-only field",
+            name,
+            Some("declare the field with 'var', or set it in 'init'"),
+            defined,
+        );
+    }
+
+    fn report_immutable(
+        &mut self,
+        span: Span,
+        kind: &str,
+        name: String,
+        help: Option<&str>,
+        defined: Option<(FileId, Span, String)>,
+    ) {
+        self.report_with_help(
+            span,
+            SemanticErrorKind::ImmutableAssignment {
+                kind: kind.to_string(),
+                name,
+            },
+            help.map(str::to_string),
+        );
+        if let (Some((file_id, def_span, message)), Some(err)) = (defined, self.errors.last_mut()) {
+            err.labels.push(Label::new(file_id, def_span, message));
+        }
+    }
+
     /// 处理索引赋值 (Logic for `arr[i] = val`)
     fn check_index_assignment(
         &mut self,
@@ -219,6 +346,15 @@ impl<'a> Analyzer<'a> {
         rhs_span: Span,
     ) -> Type {
         let target_ty = self.check_expression(target);
+
+        // [New] 切片是新数组，给它赋值没有意义
+        if matches!(index.data, ExpressionData::Range { .. }) {
+            self.report(
+                index.span,
+                SemanticErrorKind::InvalidAssignmentTarget("cannot assign to a slice".into()),
+            );
+            return Type::Unit;
+        }
         let index_ty = self.check_expression(index);
 
         match target_ty {
@@ -236,6 +372,19 @@ impl<'a> Analyzer<'a> {
                 }
             }
             Type::Error => {}
+            // [New] 元组创建后不能修改
+            Type::Tuple(_) => {
+                self.report(
+                    target.span,
+                    SemanticErrorKind::InvalidAssignmentTarget("tuples are immutable".into()),
+                );
+            }
+            Type::Str => {
+                self.report(
+                    target.span,
+                    SemanticErrorKind::InvalidAssignmentTarget("strings are immutable".into()),
+                );
+            }
             _ => {
                 self.report(
                     target.span,
@@ -250,11 +399,7 @@ impl<'a> Analyzer<'a> {
 }
 
 enum LookupResult {
-    Found {
-        file_id: FileId,
-        span: Span,
-        ty: Type,
-    },
-    FieldMissing,
+    Found { ty: Type },
+    FieldMissing { help: Option<String> },
     TableMissing,
 }
@@ -0,0 +1,193 @@
+// src/analyzer/check/expr/exhaustive.rs
+//
+// [New] match 的穷尽性检查 (Maranget, "Warnings for pattern matching")
+// 分支的模式先化简成 "构造器 + 子模式" 的形式 (Pat)，绑定名字和 `_` 都是通配符；
+// 然后找所有分支都匹配不到的值 (witness)：找得到说明 match 不完整，witness 就是报错里的例子
+
+use crate::analyzer::{Analyzer, Type};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Ctor {
+    /// 枚举的第几个变体
+    Variant(usize),
+    Bool(bool),
+    /// 元组只有一个构造器
+    Tuple,
+    /// int / float / str / char / nil 字面量：取值 (几乎) 无穷多，只有通配符能覆盖
+    Literal(String),
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Pat {
+    Wild,
+    Ctor(Ctor, Vec<Pat>),
+}
+
+/// 最多举几个没覆盖到的例子
+const MAX_WITNESSES: usize = 3;
+
+impl<'a> Analyzer<'a> {
+    /// 所有分支都匹配不到的值 (写成模式)，为空说明 match 是完整的
+    pub(super) fn missing_patterns(&self, arms: &[Pat], ty: &Type) -> Vec<String> {
+        let rows: Vec<Vec<Pat>> = arms.iter().map(|pat| vec![pat.clone()]).collect();
+        self.witnesses(&rows, std::slice::from_ref(ty))
+            .iter()
+            .map(|witness| self.show_witness(&witness[0], ty))
+            .collect()
+    }
+
+    /// 模式矩阵 rows (每行一个分支，第 i 列的类型是 tys[i]) 匹配不到的值
+    fn witnesses(&self, rows: &[Vec<Pat>], tys: &[Type]) -> Vec<Vec<Pat>> {
+        let Some((ty, rest_tys)) = tys.split_first() else {
+            // 没有列了：只要还剩一行就能匹配上
+            return if rows.is_empty() {
+                vec![Vec::new()]
+            } else {
+                Vec::new()
+            };
+        };
+
+        let used: Vec<&Ctor> = rows
+            .iter()
+            .filter_map(|row| match &row[0] {
+                Pat::Ctor(ctor, _) => Some(ctor),
+                Pat::Wild => None,
+            })
+            .collect();
+
+        match self.signature(ty) {
+            // 第一列用到了这个类型的全部构造器：按构造器逐个展开
+            Some(all) if all.iter().all(|(ctor, _)| used.contains(&ctor)) => {
+                let mut result = Vec::new();
+                for (ctor, fields) in all {
+                    let arity = fields.len();
+                    let specialized: Vec<Vec<Pat>> = rows
+                        .iter()
+                        .filter_map(|row| specialize(row, &ctor, arity))
+                        .collect();
+                    let sub_tys: Vec<Type> = fields.into_iter().chain(rest_tys.to_vec()).collect();
+                    for mut witness in self.witnesses(&specialized, &sub_tys) {
+                        let rest = witness.split_off(arity);
+                        let mut row = vec![Pat::Ctor(ctor.clone(), witness)];
+                        row.extend(rest);
+                        result.push(row);
+                        if result.len() >= MAX_WITNESSES {
+                            return result;
+                        }
+                    }
+                }
+                result
+            }
+            // 否则只需要看第一列是通配符的行：剩下的列匹配不到的值，
+            // 配上一个第一列没出现过的构造器 (或者 `_`) 就是答案
+            signature => {
+                let default: Vec<Vec<Pat>> = rows
+                    .iter()
+                    .filter(|row| matches!(row[0], Pat::Wild))
+                    .map(|row| row[1..].to_vec())
+                    .collect();
+                let rest = self.witnesses(&default, rest_tys);
+                let heads: Vec<Pat> = match signature {
+                    Some(all) if !used.is_empty() => all
+                        .into_iter()
+                        .filter(|(ctor, _)| !used.contains(&ctor))
+                        .map(|(ctor, fields)| Pat::Ctor(ctor, vec![Pat::Wild; fields.len()]))
+                        .collect(),
+                    _ => vec![Pat::Wild],
+                };
+                heads
+                    .iter()
+                    .flat_map(|head| {
+                        rest.iter().map(move |witness| {
+                            let mut row = vec![head.clone()];
+                            row.extend(witness.iter().cloned());
+                            row
+                        })
+                    })
+                    .take(MAX_WITNESSES)
+                    .collect()
+            }
+        }
+    }
+
+    /// 类型的全部构造器和各自的字段类型；取值无穷多的类型返回 None
+    fn signature(&self, ty: &Type) -> Option<Vec<(Ctor, Vec<Type>)>> {
+        match ty {
+            Type::Bool => Some(vec![
+                (Ctor::Bool(true), Vec::new()),
+                (Ctor::Bool(false), Vec::new()),
+            ]),
+            Type::Tuple(items) => Some(vec![(Ctor::Tuple, items.clone())]),
+            Type::Enum(id) => Some(
+                self.enum_info(*id)?
+                    .variants
+                    .iter()
+                    .enumerate()
+                    .map(|(tag, variant)| (Ctor::Variant(tag), variant.fields.clone()))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// witness 写成源码里的模式: Shape.Circle(_) / Shape.Rect { w: _, h: _ } / (true, _)
+    fn show_witness(&self, pat: &Pat, ty: &Type) -> String {
+        let Pat::Ctor(ctor, args) = pat else {
+            return "_".to_string();
+        };
+        match (ctor, ty) {
+            (Ctor::Bool(b), _) => b.to_string(),
+            (Ctor::Literal(text), _) => text.clone(),
+            (Ctor::Tuple, Type::Tuple(items)) => {
+                let args: Vec<String> = args
+                    .iter()
+                    .zip(items)
+                    .map(|(arg, ty)| self.show_witness(arg, ty))
+                    .collect();
+                format!("({})", args.join(", "))
+            }
+            (Ctor::Variant(tag), Type::Enum(id)) => {
+                let Some(info) = self.enum_info(*id) else {
+                    return "_".to_string();
+                };
+                let variant = &info.variants[*tag];
+                let name = format!(
+                    "{}.{}",
+                    self.ctx.resolve_symbol(info.name),
+                    self.ctx.resolve_symbol(variant.name)
+                );
+                let args: Vec<String> = args
+                    .iter()
+                    .zip(&variant.fields)
+                    .map(|(arg, ty)| self.show_witness(arg, ty))
+                    .collect();
+                match &variant.field_names {
+                    Some(names) => {
+                        let fields: Vec<String> = names
+                            .iter()
+                            .zip(&args)
+                            .map(|(field, arg)| {
+                                format!("{}: {}", self.ctx.resolve_symbol(*field), arg)
+                            })
+                            .collect();
+                        format!("{} {{ {} }}", name, fields.join(", "))
+                    }
+                    None if args.is_empty() => name,
+                    None => format!("{}({})", name, args.join(", ")),
+                }
+            }
+            _ => "_".to_string(),
+        }
+    }
+}
+
+/// 第一列按构造器 ctor 展开：通配符展开成 arity 个通配符，其它构造器的行被丢掉
+fn specialize(row: &[Pat], ctor: &Ctor, arity: usize) -> Option<Vec<Pat>> {
+    let mut head = match &row[0] {
+        Pat::Wild => vec![Pat::Wild; arity],
+        Pat::Ctor(c, args) if c == ctor => args.clone(),
+        Pat::Ctor(..) => return None,
+    };
+    head.extend(row[1..].iter().cloned());
+    Some(head)
+}
@@ -0,0 +1,66 @@
+// src/analyzer/check/expr/lambda.rs
+//
+// [New] 匿名函数 fn(x: int) int => x * 2
+// 函数体在一个新的函数帧里检查，外层函数的局部变量仍然可见：
+// 引用它们时 resolve_slot 返回 depth > 0 的地址，运行时沿着捕获的帧链找到变量 (闭包)
+
+use std::collections::HashSet;
+
+use crate::analyzer::{Analyzer, SymbolKind, Type};
+use crate::ast::MethodDefinition;
+
+impl<'a> Analyzer<'a> {
+    /// 匿名函数的类型是 Type::Function，赋值 / 传参时按函数类型的规则检查兼容性
+    pub(in crate::analyzer::check) fn check_lambda_expr(&mut self, def: &MethodDefinition) -> Type {
+        let params: Vec<Type> = def
+            .params
+            .iter()
+            .map(|p| self.resolve_ast_type(&p.type_annotation, &HashSet::new()))
+            .collect();
+        let ret = match &def.return_type {
+            Some(t) => self.resolve_ast_type(t, &HashSet::new()),
+            None => Type::Unit,
+        };
+
+        // 函数体里的 return 针对的是匿名函数自己；
+        // 闭包可能在 init 之后才被调用，不能借用 init 给只读字段赋值的特权
+        let prev_return_type = self.current_return_type.replace(ret.clone());
+        let prev_in_initializer = std::mem::replace(&mut self.in_initializer, false);
+        self.scopes.enter_function();
+
+        for (param, ty) in def.params.iter().zip(&params) {
+            self.record_binding(param.id, param.name, param.span, ty.clone());
+            if let Ok(Some(slot)) = self.scopes.define(
+                param.name,
+                ty.clone(),
+                SymbolKind::Parameter,
+                param.span,
+                self.current_file_id,
+                false,
+            ) {
+                self.ctx.db.record_slot(param.id, slot);
+            }
+        }
+
+        if let Some(body) = &def.body {
+            let body_type = self.check_block(body);
+            // 和具名函数一样：没有返回类型时函数体的值被丢掉，Never 说明每条路径都 return 了
+            if ret != Type::Unit
+                && body_type != Type::Never
+                && !ret.is_assignable_from(&body_type, self)
+            {
+                self.error_type_mismatch(body.span, &ret, &body_type);
+            }
+        }
+
+        self.scopes.exit_function();
+        self.in_initializer = prev_in_initializer;
+        self.current_return_type = prev_return_type;
+
+        Type::Function {
+            generic_params: Vec::new(),
+            params,
+            ret: Box::new(ret),
+        }
+    }
+}
@@ -0,0 +1,470 @@
+// src/analyzer/check/expr/matching.rs
+//
+// [New] 枚举和 match
+// - Shape.Circle / lib.Shape.Empty：枚举类型名后面的成员访问是变体
+//   (单元变体是枚举值，元组变体是构造函数，结构体变体只能用 `{ ... }` 构造)
+// - match：每个分支一个作用域，模式里的名字绑定在分支作用域里；
+//   所有分支检查完后做穷尽性检查 (见 exhaustive.rs)
+
+use std::collections::HashSet;
+
+use super::exhaustive::{Ctor, Pat};
+use crate::analyzer::{Analyzer, SemanticErrorKind, SymbolKind, TableId, Type, VariantInfo};
+use crate::ast::{
+    Expression, ExpressionData, Literal, MatchArm, Pattern, PatternData, TypeRef,
+    VariantPatternArgs,
+};
+use crate::utils::{NodeId, Span, Symbol};
+
+impl<'a> Analyzer<'a> {
+    /// 表达式是不是一个类型名 (Shape / lib.Shape)，而不是某个类型的值
+    pub(in crate::analyzer::check) fn is_type_path(&self, expr: &Expression) -> bool {
+        match &expr.data {
+            ExpressionData::Identifier(sym) => self
+                .scopes
+                .resolve(*sym)
+                .is_some_and(|info| info.kind == SymbolKind::Table),
+            ExpressionData::FieldAccess { target, .. } => {
+                matches!(self.ctx.db.type_map.get(&target.id), Some(Type::Module(_)))
+            }
+            _ => false,
+        }
+    }
+
+    /// Shape.Circle：单元变体的类型是枚举本身，元组变体是返回枚举的构造函数
+    pub(in crate::analyzer::check) fn check_variant_access(
+        &mut self,
+        site: NodeId,
+        enum_id: TableId,
+        name: Symbol,
+        span: Span,
+    ) -> Type {
+        let Some((_, variant)) = self.lookup_variant(site, enum_id, name, span) else {
+            return Type::Error;
+        };
+        if variant.field_names.is_some() {
+            let variant_name = self.variant_display(enum_id, name);
+            self.report(
+                span,
+                SemanticErrorKind::VariantFieldMismatch {
+                    reason: format!(
+                        "has named fields; construct it with '{} {{ ... }}'",
+                        variant_name
+                    ),
+                    variant: variant_name,
+                },
+            );
+            return Type::Error;
+        }
+        if variant.fields.is_empty() {
+            Type::Enum(enum_id)
+        } else {
+            Type::Function {
+                generic_params: vec![],
+                params: variant.fields,
+                ret: Box::new(Type::Enum(enum_id)),
+            }
+        }
+    }
+
+    /// Shape.Rect { w: 1.0, h: 2.0 }：字段要和声明一一对应
+    pub(in crate::analyzer::check) fn check_variant_literal(
+        &mut self,
+        target: &Expression,
+        fields: &[(Symbol, Expression)],
+    ) -> Type {
+        let variant = match &target.data {
+            ExpressionData::FieldAccess {
+                target: path,
+                field,
+            } => {
+                let path_ty = self.check_expression(path);
+                match path_ty {
+                    Type::Enum(id) if self.is_type_path(path) => self
+                        .lookup_variant(target.id, id, *field, target.span)
+                        .map(|(_, variant)| (id, variant)),
+                    Type::Error => None,
+                    _ => {
+                        let ty = path_ty.display(self.ctx).to_string();
+                        self.report(
+                            target.span,
+                            SemanticErrorKind::Custom(format!(
+                                "'{}' is not an enum; only enum variants can be constructed with '{{ ... }}'",
+                                ty
+                            )),
+                        );
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let Some((enum_id, variant)) = variant else {
+            for (_, value) in fields {
+                self.check_expression(value);
+            }
+            return Type::Error;
+        };
+        self.record_node(target.id, target.span, Type::Enum(enum_id));
+        let variant_name = self.variant_display(enum_id, variant.name);
+
+        let Some(names) = &variant.field_names else {
+            for (_, value) in fields {
+                self.check_expression(value);
+            }
+            self.report(
+                target.span,
+                SemanticErrorKind::VariantFieldMismatch {
+                    reason: format!("{}, but is constructed with named fields", shape(&variant)),
+                    variant: variant_name,
+                },
+            );
+            return Type::Error;
+        };
+
+        let mut seen = Vec::new();
+        for (field, value) in fields {
+            let value_ty = self.check_expression(value);
+            let field_name = self.ctx.resolve_symbol(*field).to_string();
+            match names.iter().position(|name| name == field) {
+                Some(_) if seen.contains(field) => self.report(
+                    value.span,
+                    SemanticErrorKind::VariantFieldMismatch {
+                        variant: variant_name.clone(),
+                        reason: format!("has field '{}' specified more than once", field_name),
+                    },
+                ),
+                Some(i) => {
+                    seen.push(*field);
+                    let expected = &variant.fields[i];
+                    if !self.check_type_compatibility(expected, &value_ty)
+                        && value_ty != Type::Error
+                    {
+                        let expected = expected.display(self.ctx).to_string();
+                        let found = value_ty.display(self.ctx).to_string();
+                        self.report(
+                            value.span,
+                            SemanticErrorKind::FieldTypeMismatch {
+                                field: field_name,
+                                expected,
+                                found,
+                            },
+                        );
+                    }
+                }
+                None => self.report(
+                    value.span,
+                    SemanticErrorKind::VariantFieldMismatch {
+                        variant: variant_name.clone(),
+                        reason: format!("has no field '{}'", field_name),
+                    },
+                ),
+            }
+        }
+
+        let missing: Vec<String> = names
+            .iter()
+            .filter(|name| !seen.contains(name))
+            .map(|name| format!("'{}'", self.ctx.resolve_symbol(*name)))
+            .collect();
+        if !missing.is_empty() {
+            self.report(
+                target.span,
+                SemanticErrorKind::VariantFieldMismatch {
+                    variant: variant_name,
+                    reason: format!("is missing field(s) {}", missing.join(", ")),
+                },
+            );
+        }
+        Type::Enum(enum_id)
+    }
+
+    /// match value
+    ///     pattern => body
+    pub(in crate::analyzer::check) fn check_match_expr(
+        &mut self,
+        scrutinee: &Expression,
+        arms: &[MatchArm],
+    ) -> Type {
+        let scrutinee_ty = self.check_expression(scrutinee);
+
+        let mut pattern_errors = false;
+        let mut pats = Vec::new();
+        let mut result_ty: Option<Type> = None;
+        let mut incompatible = false;
+        for arm in arms {
+            self.scopes.enter_scope();
+            let errors_before = self.errors.len();
+            pats.push(self.check_match_pattern(&arm.pattern, &scrutinee_ty));
+            pattern_errors |= self.errors.len() > errors_before;
+            let arm_ty = self.check_block_expr(&arm.body);
+            self.scopes.exit_scope();
+
+            // 分支类型的合并规则和 if / else 一样 (Never 的分支不参与)
+            match &result_ty {
+                _ if arm_ty == Type::Never => {}
+                None => result_ty = Some(arm_ty),
+                Some(expected) => {
+                    // [Changed] 结果类型是各分支的公共父类型
+                    if let Some(joined) = self.common_supertype(expected, &arm_ty) {
+                        result_ty = Some(joined);
+                    } else {
+                        let expected = expected.display(self.ctx).to_string();
+                        let found = arm_ty.display(self.ctx).to_string();
+                        self.report(
+                            arm.body.span,
+                            SemanticErrorKind::MatchArmIncompatible { expected, found },
+                        );
+                        incompatible = true;
+                    }
+                }
+            }
+        }
+
+        // 模式本身有错时不做穷尽性检查 (出错的模式当作通配符，结果没有意义)
+        if scrutinee_ty != Type::Error && !pattern_errors {
+            let missing = self.missing_patterns(&pats, &scrutinee_ty);
+            if !missing.is_empty() {
+                let ty = scrutinee_ty.display(self.ctx).to_string();
+                self.report(
+                    scrutinee.span,
+                    SemanticErrorKind::NonExhaustiveMatch { ty, missing },
+                );
+            }
+        }
+
+        if incompatible {
+            return Type::Error;
+        }
+        // 没有分支 (或者所有分支都不返回) 时 match 本身也不会产生值
+        result_ty.unwrap_or(Type::Never)
+    }
+
+    /// 检查 match 分支的模式 (绑定的名字定义在当前作用域)，同时化简成穷尽性检查用的 Pat
+    fn check_match_pattern(&mut self, pattern: &Pattern, ty: &Type) -> Pat {
+        match &pattern.data {
+            PatternData::Wildcard => Pat::Wild,
+            PatternData::Identifier(sym) => {
+                self.record_node(pattern.id, pattern.span, ty.clone());
+                match self.scopes.define(
+                    *sym,
+                    ty.clone(),
+                    SymbolKind::Variable,
+                    pattern.span,
+                    self.current_file_id,
+                    false,
+                ) {
+                    Ok(Some(slot)) => self.record_slot(pattern.id, slot),
+                    Ok(None) => {}
+                    Err(_) => {
+                        let previous = self.previous_definition(*sym);
+                        self.report_duplicate(*sym, pattern.span, previous);
+                    }
+                }
+                Pat::Wild
+            }
+            PatternData::Literal(lit) => {
+                let lit_ty = self.check_literal_expr(lit);
+                self.record_node(pattern.id, pattern.span, lit_ty.clone());
+                if !ty.is_assignable_from(&lit_ty, self) {
+                    self.error_type_mismatch(pattern.span, ty, &lit_ty);
+                    return Pat::Wild;
+                }
+                match lit {
+                    Literal::Bool(b) => Pat::Ctor(Ctor::Bool(*b), Vec::new()),
+                    _ => Pat::Ctor(Ctor::Literal(format!("{:?}", lit)), Vec::new()),
+                }
+            }
+            PatternData::Tuple(items) => {
+                let types = self.pattern_element_types(pattern, items.len(), ty.clone());
+                let items = items
+                    .iter()
+                    .zip(types)
+                    .map(|(item, ty)| self.check_match_pattern(item, &ty))
+                    .collect();
+                Pat::Ctor(Ctor::Tuple, items)
+            }
+            PatternData::Variant {
+                qualifier,
+                name,
+                args,
+            } => self.check_variant_pattern(pattern, qualifier.as_ref(), *name, args, ty),
+        }
+    }
+
+    /// .Circle(r) / Shape.Rect { w, h: _ }
+    fn check_variant_pattern(
+        &mut self,
+        pattern: &Pattern,
+        qualifier: Option<&TypeRef>,
+        name: Symbol,
+        args: &VariantPatternArgs,
+        ty: &Type,
+    ) -> Pat {
+        // 1. 确定是哪个枚举：写出来的类型名，或者 `.V` 简写时被匹配的值的类型
+        let enum_id = match qualifier {
+            Some(qualifier) => match self.resolve_ast_type(qualifier, &HashSet::new()) {
+                Type::Enum(id) if ty.is_assignable_from(&Type::Enum(id), self) => Some(id),
+                Type::Enum(id) => {
+                    self.error_type_mismatch(pattern.span, ty, &Type::Enum(id));
+                    None
+                }
+                Type::Error => None,
+                other => {
+                    let other = other.display(self.ctx).to_string();
+                    self.report(
+                        qualifier.span,
+                        SemanticErrorKind::InvalidPattern(format!("'{}' is not an enum", other)),
+                    );
+                    None
+                }
+            },
+            None => match ty {
+                Type::Enum(id) => Some(*id),
+                Type::Error => None,
+                _ => {
+                    let ty = ty.display(self.ctx).to_string();
+                    self.report(
+                        pattern.span,
+                        SemanticErrorKind::InvalidPattern(format!(
+                            "'.{}' can only match an enum value, found '{}'",
+                            self.ctx.resolve_symbol(name),
+                            ty
+                        )),
+                    );
+                    None
+                }
+            },
+        };
+
+        // 2. 找到变体
+        let variant = enum_id.and_then(|id| {
+            self.lookup_variant(pattern.id, id, name, pattern.span)
+                .map(|(tag, variant)| (id, tag, variant))
+        });
+        let Some((enum_id, tag, variant)) = variant else {
+            self.bind_variant_args(args);
+            return Pat::Wild;
+        };
+        self.record_node(pattern.id, pattern.span, Type::Enum(enum_id));
+
+        // 3. 载荷的写法要和声明一致；结构体变体可以只写一部分字段
+        let fields = match (args, &variant.field_names) {
+            (VariantPatternArgs::None, _) if variant.fields.is_empty() => Vec::new(),
+            (VariantPatternArgs::Tuple(items), None) if items.len() == variant.fields.len() => {
+                items
+                    .iter()
+                    .zip(&variant.fields)
+                    .map(|(item, ty)| self.check_match_pattern(item, ty))
+                    .collect()
+            }
+            (VariantPatternArgs::Struct(items), Some(names)) => {
+                let variant_name = self.variant_display(enum_id, name);
+                let mut fields = vec![Pat::Wild; names.len()];
+                let mut seen = Vec::new();
+                for (field, item) in items {
+                    let field_name = self.ctx.resolve_symbol(*field).to_string();
+                    let reason = match names.iter().position(|n| n == field) {
+                        Some(_) if seen.contains(field) => {
+                            format!("has field '{}' bound more than once", field_name)
+                        }
+                        Some(i) => {
+                            seen.push(*field);
+                            fields[i] = self.check_match_pattern(item, &variant.fields[i]);
+                            continue;
+                        }
+                        None => format!("has no field '{}'", field_name),
+                    };
+                    self.report(
+                        item.span,
+                        SemanticErrorKind::VariantFieldMismatch {
+                            variant: variant_name.clone(),
+                            reason,
+                        },
+                    );
+                    self.check_match_pattern(item, &Type::Error);
+                }
+                fields
+            }
+            _ => {
+                let found = match args {
+                    VariantPatternArgs::None => "has no fields".to_string(),
+                    VariantPatternArgs::Tuple(items) => {
+                        format!("has {} positional field(s)", items.len())
+                    }
+                    VariantPatternArgs::Struct(_) => "has named fields".to_string(),
+                };
+                self.report(
+                    pattern.span,
+                    SemanticErrorKind::VariantFieldMismatch {
+                        variant: self.variant_display(enum_id, name),
+                        reason: format!("{}, but the pattern {}", shape(&variant), found),
+                    },
+                );
+                self.bind_variant_args(args);
+                return Pat::Wild;
+            }
+        };
+        Pat::Ctor(Ctor::Variant(tag), fields)
+    }
+
+    /// 变体模式出错时，里面的名字仍然要定义 (按 Error 类型)，免得分支体里再报未定义
+    fn bind_variant_args(&mut self, args: &VariantPatternArgs) {
+        let items: Vec<&Pattern> = match args {
+            VariantPatternArgs::None => Vec::new(),
+            VariantPatternArgs::Tuple(items) => items.iter().collect(),
+            VariantPatternArgs::Struct(fields) => fields.iter().map(|(_, item)| item).collect(),
+        };
+        for item in items {
+            self.check_match_pattern(item, &Type::Error);
+        }
+    }
+
+    /// 按名字找变体，找不到时报错 (附上近似的变体名)；找到时记录定义位置
+    fn lookup_variant(
+        &mut self,
+        site: NodeId,
+        enum_id: TableId,
+        name: Symbol,
+        span: Span,
+    ) -> Option<(usize, VariantInfo)> {
+        let info = self.enum_info(enum_id)?.clone();
+        match info.variant(name) {
+            Some((tag, variant)) => {
+                self.record_def(site, name, info.file_id, variant.span);
+                Some((tag, variant.clone()))
+            }
+            None => {
+                let help = self.suggest_for_variant(&info, name);
+                self.report_with_help(
+                    span,
+                    SemanticErrorKind::UnknownVariant {
+                        enum_name: self.ctx.resolve_symbol(info.name).to_string(),
+                        variant: self.ctx.resolve_symbol(name).to_string(),
+                    },
+                    help,
+                );
+                None
+            }
+        }
+    }
+
+    /// Shape.Circle
+    fn variant_display(&self, enum_id: TableId, name: Symbol) -> String {
+        format!(
+            "{}.{}",
+            self.ctx.resolve_symbol(enum_id.symbol()),
+            self.ctx.resolve_symbol(name)
+        )
+    }
+}
+
+/// 变体声明的载荷形状 (报错用)
+fn shape(variant: &VariantInfo) -> String {
+    match &variant.field_names {
+        Some(_) => "has named fields".to_string(),
+        None if variant.fields.is_empty() => "has no fields".to_string(),
+        None => format!("has {} positional field(s)", variant.fields.len()),
+    }
+}
@@ -0,0 +1,101 @@
+// src/analyzer/check/expr/pattern.rs
+//
+// [New] 绑定模式: for (k, v) in pairs / (a, b) = pair
+// 每个绑定名字都是模式里的一个节点，槽位和定义位置记在这个节点上
+
+use crate::analyzer::{Analyzer, SemanticErrorKind, SymbolKind, Type};
+use crate::ast::{Expression, Pattern, PatternData};
+
+impl<'a> Analyzer<'a> {
+    /// (a, b) = pair：和 `x = v` 一样，已有的变量被赋值，不存在的变量被定义
+    pub(in crate::analyzer::check) fn check_destructure_expr(
+        &mut self,
+        pattern: &Pattern,
+        value: &Expression,
+        is_mut: Option<bool>,
+    ) -> Type {
+        let value_ty = self.check_expression(value);
+        match is_mut {
+            None => self.assign_pattern(pattern, value_ty, value),
+            // [New] let / var 解构：和 for 的迭代变量一样定义新变量，let 的每个名字都不可变
+            Some(is_mut) => {
+                self.define_pattern(pattern, value_ty);
+                if !is_mut {
+                    for (_, name) in pattern.bindings() {
+                        self.scopes.mark_immutable(name, false);
+                    }
+                }
+            }
+        }
+        Type::Unit
+    }
+
+    fn assign_pattern(&mut self, pattern: &Pattern, ty: Type, value: &Expression) {
+        match &pattern.data {
+            PatternData::Identifier(sym) => {
+                self.check_variable_assignment(*sym, pattern.id, pattern.span, ty, value.span);
+            }
+            PatternData::Wildcard => {}
+            PatternData::Tuple(items) => {
+                let types = self.pattern_element_types(pattern, items.len(), ty);
+                for (item, ty) in items.iter().zip(types) {
+                    self.assign_pattern(item, ty, value);
+                }
+            }
+            // 解构赋值的左边是从表达式转来的，不会出现只用于 match 的模式
+            PatternData::Literal(_) | PatternData::Variant { .. } => {}
+        }
+    }
+
+    /// for 循环的迭代变量：总是在当前 (循环体) 作用域里定义新变量
+    pub(in crate::analyzer::check) fn define_pattern(&mut self, pattern: &Pattern, ty: Type) {
+        match &pattern.data {
+            PatternData::Identifier(sym) => {
+                self.record_node(pattern.id, pattern.span, ty.clone());
+                if let Ok(Some(slot)) = self.scopes.define(
+                    *sym,
+                    ty,
+                    SymbolKind::Variable,
+                    pattern.span,
+                    self.current_file_id,
+                    true,
+                ) {
+                    self.record_slot(pattern.id, slot);
+                }
+            }
+            PatternData::Wildcard => {}
+            PatternData::Tuple(items) => {
+                let types = self.pattern_element_types(pattern, items.len(), ty);
+                for (item, ty) in items.iter().zip(types) {
+                    self.define_pattern(item, ty);
+                }
+            }
+            // for 的迭代变量由 parse_pattern 解析，不会出现只用于 match 的模式
+            PatternData::Literal(_) | PatternData::Variant { .. } => {}
+        }
+    }
+
+    /// 元组模式每个位置对应的类型；形状对不上时报错，后面的绑定都当作 Error 处理
+    pub(super) fn pattern_element_types(
+        &mut self,
+        pattern: &Pattern,
+        len: usize,
+        ty: Type,
+    ) -> Vec<Type> {
+        match ty {
+            Type::Tuple(items) if items.len() == len => items,
+            Type::Error | Type::Any => vec![ty; len],
+            _ => {
+                let ty = ty.display(self.ctx).to_string();
+                self.report(
+                    pattern.span,
+                    SemanticErrorKind::DestructureMismatch {
+                        pattern_len: len,
+                        ty,
+                    },
+                );
+                vec![Type::Error; len]
+            }
+        }
+    }
+}
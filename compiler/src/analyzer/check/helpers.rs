@@ -1,64 +1,41 @@
 use super::*;
-use crate::analyzer::TableId;
+use crate::analyzer::{ClassHierarchy, TableId};
 
 impl<'a> Analyzer<'a> {
+    /// [Changed] 子类型关系都在 Type::is_assignable_from 里，Analyzer 提供类层次 (见 ClassHierarchy)
     pub fn check_type_compatibility(&self, target: &Type, source: &Type) -> bool {
-        // 1. 基础检查 (Int, Str, Bool, Any 等)
-        if target.is_assignable_from(source) {
-            return true;
-        }
+        target.is_assignable_from(source, self)
+    }
 
-        match (target, source) {
-            // Case A: 普通类的继承 (Animal vs Dog)
-            // [Fix] 使用 TableId
-            (Type::Table(target_id), Type::Table(source_id)) => {
-                self.is_subtype(*source_id, *target_id)
+    /// [New] 两个类型的最近公共父类型 (if / match 的分支、数组元素的类型合并)
+    /// 一方兼容另一方时取较宽的那个；否则沿 a 的父类和 trait 由近到远找第一个能容纳 b 的
+    /// 例如 Dog 和 Cat 合并成 Animal
+    pub(super) fn common_supertype(&self, a: &Type, b: &Type) -> Option<Type> {
+        if a.is_assignable_from(b, self) {
+            return Some(a.clone());
+        }
+        if b.is_assignable_from(a, self) {
+            return Some(b.clone());
+        }
+        let mut queue = std::collections::VecDeque::from([a.clone()]);
+        let mut seen = std::collections::HashSet::new();
+        while let Some(ty) = queue.pop_front() {
+            let (id, args) = match &ty {
+                Type::Table(id) => (*id, &[][..]),
+                Type::GenericInstance { base, args } => (*base, &args[..]),
+                _ => continue,
+            };
+            if !seen.insert(id) {
+                continue;
             }
-
-            // Case B: 泛型实例的继承 (List<Animal> vs List<Dog>)
-            (
-                Type::GenericInstance {
-                    base: target_base,
-                    args: target_args,
-                },
-                Type::GenericInstance {
-                    base: source_base,
-                    args: source_args,
-                },
-            ) => {
-                // 2.1 基础类必须相同 (List vs List)
-                // [Fix] 比较 TableId
-                if target_base != source_base {
-                    // 未来可以扩展：允许 ArrayList<T> 赋值给 List<T>
-                    // return self.is_subtype(*source_base, *target_base);
-                    return false;
-                }
-
-                // 2.2 参数数量必须一致
-                if target_args.len() != source_args.len() {
-                    return false;
-                }
-
-                // 2.3 泛型参数协变检查
-                for (t_arg, s_arg) in target_args.iter().zip(source_args.iter()) {
-                    if !self.check_type_compatibility(t_arg, s_arg) {
-                        return false;
-                    }
+            for parent in self.supertypes(id, args) {
+                if parent.is_assignable_from(b, self) {
+                    return Some(parent);
                 }
-                true
+                queue.push_back(parent);
             }
-
-            // Case C: 模块类型兼容性 (通常模块不能赋值，或者是单例)
-            // (Type::Module(id1), Type::Module(id2)) => id1 == id2,
-            // Case D: 数组协变检查
-            // 允许 [Dog] 赋值给 [Animal]
-            (Type::Array(target_inner), Type::Array(source_inner)) => {
-                // 递归调用 check_type_compatibility
-                // 这样能利用已有的 is_subtype 逻辑处理内部元素
-                self.check_type_compatibility(target_inner, source_inner)
-            }
-            _ => false,
         }
+        None
     }
 
     /// [Refactor] 递归检查 source 是否继承自 target
@@ -70,23 +47,156 @@ impl<'a> Analyzer<'a> {
         }
 
         // 1. 查找 Child 的定义 (支持跨文件查找)
-        if let Some(info) = self.find_table_info(child_id) {
-            // 2. 看看它有没有父类
-            if let Some(parent_type) = &info.parent {
-                // 3. 提取父类 ID
-                // 注意：resolve 阶段已经保证 parent 是 Table 或 GenericInstance
-                let parent_id = match parent_type {
-                    Type::Table(id) => *id,
-                    Type::GenericInstance { base, .. } => *base,
-                    _ => return false,
-                };
+        let Some(info) = self.find_table_info(child_id) else {
+            return false;
+        };
+
+        // 2. 沿父类和实现的 trait 向上找 ([New] 实现了 trait 的类也是它的子类型)
+        // 注意：resolve 阶段已经保证 parent / traits 是 Table 或 GenericInstance
+        info.parent.iter().chain(&info.traits).any(|ty| match ty {
+            Type::Table(id) | Type::GenericInstance { base: id, .. } => {
+                self.is_subtype(*id, target_id)
+            }
+            _ => false,
+        })
+    }
 
-                // 4. 递归检查
-                return self.is_subtype(parent_id, target_id);
+    /// [New] 检查泛型实参是否满足约束 (Box<int> 而 T: Animal)
+    /// 约束里可能引用其它泛型参数 (T: Comparable<T>)，先替换成对应的实参
+    pub(super) fn check_generic_bounds(
+        &mut self,
+        span: crate::utils::Span,
+        params: &[Symbol],
+        bounds: &HashMap<Symbol, Type>,
+        args: &[Type],
+    ) {
+        if bounds.is_empty() {
+            return;
+        }
+        let mapping: HashMap<Symbol, Type> =
+            params.iter().copied().zip(args.iter().cloned()).collect();
+        for (param, arg) in params.iter().zip(args) {
+            let Some(bound) = bounds.get(param) else {
+                continue;
+            };
+            let bound = bound.substitute(&mapping);
+            if matches!(arg, Type::Error | Type::Infer)
+                || self.check_type_compatibility(&bound, arg)
+            {
+                continue;
             }
+            let kind = SemanticErrorKind::GenericConstraintViolation {
+                param: self.ctx.resolve_symbol(*param).to_string(),
+                bound: bound.display(self.ctx).to_string(),
+                found: arg.display(self.ctx).to_string(),
+            };
+            self.report(span, kind);
         }
+    }
 
-        false
+    /// [New] 被调用的泛型函数 / 方法声明的约束
+    /// Type::Function 里没有约束，要回到定义处找：顶层函数、模块函数 (lib.f) 或方法 (obj.m)
+    pub(super) fn callee_generic_bounds(&self, callee: &Expression) -> HashMap<Symbol, Type> {
+        match &callee.data {
+            ExpressionData::Identifier(name) => self
+                .functions
+                .get(name)
+                .map(|f| f.signature.generic_bounds.clone())
+                .unwrap_or_default(),
+            ExpressionData::FieldAccess { field, .. } => {
+                match self.ctx.db.receivers.get(&callee.id) {
+                    Some(Type::Module(file_id)) => self
+                        .ctx
+                        .source_manager
+                        .get_file_path(*file_id)
+                        .and_then(|path| self.ctx.modules.get(path))
+                        .and_then(|module| module.functions.get(field))
+                        .map(|f| f.signature.generic_bounds.clone())
+                        .unwrap_or_default(),
+                    Some(Type::Table(id)) => self
+                        .find_table_info(*id)
+                        .and_then(|info| info.methods.get(field).cloned())
+                        .map(|m| m.signature.generic_bounds)
+                        .unwrap_or_default(),
+                    // 方法约束里可能引用类的泛型参数 (fn put<U: T>)，替换成实例的实参
+                    Some(Type::GenericInstance { base, args }) => {
+                        let Some(info) = self.find_table_info(*base) else {
+                            return HashMap::new();
+                        };
+                        let mapping: HashMap<Symbol, Type> = info
+                            .generic_params
+                            .iter()
+                            .copied()
+                            .zip(args.iter().cloned())
+                            .collect();
+                        info.methods
+                            .get(field)
+                            .map(|m| {
+                                m.signature
+                                    .generic_bounds
+                                    .iter()
+                                    .map(|(g, bound)| (*g, bound.substitute(&mapping)))
+                                    .collect()
+                            })
+                            .unwrap_or_default()
+                    }
+                    _ => HashMap::new(),
+                }
+            }
+            _ => HashMap::new(),
+        }
+    }
+
+    /// [New] 类型标注里的泛型实例 (收集阶段记下来的) 逐个检查约束
+    /// 每处标注只检查一次；检查时恢复标注所在位置可见的约束 (实参本身可能是有约束的泛型参数)
+    pub(super) fn check_generic_uses(&mut self) {
+        let mut seen = std::collections::HashSet::new();
+        for (span, id, args, bounds) in std::mem::take(&mut self.generic_uses) {
+            if !seen.insert(span) {
+                continue;
+            }
+            let Some(info) = self.find_table_info(id) else {
+                continue;
+            };
+            let prev_bounds = std::mem::replace(&mut self.generic_bounds, bounds);
+            self.check_generic_bounds(span, &info.generic_params, &info.generic_bounds, &args);
+            self.generic_bounds = prev_bounds;
+        }
+    }
+
+    /// [New] id 是否是 trait
+    pub fn is_trait(&self, id: TableId) -> bool {
+        self.find_table_info(id).is_some_and(|info| info.is_trait)
+    }
+
+    /// [New] const 的初值只能由字面量、运算符和其他常量组成
+    pub(super) fn check_const_initializer(&mut self, name: Symbol, init: &Expression) {
+        if !self.is_constant_expr(init) {
+            let name = self.ctx.resolve_symbol(name).to_string();
+            self.report(init.span, SemanticErrorKind::NonConstantInitializer(name));
+        }
+    }
+
+    fn is_constant_expr(&self, expr: &Expression) -> bool {
+        match &expr.data {
+            ExpressionData::Literal(_) => true,
+            ExpressionData::Identifier(sym) => {
+                self.scopes.resolve(*sym).is_some_and(|i| i.is_const)
+            }
+            ExpressionData::Unary { expr, .. } | ExpressionData::Cast { expr, .. } => {
+                self.is_constant_expr(expr)
+            }
+            ExpressionData::Binary { left, right, .. } => {
+                self.is_constant_expr(left) && self.is_constant_expr(right)
+            }
+            // 元组和区间本身不可变
+            ExpressionData::Tuple(items) => items.iter().all(|item| self.is_constant_expr(item)),
+            ExpressionData::Range { start, end, .. } => [start, end]
+                .into_iter()
+                .flatten()
+                .all(|bound| self.is_constant_expr(bound)),
+            _ => false,
+        }
     }
 
     /// 通用辅助函数：报告类型不匹配错误
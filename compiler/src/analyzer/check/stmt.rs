@@ -29,7 +29,8 @@ impl<'a> Analyzer<'a> {
                 return then_ty;
             }
 
-            if !then_ty.is_assignable_from(&else_ty) && !else_ty.is_assignable_from(&then_ty) {
+            // [Changed] 结果是两个分支的公共父类型 (if c then Dog() else Cat() 是 Animal)
+            let Some(joined) = self.common_supertype(&then_ty, &else_ty) else {
                 let t_str = then_ty.display(self.ctx).to_string();
                 let e_str = else_ty.display(self.ctx).to_string();
                 self.report(
@@ -40,8 +41,8 @@ impl<'a> Analyzer<'a> {
                     },
                 );
                 return Type::Error;
-            }
-            then_ty
+            };
+            joined
         } else {
             if then_ty != Type::Unit && then_ty != Type::Never && then_ty != Type::Error {
                 let t_str = then_ty.display(self.ctx).to_string();
@@ -69,7 +70,7 @@ impl<'a> Analyzer<'a> {
 
     pub(super) fn check_for_expr(
         &mut self,
-        iterator: Symbol,
+        pattern: &Pattern,
         iterable: &Expression,
         body: &Block,
     ) -> Type {
@@ -78,7 +79,8 @@ impl<'a> Analyzer<'a> {
         let item_ty = match iterable_ty {
             Type::Array(inner) => *inner,
             Type::Range(inner) => *inner,
-            Type::Str => Type::Str,
+            // [Changed] 遍历字符串得到的是字符
+            Type::Str => Type::Char,
             Type::Error => Type::Error,
             _ => {
                 let ty_str = iterable_ty.display(self.ctx).to_string();
@@ -88,14 +90,8 @@ impl<'a> Analyzer<'a> {
         };
 
         self.scopes.enter_scope();
-        let _ = self.scopes.define(
-            iterator,
-            item_ty,
-            SymbolKind::Variable,
-            iterable.span,
-            self.current_file_id,
-            true,
-        );
+        // [Changed] 迭代变量是一个模式，槽位记在模式里每个名字的节点上
+        self.define_pattern(pattern, item_ty);
         self.check_block_expr(body);
         self.scopes.exit_scope();
         Type::Unit
@@ -120,7 +116,7 @@ impl<'a> Analyzer<'a> {
         match expected_opt {
             Some(expected) => {
                 // 这里的 expected 是一个独立的 Type 对象，不再指向 self
-                if !expected.is_assignable_from(&actual_type) {
+                if !expected.is_assignable_from(&actual_type, self) {
                     // 现在可以安全地以 &mut self 调用报错函数了
                     self.error_type_mismatch(span, &expected, &actual_type);
                 }
@@ -0,0 +1,128 @@
+// src/analyzer/check/suggest.rs
+//
+// [New] 名字解析失败时的修改建议 ("did you mean ...")
+// - 未定义的标识符：在作用域里可见的名字中找近似的；
+//   找不到时看项目里其它文件有没有在顶层定义这个名字 (可能是忘了 import)
+// - 成员 / 模块导出：在 TableInfo 的字段和方法 / ModuleInfo 的导出里找
+
+use super::*;
+use crate::analyzer::{EnumInfo, ModuleInfo};
+use crate::utils::best_match;
+use std::path::Path;
+
+impl<'a> Analyzer<'a> {
+    /// 未定义的标识符
+    pub(super) fn suggest_for_identifier(&mut self, sym: Symbol) -> Option<String> {
+        let name = self.ctx.resolve_symbol(sym);
+        let candidates: Vec<&str> = self
+            .scopes
+            .visible()
+            .map(|info| self.ctx.resolve_symbol(info.name))
+            .collect();
+        if let Some(hint) = best_match(name, candidates).map(did_you_mean) {
+            return Some(hint);
+        }
+        self.unimported_definition_hint(sym)
+    }
+
+    /// Table 的字段和方法 (resolve_hierarchy 之后已包含继承来的成员)
+    pub(super) fn suggest_for_member(&self, info: &TableInfo, field: Symbol) -> Option<String> {
+        let candidates = info
+            .fields
+            .keys()
+            .chain(info.methods.keys())
+            .map(|name| self.ctx.resolve_symbol(*name));
+        best_match(self.ctx.resolve_symbol(field), candidates).map(did_you_mean)
+    }
+
+    /// 赋值目标只能是字段
+    pub(super) fn suggest_for_field(&self, info: &TableInfo, field: Symbol) -> Option<String> {
+        let candidates = info
+            .fields
+            .keys()
+            .map(|name| self.ctx.resolve_symbol(*name));
+        best_match(self.ctx.resolve_symbol(field), candidates).map(did_you_mean)
+    }
+
+    /// 数组 / 字符串的内置成员 (见 check_builtin_member_access)
+    pub(super) fn suggest_for_builtin_member(&self, ty: &Type, field: Symbol) -> Option<String> {
+        let candidates: &[&str] = match ty {
+            Type::Array(_) => &["len", "push"],
+            Type::Str => &["len"],
+            Type::Range(_) => &["contains", "len", "rev", "step"],
+            _ => return None,
+        };
+        best_match(self.ctx.resolve_symbol(field), candidates.iter().copied()).map(did_you_mean)
+    }
+
+    /// [New] 枚举的变体
+    pub(super) fn suggest_for_variant(&self, info: &EnumInfo, variant: Symbol) -> Option<String> {
+        let candidates = info
+            .variants
+            .iter()
+            .map(|v| self.ctx.resolve_symbol(v.name));
+        best_match(self.ctx.resolve_symbol(variant), candidates).map(did_you_mean)
+    }
+
+    /// 模块导出的类、函数和全局变量
+    pub(super) fn suggest_for_export(&self, module: &ModuleInfo, field: Symbol) -> Option<String> {
+        let candidates = module
+            .tables
+            .keys()
+            .map(|id| id.symbol())
+            .chain(module.functions.keys().copied())
+            .chain(module.globals.keys().copied())
+            .chain(module.enums.keys().map(|id| id.symbol()))
+            .map(|name| self.ctx.resolve_symbol(name));
+        best_match(self.ctx.resolve_symbol(field), candidates).map(did_you_mean)
+    }
+
+    /// 项目里其它文件在顶层定义了这个名字：
+    /// 已经 import 过 (换了名字) 时提示加上模块前缀，否则提示 import 它
+    /// [Changed] 查 Context::project_index (每次分析只建一次)，不再为每个未定义的名字遍历项目目录
+    fn unimported_definition_hint(&mut self, sym: Symbol) -> Option<String> {
+        let current = self.current_file_path.clone();
+        let path = self
+            .ctx
+            .project_index()
+            .get(&sym)?
+            .iter()
+            .find(|path| **path != current)?
+            .clone();
+        let file_id = self.ctx.source_manager.find_file(&path)?;
+
+        let name = self.ctx.resolve_symbol(sym);
+        // 已经 import 的模块：作用域里有指向这个文件的模块符号
+        let imported_as = self
+            .scopes
+            .visible()
+            .find(|info| info.ty == Type::Module(file_id))
+            .map(|info| self.ctx.resolve_symbol(info.name));
+        if let Some(alias) = imported_as {
+            return Some(format!(
+                "'{}' is defined in module '{}'; refer to it as '{}.{}'",
+                name, alias, alias, name
+            ));
+        }
+        let use_path = module_use_path(&self.ctx.root_dir, &path)?;
+        let module_name = use_path.rsplit('.').next().unwrap_or(&use_path);
+        Some(format!(
+            "'{}' is defined in module '{}', which is not imported; add 'use {}' and refer to it as '{}.{}'",
+            name, use_path, use_path, module_name, name
+        ))
+    }
+}
+
+fn did_you_mean(name: &str) -> String {
+    format!("did you mean '{}'?", name)
+}
+
+/// 从项目根目录 import 这个文件用的路径：libs/zoo.lm -> libs.zoo
+fn module_use_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?.with_extension("");
+    let segments: Vec<&str> = relative
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect::<Option<_>>()?;
+    Some(segments.join("."))
+}
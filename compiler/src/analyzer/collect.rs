@@ -2,17 +2,14 @@ use super::SymbolKind;
 use crate::analyzer::errors::SemanticErrorKind;
 use crate::analyzer::path::resolve_module_path;
 use crate::analyzer::{
-    Analyzer, FieldInfo, FunctionInfo, FunctionSignature, GlobalVarInfo, MethodInfo, ModuleInfo,
-    TableId, TableInfo, Type,
+    Analyzer, EnumInfo, FieldInfo, FunctionInfo, FunctionSignature, GlobalVarInfo, MethodInfo,
+    ModuleInfo, TableId, TableInfo, Type, VTableEntry, VariantInfo,
 };
 use crate::ast::*;
-use crate::lexer::Lexer;
-use crate::parser::Parser;
 use crate::source::FileId;
-use crate::utils::Symbol;
+use crate::utils::{Span, Symbol};
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::rc::Rc;
 
 impl<'a> Analyzer<'a> {
     pub fn collect_program(&mut self, program: &Program) {
@@ -22,6 +19,14 @@ impl<'a> Analyzer<'a> {
         // 第二步：注入内置函数
         self.register_builtins();
 
+        // [New] 先登记枚举名：类型标注里的名字默认解析成类，
+        // 所以枚举 (包括变体里引用自身的递归枚举) 必须在解析任何标注之前就能查到
+        for item in &program.definitions {
+            if let TopLevelItem::Enum(def) = item {
+                self.declare_enum(def);
+            }
+        }
+
         // 第三步：收集用户定义
         for item in &program.definitions {
             match item {
@@ -33,10 +38,95 @@ impl<'a> Analyzer<'a> {
 
                 // [New] 处理顶层变量
                 TopLevelItem::Field(field_def) => self.collect_top_level_field(field_def),
+
+                // [New] 枚举名已经登记，这里解析变体
+                TopLevelItem::Enum(enum_def) => self.collect_enum_definition(enum_def),
             }
         }
     }
 
+    /// [New] 登记枚举名 (变体在 collect_enum_definition 里解析)
+    fn declare_enum(&mut self, def: &EnumDefinition) {
+        let id = TableId(self.current_file_id, def.name);
+        if self
+            .scopes
+            .define(
+                def.name,
+                Type::Enum(id),
+                SymbolKind::Table,
+                def.span,
+                self.current_file_id,
+                false,
+            )
+            .is_err()
+        {
+            let previous = self.previous_definition(def.name);
+            self.report_duplicate(def.name, def.span, previous);
+        }
+    }
+
+    fn collect_enum_definition(&mut self, def: &EnumDefinition) {
+        let id = TableId(self.current_file_id, def.name);
+        // 重名的枚举在 declare_enum 里已经报过错
+        if self.enums.contains_key(&id)
+            || self
+                .scopes
+                .resolve(def.name)
+                .is_none_or(|info| info.defined_span != def.span)
+        {
+            return;
+        }
+
+        let no_generics = HashSet::new();
+        let mut variants: Vec<VariantInfo> = Vec::new();
+        for variant in &def.variants {
+            if let Some(previous) = variants.iter().find(|v| v.name == variant.name) {
+                let previous = Some((self.current_file_id, previous.span));
+                self.report_duplicate(variant.name, variant.span, previous);
+                continue;
+            }
+            let (fields, field_names) = match &variant.kind {
+                VariantKind::Unit => (Vec::new(), None),
+                VariantKind::Tuple(types) => (
+                    types
+                        .iter()
+                        .map(|t| self.resolve_ast_type(t, &no_generics))
+                        .collect(),
+                    None,
+                ),
+                VariantKind::Struct(fields) => {
+                    let mut names: Vec<Symbol> = Vec::new();
+                    let mut types = Vec::new();
+                    for (name, type_ref) in fields {
+                        if names.contains(name) {
+                            self.report_duplicate(*name, type_ref.span, None);
+                            continue;
+                        }
+                        names.push(*name);
+                        types.push(self.resolve_ast_type(type_ref, &no_generics));
+                    }
+                    (types, Some(names))
+                }
+            };
+            variants.push(VariantInfo {
+                name: variant.name,
+                fields,
+                field_names,
+                span: variant.span,
+            });
+        }
+
+        self.enums.insert(
+            id,
+            EnumInfo {
+                name: def.name,
+                file_id: self.current_file_id,
+                variants,
+                defined_span: def.span,
+            },
+        );
+    }
+
     // 收集顶层函数
     fn collect_top_level_function(&mut self, def: &MethodDefinition) {
         let name = def.name;
@@ -72,8 +162,8 @@ impl<'a> Analyzer<'a> {
             .define(name, func_type, SymbolKind::Function, def.span, id, false)
             .is_err()
         {
-            let name_str = self.ctx.resolve_symbol(name).to_string();
-            self.report(def.span, SemanticErrorKind::DuplicateDefinition(name_str));
+            let previous = self.previous_definition(name);
+            self.report_duplicate(name, def.span, previous);
             return;
         }
 
@@ -113,20 +203,24 @@ impl<'a> Analyzer<'a> {
             )
             .is_err()
         {
-            // 从 interner 解析出字符串名称用于报错
-            let name_str = self.ctx.resolve_symbol(def.name).to_string();
-            self.report(def.span, SemanticErrorKind::DuplicateDefinition(name_str));
+            let previous = self.previous_definition(def.name);
+            self.report_duplicate(def.name, def.span, previous);
             // 即使报错，也可以选择继续往后跑，或者直接 return，取决于你是否想做错误恢复
             // 这里通常 return 避免后续逻辑产生更多混乱的错误
             return;
         }
 
+        if !def.is_mut {
+            self.scopes.mark_immutable(def.name, def.is_const);
+        }
+
         let info = GlobalVarInfo {
             name: def.name,
             ty,
             span: def.span,
             file_id: self.current_file_id,
-            is_const: false,
+            is_const: def.is_const,
+            is_mut: def.is_mut,
         };
 
         self.globals.insert(def.name, info); // 假设你在 Analyzer 里加了这个字段
@@ -151,8 +245,8 @@ impl<'a> Analyzer<'a> {
             )
             .is_err()
         {
-            let name_str = self.ctx.resolve_symbol(name).to_string();
-            self.report(def.span, SemanticErrorKind::DuplicateDefinition(name_str));
+            let previous = self.previous_definition(name);
+            self.report_duplicate(name, def.span, previous);
             return;
         }
 
@@ -162,23 +256,35 @@ impl<'a> Analyzer<'a> {
 
         for g in &def.generics {
             if local_generics_scope.contains(&g.name) {
-                let g_name = self.ctx.resolve_symbol(g.name).to_string();
-                self.report(g.span, SemanticErrorKind::DuplicateDefinition(g_name));
+                let previous = def.generics.iter().find(|p| p.name == g.name);
+                self.report_duplicate(g.name, g.span, previous.map(|p| (id, p.span)));
             } else {
                 generic_params.push(g.name);
                 local_generics_scope.insert(g.name);
             }
         }
 
+        // [New] 泛型约束 <T: Animal>；解析字段和方法签名时这些约束可见
+        let generic_bounds = self.resolve_generic_bounds(&def.generics, &local_generics_scope);
+        let prev_bounds = std::mem::replace(&mut self.generic_bounds, generic_bounds.clone());
+
         // 3. 解析继承关系
         let parent = def
             .prototype
             .as_ref()
             .map(|proto_type| self.resolve_ast_type(proto_type, &local_generics_scope));
+        // [New] 其余的都应该是 trait (resolve_hierarchy 里检查)
+        let traits = def
+            .traits
+            .iter()
+            .map(|trait_type| self.resolve_ast_type(trait_type, &local_generics_scope))
+            .collect();
 
         // 4. 解析 Fields 和 Methods
         let mut fields = HashMap::new();
         let mut methods = HashMap::new();
+        let mut vtable = Vec::new();
+        let mut field_layout = Vec::new();
 
         for item in &def.items {
             match item {
@@ -193,11 +299,13 @@ impl<'a> Analyzer<'a> {
                         ty,
                         span: field.span,
                         value: field.value.clone(),
+                        is_mut: field.is_mut,
                     };
 
-                    if fields.insert(field.name, field_info).is_some() {
-                        let f_name = self.ctx.resolve_symbol(field.name).to_string();
-                        self.report(field.span, SemanticErrorKind::DuplicateDefinition(f_name));
+                    if let Some(previous) = fields.insert(field.name, field_info) {
+                        self.report_duplicate(field.name, field.span, Some((id, previous.span)));
+                    } else {
+                        field_layout.push(field.name);
                     }
                 }
                 TableItem::Method(method) => {
@@ -209,22 +317,33 @@ impl<'a> Analyzer<'a> {
                         signature: sig,
                         span: method.span,
                     };
-                    if methods.insert(method.name, method_info).is_some() {
-                        let m_name = self.ctx.resolve_symbol(method.name).to_string();
-                        self.report(method.span, SemanticErrorKind::DuplicateDefinition(m_name));
+                    if let Some(previous) = methods.insert(method.name, method_info) {
+                        self.report_duplicate(method.name, method.span, Some((id, previous.span)));
+                    } else {
+                        vtable.push(VTableEntry {
+                            name: method.name,
+                            owner: table_id,
+                        });
                     }
                 }
             }
         }
 
+        self.generic_bounds = prev_bounds;
+
         // 5. 存入 Analyzer Tables
         let info = TableInfo {
             name,
             file_id: id,
             parent,
+            traits,
+            is_trait: def.is_trait,
             generic_params,
+            generic_bounds,
             fields,
             methods,
+            vtable,
+            field_layout,
             defined_span: def.span,
         };
 
@@ -254,6 +373,12 @@ impl<'a> Analyzer<'a> {
             }
         }
 
+        // [New] 方法自己的泛型约束，和类的约束一起在签名里可见
+        let generic_bounds = self.resolve_generic_bounds(&method.generics, &valid_generics);
+        let mut visible_bounds = self.generic_bounds.clone();
+        visible_bounds.extend(generic_bounds.clone());
+        let prev_bounds = std::mem::replace(&mut self.generic_bounds, visible_bounds);
+
         // 2. 解析参数 (使用合并后的 scope)
         let mut params = Vec::new();
         for param in &method.params {
@@ -269,14 +394,61 @@ impl<'a> Analyzer<'a> {
         };
 
         let is_abstract = method.body.is_none();
+        self.generic_bounds = prev_bounds;
 
         FunctionSignature {
             params,
             ret,
             is_abstract,
+            generic_bounds,
         }
     }
 
+    /// [New] Range<T>：元素目前只能是 int
+    fn resolve_range_type(&mut self, span: Span, args: Vec<Type>) -> Type {
+        match args.as_slice() {
+            [Type::Int] | [Type::Error] => Type::Range(Box::new(Type::Int)),
+            [other] => {
+                let found = other.display(self.ctx).to_string();
+                self.report(span, SemanticErrorKind::InvalidRangeElement(found));
+                Type::Error
+            }
+            _ => {
+                self.report(
+                    span,
+                    SemanticErrorKind::GenericArgumentCountMismatch {
+                        name: "Range".to_string(),
+                        expected: 1,
+                        found: args.len(),
+                    },
+                );
+                Type::Error
+            }
+        }
+    }
+
+    /// [New] 解析泛型参数的约束 (约束里可以引用同一列表里的其它泛型参数)
+    pub(crate) fn resolve_generic_bounds(
+        &mut self,
+        generics: &[GenericParam],
+        valid_generics: &HashSet<Symbol>,
+    ) -> HashMap<Symbol, Type> {
+        let first_use = self.generic_uses.len();
+        let bounds: HashMap<Symbol, Type> = generics
+            .iter()
+            .filter_map(|g| {
+                let bound = g.constraint.as_ref()?;
+                Some((g.name, self.resolve_ast_type(bound, valid_generics)))
+            })
+            .collect();
+        // [Fix] 约束里对泛型类的使用 (U: Kennel<T>) 也要看得到同一列表的约束 (T: Animal)。
+        // 解析约束时它们还没生效，所以解析完再补上 (这些用法在 check_program 结束时才检查)
+        for (_, _, _, visible) in &mut self.generic_uses[first_use..] {
+            visible.extend(bounds.clone());
+        }
+        bounds
+    }
+
     /// 处理 use 语句
     pub fn collect_use_statement(&mut self, stmt: &UseStatement) {
         // 1. 获取模块名和 import 名称
@@ -329,9 +501,14 @@ impl<'a> Analyzer<'a> {
             }
         };
 
+        // [New] 记录 use 边 (不论是否命中缓存)，文件修改时据此找到依赖者
+        self.ctx
+            .record_dependency(self.current_file_path.clone(), abs_path.clone());
+
         // 4. 检查是否需要分析 (Cache Miss)
         // 我们使用 abs_path 作为模块缓存的 Key (Analyzer 阶段)
-        if !self.ctx.modules.contains_key(&abs_path) {
+        // (入口检查失败时留下的 ModuleInfo 不能复用，重新分析才能报告它的错误)
+        if self.ctx.modules.get(&abs_path).is_none_or(|m| m.has_errors) {
             // 4.1 循环依赖检测
             if self.ctx.loading_stack.contains(&abs_path) {
                 self.report(
@@ -356,6 +533,9 @@ impl<'a> Analyzer<'a> {
             }
         }
 
+        // [LSP] use 语句记为模块类型：补全据此找到文件 import 的模块 (包括类型标注里的 `lib.`)
+        self.record_node(stmt.id, stmt.span, Type::Module(file_id));
+
         // 5. 将模块注册到当前作用域
         // [Key Fix] 使用 file_id 构造 Type::Module
         if self
@@ -370,29 +550,48 @@ impl<'a> Analyzer<'a> {
             )
             .is_err()
         {
-            let name = self.ctx.resolve_symbol(import_name).to_string();
-            self.report(stmt.span, SemanticErrorKind::DuplicateDefinition(name));
+            let previous = self.previous_definition(import_name);
+            self.report_duplicate(import_name, stmt.span, previous);
+            return;
         }
+        // [New] 模块名不能被重新赋值
+        self.scopes.mark_immutable(import_name, false);
     }
 
     fn analyze_module_file(&mut self, file_id: FileId, path: PathBuf) -> Option<ModuleInfo> {
-        let source_text = self.ctx.source_manager.get_file(file_id).src.as_str();
-
-        let lexer = Lexer::new(source_text);
-        let mut parser = Parser::new(source_text, lexer, file_id, &mut self.ctx.interner);
-
-        let program = match parser.parse_program() {
+        // [Changed] 源码没变的模块复用缓存的 AST
+        let parsed = self.ctx.parse_file(file_id);
+        let program = match parsed.program {
             Ok(p) => p,
             Err(e) => {
-                self.report(e.span, SemanticErrorKind::ModuleParseError(e.message));
+                self.report_in(
+                    file_id,
+                    e.span,
+                    SemanticErrorKind::ModuleParseError(e.message),
+                );
                 return None;
             }
         };
+        // [Fix] 可恢复的语法错误同样要报告 (原来被忽略，模块会带着被跳过的语句继续运行)
+        if !parsed.errors.is_empty() {
+            for e in parsed.errors {
+                self.report_in(
+                    file_id,
+                    e.span,
+                    SemanticErrorKind::ModuleParseError(e.message),
+                );
+            }
+            return None;
+        }
+
+        // 重新检查前清掉上一次的记录，并重建它的 use 边
+        self.ctx.clear_semantics(file_id);
+        self.ctx.module_deps.remove(&path);
 
         // 隔离环境分析子模块
         let mut sub_analyzer = Analyzer::new(self.ctx, file_id);
 
-        // 收集符号 (会填充 sub_analyzer.functions / tables / globals)
+        // 收集符号 (会填充 sub_analyzer.functions / tables / globals / enums)
         sub_analyzer.collect_program(&program);
 
         if !sub_analyzer.errors.is_empty() {
@@ -407,6 +606,17 @@ impl<'a> Analyzer<'a> {
             return None;
         }
 
+        // [New] 子模块同样要做类型检查：
+        // 除了报错之外，这一步还会为模块内的局部变量分配槽位，解释器依赖这些信息
+        sub_analyzer.check_program(&program);
+
+        if !sub_analyzer.errors.is_empty() {
+            self.errors.extend(sub_analyzer.errors);
+            return None;
+        }
+
+        sub_analyzer.lint_program(&program);
+
         // --- 收集 AST (供解释器使用) ---
         let mut ast_defs = HashMap::new();
         let mut ast_funcs = HashMap::new(); // [New]
@@ -433,16 +643,19 @@ impl<'a> Analyzer<'a> {
             tables: sub_analyzer.tables,
             functions: sub_analyzer.functions,
             globals: sub_analyzer.globals,
+            enums: sub_analyzer.enums,
 
             // 导出 AST
             ast_definitions: ast_defs,
             ast_functions: ast_funcs,
-            program: Rc::new(program),
+            program,
+            has_errors: false,
+            warnings: sub_analyzer.warnings,
         })
     }
 
     pub fn resolve_ast_type(
-        &self,
+        &mut self,
         type_ref: &TypeRef,
         valid_generics: &HashSet<crate::utils::Symbol>,
     ) -> Type {
@@ -460,31 +673,45 @@ impl<'a> Analyzer<'a> {
                     "float" => Type::Float,
                     "bool" => Type::Bool,
                     "str" => Type::Str,
+                    "char" => Type::Char,
                     "nil" => Type::Nil,
                     "any" => Type::Error, // 暂定为 Error
+                    // [New] 区间是内置值类型，元素目前只能是 int
+                    "Range" if self.scopes.resolve(*sym).is_none() => {
+                        Type::Range(Box::new(Type::Int))
+                    }
                     _ => {
                         // [Fix 1] scope.resolve 返回的是 SymbolInfo，不是 Type
                         // 我们需要访问 info.ty
                         if let Some(info) = self.scopes.resolve(*sym) {
                             // 如果已经是 Table 类型（比如 import 进来的），直接复用
                             // 这样能保留它携带的原始 FileId
-                            if let Type::Table(_) = &info.ty {
+                            if let Type::Table(id) | Type::Enum(id) = info.ty {
+                                self.type_refs.push((type_ref.id, type_ref.span, id));
                                 return info.ty.clone();
                             }
                         }
 
                         // [Fix 2] 如果没找到，或者不是 Table，默认为当前文件定义的 Table
                         // 使用 TableId(FileId, Symbol) 构造
-                        Type::Table(TableId(self.current_file_id, *sym))
+                        let id = TableId(self.current_file_id, *sym);
+                        self.type_refs.push((type_ref.id, type_ref.span, id));
+                        Type::Table(id)
                     }
                 }
             }
             TypeRefData::GenericInstance { base, args } => {
-                let resolved_args = args
+                let resolved_args: Vec<Type> = args
                     .iter()
                     .map(|a| self.resolve_ast_type(a, valid_generics))
                     .collect();
 
+                // [Fix] Range<int> 是内置的区间类型，不是用户定义的泛型类
+                if self.ctx.resolve_symbol(*base) == "Range" && self.scopes.resolve(*base).is_none()
+                {
+                    return self.resolve_range_type(type_ref.span, resolved_args);
+                }
+
                 let base_id = if let Some(info) = self.scopes.resolve(*base) {
                     if let Type::Table(id) = &info.ty {
                         *id
@@ -496,6 +723,14 @@ impl<'a> Analyzer<'a> {
                     TableId(self.current_file_id, *base)
                 };
 
+                self.type_refs.push((type_ref.id, type_ref.span, base_id));
+                // [New] 实参是否满足约束要等所有类都收集、解析完之后再检查
+                self.generic_uses.push((
+                    type_ref.span,
+                    base_id,
+                    resolved_args.clone(),
+                    self.generic_bounds.clone(),
+                ));
                 Type::GenericInstance {
                     base: base_id,
                     args: resolved_args,
@@ -510,7 +745,13 @@ impl<'a> Analyzer<'a> {
                     // 注意：Analyzer 的 Type enum 中需要有 Module(FileId) 变体
                     if let Type::Module(file_id) = info.ty {
                         // 3. 构造指向该模块的 TableId
-                        return Type::Table(TableId(file_id, *member));
+                        let id = TableId(file_id, *member);
+                        self.type_refs.push((type_ref.id, type_ref.span, id));
+                        // [New] 也可能是模块导出的枚举
+                        if self.enum_info(id).is_some() {
+                            return Type::Enum(id);
+                        }
+                        return Type::Table(id);
                     }
                 }
 
@@ -524,6 +765,25 @@ impl<'a> Analyzer<'a> {
                 let resolved_inner = self.resolve_ast_type(inner, valid_generics);
                 Type::Array(Box::new(resolved_inner))
             }
+            // [New] 元组类型 (int, str)
+            TypeRefData::Tuple(items) => Type::Tuple(
+                items
+                    .iter()
+                    .map(|t| self.resolve_ast_type(t, valid_generics))
+                    .collect(),
+            ),
+            // [New] 函数类型 fn(int) bool；没写返回类型就是 ()
+            TypeRefData::Function { params, ret } => Type::Function {
+                generic_params: Vec::new(),
+                params: params
+                    .iter()
+                    .map(|t| self.resolve_ast_type(t, valid_generics))
+                    .collect(),
+                ret: Box::new(match ret {
+                    Some(ret) => self.resolve_ast_type(ret, valid_generics),
+                    None => Type::Unit,
+                }),
+            },
         }
     }
 }
@@ -2,8 +2,9 @@
 
 use super::types::Type;
 use crate::source::FileId;
-use crate::utils::{NodeId, Span};
+use crate::utils::{NodeId, Span, Symbol};
 use std::collections::HashMap;
+use std::ops::Range;
 
 /// 语义数据库：Analyzer 的"副产品"，LSP 的"核心资产"
 #[derive(Debug, Default)]
@@ -15,11 +16,107 @@ pub struct SemanticDB {
     /// AST 引用节点 -> 定义位置 (FileId, Span)
     /// 用于 Goto Definition
     pub def_map: HashMap<NodeId, Location>,
+
+    /// [New] 引用节点 -> 被引用的名字
+    /// 用于 Find References / Rename 在引用区间里定位名字本身 (`obj.field` 只改 `field`)
+    pub ref_names: HashMap<NodeId, Symbol>,
+
+    /// [New] 成员访问节点 (`obj.field`) -> 点号左边 obj 的类型
+    /// 用于补全：光标在 `obj.` 后面时按它列出字段 / 方法 / 模块导出
+    pub receivers: HashMap<NodeId, Type>,
+
+    /// [New] AST 节点 -> 源码区间 (所在文件由 Context::file_of_node 反查)
+    /// 用于 Hover 按光标位置找到最内层的表达式
+    pub node_spans: HashMap<NodeId, Span>,
+
+    /// [New] 局部变量节点 -> 栈帧地址 (depth, slot)
+    /// 供解释器按下标存取局部变量。NodeId 在同一个 Context 内连续分配，
+    /// 所以直接用 Vec 下标索引，查询不需要哈希
+    pub local_slots: Vec<Option<LocalSlot>>,
+
+    /// [New] 声明节点 (let / 参数 / 全局变量 / 字段) -> (名字, 声明区间, 绑定的类型)
+    /// 用于 Hover：光标在声明的名字上时显示变量的类型，而不是整条声明语句的 ()
+    pub bindings: HashMap<NodeId, (Symbol, Span, Type)>,
+
+    /// [New] 字段访问节点 -> 字段在实例布局中的下标
+    /// 只有静态类型是类的访问才有记录，其余情况解释器按名字查找
+    pub field_slots: Vec<Option<u32>>,
     // 全局符号表其实已经分散在 context.modules 和 analyzer.tables 里了
     // 如果需要统一查询，可以考虑在这里加索引，或者 LSP 直接查 context.modules
 }
 
-#[derive(Debug, Clone, Copy)]
+impl SemanticDB {
+    pub fn record_slot(&mut self, id: NodeId, slot: LocalSlot) {
+        let idx = id.0 as usize;
+        if idx >= self.local_slots.len() {
+            self.local_slots.resize(idx + 1, None);
+        }
+        self.local_slots[idx] = Some(slot);
+    }
+
+    /// 查询局部变量地址；全局变量 / 内置函数返回 None
+    #[inline]
+    pub fn slot_of(&self, id: NodeId) -> Option<LocalSlot> {
+        self.local_slots.get(id.0 as usize).copied().flatten()
+    }
+
+    pub fn record_field_slot(&mut self, id: NodeId, index: u32) {
+        let idx = id.0 as usize;
+        if idx >= self.field_slots.len() {
+            self.field_slots.resize(idx + 1, None);
+        }
+        self.field_slots[idx] = Some(index);
+    }
+
+    /// [New] 丢弃一段 NodeId 的所有记录 (文件被重新解析或重新检查时)
+    pub fn forget(&mut self, nodes: Range<u32>) {
+        self.type_map.retain(|id, _| !nodes.contains(&id.0));
+        self.def_map.retain(|id, _| !nodes.contains(&id.0));
+        self.ref_names.retain(|id, _| !nodes.contains(&id.0));
+        self.receivers.retain(|id, _| !nodes.contains(&id.0));
+        self.node_spans.retain(|id, _| !nodes.contains(&id.0));
+        self.bindings.retain(|id, _| !nodes.contains(&id.0));
+        clear_range(&mut self.local_slots, &nodes);
+        clear_range(&mut self.field_slots, &nodes);
+    }
+
+    /// [New] 反向索引：定义位置 -> 所有引用它的节点 (按 NodeId 排序)
+    /// 用于 Find References / Rename，每次查询时从 def_map 现建，不需要随增量分析维护
+    pub fn reference_index(&self) -> HashMap<Location, Vec<NodeId>> {
+        let mut index: HashMap<Location, Vec<NodeId>> = HashMap::new();
+        for (id, loc) in &self.def_map {
+            index.entry(*loc).or_default().push(*id);
+        }
+        for ids in index.values_mut() {
+            ids.sort();
+        }
+        index
+    }
+
+    /// 查询字段访问的布局下标
+    #[inline]
+    pub fn field_slot_of(&self, id: NodeId) -> Option<u32> {
+        self.field_slots.get(id.0 as usize).copied().flatten()
+    }
+}
+
+/// 按 NodeId 下标的表：把区间内的记录清空 (不收缩)
+fn clear_range<T: Copy>(table: &mut [Option<T>], nodes: &Range<u32>) {
+    let end = (nodes.end as usize).min(table.len());
+    let start = (nodes.start as usize).min(end);
+    table[start..end].fill(None);
+}
+
+/// 局部变量在栈帧中的地址
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalSlot {
+    /// 向外跨越的函数帧层数 (0 表示当前帧)
+    pub depth: u32,
+    /// 帧内的槽位下标
+    pub slot: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Location {
     pub file_id: FileId,
     pub span: Span,
@@ -1,3 +1,4 @@
+use crate::diagnostic::{Diagnostic, Label};
 use crate::source::FileId;
 use crate::utils::Span;
 use std::fmt;
@@ -11,6 +12,10 @@ pub struct SemanticError {
     pub span: Span,
     /// 具体的错误类型（包含结构化数据）
     pub kind: SemanticErrorKind,
+    /// [New] 相关位置 (例如重复定义时的上一次定义)
+    pub labels: Vec<Label>,
+    /// [New] 和具体代码相关的修改建议 (例如 "did you mean ..."，优先于 kind 的固定建议)
+    pub help: Option<String>,
 }
 
 /// 语义错误的具体变体
@@ -78,6 +83,33 @@ pub enum SemanticErrorKind {
         method: String,
     },
 
+    /// [New] 没有实现 trait 要求的方法 (类名, trait 名, 方法名)
+    MissingTraitMethod {
+        table: String,
+        trait_name: String,
+        method: String,
+    },
+
+    /// [New] 实现的方法签名和 trait 里的不兼容 (trait 名, 方法名, 详情)
+    TraitMethodMismatch {
+        trait_name: String,
+        method: String,
+        reason: String,
+    },
+
+    /// [New] 冒号后面第二个起的类型不是 trait (类型名)
+    NotATrait(String),
+
+    /// [New] trait 不能实例化 (trait 名)
+    TraitInstantiation(String),
+
+    /// [New] 泛型实参不满足约束 <T: Animal> (泛型参数名, 约束, 实际类型)
+    GenericConstraintViolation {
+        param: String,
+        bound: String,
+        found: String,
+    },
+
     /// 方法重写不匹配 (方法名, 详情信息)
     MethodOverrideMismatch {
         method: String,
@@ -122,6 +154,28 @@ pub enum SemanticErrorKind {
     /// 类型不可迭代 (类型)
     TypeNotIterable(String),
 
+    /// [New] 元组下标越界 (下标, 元组长度)
+    TupleIndexOutOfBounds {
+        index: i64,
+        len: usize,
+    },
+
+    /// [New] 解构模式和值的形状不一致 (模式元素个数, 值的类型)
+    DestructureMismatch {
+        pattern_len: usize,
+        ty: String,
+    },
+
+    /// [New] 给不可变的绑定赋值 (绑定的种类, 名字)
+    /// 种类如 "immutable variable" / "parameter" / "constant" / "read-only field"
+    ImmutableAssignment {
+        kind: String,
+        name: String,
+    },
+
+    /// [New] const 的初值不是编译期常量 (常量名)
+    NonConstantInitializer(String),
+
     /// If/Else 分支类型不兼容 (Then类型, Else类型)
     IfBranchIncompatible {
         then_ty: String,
@@ -131,6 +185,51 @@ pub enum SemanticErrorKind {
     /// If 缺少 Else 分支且返回值非 Unit
     IfMissingElse(String),
 
+    /// [New] match 没有覆盖所有可能的值 (被匹配的类型, 没覆盖到的模式举例)
+    NonExhaustiveMatch {
+        ty: String,
+        missing: Vec<String>,
+    },
+
+    /// [New] 枚举没有这个变体 (枚举名, 变体名)
+    UnknownVariant {
+        enum_name: String,
+        variant: String,
+    },
+
+    /// [New] 模式和被匹配的值对不上 (原因)
+    InvalidPattern(String),
+
+    /// [New] 变体的载荷写得不对 (变体, 原因)
+    VariantFieldMismatch {
+        variant: String,
+        reason: String,
+    },
+
+    /// [New] match 分支类型不兼容 (之前分支的类型, 这个分支的类型)
+    MatchArmIncompatible {
+        expected: String,
+        found: String,
+    },
+
+    /// [New] Range<T> 的元素或范围端点不是 int (实际写的类型)
+    InvalidRangeElement(String),
+
+    /// [New] 省略端点的范围 (2.. / ..3) 用在了切片下标以外的地方
+    OpenEndedRange,
+
+    /// [New] 对没有字段的类型做成员访问 (类型名)
+    NoFields(String),
+
+    /// [New] 类型上找不到这个字段或方法 (类型名, 成员名)
+    MemberNotFound {
+        ty: String,
+        member: String,
+    },
+
+    /// [New] 调用泛型函数时没写类型实参 (泛型参数列表，例如 "T, U")
+    MissingTypeArguments(String),
+
     /// 循环条件必须是布尔值
     ConditionNotBool(String), // "If" or "While"
 
@@ -149,6 +248,124 @@ pub enum SemanticErrorKind {
     },
 }
 
+impl SemanticErrorKind {
+    /// [New] 稳定的错误码 (分段见 diagnostic.rs)
+    pub fn code(&self) -> &'static str {
+        match self {
+            SemanticErrorKind::UndefinedSymbol(_) => "E0100",
+            SemanticErrorKind::DuplicateDefinition(_) => "E0101",
+            SemanticErrorKind::GenericShadowing(_) => "E0102",
+            SemanticErrorKind::ReturnOutsideFunction => "E0103",
+            SemanticErrorKind::MemberNotFound { .. } => "E0104",
+
+            SemanticErrorKind::TypeMismatch { .. } => "E0200",
+            SemanticErrorKind::FieldTypeMismatch { .. } => "E0201",
+            SemanticErrorKind::ArrayElementTypeMismatch { .. } => "E0202",
+            SemanticErrorKind::InvalidUnaryOperand { .. } => "E0203",
+            SemanticErrorKind::InvalidBinaryOperand { .. } => "E0204",
+            SemanticErrorKind::InvalidAssignmentTarget(_) => "E0205",
+            SemanticErrorKind::InvalidIndexType(_) => "E0206",
+            SemanticErrorKind::TypeNotIndexable(_) => "E0207",
+            SemanticErrorKind::TypeNotIterable(_) => "E0208",
+            SemanticErrorKind::IfBranchIncompatible { .. } => "E0209",
+            SemanticErrorKind::IfMissingElse(_) => "E0210",
+            SemanticErrorKind::ConditionNotBool(_) => "E0211",
+            SemanticErrorKind::NotCallable(_) => "E0212",
+            SemanticErrorKind::ArgumentCountMismatch { .. } => "E0213",
+            SemanticErrorKind::InvalidCast { .. } => "E0214",
+            SemanticErrorKind::TupleIndexOutOfBounds { .. } => "E0215",
+            SemanticErrorKind::DestructureMismatch { .. } => "E0216",
+            SemanticErrorKind::ImmutableAssignment { .. } => "E0217",
+            SemanticErrorKind::NonConstantInitializer(_) => "E0218",
+            SemanticErrorKind::NonExhaustiveMatch { .. } => "E0219",
+            SemanticErrorKind::UnknownVariant { .. } => "E0220",
+            SemanticErrorKind::InvalidPattern(_) => "E0221",
+            SemanticErrorKind::VariantFieldMismatch { .. } => "E0222",
+            SemanticErrorKind::MatchArmIncompatible { .. } => "E0223",
+            SemanticErrorKind::InvalidRangeElement(_) => "E0224",
+            SemanticErrorKind::OpenEndedRange => "E0225",
+            SemanticErrorKind::NoFields(_) => "E0226",
+
+            SemanticErrorKind::ModuleNotFound(_) => "E0300",
+            SemanticErrorKind::InvalidModulePath(_) => "E0301",
+            SemanticErrorKind::CircularDependency(_) => "E0302",
+            SemanticErrorKind::FileIOError(_) => "E0303",
+            SemanticErrorKind::ModuleParseError(_) => "E0304",
+
+            SemanticErrorKind::CyclicInheritance(_) => "E0400",
+            SemanticErrorKind::InvalidParentType(_) => "E0401",
+            SemanticErrorKind::GenericArgumentCountMismatch { .. } => "E0402",
+            SemanticErrorKind::MissingAbstractImplementation { .. } => "E0403",
+            SemanticErrorKind::MethodOverrideMismatch { .. } => "E0404",
+            SemanticErrorKind::ConstraintViolation { .. } => "E0405",
+            SemanticErrorKind::MissingTraitMethod { .. } => "E0406",
+            SemanticErrorKind::TraitMethodMismatch { .. } => "E0407",
+            SemanticErrorKind::NotATrait(_) => "E0408",
+            SemanticErrorKind::TraitInstantiation(_) => "E0409",
+            SemanticErrorKind::GenericConstraintViolation { .. } => "E0410",
+            SemanticErrorKind::MissingTypeArguments(_) => "E0411",
+
+            SemanticErrorKind::Custom(_) => "E0900",
+        }
+    }
+
+    /// [New] 固定的修改建议 (和具体代码无关的那些)
+    fn help(&self) -> Option<&'static str> {
+        match self {
+            SemanticErrorKind::IfMissingElse(_) => {
+                Some("add an 'else' branch, or end the 'if' body with a statement")
+            }
+            SemanticErrorKind::ConditionNotBool(_) => {
+                Some("compare the value explicitly, e.g. 'x != 0'")
+            }
+            SemanticErrorKind::GenericShadowing(_) => Some("rename the inner generic parameter"),
+            SemanticErrorKind::CircularDependency(_) => {
+                Some("move the shared definitions into a module that both files can import")
+            }
+            SemanticErrorKind::MissingAbstractImplementation { .. } => {
+                Some("implement the method in this table or in one of its parents")
+            }
+            SemanticErrorKind::MissingTraitMethod { .. } => {
+                Some("implement the method in this class or in one of its parents")
+            }
+            SemanticErrorKind::NotATrait(_) => {
+                Some("a class has at most one parent class, and it must be listed first")
+            }
+            SemanticErrorKind::TraitInstantiation(_) => {
+                Some("create an instance of a class that implements the trait")
+            }
+            SemanticErrorKind::GenericConstraintViolation { .. } => {
+                Some("use a type that is, inherits from or implements the bound")
+            }
+            SemanticErrorKind::NonConstantInitializer(_) => Some(
+                "constants may only use literals, operators and other constants; use 'let' instead",
+            ),
+            SemanticErrorKind::NonExhaustiveMatch { .. } => {
+                Some("add an arm for each missing pattern, or a wildcard arm '_ =>'")
+            }
+            SemanticErrorKind::OpenEndedRange => {
+                Some("write both ends (e.g. '0..n'), or use the range as an index: 'arr[2..]'")
+            }
+            SemanticErrorKind::MissingTypeArguments(_) => {
+                Some("write the type arguments after the function name, e.g. 'func<int>(...)'")
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<&SemanticError> for Diagnostic {
+    fn from(err: &SemanticError) -> Self {
+        let mut diag = Diagnostic::error(err.kind.code(), err.kind.to_string())
+            .with_primary(err.file_id, err.span);
+        diag.secondary = err.labels.clone();
+        if let Some(help) = err.help.as_deref().or(err.kind.help()) {
+            diag = diag.with_help(help);
+        }
+        diag
+    }
+}
+
 // === 手动实现 Display，替代 thiserror ===
 
 impl fmt::Display for SemanticError {
@@ -246,6 +463,43 @@ impl fmt::Display for SemanticErrorKind {
                     table, method
                 )
             }
+            SemanticErrorKind::MissingTraitMethod {
+                table,
+                trait_name,
+                method,
+            } => {
+                write!(
+                    f,
+                    "Table '{}' does not implement method '{}' required by trait '{}'",
+                    table, method, trait_name
+                )
+            }
+            SemanticErrorKind::TraitMethodMismatch {
+                trait_name,
+                method,
+                reason,
+            } => {
+                write!(
+                    f,
+                    "Method '{}' does not match trait '{}': {}",
+                    method, trait_name, reason
+                )
+            }
+            SemanticErrorKind::NotATrait(name) => {
+                write!(f, "'{}' is not a trait", name)
+            }
+            SemanticErrorKind::TraitInstantiation(name) => {
+                write!(f, "Cannot instantiate trait '{}'", name)
+            }
+            SemanticErrorKind::GenericConstraintViolation {
+                param,
+                bound,
+                found,
+            } => write!(
+                f,
+                "Type '{}' does not satisfy constraint '{}: {}'",
+                found, param, bound
+            ),
             SemanticErrorKind::MethodOverrideMismatch { method, reason } => {
                 write!(f, "Method '{}' override mismatch: {}", method, reason)
             }
@@ -304,6 +558,30 @@ impl fmt::Display for SemanticErrorKind {
             SemanticErrorKind::TypeNotIterable(ty) => {
                 write!(f, "Type '{}' is not iterable", ty)
             }
+            SemanticErrorKind::TupleIndexOutOfBounds { index, len } => {
+                write!(
+                    f,
+                    "Tuple index {} is out of bounds for a tuple of {} elements",
+                    index, len
+                )
+            }
+            SemanticErrorKind::DestructureMismatch { pattern_len, ty } => {
+                write!(
+                    f,
+                    "Cannot destructure '{}' into a pattern of {} elements",
+                    ty, pattern_len
+                )
+            }
+            SemanticErrorKind::ImmutableAssignment { kind, name } => {
+                write!(f, "Cannot assign to {} '{}'", kind, name)
+            }
+            SemanticErrorKind::NonConstantInitializer(name) => {
+                write!(
+                    f,
+                    "Initializer of constant '{}' is not a compile-time constant",
+                    name
+                )
+            }
 
             // --- 控制流 ---
             SemanticErrorKind::IfBranchIncompatible { then_ty, else_ty } => {
@@ -313,6 +591,54 @@ impl fmt::Display for SemanticErrorKind {
                     then_ty, else_ty
                 )
             }
+            SemanticErrorKind::NonExhaustiveMatch { ty, missing } => {
+                write!(
+                    f,
+                    "Non-exhaustive match on '{}': {} not covered",
+                    ty,
+                    missing
+                        .iter()
+                        .map(|p| format!("'{}'", p))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+            SemanticErrorKind::UnknownVariant { enum_name, variant } => {
+                write!(f, "Enum '{}' has no variant '{}'", enum_name, variant)
+            }
+            SemanticErrorKind::InvalidPattern(reason) => write!(f, "Invalid pattern: {}", reason),
+            SemanticErrorKind::VariantFieldMismatch { variant, reason } => {
+                write!(f, "Variant '{}' {}", variant, reason)
+            }
+            SemanticErrorKind::MatchArmIncompatible { expected, found } => {
+                write!(
+                    f,
+                    "'match' arms have incompatible types: '{}' vs '{}'",
+                    expected, found
+                )
+            }
+            SemanticErrorKind::InvalidRangeElement(found) => {
+                write!(f, "Range elements must be 'int', but found '{}'", found)
+            }
+            SemanticErrorKind::OpenEndedRange => {
+                write!(
+                    f,
+                    "Open-ended ranges can only be used to slice arrays and strings"
+                )
+            }
+            SemanticErrorKind::NoFields(ty) => {
+                write!(f, "Type '{}' does not have fields", ty)
+            }
+            SemanticErrorKind::MemberNotFound { ty, member } => {
+                write!(f, "Member '{}' not found on type '{}'", member, ty)
+            }
+            SemanticErrorKind::MissingTypeArguments(params) => {
+                write!(
+                    f,
+                    "Generic function requires explicit type arguments for <{}>",
+                    params
+                )
+            }
             SemanticErrorKind::IfMissingElse(then_ty) => {
                 write!(
                     f,
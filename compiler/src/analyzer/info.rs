@@ -1,5 +1,5 @@
 use super::types::{FunctionSignature, Type};
-use crate::analyzer::TableId;
+use crate::analyzer::{LintWarning, TableId};
 use crate::ast::{Expression, MethodDefinition, TableDefinition};
 use crate::source::FileId;
 use crate::utils::{Span, Symbol};
@@ -16,6 +16,8 @@ pub struct FieldInfo {
     // 例如 name: str = "Animal"，这里存的就是 "Animal" 的 AST
     // 如果是 field: int，则是 None (或默认值 logic)
     pub value: Option<Expression>,
+    /// [New] `let` 声明的只读字段只能在 init 里通过 self 赋值
+    pub is_mut: bool,
 }
 
 // [New] 方法元数据
@@ -34,7 +36,14 @@ pub struct TableInfo {
     pub name: Symbol,
     pub file_id: FileId,
     pub parent: Option<Type>,
+    /// [New] 声明实现的 trait (Type::Table 或 GenericInstance)
+    /// collect 阶段是冒号后第二个起的类型，resolve_hierarchy 把指向 trait 的 parent 也挪进来
+    pub traits: Vec<Type>,
+    /// [New] trait 定义：没有字段，方法都是抽象的，不能实例化
+    pub is_trait: bool,
     pub generic_params: Vec<Symbol>,
+    /// [New] 泛型参数的约束 (class Box<T: Animal> 中的 T -> Animal)，没写约束的参数不在里面
+    pub generic_bounds: HashMap<Symbol, Type>,
 
     // [Changed] 从 HashMap<Symbol, Type> 变成 HashMap<Symbol, FieldInfo>
     pub fields: HashMap<Symbol, FieldInfo>,
@@ -42,9 +51,53 @@ pub struct TableInfo {
     // [Changed] 从 HashMap<Symbol, FunctionSignature> 变成 HashMap<Symbol, MethodInfo>
     pub methods: HashMap<Symbol, MethodInfo>,
 
+    // [New] 虚表 (collect 阶段只有自身方法，resolve_hierarchy 后合并父类)
+    // 父类的方法排在前面且下标不变，覆写只替换 owner，新方法按源码顺序追加
+    pub vtable: Vec<VTableEntry>,
+
+    // [New] 字段布局 (实例里 Vec<Value> 的下标顺序)
+    // 父类字段在前且下标不变，子类重新声明的同名字段沿用父类下标
+    pub field_layout: Vec<Symbol>,
+
+    pub defined_span: Span,
+}
+
+// [New] 虚表项：方法名 + 提供实现的类
+#[derive(Debug, Clone, PartialEq)]
+pub struct VTableEntry {
+    pub name: Symbol,
+    pub owner: TableId,
+}
+
+/// [New] 枚举的元数据
+#[derive(Debug, Clone)]
+pub struct EnumInfo {
+    pub name: Symbol,
+    pub file_id: FileId,
+    /// 变体按声明顺序排列，下标就是运行时的 tag
+    pub variants: Vec<VariantInfo>,
     pub defined_span: Span,
 }
 
+impl EnumInfo {
+    pub fn variant(&self, name: Symbol) -> Option<(usize, &VariantInfo)> {
+        self.variants
+            .iter()
+            .enumerate()
+            .find(|(_, v)| v.name == name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VariantInfo {
+    pub name: Symbol,
+    /// 载荷的类型 (单元变体为空)
+    pub fields: Vec<Type>,
+    /// 结构体变体的字段名，和 fields 一一对应；元组/单元变体为 None
+    pub field_names: Option<Vec<Symbol>>,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone)]
 pub struct ModuleInfo {
     pub file_id: FileId,
@@ -55,12 +108,18 @@ pub struct ModuleInfo {
     pub tables: HashMap<TableId, TableInfo>,
     pub functions: HashMap<Symbol, FunctionInfo>, // [New]
     pub globals: HashMap<Symbol, GlobalVarInfo>,  // [New]
+    pub enums: HashMap<TableId, EnumInfo>,        // [New]
 
     // 用于 AST 缓存 (如果需要保留给 Interpreter 用)
     pub ast_definitions: HashMap<Symbol, Rc<TableDefinition>>,
     // 还需要缓存顶层函数的 AST 吗？ Interpreter 可能需要。
     pub ast_functions: HashMap<Symbol, Rc<MethodDefinition>>,
     pub program: Rc<crate::ast::Program>,
+    /// [New] 作为入口检查时有错误 (LSP 仍然保留它，供补全查询)
+    /// import 它的模块不能复用这份结果，要重新分析以报告错误
+    pub has_errors: bool,
+    /// [New] 这个模块自身的 lint 警告 (模块被复用时仍然要报告)
+    pub warnings: Vec<LintWarning>,
 }
 
 #[derive(Debug, Clone)]
@@ -72,6 +131,13 @@ pub struct SymbolInfo {
     pub defined_span: Span,
     /// [新增] 定义该符号的文件 (因为可能跳转到另一个文件)
     pub defined_file: FileId,
+    /// [New] 局部变量在函数帧中的槽位 (全局符号为 None)
+    pub slot: Option<u32>,
+    /// [New] 能否被重新赋值：只有 var (或不带关键字) 定义的变量可以
+    /// 参数、self、let / const、函数、类和模块名都不行
+    pub is_mut: bool,
+    /// [New] const 定义的常量 (报错时区分 "常量" 和 "不可变变量")
+    pub is_const: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -102,5 +168,8 @@ pub struct GlobalVarInfo {
     pub ty: Type,
     pub span: Span,
     pub file_id: FileId,
-    pub is_const: bool, // 未来扩展
+    /// [Changed] `const X = ...` 定义的全局常量
+    pub is_const: bool,
+    /// [New] `let` / `const` 定义的全局变量不能再赋值
+    pub is_mut: bool,
 }
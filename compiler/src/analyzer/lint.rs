@@ -0,0 +1,697 @@
+// src/analyzer/lint.rs
+//
+// [New] Lint：检查通过之后再遍历一遍 AST，报告不影响运行的问题 (警告)
+// 引用关系直接用 check 阶段写进 SemanticDB 的 def_map / local_slots，不重新解析名字
+//
+// - unused_variable：局部变量定义后从未被读取 (`_` 开头的名字除外)
+// - unused_import：use 引入的模块没有被用到 (表达式和类型标注都算)
+// - unreachable_code：同一个代码块里 return / break / continue 之后的语句
+// - shadowed_variable：局部变量遮蔽了同一函数里外层的变量或参数
+// - dead_function：程序文件 (定义了 main 的文件不会被 import) 里从 main 到达不了的顶层函数
+
+use super::Analyzer;
+use crate::analyzer::Location;
+use crate::ast::*;
+use crate::context::Context;
+use crate::diagnostic::{Diagnostic, Label, Severity};
+use crate::lexer::is_ident_continue;
+use crate::source::FileId;
+use crate::utils::{Span, Symbol};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedVariable,
+    UnusedImport,
+    UnreachableCode,
+    ShadowedVariable,
+    DeadFunction,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 5] = [
+        Lint::UnusedVariable,
+        Lint::UnusedImport,
+        Lint::UnreachableCode,
+        Lint::ShadowedVariable,
+        Lint::DeadFunction,
+    ];
+
+    /// 命令行里使用的名字 (`--allow=unused_variable`)
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused_variable",
+            Lint::UnusedImport => "unused_import",
+            Lint::UnreachableCode => "unreachable_code",
+            Lint::ShadowedVariable => "shadowed_variable",
+            Lint::DeadFunction => "dead_function",
+        }
+    }
+
+    /// 稳定的警告码 (分段见 diagnostic.rs)
+    pub fn code(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "W0001",
+            Lint::UnusedImport => "W0002",
+            Lint::UnreachableCode => "W0003",
+            Lint::ShadowedVariable => "W0004",
+            Lint::DeadFunction => "W0005",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// 每个 lint 的级别 (默认都是 Warn)，以及 --deny-warnings
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    levels: HashMap<Lint, LintLevel>,
+    /// 所有警告都按错误处理 (被 allow 的 lint 除外)
+    pub deny_warnings: bool,
+}
+
+impl LintConfig {
+    pub fn set(&mut self, lint: Lint, level: LintLevel) {
+        self.levels.insert(lint, level);
+    }
+
+    pub fn level(&self, lint: Lint) -> LintLevel {
+        self.levels.get(&lint).copied().unwrap_or(LintLevel::Warn)
+    }
+}
+
+/// lint 报告的一条警告 (级别在 Driver 里按 LintConfig 决定)
+#[derive(Debug, Clone)]
+pub struct LintWarning {
+    pub lint: Lint,
+    pub file_id: FileId,
+    pub span: Span,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub help: Option<String>,
+}
+
+impl LintWarning {
+    /// 按配置转换成诊断；被 allow 时返回 None
+    pub fn to_diagnostic(&self, config: &LintConfig) -> Option<Diagnostic> {
+        let name = self.lint.name();
+        let (severity, note) = match config.level(self.lint) {
+            LintLevel::Allow => return None,
+            LintLevel::Deny => (Severity::Error, format!("'{}' is set to deny", name)),
+            LintLevel::Warn if config.deny_warnings => (
+                Severity::Error,
+                format!("'{}' is denied by --deny-warnings", name),
+            ),
+            LintLevel::Warn => (
+                Severity::Warning,
+                format!(
+                    "'{}' is on by default; use --allow={} to silence it",
+                    name, name
+                ),
+            ),
+        };
+        let mut diag = Diagnostic::new(severity, self.lint.code(), self.message.clone())
+            .with_primary(self.file_id, self.span)
+            .with_note(note);
+        diag.secondary = self.labels.clone();
+        if let Some(help) = &self.help {
+            diag = diag.with_help(help.clone());
+        }
+        Some(diag)
+    }
+}
+
+impl<'a> Analyzer<'a> {
+    /// 对检查过的程序跑一遍 lint，结果追加到 self.warnings
+    pub fn lint_program(&mut self, program: &Program) {
+        let mut linter = Linter::new(self.ctx, self.current_file_id);
+        linter.program(program);
+        self.warnings.extend(linter.finish(program));
+    }
+}
+
+/// 一个函数里的局部变量定义 (遮蔽检查用)：名字 -> 定义位置
+type LocalScope = Vec<(Symbol, Location)>;
+
+struct Linter<'c> {
+    ctx: &'c Context,
+    file_id: FileId,
+    /// 被读取过的定义 (定义位置, 读取处的区间)
+    reads: Vec<(Location, Span)>,
+    /// 类型标注里以 `lib.Animal` 形式用到的模块名
+    type_modules: HashSet<Symbol>,
+    /// 所有局部变量定义：(名字, 定义位置)
+    locals: Vec<(Symbol, Location)>,
+    /// 当前函数的作用域栈
+    scopes: Vec<LocalScope>,
+    warnings: Vec<LintWarning>,
+}
+
+impl<'c> Linter<'c> {
+    fn new(ctx: &'c Context, file_id: FileId) -> Self {
+        Self {
+            ctx,
+            file_id,
+            reads: Vec::new(),
+            type_modules: HashSet::new(),
+            locals: Vec::new(),
+            scopes: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn program(&mut self, program: &Program) {
+        for item in &program.definitions {
+            match item {
+                TopLevelItem::Function(func) => self.function(func),
+                TopLevelItem::Field(field) => self.field(field),
+                TopLevelItem::Table(def) => {
+                    for proto in def.prototype.iter().chain(&def.traits) {
+                        self.type_ref(proto);
+                    }
+                    self.generics(&def.generics);
+                    for item in &def.items {
+                        match item {
+                            TableItem::Field(field) => self.field(field),
+                            TableItem::Method(method) => self.function(method),
+                        }
+                    }
+                }
+                TopLevelItem::Enum(def) => {
+                    for variant in &def.variants {
+                        match &variant.kind {
+                            VariantKind::Unit => {}
+                            VariantKind::Tuple(types) => {
+                                for ty in types {
+                                    self.type_ref(ty);
+                                }
+                            }
+                            VariantKind::Struct(fields) => {
+                                for (_, ty) in fields {
+                                    self.type_ref(ty);
+                                }
+                            }
+                        }
+                    }
+                }
+                TopLevelItem::Use(_) => {}
+            }
+        }
+    }
+
+    /// 汇总：未使用的变量 / import，以及没被调用的函数
+    fn finish(mut self, program: &Program) -> Vec<LintWarning> {
+        let read: HashSet<Location> = self.reads.iter().map(|(loc, _)| *loc).collect();
+
+        for (name, def) in std::mem::take(&mut self.locals) {
+            let name_str = self.ctx.resolve_symbol(name);
+            if read.contains(&def) || name_str.starts_with('_') {
+                continue;
+            }
+            let span = self.name_span(def.span, name);
+            self.warn(
+                Lint::UnusedVariable,
+                span,
+                format!("Unused variable '{}'", name_str),
+                Some(format!(
+                    "if this is intentional, prefix it with an underscore: '_{}'",
+                    name_str
+                )),
+            );
+        }
+
+        let mut is_program = false;
+        for item in &program.definitions {
+            match item {
+                TopLevelItem::Use(stmt) => {
+                    let Some(name) = stmt.data.alias.or(stmt.data.path.last().copied()) else {
+                        continue;
+                    };
+                    let def = Location {
+                        file_id: self.file_id,
+                        span: stmt.span,
+                    };
+                    if read.contains(&def) || self.type_modules.contains(&name) {
+                        continue;
+                    }
+                    self.warn(
+                        Lint::UnusedImport,
+                        stmt.span,
+                        format!("Unused import '{}'", self.ctx.resolve_symbol(name)),
+                        Some("remove the 'use' statement".to_string()),
+                    );
+                }
+                TopLevelItem::Function(func) if self.ctx.resolve_symbol(func.name) == "main" => {
+                    is_program = true;
+                }
+                _ => {}
+            }
+        }
+
+        // 只有程序文件里的函数算私有：库模块的函数可能被别的文件 import
+        if is_program {
+            self.dead_functions(program);
+        }
+
+        self.warnings.sort_by_key(|w| w.span.start);
+        self.warnings
+    }
+
+    /// [Changed] 从 main 出发沿调用关系找能到达的顶层函数，到不了的都报告
+    /// (只是互相调用 / 递归调用自己的函数也算没用)
+    /// 入口：main、`_` 开头的函数，以及顶层函数之外 (方法、顶层变量) 用到的函数
+    fn dead_functions(&mut self, program: &Program) {
+        let funcs: Vec<&MethodDefinition> = program
+            .definitions
+            .iter()
+            .filter_map(|item| match item {
+                TopLevelItem::Function(func) => Some(func),
+                _ => None,
+            })
+            .collect();
+        let index: HashMap<Location, usize> = funcs
+            .iter()
+            .enumerate()
+            .map(|(i, func)| (self.location(func.span), i))
+            .collect();
+
+        // 调用边：调用处所在的顶层函数 -> 被调用的函数 (None 表示不在任何顶层函数里)
+        let mut callees: HashMap<Option<usize>, Vec<usize>> = HashMap::new();
+        let mut has_caller = vec![false; funcs.len()];
+        for (loc, site) in &self.reads {
+            let Some(&callee) = index.get(loc) else {
+                continue;
+            };
+            let caller = funcs
+                .iter()
+                .position(|f| f.span.start <= site.start && site.end <= f.span.end);
+            if caller != Some(callee) {
+                has_caller[callee] = true;
+            }
+            callees.entry(caller).or_default().push(callee);
+        }
+
+        let mut reached = vec![false; funcs.len()];
+        let mut stack: Vec<usize> = callees.get(&None).cloned().unwrap_or_default();
+        stack.extend(funcs.iter().enumerate().filter_map(|(i, func)| {
+            let name = self.ctx.resolve_symbol(func.name);
+            (name == "main" || name.starts_with('_')).then_some(i)
+        }));
+        while let Some(i) = stack.pop() {
+            if !std::mem::replace(&mut reached[i], true) {
+                stack.extend(callees.get(&Some(i)).into_iter().flatten());
+            }
+        }
+
+        for (i, func) in funcs.iter().enumerate() {
+            if reached[i] {
+                continue;
+            }
+            let name = self.ctx.resolve_symbol(func.name);
+            let message = if has_caller[i] {
+                format!("Function '{}' is never reached from 'main'", name)
+            } else {
+                format!("Function '{}' is never called", name)
+            };
+            let span = self.name_span(func.span, func.name);
+            self.warn(Lint::DeadFunction, span, message, None);
+        }
+    }
+
+    fn warn(&mut self, lint: Lint, span: Span, message: String, help: Option<String>) {
+        self.warnings.push(LintWarning {
+            lint,
+            file_id: self.file_id,
+            span,
+            message,
+            labels: Vec::new(),
+            help,
+        });
+    }
+
+    fn location(&self, span: Span) -> Location {
+        Location {
+            file_id: self.file_id,
+            span,
+        }
+    }
+
+    /// 定义区间里名字本身的位置 (第一个完整匹配的单词)，找不到时用整个区间
+    fn name_span(&self, span: Span, name: Symbol) -> Span {
+        let src = &self.ctx.source_manager.get_file(self.file_id).src;
+        let text = src.get(span.start..span.end).unwrap_or("");
+        let name = self.ctx.resolve_symbol(name);
+        let is_word = |c: Option<char>| c.is_some_and(is_ident_continue);
+        for (pos, _) in text.match_indices(name) {
+            let end = pos + name.len();
+            if !is_word(text[..pos].chars().next_back()) && !is_word(text[end..].chars().next()) {
+                return Span::new(span.start + pos, span.start + end);
+            }
+        }
+        span
+    }
+
+    // --- 遍历 ---
+
+    fn function(&mut self, func: &MethodDefinition) {
+        self.generics(&func.generics);
+        if let Some(ret) = &func.return_type {
+            self.type_ref(ret);
+        }
+        // 参数是函数的最外层作用域
+        let params = func
+            .params
+            .iter()
+            .map(|p| {
+                self.type_ref(&p.type_annotation);
+                (p.name, self.location(p.span))
+            })
+            .collect();
+        self.scopes = vec![params];
+        if let Some(body) = &func.body {
+            self.block(body);
+        }
+        self.scopes.clear();
+    }
+
+    fn field(&mut self, field: &FieldDefinition) {
+        if let Some(ty) = &field.type_annotation {
+            self.type_ref(ty);
+        }
+        if let Some(value) = &field.value {
+            self.expression(value);
+        }
+    }
+
+    fn generics(&mut self, generics: &[GenericParam]) {
+        for g in generics {
+            if let Some(constraint) = &g.constraint {
+                self.type_ref(constraint);
+            }
+        }
+    }
+
+    fn type_ref(&mut self, ty: &TypeRef) {
+        match &ty.data {
+            TypeRefData::Named(_) => {}
+            TypeRefData::GenericInstance { args, .. } => {
+                for arg in args {
+                    self.type_ref(arg);
+                }
+            }
+            TypeRefData::Structural(params) => {
+                for p in params {
+                    self.type_ref(&p.type_annotation);
+                }
+            }
+            TypeRefData::Member { module, .. } => {
+                self.type_modules.insert(*module);
+            }
+            TypeRefData::Array(inner) => self.type_ref(inner),
+            TypeRefData::Tuple(items) => {
+                for item in items {
+                    self.type_ref(item);
+                }
+            }
+            TypeRefData::Function { params, ret } => {
+                for param in params {
+                    self.type_ref(param);
+                }
+                if let Some(ret) = ret {
+                    self.type_ref(ret);
+                }
+            }
+        }
+    }
+
+    /// match 模式里写出来的枚举类型 (`lib.Shape.Empty` 用到了 lib)
+    fn pattern(&mut self, pattern: &Pattern) {
+        match &pattern.data {
+            PatternData::Tuple(items) => {
+                for item in items {
+                    self.pattern(item);
+                }
+            }
+            PatternData::Variant {
+                qualifier, args, ..
+            } => {
+                if let Some(qualifier) = qualifier {
+                    self.type_ref(qualifier);
+                }
+                match args {
+                    VariantPatternArgs::None => {}
+                    VariantPatternArgs::Tuple(items) => {
+                        for item in items {
+                            self.pattern(item);
+                        }
+                    }
+                    VariantPatternArgs::Struct(fields) => {
+                        for (_, item) in fields {
+                            self.pattern(item);
+                        }
+                    }
+                }
+            }
+            PatternData::Identifier(_) | PatternData::Wildcard | PatternData::Literal(_) => {}
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.scopes.push(Vec::new());
+        let mut diverged: Option<Span> = None;
+        let mut reported = false;
+        for stmt in &block.statements {
+            if let Some(exit) = diverged
+                && !reported
+            {
+                reported = true;
+                let mut warning = LintWarning {
+                    lint: Lint::UnreachableCode,
+                    file_id: self.file_id,
+                    span: stmt.span,
+                    message: "Unreachable code".to_string(),
+                    labels: Vec::new(),
+                    help: None,
+                };
+                warning.labels.push(Label::new(
+                    self.file_id,
+                    exit,
+                    "any code following this expression is unreachable",
+                ));
+                self.warnings.push(warning);
+            }
+            self.expression(stmt);
+            if matches!(
+                stmt.data,
+                ExpressionData::Return(_) | ExpressionData::Break { .. } | ExpressionData::Continue
+            ) {
+                diverged.get_or_insert(stmt.span);
+            }
+        }
+        self.scopes.pop();
+    }
+
+    /// 定义一个局部变量：记录下来，并检查是否遮蔽了外层的变量
+    fn define_local(&mut self, name: Symbol, def: Location) {
+        let name_str = self.ctx.resolve_symbol(name);
+        let previous = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.iter().rev().find(|(n, _)| *n == name))
+            .map(|(_, loc)| *loc);
+        if let Some(previous) = previous
+            && !name_str.starts_with('_')
+        {
+            let span = self.name_span(def.span, name);
+            let prev_span = self.name_span(previous.span, name);
+            self.warnings.push(LintWarning {
+                lint: Lint::ShadowedVariable,
+                file_id: self.file_id,
+                span,
+                message: format!("Variable '{}' shadows an earlier definition", name_str),
+                labels: vec![Label::new(
+                    previous.file_id,
+                    prev_span,
+                    format!("'{}' is first defined here", name_str),
+                )],
+                help: Some("rename one of the variables".to_string()),
+            });
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push((name, def));
+        }
+        self.locals.push((name, def));
+    }
+
+    fn read(&mut self, expr: &Expression) {
+        if let Some(loc) = self.ctx.db.def_map.get(&expr.id) {
+            self.reads.push((*loc, expr.span));
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match &expr.data {
+            ExpressionData::Literal(_) | ExpressionData::Continue => {}
+            ExpressionData::Identifier(_) => self.read(expr),
+            ExpressionData::FieldAccess { target, .. } => self.expression(target),
+            ExpressionData::Binary { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            ExpressionData::Unary { expr, .. } => self.expression(expr),
+            ExpressionData::If {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                self.expression(condition);
+                self.block(then_block);
+                if let Some(else_block) = else_block {
+                    self.block(else_block);
+                }
+            }
+            ExpressionData::For {
+                pattern,
+                iterable,
+                body,
+            } => {
+                self.expression(iterable);
+                self.scopes.push(Vec::new());
+                for (binding, name) in pattern.bindings() {
+                    if self.ctx.db.slot_of(binding.id).is_some() {
+                        self.define_local(name, self.location(binding.span));
+                    }
+                }
+                self.block(body);
+                self.scopes.pop();
+            }
+            ExpressionData::Range { start, end, .. } => {
+                for bound in [start, end].into_iter().flatten() {
+                    self.expression(bound);
+                }
+            }
+            ExpressionData::Array(items) | ExpressionData::Tuple(items) => {
+                for item in items {
+                    self.expression(item);
+                }
+            }
+            ExpressionData::Index { target, index } => {
+                self.expression(target);
+                self.expression(index);
+            }
+            ExpressionData::Call {
+                callee,
+                generic_args,
+                args,
+            } => {
+                self.expression(callee);
+                for ty in generic_args {
+                    self.type_ref(ty);
+                }
+                for arg in args {
+                    self.expression(&arg.value);
+                }
+            }
+            ExpressionData::Block(block) => self.block(block),
+            ExpressionData::Return(value) | ExpressionData::Break { value } => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            ExpressionData::While { condition, body } => {
+                self.expression(condition);
+                self.block(body);
+            }
+            ExpressionData::VariableDefinition { name, ty, init, .. } => {
+                self.expression(init);
+                if let Some(ty) = ty {
+                    self.type_ref(ty);
+                }
+                if self.ctx.db.slot_of(expr.id).is_some() {
+                    self.define_local(*name, self.location(expr.span));
+                }
+            }
+            ExpressionData::Assign { op, target, value } => {
+                self.expression(value);
+                match &target.data {
+                    // `x = ...` 只写不读；第一次赋值 (没有记录定义位置) 就是定义
+                    ExpressionData::Identifier(name) if *op == AssignOp::Assign => {
+                        if !self.ctx.db.def_map.contains_key(&target.id)
+                            && self.ctx.db.slot_of(target.id).is_some()
+                        {
+                            self.define_local(*name, self.location(target.span));
+                        }
+                    }
+                    // `x += 1` 读取了旧值
+                    _ => self.expression(target),
+                }
+            }
+            // 和 `x = ...` 一样，没有记录定义位置的名字是新定义的变量
+            ExpressionData::Destructure { pattern, value, .. } => {
+                self.expression(value);
+                for (binding, name) in pattern.bindings() {
+                    if !self.ctx.db.def_map.contains_key(&binding.id)
+                        && self.ctx.db.slot_of(binding.id).is_some()
+                    {
+                        self.define_local(name, self.location(binding.span));
+                    }
+                }
+            }
+            ExpressionData::Cast { expr, target_type } => {
+                self.expression(expr);
+                self.type_ref(target_type);
+            }
+            // 和 for 一样，每个分支的绑定只在分支里可见
+            ExpressionData::Match { scrutinee, arms } => {
+                self.expression(scrutinee);
+                for arm in arms {
+                    self.pattern(&arm.pattern);
+                    self.scopes.push(Vec::new());
+                    for (binding, name) in arm.pattern.bindings() {
+                        if self.ctx.db.slot_of(binding.id).is_some() {
+                            self.define_local(name, self.location(binding.span));
+                        }
+                    }
+                    self.block(&arm.body);
+                    self.scopes.pop();
+                }
+            }
+            ExpressionData::VariantLiteral { target, fields } => {
+                self.expression(target);
+                for (_, value) in fields {
+                    self.expression(value);
+                }
+            }
+            // 匿名函数：参数和具名函数一样不参与遮蔽检查；
+            // 函数体读到的外层变量照常记为读取
+            ExpressionData::Lambda(def) => {
+                if let Some(ret) = &def.return_type {
+                    self.type_ref(ret);
+                }
+                let params = def
+                    .params
+                    .iter()
+                    .map(|p| {
+                        self.type_ref(&p.type_annotation);
+                        (p.name, self.location(p.span))
+                    })
+                    .collect();
+                self.scopes.push(params);
+                if let Some(body) = &def.body {
+                    self.block(body);
+                }
+                self.scopes.pop();
+            }
+        }
+    }
+}
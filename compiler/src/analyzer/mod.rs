@@ -3,6 +3,7 @@ mod collect;
 mod db;
 mod errors;
 mod info;
+mod lint;
 mod path;
 mod resolve;
 mod scope;
@@ -10,22 +11,29 @@ mod semantics;
 mod tableid;
 mod types;
 
-pub use db::{Location, SemanticDB};
+pub use db::{LocalSlot, Location, SemanticDB};
 pub use errors::{SemanticError, SemanticErrorKind};
-pub use info::{FieldInfo, MethodInfo, ModuleInfo, SymbolInfo, TableInfo};
+pub use info::{
+    EnumInfo, FieldInfo, MethodInfo, ModuleInfo, SymbolInfo, TableInfo, VTableEntry, VariantInfo,
+};
+pub use lint::{Lint, LintConfig, LintLevel, LintWarning};
 pub use path::resolve_module_path;
 pub use scope::ScopeManager;
 pub use tableid::TableId;
-pub use types::{FunctionSignature, Type};
+pub use types::{ClassHierarchy, FunctionSignature, Type};
 
 use crate::analyzer::info::{FunctionInfo, GlobalVarInfo, SymbolKind};
 use crate::ast::Program;
 use crate::context::Context;
+use crate::diagnostic::Label;
 use crate::source::FileId;
 use crate::utils::{NodeId, Span, Symbol};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+/// [New] 一处待检查约束的泛型实例 (区间, 泛型类, 实参, 所在位置可见的约束)
+type GenericUse = (Span, TableId, Vec<Type>, HashMap<Symbol, Type>);
+
 pub struct Analyzer<'a> {
     pub ctx: &'a mut Context,
     pub scopes: ScopeManager,
@@ -35,16 +43,33 @@ pub struct Analyzer<'a> {
     pub tables: HashMap<TableId, TableInfo>,
     pub functions: HashMap<Symbol, FunctionInfo>,
     pub globals: HashMap<Symbol, GlobalVarInfo>,
+    /// [New] 本文件定义的枚举
+    pub enums: HashMap<TableId, EnumInfo>,
 
     /// 收集到的错误 (非致命)
     pub errors: Vec<SemanticError>,
+    /// [New] lint 报告的警告 (见 lint.rs)
+    pub warnings: Vec<LintWarning>,
 
     // [New] 当前正在检查的函数的期望返回类型
     // 进入 method 时设置，退出时恢复
     pub current_return_type: Option<Type>,
+    /// [New] 正在检查 init 方法 (只读字段只能在这里通过 self 赋值)
+    pub in_initializer: bool,
 
     pub current_file_path: PathBuf,
     pub current_file_id: FileId,
+
+    /// [New] 类型标注里引用的类 (TypeRef 节点, 区间, 类)
+    /// collect 阶段解析标注时后面的类可能还没收集，所以先记下来，检查结束后统一记录定义位置
+    pub type_refs: Vec<(NodeId, Span, TableId)>,
+
+    /// [New] 当前位置可见的泛型参数约束 (所在类的 + 所在方法的)
+    /// 成员访问和类型兼容性检查把受约束的 T 当作它的约束类型
+    pub generic_bounds: HashMap<Symbol, Type>,
+    /// [New] 类型标注里的泛型实例
+    /// 和 type_refs 一样，collect 阶段约束还没收集全，检查结束后统一验证实参是否满足约束
+    pub generic_uses: Vec<GenericUse>,
 }
 
 impl<'a> Analyzer<'a> {
@@ -58,10 +83,16 @@ impl<'a> Analyzer<'a> {
             tables: Default::default(),
             functions: HashMap::new(),
             globals: HashMap::new(),
+            enums: HashMap::new(),
             errors: Vec::new(),
+            warnings: Vec::new(),
             current_return_type: None,
+            in_initializer: false,
             current_file_id: file_id, // 直接存
             current_file_path,        // 从 ID 反查
+            type_refs: Vec::new(),
+            generic_bounds: HashMap::new(),
+            generic_uses: Vec::new(),
         }
     }
 
@@ -85,31 +116,84 @@ impl<'a> Analyzer<'a> {
             file_id: self.current_file_id,
             span,
             kind,
+            labels: Vec::new(),
+            help: None,
+        });
+    }
+
+    /// [New] 报告其它文件里的错误 (例如被 import 的模块解析失败，区间属于那个文件)
+    pub fn report_in(&mut self, file_id: FileId, span: Span, kind: SemanticErrorKind) {
+        self.errors.push(SemanticError {
+            file_id,
+            span,
+            kind,
+            labels: Vec::new(),
+            help: None,
         });
     }
 
+    /// [New] 报告错误并附上修改建议 (例如 "did you mean ...")
+    pub fn report_with_help(&mut self, span: Span, kind: SemanticErrorKind, help: Option<String>) {
+        self.report(span, kind);
+        if let Some(err) = self.errors.last_mut() {
+            err.help = help;
+        }
+    }
+
+    /// [New] 报告重复定义，附上之前那次定义的位置
+    pub fn report_duplicate(&mut self, name: Symbol, span: Span, previous: Option<(FileId, Span)>) {
+        let name_str = self.ctx.resolve_symbol(name).to_string();
+        self.report(
+            span,
+            SemanticErrorKind::DuplicateDefinition(name_str.clone()),
+        );
+        if let (Some((file_id, prev_span)), Some(err)) = (previous, self.errors.last_mut()) {
+            err.labels.push(Label::new(
+                file_id,
+                prev_span,
+                format!("previous definition of '{}' here", name_str),
+            ));
+        }
+    }
+
+    /// [New] 当前作用域里同名符号的定义位置 (scopes.define 失败后用来报重复定义)
+    fn previous_definition(&self, name: Symbol) -> Option<(FileId, Span)> {
+        self.scopes
+            .resolve_current(name)
+            .map(|info| (info.defined_file, info.defined_span))
+    }
+
     /// 注册内置函数到当前作用域（全局作用域）
+    /// 运行时实现见 interpreter::native::GLOBAL_NATIVES
     fn register_builtins(&mut self) {
-        let print_sym = self.ctx.intern("print");
-
-        let print_type = Type::Function {
-            generic_params: vec![],
-            params: vec![Type::Any],
-            ret: Box::new(Type::Unit),
-        };
+        let builtins = [
+            ("print", vec![Type::Any], Type::Unit),
+            // [New] 循环回收器
+            ("gc_collect", vec![], Type::Int),
+            ("gc_stats", vec![], Type::Array(Box::new(Type::Int))),
+        ];
 
         // [Fix] 构造“空”位置信息
         // 1. 使用 Span::default() (即 0..0)
         let dummy_span = crate::utils::Span::default();
 
-        let _ = self.scopes.define(
-            print_sym,
-            print_type,
-            SymbolKind::Method, // 或 SymbolKind::Function
-            dummy_span,         // <--- 这里的 Span 是空的
-            FileId::BUILTIN,    // <--- 这里的文件 ID 是特殊的
-            false,              // 不允许覆盖
-        );
+        for (name, params, ret) in builtins {
+            let sym = self.ctx.intern(name);
+            let ty = Type::Function {
+                generic_params: vec![],
+                params,
+                ret: Box::new(ret),
+            };
+
+            let _ = self.scopes.define(
+                sym,
+                ty,
+                SymbolKind::Method, // 或 SymbolKind::Function
+                dummy_span,         // <--- 这里的 Span 是空的
+                FileId::BUILTIN,    // <--- 这里的文件 ID 是特殊的
+                false,              // 不允许覆盖
+            );
+        }
     }
 
     /// 查找 Table 定义
@@ -130,13 +214,160 @@ impl<'a> Analyzer<'a> {
         None
     }
 
+    /// [New] 借用查找 Table 定义 (本文件或已加载模块)，不克隆
+    fn table_info(&self, id: TableId) -> Option<&TableInfo> {
+        if let Some(info) = self.tables.get(&id) {
+            return Some(info);
+        }
+        let path = self.ctx.source_manager.get_file_path(id.file_id())?;
+        self.ctx.modules.get(path)?.tables.get(&id)
+    }
+
+    /// [New] 查找枚举定义 (本文件或已加载模块)
+    fn enum_info(&self, id: TableId) -> Option<&EnumInfo> {
+        if let Some(info) = self.enums.get(&id) {
+            return Some(info);
+        }
+        let path = self.ctx.source_manager.get_file_path(id.file_id())?;
+        self.ctx.modules.get(path)?.enums.get(&id)
+    }
+
+    /// [New] 字段在类布局中的下标 (不克隆 TableInfo)
+    fn field_index(&self, id: TableId, field: Symbol) -> Option<u32> {
+        self.table_info(id)?
+            .field_layout
+            .iter()
+            .position(|f| *f == field)
+            .map(|idx| idx as u32)
+    }
+
+    /// [New] 记录字段访问节点的字段下标
+    /// 子类布局以父类布局为前缀，所以按静态类型算出的下标对运行时的子类实例同样有效
+    pub fn record_field_slot(&mut self, site: NodeId, target_ty: &Type, field: Symbol) {
+        let table_id = match target_ty {
+            Type::Table(id) | Type::GenericInstance { base: id, .. } => *id,
+            _ => return,
+        };
+        if let Some(idx) = self.field_index(table_id, field) {
+            self.ctx.db.record_field_slot(site, idx);
+        }
+    }
+
+    /// [LSP Helper] 记录成员访问点号左边的类型 (补全用)
+    pub fn record_receiver(&mut self, site: NodeId, target_ty: &Type) {
+        self.ctx.db.receivers.insert(site, target_ty.clone());
+    }
+
+    /// [LSP Helper] 成员 (target.field) 的定义位置
+    /// 继承来的字段/方法沿父类链找到真正声明它的类，这样能跳转到父类所在的文件
+    fn member_location(&self, target_ty: &Type, field: Symbol) -> Option<Location> {
+        let mut id = match target_ty {
+            Type::Table(id) | Type::GenericInstance { base: id, .. } => *id,
+            Type::Module(file_id) => return self.export_location(*file_id, field),
+            _ => return None,
+        };
+        let member_span = |info: &TableInfo| {
+            info.fields
+                .get(&field)
+                .map(|f| f.span)
+                .or_else(|| info.methods.get(&field).map(|m| m.span))
+        };
+        loop {
+            let info = self.table_info(id)?;
+            let span = member_span(info)?;
+            // 父类里有同一个定义 (区间相同)：说明是继承来的，继续往上找
+            let parent_id = match &info.parent {
+                Some(Type::Table(pid)) | Some(Type::GenericInstance { base: pid, .. }) => {
+                    Some(*pid)
+                }
+                _ => None,
+            };
+            match parent_id {
+                Some(pid)
+                    if self
+                        .table_info(pid)
+                        .and_then(member_span)
+                        .is_some_and(|s| s == span) =>
+                {
+                    id = pid
+                }
+                _ => {
+                    return Some(Location {
+                        file_id: info.file_id,
+                        span,
+                    });
+                }
+            }
+        }
+    }
+
+    /// [LSP Helper] 模块导出成员 (类 / 函数 / 全局变量) 的定义位置
+    fn export_location(&self, file_id: FileId, name: Symbol) -> Option<Location> {
+        let path = self.ctx.source_manager.get_file_path(file_id)?;
+        let module = self.ctx.modules.get(path)?;
+        if let Some(info) = module.tables.get(&TableId(file_id, name)) {
+            return Some(Location {
+                file_id,
+                span: info.defined_span,
+            });
+        }
+        if let Some(info) = module.enums.get(&TableId(file_id, name)) {
+            return Some(Location {
+                file_id,
+                span: info.defined_span,
+            });
+        }
+        if let Some(info) = module.functions.get(&name) {
+            return Some(Location {
+                file_id: info.file_id,
+                span: info.span,
+            });
+        }
+        module.globals.get(&name).map(|info| Location {
+            file_id: info.file_id,
+            span: info.span,
+        })
+    }
+
+    /// [LSP Helper] 类型标注引用的类 (或枚举)：记录定义位置 (check_program 结束时调用)
+    pub fn record_type_refs(&mut self) {
+        for (node, span, id) in std::mem::take(&mut self.type_refs) {
+            let (file_id, def_span, ty) = if let Some(info) = self.table_info(id) {
+                (info.file_id, info.defined_span, Type::Table(id))
+            } else if let Some(info) = self.enum_info(id) {
+                (info.file_id, info.defined_span, Type::Enum(id))
+            } else {
+                continue;
+            };
+            self.record_def(node, id.1, file_id, def_span);
+            self.record_node(node, span, ty);
+        }
+    }
+
+    /// [LSP Helper] 记录一个表达式的类型和源码区间
+    pub fn record_node(&mut self, node_id: NodeId, span: Span, ty: Type) {
+        self.ctx.db.node_spans.insert(node_id, span);
+        self.ctx.db.type_map.insert(node_id, ty);
+    }
+
     /// [LSP Helper] 记录一个表达式的类型
     pub fn record_type(&mut self, node_id: NodeId, ty: Type) {
         self.ctx.db.type_map.insert(node_id, ty);
     }
 
-    /// [LSP Helper] 记录一个引用的定义位置
-    pub fn record_def(&mut self, usage_id: NodeId, def_file: FileId, def_span: Span) {
+    /// [LSP Helper] 记录声明节点绑定的名字和类型 (Hover 声明处的名字用)
+    pub fn record_binding(&mut self, node_id: NodeId, name: Symbol, span: Span, ty: Type) {
+        self.ctx.db.bindings.insert(node_id, (name, span, ty));
+    }
+
+    /// [New] 记录局部变量节点的栈帧地址 (供解释器按槽位存取)
+    pub fn record_slot(&mut self, node_id: NodeId, slot: LocalSlot) {
+        self.ctx.db.record_slot(node_id, slot);
+    }
+
+    /// [LSP Helper] 记录一个引用 (名字为 name) 的定义位置
+    pub fn record_def(&mut self, usage_id: NodeId, name: Symbol, def_file: FileId, def_span: Span) {
+        self.ctx.db.ref_names.insert(usage_id, name);
         self.ctx.db.def_map.insert(
             usage_id,
             Location {
@@ -146,3 +377,28 @@ impl<'a> Analyzer<'a> {
         );
     }
 }
+
+/// [New] 子类型关系用到的类层次：查本文件的类，找不到再查已加载的模块
+/// 所以 `a: lib.Animal = Dog()` 和本文件内的继承走的是同一条路径
+impl ClassHierarchy for Analyzer<'_> {
+    fn supertypes(&self, id: TableId, args: &[Type]) -> Vec<Type> {
+        let Some(info) = self.table_info(id) else {
+            return Vec::new();
+        };
+        let mapping: HashMap<Symbol, Type> = info
+            .generic_params
+            .iter()
+            .copied()
+            .zip(args.iter().cloned())
+            .collect();
+        info.parent
+            .iter()
+            .chain(&info.traits)
+            .map(|ty| ty.substitute(&mapping))
+            .collect()
+    }
+
+    fn generic_bound(&self, param: Symbol) -> Option<Type> {
+        self.generic_bounds.get(&param).cloned()
+    }
+}
@@ -40,6 +40,9 @@ impl<'a> Analyzer<'a> {
 
         visiting.insert(table_id);
 
+        // [New] 先分出 trait：冒号后第一个类型也可能是 trait，它不是父类
+        self.resolve_traits(table_id);
+
         // 获取父类引用 (Cloned to avoid borrow issues)
         let parent_type_opt = self.tables.get(&table_id).and_then(|t| t.parent.clone());
 
@@ -92,6 +95,61 @@ impl<'a> Analyzer<'a> {
         Ok(())
     }
 
+    /// [New] 把指向 trait 的 parent 挪进 traits，并检查 traits 里列出的确实都是 trait
+    fn resolve_traits(&mut self, table_id: TableId) {
+        let Some(info) = self.tables.get(&table_id) else {
+            return;
+        };
+        let mut parent = info.parent.clone();
+        let mut candidates = info.traits.clone();
+        if let Some(first) = parent.clone()
+            && let Some(id) = table_base(&first)
+            && self.fetch_table_info(id).is_some_and(|info| info.is_trait)
+        {
+            candidates.insert(0, first);
+            parent = None;
+        }
+
+        let span = self.get_table_span(table_id);
+        let mut traits = Vec::new();
+        for ty in candidates {
+            let Some(id) = table_base(&ty) else {
+                if ty != Type::Error {
+                    let name = ty.display(self.ctx).to_string();
+                    self.report(span, SemanticErrorKind::NotATrait(name));
+                }
+                continue;
+            };
+            // 找不到定义的类型在 resolve_ast_type 里已经报过错了
+            let Some(trait_info) = self.fetch_table_info(id) else {
+                continue;
+            };
+            let name = self.ctx.resolve_symbol(id.symbol()).to_string();
+            if !trait_info.is_trait {
+                self.report(span, SemanticErrorKind::NotATrait(name));
+                continue;
+            }
+            if let Type::GenericInstance { args, .. } = &ty
+                && args.len() != trait_info.generic_params.len()
+            {
+                self.report(
+                    span,
+                    SemanticErrorKind::GenericArgumentCountMismatch {
+                        name,
+                        expected: trait_info.generic_params.len(),
+                        found: args.len(),
+                    },
+                );
+                continue;
+            }
+            traits.push(ty);
+        }
+
+        let info = self.tables.get_mut(&table_id).unwrap();
+        info.parent = parent;
+        info.traits = traits;
+    }
+
     fn fill_from_parent(&mut self, child_id: TableId, parent_type: &Type) -> Result<(), ()> {
         let parent_id = match parent_type {
             Type::Table(id) => *id,
@@ -144,6 +202,7 @@ impl<'a> Analyzer<'a> {
                         span: f_info.span,
                         // [Key] 核心修复：Mixin 表达式！
                         value: f_info.value.clone(),
+                        is_mut: f_info.is_mut,
                     };
 
                     child_info.fields.insert(*f_name, new_field_info);
@@ -166,6 +225,11 @@ impl<'a> Analyzer<'a> {
                     params: new_params,
                     ret: new_ret,
                     is_abstract: m_sig.is_abstract,
+                    generic_bounds: m_sig
+                        .generic_bounds
+                        .iter()
+                        .map(|(g, bound)| (*g, bound.substitute(&type_mapping)))
+                        .collect(),
                 };
 
                 let new_info = crate::analyzer::info::MethodInfo {
@@ -176,6 +240,27 @@ impl<'a> Analyzer<'a> {
                 child_info.methods.insert(*m_name, new_info);
             }
         }
+
+        // 3.3 [New] 合并虚表：继承父类的槽位，覆写的方法沿用父类下标
+        let own_entries = std::mem::take(&mut child_info.vtable);
+        let mut vtable = parent_info.vtable.clone();
+        for entry in own_entries {
+            match vtable.iter_mut().find(|e| e.name == entry.name) {
+                Some(inherited) => inherited.owner = entry.owner,
+                None => vtable.push(entry),
+            }
+        }
+        child_info.vtable = vtable;
+
+        // 3.4 [New] 合并字段布局：父类字段在前，子类新增字段按源码顺序追加
+        let own_fields = std::mem::take(&mut child_info.field_layout);
+        let mut layout = parent_info.field_layout.clone();
+        for name in own_fields {
+            if !layout.contains(&name) {
+                layout.push(name);
+            }
+        }
+        child_info.field_layout = layout;
         Ok(())
     }
 
@@ -207,3 +292,12 @@ impl<'a> Analyzer<'a> {
         module_info.tables.get(&id).cloned()
     }
 }
+
+/// [New] 类 / 泛型实例类型对应的 TableId
+fn table_base(ty: &Type) -> Option<TableId> {
+    match ty {
+        Type::Table(id) => Some(*id),
+        Type::GenericInstance { base, .. } => Some(*base),
+        _ => None,
+    }
+}
@@ -1,3 +1,4 @@
+use super::db::LocalSlot;
 use super::info::*;
 use super::types::Type;
 use crate::source::FileId;
@@ -7,10 +8,26 @@ use std::collections::HashMap;
 #[derive(Debug, Default)]
 pub struct Scope {
     pub symbols: HashMap<Symbol, SymbolInfo>,
+    /// [New] 进入该作用域时所在帧的下一个空闲槽位
+    /// 退出作用域时恢复，块内局部变量的槽位可以被后续兄弟块复用
+    slot_mark: u32,
+}
+
+/// [New] 函数帧 (局部变量槽位的分配单位)
+#[derive(Debug)]
+struct FrameScope {
+    /// 该帧最外层作用域在 scopes 中的下标
+    base: usize,
+    next_slot: u32,
+    /// [New] 被内层闭包捕获的槽位上界：退出作用域时不回收这些槽位，
+    /// 否则闭包里的变量会和后面兄弟块的变量共用一个槽位
+    pinned: u32,
 }
 
 pub struct ScopeManager {
     pub scopes: Vec<Scope>,
+    /// [New] 当前嵌套的函数帧。为空时定义的都是模块级全局符号
+    frames: Vec<FrameScope>,
 }
 
 impl Default for ScopeManager {
@@ -23,21 +40,46 @@ impl ScopeManager {
     pub fn new() -> Self {
         Self {
             scopes: vec![Scope::default()], // 至少有一个全局作用域
+            frames: Vec::new(),
         }
     }
 
     pub fn enter_scope(&mut self) {
-        self.scopes.push(Scope::default());
+        let slot_mark = self.frames.last().map_or(0, |f| f.next_slot);
+        self.scopes.push(Scope {
+            symbols: HashMap::new(),
+            slot_mark,
+        });
     }
 
     pub fn exit_scope(&mut self) {
         if self.scopes.len() > 1 {
-            self.scopes.pop();
+            let scope = self.scopes.pop().unwrap();
+            if let Some(frame) = self.frames.last_mut() {
+                frame.next_slot = scope.slot_mark.max(frame.pinned);
+            }
         } else {
             panic!("Cannot exit global scope");
         }
     }
 
+    /// [New] 进入函数帧 (方法体 / 函数体 / 字段初始化表达式)
+    /// 帧内的变量和参数按定义顺序从 0 开始分配槽位，
+    /// 所以方法里先定义的 `self` 固定在 0 号槽位
+    pub fn enter_function(&mut self) {
+        self.frames.push(FrameScope {
+            base: self.scopes.len(),
+            next_slot: 0,
+            pinned: 0,
+        });
+        self.enter_scope();
+    }
+
+    pub fn exit_function(&mut self) {
+        self.exit_scope();
+        self.frames.pop();
+    }
+
     /// [Changed] 定义符号
     /// allow_shadow: 如果为 true，则允许覆盖当前作用域已有的同名符号 (Shadowing)
     pub fn define(
@@ -50,13 +92,25 @@ impl ScopeManager {
         // [New] 必须传入定义时的文件 (通常是 analyzer.current_file_id)
         file_id: FileId,
         allow_shadow: bool,
-    ) -> Result<(), SymbolKind> {
+    ) -> Result<Option<LocalSlot>, SymbolKind> {
         let current_scope = self.scopes.last_mut().unwrap();
 
         if !allow_shadow && let Some(existing) = current_scope.symbols.get(&name) {
             return Err(existing.kind.clone());
         }
 
+        // [New] 函数帧内的变量和参数分配槽位；帧外的都是全局符号
+        let slot = match (self.frames.last_mut(), &kind) {
+            (Some(frame), SymbolKind::Variable | SymbolKind::Parameter) => {
+                let slot = frame.next_slot;
+                frame.next_slot += 1;
+                Some(slot)
+            }
+            _ => None,
+        };
+
+        // 变量默认可变，let / const 定义之后再用 mark_immutable 标记
+        let is_mut = kind == SymbolKind::Variable;
         current_scope.symbols.insert(
             name,
             SymbolInfo {
@@ -65,9 +119,20 @@ impl ScopeManager {
                 kind,
                 defined_span: span,    // 存下来！
                 defined_file: file_id, // 存下来！
+                slot,
+                is_mut,
+                is_const: false,
             },
         );
-        Ok(())
+        Ok(slot.map(|slot| LocalSlot { depth: 0, slot }))
+    }
+
+    /// [New] 把当前作用域里刚定义的变量标记为不可变 (let / const / self / 模块名)
+    pub fn mark_immutable(&mut self, name: Symbol, is_const: bool) {
+        if let Some(info) = self.scopes.last_mut().unwrap().symbols.get_mut(&name) {
+            info.is_mut = false;
+            info.is_const = is_const;
+        }
     }
 
     /// 查找符号 (从内向外)
@@ -80,6 +145,37 @@ impl ScopeManager {
         None
     }
 
+    /// [New] 查找局部变量的帧地址
+    /// 全局符号 (没有槽位) 返回 None；depth 为定义处到当前位置之间跨越的函数帧数
+    /// [Changed] depth > 0 说明是闭包捕获了外层函数的变量，把这个槽位固定下来
+    pub fn resolve_slot(&mut self, name: Symbol) -> Option<LocalSlot> {
+        let (idx, slot) = self
+            .scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(idx, scope)| scope.symbols.get(&name).map(|info| (idx, info.slot)))?;
+        let slot = slot?;
+        let depth = self.frames.iter().filter(|f| f.base > idx).count() as u32;
+        if depth > 0
+            && let Some(owner) = self.frames.iter_mut().rev().find(|f| f.base <= idx)
+        {
+            owner.pinned = owner.pinned.max(slot + 1);
+        }
+        Some(LocalSlot { depth, slot })
+    }
+
+    /// [New] 当前位置可见的所有符号 (内层遮蔽外层的同名符号)
+    /// 用于给未定义的名字找近似的候选
+    pub fn visible(&self) -> impl Iterator<Item = &SymbolInfo> {
+        let mut seen = std::collections::HashSet::new();
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.symbols.values())
+            .filter(move |info| seen.insert(info.name))
+    }
+
     /// 专门用于查找当前作用域 (例如防止同作用域重复定义)
     pub fn resolve_current(&self, name: Symbol) -> Option<&SymbolInfo> {
         self.scopes.last().unwrap().symbols.get(&name)
@@ -2,9 +2,19 @@ use super::tableid::TableId;
 use crate::context::Context;
 use crate::source::FileId;
 use crate::utils::Symbol;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
+/// [New] 子类型判断要用到的类层次信息
+/// Type 自己看不到 TableInfo，由 Analyzer 提供 (本文件的类和已加载模块的类)
+pub trait ClassHierarchy {
+    /// 类的直接父类和声明实现的 trait，类的泛型参数已替换成 args
+    fn supertypes(&self, id: TableId, args: &[Type]) -> Vec<Type>;
+
+    /// 当前位置泛型参数的约束 (T: Animal)，没写约束返回 None
+    fn generic_bound(&self, param: Symbol) -> Option<Type>;
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Type {
     /// 基础类型
@@ -65,6 +75,9 @@ pub enum Type {
 
     Never,
     Module(FileId),
+
+    /// [New] 枚举类型 (ADT)，和 Table 一样按名字区分
+    Enum(TableId),
 }
 
 #[derive(Debug, Clone)]
@@ -72,6 +85,8 @@ pub struct FunctionSignature {
     pub params: Vec<(Symbol, Type)>,
     pub ret: Type,
     pub is_abstract: bool,
+    /// [New] 方法 / 函数自己的泛型参数的约束 (fn pick<U: Animal>)，没写约束的参数不在里面
+    pub generic_bounds: HashMap<Symbol, Type>,
 }
 
 impl Type {
@@ -79,9 +94,11 @@ impl Type {
         matches!(self, Type::Int | Type::Float)
     }
 
-    /// 简单的类型兼容性检查 (Assignability)
+    /// 类型兼容性检查 (Assignability)
     /// check if `other` can be assigned to `self`
-    pub fn is_assignable_from(&self, other: &Type) -> bool {
+    /// [Changed] 类之间按子类型关系判断：子类、实现了 trait 的类都可以赋值给祖先 / trait，
+    /// 层次信息由 classes 提供 (跨模块的父类也能查到)
+    pub fn is_assignable_from(&self, other: &Type, classes: &dyn ClassHierarchy) -> bool {
         // 1. Never 可以赋值给任何类型 (Bottom Type)
         //    例如: let x: int = return; 是合法的
         if *other == Type::Never {
@@ -108,10 +125,33 @@ impl Type {
             // 允许 Int 自动提升为 Float? Loom 偏向强类型，暂不允许隐式转换，除非显式 cast
             (Type::Bool, Type::Bool) => true,
             (Type::Str, Type::Str) => true,
+            (Type::Char, Type::Char) => true,
             (Type::Unit, Type::Unit) => true,
-            (Type::GenericParam(a), Type::GenericParam(b)) => a == b,
-            (Type::Table(s1), Type::Table(s2)) => s1 == s2, // 暂时只支持名义类型相等，原型继承兼容性稍后处理
-            (Type::Array(t1), Type::Array(t2)) => t1.is_assignable_from(t2),
+            (Type::GenericParam(a), Type::GenericParam(b)) if a == b => true,
+            // [New] 有约束的泛型参数可以当作约束类型使用 (Animal <- T，T: Animal)
+            (_, Type::GenericParam(sym)) => match classes.generic_bound(*sym) {
+                Some(bound) if !matches!(bound, Type::GenericParam(_)) => {
+                    self.is_assignable_from(&bound, classes)
+                }
+                _ => false,
+            },
+            // [Changed] 类 / 泛型类实例：沿 other 的父类链和 trait 向上找 self
+            // Animal <- Dog, Drawable <- Circle, Box<Animal> <- Box<Dog>, Source<int> <- Counter
+            (
+                Type::Table(_) | Type::GenericInstance { .. },
+                Type::Table(_) | Type::GenericInstance { .. },
+            ) => self.is_supertype_of(other, classes),
+            (Type::Enum(e1), Type::Enum(e2)) => e1 == e2,
+            (Type::Array(t1), Type::Array(t2)) => t1.is_assignable_from(t2, classes),
+            (Type::Range(t1), Type::Range(t2)) => t1.is_assignable_from(t2, classes),
+            // [New] 元组：长度相同且逐个元素兼容
+            (Type::Tuple(t1), Type::Tuple(t2)) => {
+                t1.len() == t2.len()
+                    && t1
+                        .iter()
+                        .zip(t2)
+                        .all(|(a, b)| a.is_assignable_from(b, classes))
+            }
             (
                 Type::Function {
                     generic_params: t_gens, // Target
@@ -137,45 +177,57 @@ impl Type {
                 // 3. 参数逆变检查
                 for (i, t_param) in t_params.iter().enumerate() {
                     let s_param = &s_params[i];
-                    if !s_param.is_assignable_from(t_param) {
+                    if !s_param.is_assignable_from(t_param, classes) {
                         return false;
                     }
                 }
 
                 // 4. 返回值协变检查
-                if !t_ret.is_assignable_from(s_ret) {
+                if !t_ret.is_assignable_from(s_ret, classes) {
                     return false;
                 }
 
                 true
             }
-            // Container<int> 兼容 Container<int>
-            (
-                Type::GenericInstance { base: b1, args: a1 },
-                Type::GenericInstance { base: b2, args: a2 },
-            ) => {
-                // 1. 必须是同一个基础类 (Container == Container)
-                if b1 != b2 {
-                    return false;
-                }
-
-                // 2. 泛型参数数量必须一致
-                if a1.len() != a2.len() {
-                    return false;
-                }
+            _ => false,
+        }
+    }
 
-                // 3. 递归检查每一个泛型参数
-                // 这里我们使用 is_assignable_from 递归检查
-                // 这意味着 List<Dog> 可以赋值给 List<Animal> (协变)
-                for (p1, p2) in a1.iter().zip(a2.iter()) {
-                    if !p1.is_assignable_from(p2) {
-                        return false;
-                    }
+    /// [New] self 是不是 other 本身、other 的祖先或 other (的祖先) 实现的 trait
+    /// 向上找到和 self 同一个类的那一层后，逐个检查泛型实参 (协变，List<Animal> <- List<Dog>)
+    fn is_supertype_of(&self, other: &Type, classes: &dyn ClassHierarchy) -> bool {
+        let Some((target, target_args)) = self.class_parts() else {
+            return false;
+        };
+        let mut pending = vec![other.clone()];
+        // 防止继承关系有环 (resolve 阶段报过错) 时死循环
+        let mut seen = HashSet::new();
+        while let Some(ty) = pending.pop() {
+            let Some((id, args)) = ty.class_parts() else {
+                continue;
+            };
+            if id == target {
+                if args.len() == target_args.len()
+                    && target_args
+                        .iter()
+                        .zip(args)
+                        .all(|(t, s)| t.is_assignable_from(s, classes))
+                {
+                    return true;
                 }
-
-                true
+            } else if seen.insert(id) {
+                pending.extend(classes.supertypes(id, args));
             }
-            _ => false,
+        }
+        false
+    }
+
+    /// [New] 类类型的 TableId 和泛型实参 (普通类没有实参)
+    fn class_parts(&self) -> Option<(TableId, &[Type])> {
+        match self {
+            Type::Table(id) => Some((*id, &[])),
+            Type::GenericInstance { base, args } => Some((*base, args)),
+            _ => None,
         }
     }
 
@@ -193,7 +245,7 @@ impl Type {
             Type::Error => "<error>".to_string(),
             Type::Never => "!".to_string(),
 
-            Type::Table(sym) => interner.resolve(sym.symbol()).to_string(),
+            Type::Table(sym) | Type::Enum(sym) => interner.resolve(sym.symbol()).to_string(),
 
             // 模块类型 (显示简短名字)
             Type::Module(file_id) => {
@@ -275,6 +327,9 @@ impl Type {
                 }
             }
             Type::Array(inner) => Type::Array(Box::new(inner.substitute(mapping))),
+            Type::Tuple(items) => {
+                Type::Tuple(items.iter().map(|t| t.substitute(mapping)).collect())
+            }
             // Table, Int, Str 等不受泛型影响
             _ => self.clone(),
         }
@@ -318,7 +373,7 @@ impl<'a> fmt::Display for TypePrinter<'a> {
             Type::Error => write!(f, "<error>"),
             Type::Never => write!(f, "!"),
 
-            Type::Table(sym) => write!(f, "{}", interner.resolve(sym.symbol())),
+            Type::Table(sym) | Type::Enum(sym) => write!(f, "{}", interner.resolve(sym.symbol())),
             Type::GenericParam(sym) => write!(f, "{}", interner.resolve(*sym)),
 
             Type::Module(file_id) => {
@@ -1,5 +1,6 @@
 use crate::utils::Node;
 use crate::utils::{Span, Symbol};
+use std::rc::Rc;
 
 // --- 顶级结构 ---
 
@@ -10,6 +11,8 @@ pub enum TopLevelItem {
     Function(MethodDefinition),
     Field(FieldDefinition),
     Use(UseStatement),
+    /// [New] enum Shape ...
+    Enum(EnumDefinition),
 }
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum UseAnchor {
@@ -42,6 +45,11 @@ pub struct TableDefinitionData {
     /// 继承/约束的原型 (例如 [Production: BaseServer] 中的 BaseServer)
     /// 如果没有原型，则是 None (例如 [BaseServer])
     pub prototype: Option<TypeRef>,
+    /// [New] 冒号后面列出的其余类型 (class Circle : Shape, Drawable 中的 Drawable)
+    /// 必须都是 trait；第一个是 trait 还是父类要到语义分析时才知道
+    pub traits: Vec<TypeRef>,
+    /// [New] trait Drawable ... (只有方法签名，没有字段和方法体)
+    pub is_trait: bool,
     /// 泛型参数 <T: Constraint>
     pub generics: Vec<GenericParam>,
     /// 表内的条目 (字段或方法)
@@ -49,6 +57,36 @@ pub struct TableDefinitionData {
 }
 pub type TableDefinition = Node<TableDefinitionData>;
 
+/// [New] 枚举定义 (代数数据类型):
+/// enum Shape
+///     Circle(float)
+///     Rect { w: float, h: float }
+///     Empty
+#[derive(Debug, Clone)]
+pub struct EnumDefinitionData {
+    pub name: Symbol,
+    pub variants: Vec<VariantDefinition>,
+}
+pub type EnumDefinition = Node<EnumDefinitionData>;
+
+/// 枚举的一个变体
+#[derive(Debug, Clone)]
+pub struct VariantDefinitionData {
+    pub name: Symbol,
+    pub kind: VariantKind,
+}
+pub type VariantDefinition = Node<VariantDefinitionData>;
+
+#[derive(Debug, Clone)]
+pub enum VariantKind {
+    /// Empty
+    Unit,
+    /// Circle(float)
+    Tuple(Vec<TypeRef>),
+    /// Rect { w: float, h: float }
+    Struct(Vec<(Symbol, TypeRef)>),
+}
+
 /// 泛型参数定义 <T: Base>
 #[derive(Debug, Clone, PartialEq)]
 pub struct GenericParamData {
@@ -73,6 +111,10 @@ pub struct FieldDefinitionData {
     /// 默认值/初始值 (必选，除非是纯接口定义？Spec里似乎总是有值的)
     /// 如果允许纯声明 field: int，则 value 为 Option
     pub value: Option<Expression>,
+    /// [New] `let` 声明的字段 / 全局变量只读，不带关键字或 `var` 的可写
+    pub is_mut: bool,
+    /// [New] `const X = ...` (只用于顶层)，初值必须是编译期常量
+    pub is_const: bool,
 }
 pub type FieldDefinition = Node<FieldDefinitionData>;
 
@@ -117,6 +159,13 @@ pub enum TypeRefData {
     // 数组类型
     // 对应语法: [int], [[str]]
     Array(Box<TypeRef>),
+    /// [New] 元组类型: (int, str)
+    Tuple(Vec<TypeRef>),
+    /// [New] 函数类型: fn(int, str) bool；省略返回类型表示没有返回值
+    Function {
+        params: Vec<TypeRef>,
+        ret: Option<Box<TypeRef>>,
+    },
 }
 pub type TypeRef = Node<TypeRefData>;
 
@@ -157,15 +206,17 @@ pub enum ExpressionData {
         else_block: Option<Block>, // else 是可选的
     },
     /// for i in 0..10
+    /// [Changed] 迭代变量是一个模式：for (k, v) in pairs
     For {
-        iterator: Symbol,
+        pattern: Pattern,
         iterable: Box<Expression>, // 例如 range 0..10
         body: Block,
     },
     /// range 0..10
+    /// [Changed] 两端都可以省略 (arr[2..] / arr[..3])，省略端点的范围只能用作切片下标
     Range {
-        start: Box<Expression>,
-        end: Box<Expression>,
+        start: Option<Box<Expression>>,
+        end: Option<Box<Expression>>,
         inclusive: bool, // .. vs ..= (虽然 Spec 目前只用了 ..)
     },
 
@@ -175,7 +226,7 @@ pub enum ExpressionData {
     Tuple(Vec<Expression>),
 
     /// 索引访问: arr[index]
-    /// 注意：index 可以是一个 Range 表达式 (0..10)，从而支持切片
+    /// 注意：index 可以是一个 Range 表达式 (0..10)，从而支持切片；负数下标从末尾数起
     Index {
         target: Box<Expression>, // 被索引的对象 (arr)
         index: Box<Expression>,  // 索引值 (i 或 0..5)
@@ -214,13 +265,25 @@ pub enum ExpressionData {
         body: Block,
     },
 
+    /// [Changed] `var x = 1` 可变，`let x = 1` / `const X = 1` 不可变
+    /// (不带关键字的 `x: int = 1` 仍然是可变的)
     VariableDefinition {
         is_mut: bool,
+        is_const: bool,
         name: Symbol,
         ty: Option<TypeRef>,
         init: Box<Expression>,
     },
 
+    /// [New] 解构: (a, b) = pair
+    /// 和 `x = v` 一样，已有的变量被赋值，不存在的变量被定义
+    /// [New] let (a, b) = pair / var (a, b) = pair 总是定义新变量，is_mut 是 Some(是否可变)
+    Destructure {
+        pattern: Pattern,
+        value: Box<Expression>,
+        is_mut: Option<bool>,
+    },
+
     // [New] 赋值表达式
     // 涵盖: =, +=, -=, *=, /=
     Assign {
@@ -234,9 +297,90 @@ pub enum ExpressionData {
         expr: Box<Expression>,
         target_type: TypeRef,
     },
+
+    /// [New] match value
+    ///     pattern => expr
+    Match {
+        scrutinee: Box<Expression>,
+        arms: Vec<MatchArm>,
+    },
+
+    /// [New] 结构体变体构造: Shape.Rect { w: 1.0, h: 2.0 }
+    /// target 是变体的访问路径 (Shape.Rect / lib.Shape.Rect)
+    VariantLiteral {
+        target: Box<Expression>,
+        fields: Vec<(Symbol, Expression)>,
+    },
+
+    /// [New] 匿名函数: fn(x: int) int => x * 2
+    /// 复用 MethodDefinition (名字固定为 `<lambda>`，没有泛型参数)，
+    /// 求值时捕获外层的局部变量 (闭包)
+    Lambda(Rc<MethodDefinition>),
 }
 pub type Expression = Node<ExpressionData>;
 
+/// [New] match 的一个分支: pattern => body
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArmData {
+    pub pattern: Pattern,
+    /// 单行分支 `=> expr` 也包成 Block
+    pub body: Block,
+}
+pub type MatchArm = Node<MatchArmData>;
+
+/// [New] 绑定模式 (for 的迭代变量、解构)
+/// [Changed] match 的分支还可以用字面量和枚举变体 (可能匹配失败的模式)
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternData {
+    /// 绑定一个名字
+    Identifier(Symbol),
+    /// `_`：取出这个位置的值，但不绑定
+    Wildcard,
+    /// (a, (b, _))
+    Tuple(Vec<Pattern>),
+    /// [New] 1 / "ok" / 'c' / true / nil (只用于 match)
+    Literal(Literal),
+    /// [New] .Circle(r) / Shape.Rect { w, h: _ } / lib.Shape.Empty (只用于 match)
+    Variant {
+        /// 写出来的枚举类型；`.V` 简写时为 None，按被匹配的值推导
+        qualifier: Option<TypeRef>,
+        name: Symbol,
+        args: VariantPatternArgs,
+    },
+}
+pub type Pattern = Node<PatternData>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariantPatternArgs {
+    /// .Empty
+    None,
+    /// .Circle(r)
+    Tuple(Vec<Pattern>),
+    /// .Rect { w, h: _ }，没写出来的字段不检查
+    Struct(Vec<(Symbol, Pattern)>),
+}
+
+impl Pattern {
+    /// 模式里绑定的所有名字 (Identifier 节点)，按出现顺序
+    pub fn bindings(&self) -> Vec<(&Pattern, Symbol)> {
+        match &self.data {
+            PatternData::Identifier(name) => vec![(self, *name)],
+            PatternData::Wildcard | PatternData::Literal(_) => Vec::new(),
+            PatternData::Tuple(items) => items.iter().flat_map(Pattern::bindings).collect(),
+            PatternData::Variant { args, .. } => match args {
+                VariantPatternArgs::None => Vec::new(),
+                VariantPatternArgs::Tuple(items) => {
+                    items.iter().flat_map(Pattern::bindings).collect()
+                }
+                VariantPatternArgs::Struct(fields) => fields
+                    .iter()
+                    .flat_map(|(_, pattern)| pattern.bindings())
+                    .collect(),
+            },
+        }
+    }
+}
+
 // 赋值操作符
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AssignOp {
@@ -259,7 +403,8 @@ pub type Block = Node<BlockData>;
 pub enum Literal {
     Int(i64),
     Float(f64),
-    String(String),
+    // [Changed] 解析时分配一次，求值只增加引用计数
+    String(Rc<str>),
     Char(char),
     Bool(bool),
     Nil,
@@ -1,11 +1,30 @@
 // context.rs
 use crate::analyzer::ModuleInfo;
 use crate::analyzer::SemanticDB;
+use crate::analyzer::{EnumInfo, TableId, TableInfo};
+use crate::ast::{Program, TopLevelItem};
+use crate::interpreter::gc::Heap;
+use crate::lexer::Lexer;
+use crate::parser::{ParseError, Parser};
+use crate::source::FileId;
 use crate::source::SourceManager;
-use crate::utils::{Interner, Symbol};
+use crate::utils::{Interner, NodeId, Symbol};
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// [New] 一个文件的解析结果 (缓存在 Context::parsed 里)
+#[derive(Debug, Clone)]
+pub struct ParsedFile {
+    /// 遇到无法恢复的错误时为 Err
+    pub program: Result<Rc<Program>, ParseError>,
+    /// 解析过程中恢复过的语法错误
+    pub errors: Vec<ParseError>,
+    /// 这次解析分配的 NodeId 区间
+    pub nodes: Range<u32>,
+}
 
 #[derive(Debug)]
 pub struct Context {
@@ -25,6 +44,33 @@ pub struct Context {
 
     // [New] 语义数据库
     pub db: SemanticDB,
+
+    /// [New] 下一个可用的 NodeId
+    /// 所有文件共用一个计数器，保证 NodeId 在整个 Context 内唯一
+    pub next_node_id: u32,
+
+    /// [New] 每个文件解析时的 NodeId 起点 (按起点递增)
+    /// 用于从任意 AST 节点反查它所在的文件 (例如运行时错误回溯)
+    pub node_ranges: Vec<(u32, FileId)>,
+
+    /// [New] 解析缓存：源码没变的文件直接复用 AST (NodeId 也保持不变)
+    pub parsed: HashMap<FileId, ParsedFile>,
+
+    /// [New] use 图：模块 -> 它直接 import 的模块
+    /// 文件修改后沿反向边找到所有依赖它的模块一起作废
+    pub module_deps: HashMap<PathBuf, HashSet<PathBuf>>,
+
+    /// [New] 因重新解析而作废的 NodeId 数 (SemanticDB 里按 NodeId 下标的表不会收缩)
+    /// 长时间运行的 LSP 会话据此决定何时整体重建 Context
+    pub retired_nodes: usize,
+
+    /// [New] 项目里的顶层名字 -> 定义它的文件 (绝对路径)
+    /// 给没 import 的名字找出处用；每次 Driver::analyze 开始时清空，第一次用到时才建
+    pub project_index: Option<HashMap<Symbol, Vec<PathBuf>>>,
+
+    /// [New] 运行时堆：登记实例/数组供循环回收器使用
+    /// 放在 Context 里是为了让原生函数 (gc_stats 等) 也能访问
+    pub heap: Heap,
 }
 
 impl Context {
@@ -36,6 +82,13 @@ impl Context {
             modules: HashMap::new(),
             loading_stack: HashSet::new(),
             db: SemanticDB::default(),
+            next_node_id: 0,
+            node_ranges: Vec::new(),
+            parsed: HashMap::new(),
+            module_deps: HashMap::new(),
+            retired_nodes: 0,
+            project_index: None,
+            heap: Heap::new(),
         }
     }
 
@@ -43,6 +96,186 @@ impl Context {
         self.interner.intern(name)
     }
 
+    /// 记录一个文件从 first 开始分配 NodeId
+    pub fn record_node_range(&mut self, first: u32, file_id: FileId) {
+        self.node_ranges.push((first, file_id));
+    }
+
+    /// 节点所在的文件 (节点属于已被重新解析替换掉的旧 AST 时返回 None)
+    pub fn file_of_node(&self, id: NodeId) -> Option<FileId> {
+        let idx = self
+            .node_ranges
+            .partition_point(|(start, _)| *start <= id.0);
+        let file_id = self.node_ranges[idx.checked_sub(1)?].1;
+        match self.parsed.get(&file_id) {
+            Some(parsed) if !parsed.nodes.contains(&id.0) => None,
+            _ => Some(file_id),
+        }
+    }
+
+    /// [New] 解析文件；源码没变时直接返回缓存
+    pub fn parse_file(&mut self, file_id: FileId) -> ParsedFile {
+        if let Some(parsed) = self.parsed.get(&file_id) {
+            return parsed.clone();
+        }
+
+        let source = self.source_manager.get_file(file_id).src.as_str();
+        let lexer = Lexer::new(source);
+        let first_id = self.next_node_id;
+        let mut parser =
+            Parser::new(source, lexer, file_id, &mut self.interner).with_first_node_id(first_id);
+
+        let program = parser.parse_program().map(Rc::new);
+        let parsed = ParsedFile {
+            program,
+            errors: std::mem::take(&mut parser.errors),
+            nodes: first_id..parser.next_node_id(),
+        };
+        self.next_node_id = parsed.nodes.end;
+        self.record_node_range(first_id, file_id);
+        self.parsed.insert(file_id, parsed.clone());
+        parsed
+    }
+
+    /// [New] 清掉文件当前 AST 的语义记录 (重新检查前调用，避免残留过期的类型/定义)
+    pub fn clear_semantics(&mut self, file_id: FileId) {
+        if let Some(parsed) = self.parsed.get(&file_id) {
+            self.db.forget(parsed.nodes.clone());
+        }
+    }
+
+    /// [New] 记录 use 边：from import 了 to
+    pub fn record_dependency(&mut self, from: PathBuf, to: PathBuf) {
+        self.module_deps.entry(from).or_default().insert(to);
+    }
+
+    /// [New] 文件内容变化：替换源码、丢弃解析缓存，并作废它和所有依赖它的模块
+    /// 返回被作废的模块路径 (需要重新检查)；内容没变时返回空
+    pub fn update_file(&mut self, file_id: FileId, src: String) -> Vec<PathBuf> {
+        if self.source_manager.get_file(file_id).src == src {
+            return Vec::new();
+        }
+        self.source_manager.update_file(file_id, src);
+        if let Some(old) = self.parsed.remove(&file_id) {
+            self.db.forget(old.nodes.clone());
+            self.retired_nodes += old.nodes.len();
+        }
+        let path = self.source_manager.get_file(file_id).path.clone();
+        self.invalidate_module(&path)
+    }
+
+    /// [New] 从模块缓存中移除 path 以及 (直接或间接) import 它的模块
+    /// 它们的 AST 仍在解析缓存里，重新检查时不需要重新解析
+    pub fn invalidate_module(&mut self, path: &Path) -> Vec<PathBuf> {
+        let mut invalidated = vec![path.to_path_buf()];
+        let mut i = 0;
+        while i < invalidated.len() {
+            for (module, deps) in &self.module_deps {
+                if deps.contains(&invalidated[i]) && !invalidated.contains(module) {
+                    invalidated.push(module.clone());
+                }
+            }
+            i += 1;
+        }
+        for module in &invalidated {
+            self.modules.remove(module);
+        }
+        invalidated
+    }
+
+    /// [New] 按 TableId 查已分析模块里的类 (LSP 查询用；分析过程中用 Analyzer::table_info)
+    pub fn table_info(&self, id: TableId) -> Option<&TableInfo> {
+        let path = self.source_manager.get_file_path(id.file_id())?;
+        self.modules.get(path)?.tables.get(&id)
+    }
+
+    /// [New] 按 TableId 查已分析模块里的枚举
+    pub fn enum_info(&self, id: TableId) -> Option<&EnumInfo> {
+        let path = self.source_manager.get_file_path(id.file_id())?;
+        self.modules.get(path)?.enums.get(&id)
+    }
+
+    /// [New] from 是否 (直接或间接) import 了 to
+    pub fn depends_on(&self, from: &Path, to: &Path) -> bool {
+        let mut stack = vec![from];
+        let mut visited = HashSet::new();
+        while let Some(path) = stack.pop() {
+            if path == to {
+                return true;
+            }
+            if visited.insert(path)
+                && let Some(deps) = self.module_deps.get(path)
+            {
+                stack.extend(deps.iter().map(PathBuf::as_path));
+            }
+        }
+        false
+    }
+
+    /// [New] 项目根目录下所有的 .lm 文件 (跳过隐藏目录和 target)
+    /// [Fix] 不跟随符号链接的目录，避免链接成环时无限遍历
+    pub fn project_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.root_dir.clone()];
+        while let Some(dir) = dirs.pop() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                let Ok(file_type) = entry.file_type() else {
+                    continue;
+                };
+                if file_type.is_dir() {
+                    if !name.starts_with('.') && name != "target" {
+                        dirs.push(path);
+                    }
+                } else if !file_type.is_symlink() && path.extension().is_some_and(|ext| ext == "lm")
+                {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+        files
+    }
+
+    /// [New] 顶层名字索引 (见 project_index)：一次分析只遍历一遍项目目录，文件解析走 parse_file 的缓存
+    pub fn project_index(&mut self) -> &HashMap<Symbol, Vec<PathBuf>> {
+        if self.project_index.is_none() {
+            let mut index: HashMap<Symbol, Vec<PathBuf>> = HashMap::new();
+            for path in self.project_files() {
+                let Ok(abs_path) = path.canonicalize() else {
+                    continue;
+                };
+                let file_id = match self.source_manager.find_file(&abs_path) {
+                    Some(id) => id,
+                    None => match self.source_manager.load_file(&abs_path) {
+                        Ok(id) => id,
+                        Err(_) => continue,
+                    },
+                };
+                let Ok(program) = self.parse_file(file_id).program else {
+                    continue;
+                };
+                for item in &program.definitions {
+                    let name = match item {
+                        TopLevelItem::Table(def) => def.data.name,
+                        TopLevelItem::Function(def) => def.data.name,
+                        TopLevelItem::Field(def) => def.data.name,
+                        TopLevelItem::Enum(def) => def.data.name,
+                        TopLevelItem::Use(_) => continue,
+                    };
+                    index.entry(name).or_default().push(abs_path.clone());
+                }
+            }
+            self.project_index = Some(index);
+        }
+        self.project_index.get_or_insert_default()
+    }
+
     pub fn resolve_symbol(&self, sym: Symbol) -> &str {
         self.interner.resolve(sym)
     }
@@ -0,0 +1,248 @@
+// src/diagnostic.rs
+//
+// 统一的诊断信息：解析器、分析器和解释器的错误都转换成 Diagnostic，
+// 再由 Driver 按 --message-format 渲染成给人看的文本，或者给编辑器 / CI 用的 JSON
+//
+// 错误码是稳定的 (只增不改)，分段如下：
+// - E00xx 解析 / 文件加载
+// - E01xx 名字与定义        E02xx 类型检查
+// - E03xx 模块系统          E04xx 继承与泛型
+// - E09xx 其它
+// - R0xxx 运行时错误
+// - W0xxx lint 警告 (被 deny 时作为错误报告，错误码不变)
+
+use crate::source::{FileId, SourceManager};
+use crate::utils::{Json, Span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// 输出格式 (`--message-format=human|json`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MessageFormat {
+    #[default]
+    Human,
+    /// 每条诊断一行 JSON
+    Json,
+}
+
+/// 源码上的一段标注
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub file_id: FileId,
+    pub span: Span,
+    /// 标注在下划线后面的说明 (可以为空)
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(file_id: FileId, span: Span, message: impl Into<String>) -> Self {
+        Self {
+            file_id,
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 稳定的错误码，例如 "E0100"
+    pub code: &'static str,
+    pub message: String,
+    /// 出错位置 (无法定位时为 None，例如文件读取失败)
+    pub primary: Option<Label>,
+    /// 相关位置，例如重复定义时的上一次定义
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code,
+            message: message.into(),
+            primary: None,
+            secondary: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn error(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, code, message)
+    }
+
+    pub fn warning(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, code, message)
+    }
+
+    pub fn with_primary(mut self, file_id: FileId, span: Span) -> Self {
+        self.primary = Some(Label::new(file_id, span, ""));
+        self
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.secondary.push(label);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// 类似 rustc 的文本格式
+    /// Error[E0200]: message
+    ///   --> src/main.lm:10:5
+    ///    |
+    ///  10| x = "hello"
+    ///    |     ^^^^^^^
+    ///    = help: ...
+    pub fn render(&self, sm: &SourceManager) -> String {
+        let title = match self.severity {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+        };
+        let mut out = format!("{}[{}]: {}", title, self.code, self.message);
+        if let Some(label) = &self.primary {
+            render_snippet(&mut out, sm, label, "-->", '^');
+        }
+        for label in &self.secondary {
+            render_snippet(&mut out, sm, label, ":::", '-');
+        }
+        for note in &self.notes {
+            out.push_str(&format!("\n   = note: {}", note));
+        }
+        if let Some(help) = &self.help {
+            out.push_str(&format!("\n   = help: {}", help));
+        }
+        out
+    }
+
+    /// JSON 格式 (字段名参照 cargo 的 --message-format=json)
+    pub fn to_json(&self, sm: &SourceManager) -> Json {
+        let primary = self.primary.iter().map(|label| (label, true));
+        let secondary = self.secondary.iter().map(|label| (label, false));
+        let spans: Vec<Json> = primary
+            .chain(secondary)
+            .map(|(label, is_primary)| span_json(sm, label, is_primary))
+            .collect();
+        Json::object([
+            ("severity", self.severity.as_str().into()),
+            ("code", self.code.into()),
+            ("message", self.message.clone().into()),
+            ("spans", spans.into()),
+            (
+                "notes",
+                Json::Array(self.notes.iter().map(|n| n.clone().into()).collect()),
+            ),
+            ("help", self.help.clone().map_or(Json::Null, Json::String)),
+            ("rendered", self.render(sm).into()),
+        ])
+    }
+}
+
+/// 按格式渲染一组诊断：文本格式之间空一行，JSON 每条一行
+pub fn render_all(diagnostics: &[Diagnostic], sm: &SourceManager, format: MessageFormat) -> String {
+    let rendered: Vec<String> = diagnostics
+        .iter()
+        .map(|d| match format {
+            MessageFormat::Human => d.render(sm),
+            MessageFormat::Json => d.to_json(sm).to_string(),
+        })
+        .collect();
+    match format {
+        MessageFormat::Human => rendered.join("\n\n"),
+        MessageFormat::Json => rendered.join("\n"),
+    }
+}
+
+fn render_snippet(out: &mut String, sm: &SourceManager, label: &Label, arrow: &str, marker: char) {
+    let file_name = sm.get_file_name(label.file_id).unwrap_or("<unknown>");
+    let Some((line, col, line_text)) = sm.lookup_location(label.file_id, label.span.start) else {
+        // 找不到源码位置 (例如文件丢失)
+        out.push_str(&format!(
+            "\n  {} {} (at {:?})",
+            arrow, file_name, label.span
+        ));
+        return;
+    };
+
+    // 高亮长度：不超出这一行，且至少为 1
+    let highlight_len = if label.span.end > label.span.start {
+        let max_len = line_text.len().saturating_sub(col - 1);
+        std::cmp::min(label.span.end - label.span.start, max_len).max(1)
+    } else {
+        1
+    };
+    let pointer = marker.to_string().repeat(highlight_len);
+    let padding = " ".repeat(col.saturating_sub(1));
+
+    out.push_str(&format!(
+        "\n  {} {}:{}:{}\n   |\n{:3}| {}\n   | {}{}",
+        arrow,
+        file_name,
+        line,
+        col,
+        line,
+        line_text.trim_end(),
+        padding,
+        pointer
+    ));
+    if !label.message.is_empty() {
+        out.push_str(&format!(" {}", label.message));
+    }
+}
+
+fn span_json(sm: &SourceManager, label: &Label, is_primary: bool) -> Json {
+    let position = |offset: usize| {
+        sm.lookup_location(label.file_id, offset)
+            .map_or((0, 0), |(line, col, _)| (line, col))
+    };
+    let (line_start, column_start) = position(label.span.start);
+    let (line_end, column_end) = position(label.span.end);
+    Json::object([
+        (
+            "file",
+            sm.get_file_name(label.file_id)
+                .unwrap_or("<unknown>")
+                .into(),
+        ),
+        ("byte_start", label.span.start.into()),
+        ("byte_end", label.span.end.into()),
+        ("line_start", line_start.into()),
+        ("column_start", column_start.into()),
+        ("line_end", line_end.into()),
+        ("column_end", column_end.into()),
+        ("is_primary", is_primary.into()),
+        (
+            "label",
+            if label.message.is_empty() {
+                Json::Null
+            } else {
+                label.message.clone().into()
+            },
+        ),
+    ])
+}
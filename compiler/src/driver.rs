@@ -1,29 +1,179 @@
-use crate::analyzer::{Analyzer, SemanticError, TableId};
-use crate::ast::TopLevelItem;
+use crate::analyzer::{Analyzer, LintConfig, LintWarning, ModuleInfo, TableId};
+use crate::ast::{Program, TopLevelItem};
 use crate::context::Context;
-use crate::interpreter::Interpreter;
+use crate::diagnostic::{self, Diagnostic, MessageFormat, Severity};
 use crate::interpreter::value::Value;
-use crate::lexer::Lexer;
-use crate::parser::Parser;
+use crate::interpreter::vm::Vm;
+use crate::interpreter::{DEFAULT_MAX_CALL_DEPTH, Interpreter};
 use crate::source::FileId;
-use crate::utils::Span;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
+/// 执行后端
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// 树遍历解释器 (interpreter::evaluate)
+    #[default]
+    TreeWalk,
+    /// 字节码虚拟机 (interpreter::vm)
+    Bytecode,
+}
+
 pub struct Driver {
     pub ctx: Context,
+    pub backend: Backend,
+    /// [New] 最大调用深度，超过时抛出 StackOverflow 而不是撑爆宿主栈
+    pub max_call_depth: usize,
+    /// [New] 诊断的输出格式 (`--message-format`)
+    pub message_format: MessageFormat,
+    /// [New] 每个 lint 的级别 (`--allow` / `--deny` / `--deny-warnings`)
+    pub lints: LintConfig,
+    /// [New] 最近一次成功的 analyze 产生的警告
+    pub warnings: Vec<Diagnostic>,
 }
 
 impl Driver {
     pub fn new(root_dir: PathBuf) -> Self {
         Self {
             ctx: Context::new(root_dir),
+            backend: Backend::default(),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            message_format: MessageFormat::default(),
+            lints: LintConfig::default(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// 选择执行后端
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// 设置最大调用深度
+    pub fn with_max_call_depth(mut self, depth: usize) -> Self {
+        self.max_call_depth = depth;
+        self
+    }
+
+    /// 设置诊断的输出格式
+    pub fn with_message_format(mut self, format: MessageFormat) -> Self {
+        self.message_format = format;
+        self
+    }
+
+    /// 设置 lint 级别
+    pub fn with_lints(mut self, lints: LintConfig) -> Self {
+        self.lints = lints;
+        self
+    }
+
+    /// [New] 支撑 max_call_depth 层调用所需的宿主线程栈大小
+    /// 树遍历解释器每层 Loom 调用要消耗若干个 Rust 栈帧 (debug 构建下约 10 KiB)，这里留足余量。
+    /// 只是预留虚拟地址空间，实际用到的页才会分配。
+    /// [Changed] 上限为 MAX_STACK (再大线程可能创建失败)；字节码 VM 的调用帧在堆上，
+    /// 宿主栈只需要容纳分析器的递归，不随调用深度增长
+    pub fn stack_size_for(backend: Backend, max_call_depth: usize) -> usize {
+        match backend {
+            Backend::TreeWalk => max_call_depth
+                .saturating_mul(Self::BYTES_PER_CALL)
+                .clamp(Self::MIN_STACK, Self::MAX_STACK),
+            Backend::Bytecode => Self::MIN_STACK,
         }
     }
 
+    /// [New] 树遍历解释器在栈大小上限内能支持的最大调用深度
+    pub const MAX_TREE_WALK_CALL_DEPTH: usize = Self::MAX_STACK / Self::BYTES_PER_CALL;
+
+    const MIN_STACK: usize = 8 * 1024 * 1024;
+    const MAX_STACK: usize = 2 * 1024 * 1024 * 1024;
+    const BYTES_PER_CALL: usize = 32 * 1024;
+
     /// 入口：运行一个文件
-    pub fn run_file(&mut self, path: &Path) -> Result<Value, String> {
+    /// [Changed] 失败时返回结构化的诊断，用 render 按 message_format 输出
+    pub fn run_file(&mut self, path: &Path) -> Result<Value, Vec<Diagnostic>> {
+        let (file_id, abs_path) = self
+            .load(path)
+            .map_err(|e| vec![Diagnostic::error("E0002", e)])?;
+
+        // 3. 执行管线
+        self.run_pipeline(file_id, abs_path)
+    }
+
+    /// [New] 入口：`loom rename`，把 path 文件 line:col (1-based, 列按字节) 处的符号改名为 new_name
+    /// 修改直接写回磁盘，返回每个被修改的文件和修改处数。程序有错误时拒绝改名
+    /// (分析不完整时引用可能漏掉，改出来的代码反而更乱)
+    pub fn rename(
+        &mut self,
+        path: &Path,
+        line: usize,
+        col: usize,
+        new_name: &str,
+    ) -> Result<Vec<(PathBuf, usize)>, String> {
+        let (file_id, abs_path) = self.load(path)?;
+        if let Err(errors) = self.analyze(file_id, abs_path.clone()) {
+            return Err(self.render(&errors));
+        }
+        // 引用可能来自任何 import 了这个文件的模块：把整个项目都分析一遍。
+        // 和它无关的文件有错误不影响；依赖它的文件有错误时拒绝 (那里的引用可能没记录下来)
+        for (failed, errors) in self.analyze_project() {
+            if self.ctx.depends_on(&failed, &abs_path) {
+                return Err(self.render(&errors));
+            }
+        }
+
+        let offset = self
+            .ctx
+            .source_manager
+            .get_file(file_id)
+            .offset_at(line.wrapping_sub(1), col.wrapping_sub(1))
+            .ok_or_else(|| format!("Position {}:{} is out of range", line, col))?;
+        let edits = crate::refactor::rename(&self.ctx, file_id, offset, new_name)
+            .map_err(|e| format!("Error: cannot rename: {}", e))?;
+
+        // 按文件分组
+        let mut files: Vec<FileId> = edits.iter().map(|edit| edit.file_id).collect();
+        files.sort();
+        files.dedup();
+        let mut changed = Vec::new();
+        for file_id in files {
+            let file = self.ctx.source_manager.get_file(file_id);
+            let src = crate::refactor::apply_edits(&file.src, file_id, &edits);
+            std::fs::write(&file.path, &src)
+                .map_err(|e| format!("Could not write file {:?}: {}", file.path, e))?;
+            let count = edits.iter().filter(|edit| edit.file_id == file_id).count();
+            changed.push((file.path.clone(), count));
+        }
+        Ok(changed)
+    }
+
+    /// [New] 把项目里还没分析过 (或已被作废) 的文件都作为入口分析一遍，
+    /// 这样 SemanticDB 里有整个项目的引用 (Find References / Rename 用)
+    /// 返回分析失败的文件和它们的错误
+    pub fn analyze_project(&mut self) -> Vec<(PathBuf, Vec<Diagnostic>)> {
+        let mut failed = Vec::new();
+        for path in self.ctx.project_files() {
+            let Ok((file_id, abs_path)) = self.load(&path) else {
+                continue;
+            };
+            if self
+                .ctx
+                .modules
+                .get(&abs_path)
+                .is_some_and(|m| !m.has_errors)
+            {
+                continue;
+            }
+            if let Err(errors) = self.analyze(file_id, abs_path.clone()) {
+                failed.push((abs_path, errors));
+            }
+        }
+        failed
+    }
+
+    /// 读入文件，返回 FileId 和绝对路径
+    fn load(&mut self, path: &Path) -> Result<(FileId, PathBuf), String> {
         // 1. 转为绝对路径 (Canonicalize)
         // 这一步必须做，确保 FileId 的唯一性基于绝对路径
         let abs_path = path
@@ -38,98 +188,21 @@ impl Driver {
             .source_manager
             .load_file(&abs_path)
             .map_err(|e| format!("Could not load file {:?}: {}", abs_path, e))?;
-
-        // 3. 执行管线
-        self.run_pipeline(file_id, abs_path)
+        Ok((file_id, abs_path))
     }
 
     // core/src/driver.rs
 
     /// 核心编译管线
-    fn run_pipeline(&mut self, file_id: FileId, path: PathBuf) -> Result<Value, String> {
-        let source = self.ctx.source_manager.get_file(file_id).src.as_str();
-
-        // ==========================================
-        // Step 1: Parsing (语法解析)
-        // ==========================================
-        let lexer = Lexer::new(source);
-        let mut parser = Parser::new(source, lexer, file_id, &mut self.ctx.interner);
-
-        // 解析出主程序的 AST (Owned)
-        let program = match parser.parse_program() {
-            Ok(p) => p,
-            Err(e) => {
-                let msg = format!("Parse Error: {}", e.message);
-                return Err(self.format_diagnostic(file_id, e.span, &msg));
-            }
-        };
-
-        if !parser.errors.is_empty() {
-            let mut error_msgs = Vec::new();
-            for err in &parser.errors {
-                error_msgs.push(self.format_diagnostic(
-                    file_id,
-                    err.span,
-                    &format!("Syntax Error: {}", err.message),
-                ));
+    fn run_pipeline(&mut self, file_id: FileId, path: PathBuf) -> Result<Value, Vec<Diagnostic>> {
+        let program = self.analyze(file_id, path.clone())?;
+        // 警告不影响运行，在程序输出之前打印到 stderr
+        if !self.warnings.is_empty() {
+            let rendered = self.render(&self.warnings);
+            match self.message_format {
+                MessageFormat::Human => eprintln!("{}\n", rendered),
+                MessageFormat::Json => eprintln!("{}", rendered),
             }
-            return Err(error_msgs.join("\n\n"));
-        }
-
-        // ==========================================
-        // Step 2: Analysis (语义分析)
-        // ==========================================
-        let mut analyzer = Analyzer::new(&mut self.ctx, file_id);
-
-        // 注意：analyzer 会递归加载 import 的模块，并把它们的 AST 缓存在 ctx.modules 里
-        analyzer.collect_program(&program);
-        analyzer.resolve_hierarchy();
-        analyzer.check_program(&program);
-
-        if !analyzer.errors.is_empty() {
-            let error_msgs: Vec<String> = analyzer
-                .errors
-                .iter()
-                .map(|e| self.format_semantic_error(e))
-                .collect();
-            return Err(error_msgs.join("\n\n"));
-        }
-        {
-            // 1. 获取主文件的 Analyzer 产物 (Clone 出来)
-            let main_tables = analyzer.tables.clone();
-            let main_functions = analyzer.functions.clone();
-            let main_globals = analyzer.globals.clone();
-
-            // 2. 收集 AST (可选，Interpreter 3.2 步骤已经手动处理了 Main AST，但为了 ModuleInfo 完整性最好加上)
-            let mut main_ast_defs = HashMap::new();
-            let mut main_ast_funcs = HashMap::new();
-
-            for item in &program.definitions {
-                match item {
-                    TopLevelItem::Table(def) => {
-                        main_ast_defs.insert(def.name, Rc::new(def.clone()));
-                    }
-                    TopLevelItem::Function(func) => {
-                        main_ast_funcs.insert(func.name, Rc::new(func.clone()));
-                    }
-                    _ => {}
-                }
-            }
-
-            // 3. 构建 ModuleInfo
-            let main_module_info = crate::analyzer::ModuleInfo {
-                file_id,
-                file_path: path.clone(), // 使用传入的 path (已经是绝对路径)
-                tables: main_tables,
-                functions: main_functions,
-                globals: main_globals,
-                ast_definitions: main_ast_defs,
-                ast_functions: main_ast_funcs,
-                program: Rc::new(program.clone()),
-            };
-
-            // 4. 插入 Context
-            self.ctx.modules.insert(path.clone(), main_module_info);
         }
 
         // ==========================================
@@ -160,9 +233,7 @@ impl Driver {
         }
 
         // --- 3.2 注入主程序 (Main) 的定义 ---
-        // Main 的 Program 目前还在我们手里 (program 变量)，需要封装成 Rc
-        let main_program_rc = Rc::new(program.clone()); // 这里的 Clone 无法避免，除非重构 Parser 返回 Rc
-        module_programs.insert(file_id, main_program_rc);
+        module_programs.insert(file_id, program.clone());
 
         for item in &program.definitions {
             match item {
@@ -177,75 +248,166 @@ impl Driver {
             }
         }
 
-        // --- 3.3 初始化 Interpreter ---
+        // --- 3.3 字节码后端 ---
+        if self.backend == Backend::Bytecode {
+            let mut vm = Vm::new(&mut self.ctx, file_id);
+            vm.max_call_depth = self.max_call_depth;
+            return match vm.run_program(&module_programs, &table_defs) {
+                Ok(v) => Ok(v),
+                Err(e) => Err(vec![e.to_diagnostic(&self.ctx.source_manager)]),
+            };
+        }
+
+        // --- 3.4 初始化 Interpreter ---
         let mut interpreter = Interpreter::new(&mut self.ctx, path, file_id);
 
         // 填充数据
         interpreter.table_definitions = table_defs;
         interpreter.function_definitions = func_defs;
         interpreter.module_programs = module_programs;
+        interpreter.max_call_depth = self.max_call_depth;
 
-        // --- 3.4 运行 ---
+        // --- 3.5 运行 ---
         // eval_program 会先执行 Main 的 TopLevel，然后尝试调用 main() 函数
         match interpreter.eval_program(&program) {
             Ok(v) => Ok(v),
-            Err(e) => Err(format!("Runtime Error: {}", e)),
+            Err(e) => Err(vec![e.to_diagnostic(&self.ctx.source_manager)]),
         }
     }
 
-    /// 专门格式化 SemanticError
-    fn format_semantic_error(&self, err: &SemanticError) -> String {
-        // err.kind 实现了 Display，所以可以直接 to_string
-        let msg = format!("Error: {}", err.kind);
-        // 使用 err 内部记录的 file_id (可能是 import 的文件)
-        self.format_diagnostic(err.file_id, err.span, &msg)
-    }
+    /// [New] 编译前端：解析 + 语义分析 (不执行)
+    /// 成功时主文件的 ModuleInfo 写入 ctx.modules，返回主程序 AST；
+    /// 失败时返回全部错误 (可能来自被 import 的文件)。LSP 也走这条路径
+    /// 解析结果和 import 的模块都缓存在 ctx 里，重复调用只重新检查被作废的部分
+    pub fn analyze(
+        &mut self,
+        file_id: FileId,
+        path: PathBuf,
+    ) -> Result<Rc<Program>, Vec<Diagnostic>> {
+        // ==========================================
+        // Step 1: Parsing (语法解析)
+        // ==========================================
+        // [Changed] 走 Context 的解析缓存：源码没变时复用上一次的 AST
+        let parsed = self.ctx.parse_file(file_id);
+        let program = match parsed.program {
+            Ok(p) => p,
+            Err(e) => return Err(vec![e.to_diagnostic(file_id)]),
+        };
 
-    /// 通用的类似 Rustc 的错误打印机
-    /// Error: message
-    ///   --> src/main.loom:10:5
-    ///    |
-    /// 10 |     x = "hello"
-    ///    |     ^^^^^^^^^^^
-    fn format_diagnostic(&self, file_id: FileId, span: Span, message: &str) -> String {
-        let file_name = self
-            .ctx
-            .source_manager
-            .get_file_name(file_id)
-            .unwrap_or("<unknown>");
+        // [Changed] 可恢复的语法错误 (例如写了一半的语句) 不再直接返回：
+        // 剩下的代码照常分析，编辑器的补全 / Hover 依赖这些结果
+        let syntax_errors: Vec<Diagnostic> = parsed
+            .errors
+            .iter()
+            .map(|err| err.to_diagnostic(file_id))
+            .collect();
+
+        // 重新检查前清掉上一次的记录，并重建它的 use 边
+        self.ctx.clear_semantics(file_id);
+        // [New] 项目文件可能变了，顶层名字索引在这次分析里按需重建
+        self.ctx.project_index = None;
+        self.ctx.module_deps.remove(&path);
 
-        // 使用 SourceManager 查找行号列号
-        if let Some((line, col, line_text)) =
-            self.ctx.source_manager.lookup_location(file_id, span.start)
+        // ==========================================
+        // Step 2: Analysis (语义分析)
+        // ==========================================
+        let mut analyzer = Analyzer::new(&mut self.ctx, file_id);
+
+        // 注意：analyzer 会递归加载 import 的模块，并把它们的 AST 缓存在 ctx.modules 里
+        analyzer.collect_program(&program);
+        analyzer.resolve_hierarchy();
+        analyzer.check_program(&program);
+
+        // 有语法错误时只报语法错误 (被跳过的语句会引出一连串无关的语义错误)
+        let mut errors: Vec<Diagnostic> = if syntax_errors.is_empty() {
+            // lint 依赖完整的 AST 和引用记录：有错误时 (引用可能没记录下来) 不跑，免得误报
+            if analyzer.errors.is_empty() {
+                analyzer.lint_program(&program);
+            }
+            analyzer.errors.iter().map(Diagnostic::from).collect()
+        } else {
+            syntax_errors
+        };
         {
-            // 计算高亮箭头的长度
-            // 确保不越界，且至少长度为 1
-            let highlight_len = if span.end > span.start {
-                let max_len = line_text.len().saturating_sub(col - 1);
-                std::cmp::min(span.end - span.start, max_len)
-            } else {
-                1
+            // 1. 获取主文件的 Analyzer 产物 (Clone 出来)
+            let main_tables = analyzer.tables.clone();
+            let main_functions = analyzer.functions.clone();
+            let main_globals = analyzer.globals.clone();
+            let main_enums = analyzer.enums.clone();
+
+            // 2. 收集 AST (可选，Interpreter 3.2 步骤已经手动处理了 Main AST，但为了 ModuleInfo 完整性最好加上)
+            let mut main_ast_defs = HashMap::new();
+            let mut main_ast_funcs = HashMap::new();
+
+            for item in &program.definitions {
+                match item {
+                    TopLevelItem::Table(def) => {
+                        main_ast_defs.insert(def.name, Rc::new(def.clone()));
+                    }
+                    TopLevelItem::Function(func) => {
+                        main_ast_funcs.insert(func.name, Rc::new(func.clone()));
+                    }
+                    _ => {}
+                }
+            }
+
+            // 3. 构建 ModuleInfo
+            let main_module_info = crate::analyzer::ModuleInfo {
+                file_id,
+                file_path: path.clone(), // 使用传入的 path (已经是绝对路径)
+                tables: main_tables,
+                functions: main_functions,
+                globals: main_globals,
+                enums: main_enums,
+                ast_definitions: main_ast_defs,
+                ast_functions: main_ast_funcs,
+                program: program.clone(),
+                has_errors: !errors.is_empty(),
+                warnings: analyzer.warnings,
             };
 
-            // 构建下划线
-            let pointer = "^".repeat(highlight_len);
-            let padding = " ".repeat(col.saturating_sub(1));
-
-            // 拼装格式
-            format!(
-                "{}\n  --> {}:{}:{}\n   |\n{:3}| {}\n   | {}{}",
-                message,              // Error: Type mismatch...
-                file_name,            // src/main.loom
-                line,                 // 10
-                col,                  // 5
-                line,                 // 10
-                line_text.trim_end(), // x = "hello"
-                padding,              //
-                pointer               // ^^^^^^^^^^^
-            )
+            // 4. 插入 Context (有错误时也插入，补全需要它的类信息)
+            self.ctx.modules.insert(path.clone(), main_module_info);
+        }
+
+        // [New] 警告：主文件和它 (直接或间接) import 的模块的 lint 结果，按 LintConfig 定级
+        // 被 deny 的 lint 和错误一样让分析失败
+        let (denied, warnings): (Vec<Diagnostic>, Vec<Diagnostic>) = self
+            .module_warnings(&path)
+            .iter()
+            .filter_map(|w| w.to_diagnostic(&self.lints))
+            .partition(|d| d.severity == Severity::Error);
+        errors.extend(denied);
+        if errors.is_empty() {
+            self.warnings = warnings;
+            Ok(program)
         } else {
-            // 如果找不到源码位置 (例如 Span 是 0..0 或文件丢失)
-            format!("{} (at {:?})", message, span)
+            self.warnings.clear();
+            errors.extend(warnings);
+            Err(errors)
         }
     }
+
+    /// 入口文件及其依赖模块的 lint 警告 (入口文件在前，其余按路径排序)
+    fn module_warnings(&self, path: &Path) -> Vec<LintWarning> {
+        let mut modules: Vec<&ModuleInfo> = self
+            .ctx
+            .modules
+            .values()
+            .filter(|m| m.file_path != path && self.ctx.depends_on(path, &m.file_path))
+            .collect();
+        modules.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        self.ctx
+            .modules
+            .get(path)
+            .into_iter()
+            .chain(modules)
+            .flat_map(|m| m.warnings.iter().cloned())
+            .collect()
+    }
+
+    /// [New] 按 message_format 渲染诊断 (文本或每行一条 JSON)
+    pub fn render(&self, diagnostics: &[Diagnostic]) -> String {
+        diagnostic::render_all(diagnostics, &self.ctx.source_manager, self.message_format)
+    }
 }
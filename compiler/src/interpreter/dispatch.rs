@@ -0,0 +1,70 @@
+// src/interpreter/dispatch.rs
+//
+// 方法分派
+// 每个类一张虚表 (由 Analyzer 在 resolve_hierarchy 之后合并好的 TableInfo.vtable 生成)，
+// 调用点再挂一个小的多态内联缓存，命中时连虚表都不用查。
+// 树遍历解释器和字节码虚拟机共用这两个结构，只是方法的表示 M 不同。
+
+use crate::analyzer::TableId;
+use crate::utils::Symbol;
+use std::collections::HashMap;
+
+/// 内联缓存最多记住的类数，超过后视为"超多态"调用点，不再缓存
+const POLYMORPHIC_LIMIT: usize = 4;
+
+/// 运行时虚表：继承来的方法和覆写后的方法已经合并，查找不需要遍历继承链
+#[derive(Debug)]
+pub struct VTable<M> {
+    methods: Vec<M>,
+    index: HashMap<Symbol, usize>,
+}
+
+impl<M> Default for VTable<M> {
+    fn default() -> Self {
+        Self {
+            methods: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl<M> VTable<M> {
+    /// 按虚表顺序追加方法
+    pub fn push(&mut self, name: Symbol, method: M) {
+        self.index.insert(name, self.methods.len());
+        self.methods.push(method);
+    }
+
+    pub fn lookup(&self, name: Symbol) -> Option<&M> {
+        self.index.get(&name).map(|&i| &self.methods[i])
+    }
+}
+
+/// 调用点的多态内联缓存 (类 -> 方法)
+#[derive(Debug)]
+pub struct InlineCache<M> {
+    entries: Vec<(TableId, M)>,
+}
+
+impl<M> Default for InlineCache<M> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<M> InlineCache<M> {
+    pub fn get(&self, class: TableId) -> Option<&M> {
+        self.entries
+            .iter()
+            .find(|(id, _)| *id == class)
+            .map(|(_, m)| m)
+    }
+
+    pub fn insert(&mut self, class: TableId, method: M) {
+        if self.entries.len() < POLYMORPHIC_LIMIT {
+            self.entries.push((class, method));
+        }
+    }
+}
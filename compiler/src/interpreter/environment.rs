@@ -64,3 +64,44 @@ impl Environment {
         false
     }
 }
+
+/// 函数调用帧
+/// 局部变量按 Analyzer 分配的槽位下标存取，不再逐层哈希查找
+#[derive(Debug, Default)]
+pub struct Frame {
+    slots: Vec<Value>,
+    // 外层函数帧 (depth > 0 的地址沿这条链向上找)
+    pub enclosing: Option<Rc<RefCell<Frame>>>,
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 读取槽位 (未写入过的槽位为 Nil)
+    pub fn get(&self, depth: u32, slot: u32) -> Value {
+        if depth > 0 {
+            return match &self.enclosing {
+                Some(enclosing) => enclosing.borrow().get(depth - 1, slot),
+                None => Value::Nil,
+            };
+        }
+        self.slots.get(slot as usize).cloned().unwrap_or(Value::Nil)
+    }
+
+    /// 写入槽位，帧按需增长
+    pub fn set(&mut self, depth: u32, slot: u32, value: Value) {
+        if depth > 0 {
+            if let Some(enclosing) = &self.enclosing {
+                enclosing.borrow_mut().set(depth - 1, slot, value);
+            }
+            return;
+        }
+        let idx = slot as usize;
+        if idx >= self.slots.len() {
+            self.slots.resize(idx + 1, Value::Nil);
+        }
+        self.slots[idx] = value;
+    }
+}
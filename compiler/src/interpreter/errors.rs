@@ -1,3 +1,7 @@
+use crate::diagnostic::Diagnostic;
+use crate::source::{FileId, SourceManager};
+use crate::utils::Span;
+
 #[derive(Debug, Clone)]
 pub enum RuntimeErrorKind {
     /// 变量未定义 (Lexical Scope)
@@ -25,10 +29,23 @@ pub enum RuntimeErrorKind {
         len: usize,
     },
 
+    /// [New] 切片范围越界 (负数端点已换算成正数)
+    SliceOutOfBounds {
+        start: i64,
+        end: i64,
+        len: usize,
+    },
+
+    /// [New] 参数的值不合法 (比如 range.step(0))
+    InvalidArgument(String),
+
     /// 属性不存在 (Member Access)
     PropertyNotFound {
         target_type: String,
         property: String,
+        /// [New] 近似的成员名 (见 ops::property_not_found)
+        /// 用 Box<str> 控制 RuntimeErrorKind 的大小
+        suggestion: Option<Box<str>>,
     },
 
     /// 除零错误
@@ -44,6 +61,140 @@ pub enum RuntimeErrorKind {
         src: String,
         target: String,
     },
+
+    /// [New] int as char 时码位无效 (超出范围或落在代理区)
+    InvalidCharCode(i64),
+
+    /// [New] 调用层数超过上限 (防止宿主栈溢出)，调用回溯见 RuntimeError::trace
+    StackOverflow {
+        limit: usize,
+    },
+}
+
+/// 源码位置：所在文件 + 区间
+pub type SourceLocation = (FileId, Span);
+
+/// [New] 带位置和 Loom 调用栈的运行时错误
+/// 由解释器 / VM 在错误抛出点补全位置，Driver 负责渲染
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    /// 出错表达式的位置 (无法定位时为 None)
+    pub location: Option<SourceLocation>,
+    /// Loom 调用栈，最内层在前
+    pub trace: Vec<StackFrame>,
+}
+
+/// 调用栈中的一帧：函数名 + 该帧当前执行到的位置
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackFrame {
+    pub name: String,
+    pub location: Option<SourceLocation>,
+}
+
+impl From<RuntimeErrorKind> for RuntimeError {
+    fn from(kind: RuntimeErrorKind) -> Self {
+        Self {
+            kind,
+            location: None,
+            trace: Vec::new(),
+        }
+    }
+}
+
+impl RuntimeErrorKind {
+    /// [New] 稳定的错误码 (分段见 diagnostic.rs)
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeErrorKind::UndefinedVariable(_) => "R0001",
+            RuntimeErrorKind::NotCallable(_) => "R0002",
+            RuntimeErrorKind::TypeError { .. } => "R0003",
+            RuntimeErrorKind::ArgumentCountMismatch { .. } => "R0004",
+            RuntimeErrorKind::IndexOutOfBounds { .. } => "R0005",
+            RuntimeErrorKind::PropertyNotFound { .. } => "R0006",
+            RuntimeErrorKind::DivisionByZero => "R0007",
+            RuntimeErrorKind::Custom(_) => "R0008",
+            RuntimeErrorKind::InvalidCast { .. } => "R0009",
+            RuntimeErrorKind::InvalidCharCode(_) => "R0013",
+            RuntimeErrorKind::StackOverflow { .. } => "R0010",
+            RuntimeErrorKind::SliceOutOfBounds { .. } => "R0011",
+            RuntimeErrorKind::InvalidArgument(_) => "R0012",
+            RuntimeErrorKind::Internal(_) => "R0999",
+        }
+    }
+}
+
+impl RuntimeError {
+    /// [New] 转换成诊断：Loom 调用栈作为一条 note (需要 SourceManager 把位置换成 文件:行:列)
+    pub fn to_diagnostic(&self, sm: &SourceManager) -> Diagnostic {
+        let mut diag = Diagnostic::error(self.kind.code(), self.kind.to_string());
+        if let Some((file_id, span)) = self.location {
+            diag = diag.with_primary(file_id, span);
+        }
+        if !self.trace.is_empty() {
+            diag = diag.with_note(self.format_trace(sm));
+        }
+        match &self.kind {
+            RuntimeErrorKind::StackOverflow { .. } => {
+                diag = diag.with_help("raise the limit with --max-call-depth=N");
+            }
+            RuntimeErrorKind::PropertyNotFound {
+                suggestion: Some(name),
+                ..
+            } => {
+                diag = diag.with_help(format!("did you mean '{}'?", name));
+            }
+            _ => {}
+        }
+        diag
+    }
+
+    fn format_trace(&self, sm: &SourceManager) -> String {
+        // 连续相同的帧 (递归) 合并成一行
+        let mut frames: Vec<(&StackFrame, usize)> = Vec::new();
+        for frame in &self.trace {
+            match frames.last_mut() {
+                Some((last, count)) if *last == frame => *count += 1,
+                _ => frames.push((frame, 1)),
+            }
+        }
+
+        // 回溯太长时只显示最内层和最外层的若干帧
+        const HEAD: usize = 12;
+        const TAIL: usize = 3;
+        let elided = frames.len() > HEAD + TAIL;
+
+        let mut out = String::from("stack trace:");
+        for (i, (frame, count)) in frames.iter().enumerate() {
+            if elided && i >= HEAD && i < frames.len() - TAIL {
+                if i == HEAD {
+                    let skipped: usize = frames[HEAD..frames.len() - TAIL]
+                        .iter()
+                        .map(|(_, n)| n)
+                        .sum();
+                    out.push_str(&format!("\n    ... {} more frames", skipped));
+                }
+                continue;
+            }
+            out.push_str(&format!("\n    at {}", frame.name));
+            if let Some((file_id, span)) = frame.location {
+                let file_name = sm.get_file_name(file_id).unwrap_or("<unknown>");
+                if let Some((line, col, _)) = sm.lookup_location(file_id, span.start) {
+                    out.push_str(&format!(" ({}:{}:{})", file_name, line, col));
+                }
+            }
+            if *count > 1 {
+                out.push_str(&format!(" (repeated {} times)", count));
+            }
+        }
+        out
+    }
+}
+
+impl std::fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
 }
 
 impl std::fmt::Display for RuntimeErrorKind {
@@ -76,9 +227,18 @@ impl std::fmt::Display for RuntimeErrorKind {
                     index, len
                 )
             }
+            RuntimeErrorKind::SliceOutOfBounds { start, end, len } => {
+                write!(
+                    f,
+                    "Index Error: slice {}..{} out of bounds (len {})",
+                    start, end, len
+                )
+            }
+            RuntimeErrorKind::InvalidArgument(msg) => write!(f, "Argument Error: {}", msg),
             RuntimeErrorKind::PropertyNotFound {
                 target_type,
                 property,
+                ..
             } => {
                 write!(
                     f,
@@ -92,6 +252,16 @@ impl std::fmt::Display for RuntimeErrorKind {
             RuntimeErrorKind::InvalidCast { src, target } => {
                 write!(f, "Cast Error: cannot cast type '{}' to '{}'", src, target)
             }
+            RuntimeErrorKind::InvalidCharCode(code) => {
+                write!(f, "Cast Error: invalid char code point {}", code)
+            }
+            RuntimeErrorKind::StackOverflow { limit } => {
+                write!(
+                    f,
+                    "Stack Overflow: maximum call depth of {} exceeded",
+                    limit
+                )
+            }
         }
     }
 }
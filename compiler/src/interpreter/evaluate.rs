@@ -12,33 +12,54 @@ macro_rules! require_ok {
 
 mod field_access;
 
-use super::environment::Environment;
+use super::environment::{Environment, Frame};
 use super::errors::RuntimeErrorKind;
-use super::value::{Instance, Value};
+use super::ops;
+use super::value::{Closure, Instance, Value};
 use super::{EvalResult, Interpreter};
 use crate::analyzer::TableId;
 use crate::ast::*;
-use crate::utils::Symbol;
+use crate::utils::{NodeId, Span, Symbol};
 use std::cell::RefCell;
 use std::rc::Rc;
 
 impl<'a> Interpreter<'a> {
     /// === 主入口：表达式求值 ===
     pub fn evaluate(&mut self, expr: &Expression) -> EvalResult {
+        let result = self.eval_expr(expr);
+        // [New] 错误向上传播时，第一个看到它的表达式就是出错点
+        if matches!(result, EvalResult::Err(_)) {
+            self.record_error_origin(expr.id, expr.span);
+        }
+        result
+    }
+
+    fn eval_expr(&mut self, expr: &Expression) -> EvalResult {
         match &expr.data {
             // 1. 原子类型
             ExpressionData::Literal(lit) => self.eval_literal(lit),
-            ExpressionData::Identifier(sym) => self.eval_identifier(*sym),
+            ExpressionData::Identifier(sym) => self.eval_identifier(expr.id, *sym),
 
             // 2. 访问与调用
-            ExpressionData::FieldAccess { target, field } => self.eval_field_access(target, *field),
+            ExpressionData::FieldAccess { target, field } => {
+                self.eval_field_access(expr.id, target, *field)
+            }
             ExpressionData::Index { target, index } => self.eval_index(target, index),
-            ExpressionData::Call { callee, args, .. } => self.eval_call(callee, args),
+            ExpressionData::Call { callee, args, .. } => {
+                self.eval_call((expr.id, expr.span), callee, args)
+            }
 
             // 3. 赋值
             ExpressionData::Assign { op, target, value } => {
                 self.eval_assignment(*op, target, value)
             }
+            ExpressionData::Destructure { pattern, value, .. } => {
+                let val = require_ok!(self.evaluate(value));
+                match self.bind_pattern(pattern, val) {
+                    Ok(()) => EvalResult::Ok(Value::Unit),
+                    Err(e) => EvalResult::Err(e),
+                }
+            }
 
             // 4. 运算
             ExpressionData::Binary { op, left, right } => self.eval_binary(*op, left, right),
@@ -55,34 +76,65 @@ impl<'a> Interpreter<'a> {
 
             // 6. 其他
             ExpressionData::Array(elements) => self.eval_array(elements),
+            ExpressionData::Tuple(elements) => self.eval_tuple(elements),
 
             ExpressionData::Return(val) => self.eval_return(val),
 
             ExpressionData::VariableDefinition { name, init, .. } => {
                 let val = require_ok!(self.evaluate(init));
-                self.environment.borrow_mut().define(*name, val);
+                let Some(addr) = self.ctx.db.slot_of(expr.id) else {
+                    return self.unresolved_local(*name);
+                };
+                self.frame.borrow_mut().set(addr.depth, addr.slot, val);
                 EvalResult::Ok(Value::Unit)
             }
             ExpressionData::For {
-                iterator,
+                pattern,
                 iterable,
                 body,
-            } => self.eval_for(*iterator, iterable, body),
+            } => self.eval_for(pattern, iterable, body),
             ExpressionData::Break { .. } => EvalResult::Break,
             ExpressionData::Continue => EvalResult::Continue,
 
-            ExpressionData::Range { start, end, .. } => {
-                let start_val = require_ok!(self.evaluate(start));
-                let end_val = require_ok!(self.evaluate(end));
-                EvalResult::Ok(Value::Range(Box::new(start_val), Box::new(end_val)))
+            ExpressionData::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                // [Changed] 省略的端点是 nil (只会出现在切片下标里)
+                let start_val = match start {
+                    Some(start) => require_ok!(self.evaluate(start)),
+                    None => Value::Nil,
+                };
+                let end_val = match end {
+                    Some(end) => require_ok!(self.evaluate(end)),
+                    None => Value::Nil,
+                };
+                match ops::make_range(start_val, end_val, *inclusive) {
+                    Ok(range) => EvalResult::Ok(range),
+                    Err(e) => EvalResult::Err(e),
+                }
             }
             ExpressionData::Cast { expr, target_type } => self.eval_cast(expr, target_type),
+            ExpressionData::Match { scrutinee, arms } => self.eval_match(scrutinee, arms),
+            ExpressionData::VariantLiteral { target, fields } => {
+                let ctor = require_ok!(self.evaluate(target));
+                let mut values = Vec::with_capacity(fields.len());
+                for (name, value) in fields {
+                    values.push((*name, require_ok!(self.evaluate(value))));
+                }
+                match ops::construct_struct_variant(self.ctx, ctor, values) {
+                    Ok(v) => EvalResult::Ok(v),
+                    Err(e) => EvalResult::Err(e),
+                }
+            }
 
-            // [Error] 不支持的表达式
-            _ => EvalResult::Err(RuntimeErrorKind::Internal(format!(
-                "Unsupported expression type: {:?}",
-                expr.data
-            ))),
+            // [New] 匿名函数：捕获当前帧 (外层局部变量) 和模块环境
+            ExpressionData::Lambda(def) => EvalResult::Ok(Value::Closure(Rc::new(Closure {
+                def: def.clone(),
+                frame: self.frame.clone(),
+                globals: self.globals.clone(),
+            }))),
         }
     }
 
@@ -90,8 +142,14 @@ impl<'a> Interpreter<'a> {
     //            Section 1: Basic
     // ==========================================
 
-    fn eval_identifier(&mut self, sym: Symbol) -> EvalResult {
-        match self.environment.borrow().get(sym) {
+    fn eval_identifier(&mut self, id: NodeId, sym: Symbol) -> EvalResult {
+        // 局部变量：直接按槽位取值
+        if let Some(addr) = self.ctx.db.slot_of(id) {
+            return EvalResult::Ok(self.frame.borrow().get(addr.depth, addr.slot));
+        }
+
+        // 全局变量 / 内置函数：查模块环境
+        match self.globals.borrow().get(sym) {
             Some(val) => EvalResult::Ok(val),
             None => EvalResult::Err(RuntimeErrorKind::UndefinedVariable(
                 self.ctx.resolve_symbol(sym).to_string(),
@@ -99,23 +157,28 @@ impl<'a> Interpreter<'a> {
         }
     }
 
+    /// 局部变量定义没有槽位，说明 Analyzer 没有检查过这段代码
+    fn unresolved_local(&self, name: Symbol) -> EvalResult {
+        EvalResult::Err(RuntimeErrorKind::Internal(format!(
+            "Local variable '{}' has no slot (not analyzed)",
+            self.ctx.resolve_symbol(name)
+        )))
+    }
+
     fn eval_literal(&self, lit: &Literal) -> EvalResult {
-        let val = match lit {
-            Literal::Int(i) => Value::Int(*i),
-            Literal::Float(f) => Value::Float(*f),
-            Literal::String(s) => Value::Str(s.clone()),
-            Literal::Bool(b) => Value::Bool(*b),
-            Literal::Nil => Value::Nil,
-            _ => Value::Nil,
-        };
-        EvalResult::Ok(val)
+        EvalResult::Ok(ops::literal_value(lit))
     }
 
     // ==========================================
     //          Section 2: Access & Call
     // ==========================================
 
-    fn eval_call(&mut self, callee: &Expression, args: &[CallArg]) -> EvalResult {
+    fn eval_call(
+        &mut self,
+        site: (NodeId, Span),
+        callee: &Expression,
+        args: &[CallArg],
+    ) -> EvalResult {
         let func = require_ok!(self.evaluate(callee));
 
         let mut arg_values = Vec::new();
@@ -125,7 +188,7 @@ impl<'a> Interpreter<'a> {
 
         match func {
             // [Case A] 原生函数
-            Value::NativeFunction(_) => self.call_value(func, &arg_values, None),
+            Value::NativeFunction(_) => self.call_value(func, &arg_values, Some(site)),
 
             // [Case B] 绑定原生方法 (str.len, arr.push)
             Value::BoundNativeMethod(receiver, f) => {
@@ -143,7 +206,7 @@ impl<'a> Interpreter<'a> {
 
             // [Case C] 用户自定义方法 (Bound Method)
             Value::BoundMethod(instance, method_def, def_env) => {
-                self.call_user_method(instance, &method_def, def_env, &arg_values)
+                self.call_user_method(instance, &method_def, def_env, &arg_values, Some(site))
             }
 
             // [Case D] 顶层函数 (Top-level Function)
@@ -151,9 +214,12 @@ impl<'a> Interpreter<'a> {
             // 不使用 @，直接匹配解构，然后重组传给 call_value
             Value::Function(file_id, func_name, env) => {
                 let func_val = Value::Function(file_id, func_name, env);
-                self.call_value(func_val, &arg_values, None)
+                self.call_value(func_val, &arg_values, Some(site))
             }
 
+            // [New] 闭包
+            Value::Closure(_) => self.call_value(func, &arg_values, Some(site)),
+
             // [Case E] 构造函数调用 (Table)
             Value::Table(table_id) => {
                 // 1. 查找 Table 定义
@@ -168,7 +234,15 @@ impl<'a> Interpreter<'a> {
                 };
 
                 // 2. 实例化
-                self.instantiate_table(&def, table_id.file_id())
+                self.instantiate_table(&def, table_id.file_id(), Some(site))
+            }
+
+            // [New] 元组变体的构造器 Shape.Circle(1.0)
+            Value::VariantCtor(desc, tag) => {
+                match ops::construct_variant(self.ctx, desc, tag, arg_values) {
+                    Ok(v) => EvalResult::Ok(v),
+                    Err(e) => EvalResult::Err(e),
+                }
             }
 
             // [Error] 不可调用
@@ -184,15 +258,8 @@ impl<'a> Interpreter<'a> {
         method: &MethodDefinition,
         def_env: Rc<RefCell<Environment>>, // [New] 传入定义环境
         args: &[Value],
+        call_site: Option<(NodeId, Span)>,
     ) -> EvalResult {
-        // 1. 构造局部环境
-        // [Key Fix] 父环境是 def_env (定义时的模块环境)
-        // 这样方法内部就能访问到它定义所在文件的全局变量了！
-        let mut env = Environment::with_enclosing(def_env.clone());
-
-        // 2. 定义 self 和参数
-        env.define(self.ctx.intern("self"), Value::Instance(receiver));
-
         if args.len() != method.params.len() {
             return EvalResult::Err(RuntimeErrorKind::ArgumentCountMismatch {
                 func_name: self.ctx.resolve_symbol(method.name).to_string(),
@@ -200,21 +267,31 @@ impl<'a> Interpreter<'a> {
                 found: args.len(),
             });
         }
+
+        if let Err(e) = self.enter_call(method.name, call_site) {
+            return EvalResult::Err(e);
+        }
+
+        // 1. 构造调用帧：self 和参数写入 Analyzer 分配的槽位
+        let mut frame = Frame::new();
+        if let Some(addr) = self.ctx.db.slot_of(method.id) {
+            frame.set(0, addr.slot, Value::Instance(receiver));
+        }
         for (i, param) in method.params.iter().enumerate() {
-            env.define(param.name, args[i].clone());
+            if let Some(addr) = self.ctx.db.slot_of(param.id) {
+                frame.set(0, addr.slot, args[i].clone());
+            }
         }
 
-        // 3. 切换上下文 (保存 -> 切换 -> 执行 -> 恢复)
-        let prev_env = self.environment.clone();
+        // 2. 切换上下文 (保存 -> 切换 -> 执行 -> 恢复)
+        let prev_frame = std::mem::replace(&mut self.frame, Rc::new(RefCell::new(frame)));
         let prev_globals = self.globals.clone(); // 保存当前的 globals
 
-        // 切换到方法内部环境
-        self.environment = Rc::new(RefCell::new(env));
         // [Key Fix] 切换 globals 为定义该方法的模块环境
         // 这样在方法里再调用其他顶层函数时，也能找到正确的函数
         self.globals = def_env;
 
-        // 4. 执行方法体
+        // 3. 执行方法体
         let result = if let Some(body) = &method.body {
             self.execute_block(body)
         } else {
@@ -223,9 +300,10 @@ impl<'a> Interpreter<'a> {
             ))
         };
 
-        // 5. 恢复上下文
-        self.environment = prev_env;
+        // 4. 恢复上下文
+        self.frame = prev_frame;
         self.globals = prev_globals;
+        self.call_stack.pop();
 
         match result {
             EvalResult::Return(v) => EvalResult::Ok(v),
@@ -253,17 +331,20 @@ impl<'a> Interpreter<'a> {
 
         match &target.data {
             ExpressionData::Identifier(name) => {
-                if self
-                    .environment
-                    .borrow_mut()
-                    .assign(*name, right_val.clone())
-                {
-                    EvalResult::Ok(Value::Unit)
-                } else {
-                    // 自动定义
-                    self.environment.borrow_mut().define(*name, right_val);
-                    EvalResult::Ok(Value::Unit)
+                // 局部变量 (包括自动定义的新变量) 都已分配槽位
+                if let Some(addr) = self.ctx.db.slot_of(target.id) {
+                    self.frame
+                        .borrow_mut()
+                        .set(addr.depth, addr.slot, right_val);
+                    return EvalResult::Ok(Value::Unit);
                 }
+
+                // 否则是模块级全局变量
+                let mut globals = self.globals.borrow_mut();
+                if !globals.assign(*name, right_val.clone()) {
+                    globals.define(*name, right_val);
+                }
+                EvalResult::Ok(Value::Unit)
             }
 
             ExpressionData::FieldAccess {
@@ -272,8 +353,18 @@ impl<'a> Interpreter<'a> {
             } => {
                 let obj_val = require_ok!(self.evaluate(obj_expr));
                 if let Value::Instance(instance) = obj_val {
-                    instance.fields.borrow_mut().insert(*field, right_val);
-                    EvalResult::Ok(Value::Unit)
+                    // 实例布局是固定的，不能再动态添加字段
+                    let hint = self.ctx.db.field_slot_of(target.id);
+                    if instance.set_field(*field, hint, right_val) {
+                        EvalResult::Ok(Value::Unit)
+                    } else {
+                        EvalResult::Err(ops::property_not_found(
+                            self.ctx,
+                            &Value::Instance(instance),
+                            *field,
+                            true,
+                        ))
+                    }
                 } else {
                     EvalResult::Err(RuntimeErrorKind::TypeError {
                         expected: "Instance".into(),
@@ -289,22 +380,9 @@ impl<'a> Interpreter<'a> {
                 let arr_val = require_ok!(self.evaluate(arr_expr));
                 let idx_val = require_ok!(self.evaluate(idx_expr));
 
-                if let (Value::Array(vec_rc), Value::Int(i)) = (arr_val, idx_val) {
-                    let mut vec = vec_rc.borrow_mut();
-                    if i >= 0 && (i as usize) < vec.len() {
-                        vec[i as usize] = right_val;
-                        EvalResult::Ok(Value::Unit)
-                    } else {
-                        EvalResult::Err(RuntimeErrorKind::IndexOutOfBounds {
-                            index: i,
-                            len: vec.len(),
-                        })
-                    }
-                } else {
-                    EvalResult::Err(RuntimeErrorKind::TypeError {
-                        expected: "Array and Int".into(),
-                        found: "Invalid Types".into(),
-                    })
+                match ops::index_set(arr_val, idx_val, right_val) {
+                    Ok(()) => EvalResult::Ok(Value::Unit),
+                    Err(e) => EvalResult::Err(e),
                 }
             }
 
@@ -319,25 +397,13 @@ impl<'a> Interpreter<'a> {
     // ==========================================
 
     pub(super) fn execute_block(&mut self, block: &Block) -> EvalResult {
-        let prev_env = self.environment.clone();
-        self.environment = Rc::new(RefCell::new(Environment::with_enclosing(prev_env.clone())));
-
+        // 块内局部变量的槽位已由 Analyzer 分配，这里不需要再建作用域
         let mut last_val = Value::Unit;
 
         for stmt in &block.statements {
-            let result = self.evaluate(stmt);
-            match result {
-                EvalResult::Ok(v) => {
-                    last_val = v;
-                }
-                other_result => {
-                    self.environment = prev_env;
-                    return other_result;
-                }
-            }
+            last_val = require_ok!(self.evaluate(stmt));
         }
 
-        self.environment = prev_env;
         EvalResult::Ok(last_val)
     }
 
@@ -349,7 +415,7 @@ impl<'a> Interpreter<'a> {
     ) -> EvalResult {
         let cond_val = require_ok!(self.evaluate(condition));
 
-        if self.is_truthy(&cond_val) {
+        if ops::is_truthy(&cond_val) {
             self.execute_block(then_block)
         } else if let Some(else_blk) = else_block {
             self.execute_block(else_blk)
@@ -376,14 +442,8 @@ impl<'a> Interpreter<'a> {
                 break;
             }
 
-            let prev_env = self.environment.clone();
-            let loop_env = Environment::with_enclosing(prev_env.clone());
-            self.environment = Rc::new(RefCell::new(loop_env));
-
             let result = self.execute_block(body);
 
-            self.environment = prev_env;
-
             match result {
                 EvalResult::Ok(_) => continue,
                 EvalResult::Continue => continue,
@@ -397,7 +457,7 @@ impl<'a> Interpreter<'a> {
 
     fn eval_for(
         &mut self,
-        iterator_sym: Symbol,
+        iterator: &Pattern,
         iterable_expr: &Expression,
         body: &Block,
     ) -> EvalResult {
@@ -407,7 +467,7 @@ impl<'a> Interpreter<'a> {
             Value::Array(arr_rc) => {
                 let elements = arr_rc.borrow().clone();
                 for item in elements {
-                    let result = self.eval_loop_body(body, Some((iterator_sym, item)));
+                    let result = self.eval_loop_body(body, iterator, item);
                     match result {
                         EvalResult::Ok(_) => continue,
                         EvalResult::Continue => continue,
@@ -420,8 +480,7 @@ impl<'a> Interpreter<'a> {
 
             Value::Str(s) => {
                 for c in s.chars() {
-                    let char_val = Value::Str(c.to_string());
-                    let result = self.eval_loop_body(body, Some((iterator_sym, char_val)));
+                    let result = self.eval_loop_body(body, iterator, Value::Char(c));
                     match result {
                         EvalResult::Ok(_) => continue,
                         EvalResult::Continue => continue,
@@ -432,29 +491,18 @@ impl<'a> Interpreter<'a> {
                 EvalResult::Ok(Value::Unit)
             }
 
-            Value::Range(start, end) => {
-                let start_i = match start.as_int() {
-                    Some(i) => i,
-                    None => {
-                        return EvalResult::Err(RuntimeErrorKind::TypeError {
-                            expected: "Int".into(),
-                            found: "Non-Int".into(),
-                        });
-                    }
-                };
-                let end_i = match end.as_int() {
-                    Some(i) => i,
-                    None => {
-                        return EvalResult::Err(RuntimeErrorKind::TypeError {
-                            expected: "Int".into(),
-                            found: "Non-Int".into(),
-                        });
-                    }
+            // [Changed] 按下标遍历，步长和方向见 RangeValue
+            Value::Range(range) => {
+                let len = match range.len() {
+                    Ok(len) => len,
+                    Err(e) => return EvalResult::Err(e),
                 };
-
-                for i in start_i..end_i {
-                    let int_val = Value::Int(i);
-                    let result = self.eval_loop_body(body, Some((iterator_sym, int_val)));
+                for k in 0..len {
+                    let item = match range.get(k) {
+                        Ok(i) => Value::Int(i),
+                        Err(e) => return EvalResult::Err(e),
+                    };
+                    let result = self.eval_loop_body(body, iterator, item);
                     match result {
                         EvalResult::Ok(_) => continue,
                         EvalResult::Continue => continue,
@@ -472,23 +520,63 @@ impl<'a> Interpreter<'a> {
         }
     }
 
-    fn eval_loop_body(
-        &mut self,
-        body: &Block,
-        iterator_sym: Option<(Symbol, Value)>,
-    ) -> EvalResult {
-        let prev_env = self.environment.clone();
-        let mut loop_env = Environment::with_enclosing(prev_env.clone());
+    /// [New] 按顺序找第一个匹配的分支，绑定模式里的名字后执行分支体
+    fn eval_match(&mut self, scrutinee: &Expression, arms: &[MatchArm]) -> EvalResult {
+        let value = require_ok!(self.evaluate(scrutinee));
+        for arm in arms {
+            let mut binds = Vec::new();
+            if !ops::match_pattern(&arm.pattern, &value, &mut binds) {
+                continue;
+            }
+            for ((binding, name), value) in arm.pattern.bindings().into_iter().zip(binds) {
+                self.bind_name(binding, name, value);
+            }
+            return self.execute_block(&arm.body);
+        }
+        // Analyzer 保证 match 是完整的
+        EvalResult::Err(RuntimeErrorKind::Internal(
+            "No match arm matched the value".into(),
+        ))
+    }
 
-        if let Some((sym, val)) = iterator_sym {
-            loop_env.define(sym, val);
+    fn eval_loop_body(&mut self, body: &Block, iterator: &Pattern, item: Value) -> EvalResult {
+        if let Err(e) = self.bind_pattern(iterator, item) {
+            return EvalResult::Err(e);
         }
+        self.execute_block(body)
+    }
 
-        self.environment = Rc::new(RefCell::new(loop_env));
-        let result = self.execute_block(body);
-        self.environment = prev_env;
+    /// [New] 把值绑定到模式里的名字上 (for 的迭代变量、解构赋值)
+    /// 和 `x = v` 一样：有槽位的写局部变量，否则写模块级全局变量
+    fn bind_pattern(&mut self, pattern: &Pattern, value: Value) -> Result<(), RuntimeErrorKind> {
+        match &pattern.data {
+            PatternData::Identifier(name) => self.bind_name(pattern, *name, value),
+            PatternData::Wildcard => {}
+            PatternData::Tuple(items) => {
+                let values = ops::unpack_tuple(value, items.len())?;
+                for (item, value) in items.iter().zip(values.iter()) {
+                    self.bind_pattern(item, value.clone())?;
+                }
+            }
+            // 可能匹配失败的模式只出现在 match 里 (见 eval_match)
+            PatternData::Literal(_) | PatternData::Variant { .. } => {
+                return Err(RuntimeErrorKind::Internal(
+                    "Refutable pattern outside of match".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
 
-        result
+    fn bind_name(&mut self, pattern: &Pattern, name: Symbol, value: Value) {
+        if let Some(addr) = self.ctx.db.slot_of(pattern.id) {
+            self.frame.borrow_mut().set(addr.depth, addr.slot, value);
+        } else {
+            let mut globals = self.globals.borrow_mut();
+            if !globals.assign(name, value.clone()) {
+                globals.define(name, value);
+            }
+        }
     }
 
     fn eval_return(&mut self, val_opt: &Option<Box<Expression>>) -> EvalResult {
@@ -517,50 +605,33 @@ impl<'a> Interpreter<'a> {
             TypeRefData::Named(sym) => {
                 let type_name = self.ctx.resolve_symbol(*sym);
 
-                match type_name {
-                    // --- 基础类型转换 ---
-                    "int" => match val {
-                        Value::Float(f) => EvalResult::Ok(Value::Int(f as i64)),
-                        Value::Int(i) => EvalResult::Ok(Value::Int(i)),
-                        Value::Bool(b) => EvalResult::Ok(Value::Int(if b { 1 } else { 0 })),
-                        _ => self.runtime_cast_error(val, "int"),
-                    },
-                    "float" => match val {
-                        Value::Int(i) => EvalResult::Ok(Value::Float(i as f64)),
-                        Value::Float(f) => EvalResult::Ok(Value::Float(f)),
-                        _ => self.runtime_cast_error(val, "float"),
-                    },
-                    "str" => {
-                        // as str: 显式转字符串
-                        EvalResult::Ok(Value::Str(val.to_string(&self.ctx.interner)))
-                    }
-                    "bool" => match val {
-                        Value::Bool(b) => EvalResult::Ok(Value::Bool(b)),
-                        _ => self.runtime_cast_error(val, "bool"),
-                    },
-
-                    // --- 对象类型转换 (RTTI) ---
-                    _ => {
-                        if let Value::Instance(ref instance) = val {
-                            // 执行运行时类型检查
-                            // 检查 instance 是否是 target_name 的实例或子类
-                            if self.check_instance_of(instance, *sym) {
-                                EvalResult::Ok(val)
-                            } else {
-                                let src_type = self
-                                    .ctx
-                                    .resolve_symbol(instance.table_id.symbol())
-                                    .to_string();
-                                EvalResult::Err(RuntimeErrorKind::InvalidCast {
-                                    src: src_type,
-                                    target: type_name.to_string(),
-                                })
-                            }
-                        } else {
-                            // 试图把非 Instance 转换为 Class
-                            self.runtime_cast_error(val, type_name)
-                        }
+                // --- 基础类型转换 ---
+                if let Some(res) = ops::cast_primitive(val.clone(), type_name, &self.ctx.interner) {
+                    return match res {
+                        Ok(v) => EvalResult::Ok(v),
+                        Err(e) => EvalResult::Err(e),
+                    };
+                }
+
+                // --- 对象类型转换 (RTTI) ---
+                if let Value::Instance(ref instance) = val {
+                    // 执行运行时类型检查
+                    // 检查 instance 是否是 target_name 的实例或子类
+                    if ops::instance_of(self.ctx, instance.table_id, *sym) {
+                        EvalResult::Ok(val)
+                    } else {
+                        let src_type = self
+                            .ctx
+                            .resolve_symbol(instance.table_id.symbol())
+                            .to_string();
+                        EvalResult::Err(RuntimeErrorKind::InvalidCast {
+                            src: src_type,
+                            target: type_name.to_string(),
+                        })
                     }
+                } else {
+                    // 试图把非 Instance 转换为 Class
+                    EvalResult::Err(ops::cast_error(&val, type_name, &self.ctx.interner))
                 }
             }
             // 对于泛型、数组等复杂类型，Analyzer 已经保证了结构兼容性
@@ -569,58 +640,22 @@ impl<'a> Interpreter<'a> {
         }
     }
 
-    /// 运行时类型检查 (Runtime Type Identification)
-    fn check_instance_of(&self, instance: &Instance, target_sym: Symbol) -> bool {
-        let mut current_id = instance.table_id;
-
-        loop {
-            // 比较 Symbol
-            if current_id.symbol() == target_sym {
-                return true;
-            }
-
-            // 向上查找
-            if let Some(parent_id) = self.get_parent_table_id(current_id) {
-                current_id = parent_id;
-            } else {
-                break;
-            }
-        }
-        false
-    }
-
-    fn runtime_cast_error(&self, val: Value, target: &str) -> EvalResult {
-        EvalResult::Err(RuntimeErrorKind::InvalidCast {
-            src: val.to_string(&self.ctx.interner),
-            target: target.to_string(),
-        })
-    }
-
     // ==========================================
     //          Section 6: Helpers
     // ==========================================
 
-    fn is_truthy(&self, val: &Value) -> bool {
-        match val {
-            Value::Nil => false,
-            Value::Bool(b) => *b,
-            Value::Int(i) => *i != 0,
-            _ => true,
-        }
-    }
-
     fn eval_binary(&mut self, op: BinaryOp, left: &Expression, right: &Expression) -> EvalResult {
         match op {
             BinaryOp::And => {
                 let left_val = require_ok!(self.evaluate(left));
-                if !self.is_truthy(&left_val) {
+                if !ops::is_truthy(&left_val) {
                     return EvalResult::Ok(left_val);
                 }
                 return self.evaluate(right);
             }
             BinaryOp::Or => {
                 let left_val = require_ok!(self.evaluate(left));
-                if self.is_truthy(&left_val) {
+                if ops::is_truthy(&left_val) {
                     return EvalResult::Ok(left_val);
                 }
                 return self.evaluate(right);
@@ -631,152 +666,18 @@ impl<'a> Interpreter<'a> {
         let l = require_ok!(self.evaluate(left));
         let r = require_ok!(self.evaluate(right));
 
-        let res = match op {
-            BinaryOp::Add => match (l, r) {
-                (Value::Int(a), Value::Int(b)) => Value::Int(a + b),
-                (Value::Float(a), Value::Float(b)) => Value::Float(a + b),
-                (Value::Str(a), Value::Str(b)) => Value::Str(a + &b),
-                (Value::Str(a), other) => Value::Str(format!("{}{}", a, other)),
-                (other, Value::Str(b)) => Value::Str(format!("{}{}", other, b)),
-                _ => {
-                    return EvalResult::Err(RuntimeErrorKind::TypeError {
-                        expected: "Addable".into(),
-                        found: "Mismatch".into(),
-                    });
-                }
-            },
-
-            BinaryOp::Sub => match (l, r) {
-                (Value::Int(a), Value::Int(b)) => Value::Int(a - b),
-                (Value::Float(a), Value::Float(b)) => Value::Float(a - b),
-                _ => {
-                    return EvalResult::Err(RuntimeErrorKind::TypeError {
-                        expected: "Number".into(),
-                        found: "Mismatch".into(),
-                    });
-                }
-            },
-
-            BinaryOp::Mul => match (l, r) {
-                (Value::Int(a), Value::Int(b)) => Value::Int(a * b),
-                (Value::Float(a), Value::Float(b)) => Value::Float(a * b),
-                _ => {
-                    return EvalResult::Err(RuntimeErrorKind::TypeError {
-                        expected: "Number".into(),
-                        found: "Mismatch".into(),
-                    });
-                }
-            },
-
-            BinaryOp::Div => match (l, r) {
-                (Value::Int(a), Value::Int(b)) => {
-                    if b == 0 {
-                        return EvalResult::Err(RuntimeErrorKind::DivisionByZero);
-                    }
-                    Value::Int(a / b)
-                }
-                (Value::Float(a), Value::Float(b)) => Value::Float(a / b),
-                _ => {
-                    return EvalResult::Err(RuntimeErrorKind::TypeError {
-                        expected: "Number".into(),
-                        found: "Mismatch".into(),
-                    });
-                }
-            },
-
-            BinaryOp::Mod => match (l, r) {
-                (Value::Int(a), Value::Int(b)) => {
-                    if b == 0 {
-                        return EvalResult::Err(RuntimeErrorKind::DivisionByZero);
-                    }
-                    Value::Int(a % b)
-                }
-                (Value::Float(a), Value::Float(b)) => Value::Float(a % b),
-                _ => {
-                    return EvalResult::Err(RuntimeErrorKind::TypeError {
-                        expected: "Number".into(),
-                        found: "Mismatch".into(),
-                    });
-                }
-            },
-
-            BinaryOp::Eq => Value::Bool(l == r),
-            BinaryOp::Neq => Value::Bool(l != r),
-
-            BinaryOp::Lt => match (l, r) {
-                (Value::Int(a), Value::Int(b)) => Value::Bool(a < b),
-                (Value::Float(a), Value::Float(b)) => Value::Bool(a < b),
-                (Value::Int(a), Value::Float(b)) => Value::Bool((a as f64) < b),
-                (Value::Float(a), Value::Int(b)) => Value::Bool(a < (b as f64)),
-                _ => {
-                    return EvalResult::Err(RuntimeErrorKind::TypeError {
-                        expected: "Comparable".into(),
-                        found: "Mismatch".into(),
-                    });
-                }
-            },
-
-            BinaryOp::Lte => match (l, r) {
-                (Value::Int(a), Value::Int(b)) => Value::Bool(a <= b),
-                (Value::Float(a), Value::Float(b)) => Value::Bool(a <= b),
-                (Value::Int(a), Value::Float(b)) => Value::Bool((a as f64) <= b),
-                (Value::Float(a), Value::Int(b)) => Value::Bool(a <= (b as f64)),
-                _ => {
-                    return EvalResult::Err(RuntimeErrorKind::TypeError {
-                        expected: "Comparable".into(),
-                        found: "Mismatch".into(),
-                    });
-                }
-            },
-
-            BinaryOp::Gt => match (l, r) {
-                (Value::Int(a), Value::Int(b)) => Value::Bool(a > b),
-                (Value::Float(a), Value::Float(b)) => Value::Bool(a > b),
-                (Value::Int(a), Value::Float(b)) => Value::Bool((a as f64) > b),
-                (Value::Float(a), Value::Int(b)) => Value::Bool(a > (b as f64)),
-                _ => {
-                    return EvalResult::Err(RuntimeErrorKind::TypeError {
-                        expected: "Comparable".into(),
-                        found: "Mismatch".into(),
-                    });
-                }
-            },
-
-            BinaryOp::Gte => match (l, r) {
-                (Value::Int(a), Value::Int(b)) => Value::Bool(a >= b),
-                (Value::Float(a), Value::Float(b)) => Value::Bool(a >= b),
-                (Value::Int(a), Value::Float(b)) => Value::Bool((a as f64) >= b),
-                (Value::Float(a), Value::Int(b)) => Value::Bool(a >= (b as f64)),
-                _ => {
-                    return EvalResult::Err(RuntimeErrorKind::TypeError {
-                        expected: "Comparable".into(),
-                        found: "Mismatch".into(),
-                    });
-                }
-            },
-
-            _ => Value::Unit,
-        };
-
-        EvalResult::Ok(res)
+        match ops::binary_op(op, l, r) {
+            Ok(v) => EvalResult::Ok(v),
+            Err(e) => EvalResult::Err(e),
+        }
     }
 
     fn eval_unary(&mut self, op: UnaryOp, expr: &Expression) -> EvalResult {
         let val = require_ok!(self.evaluate(expr));
-        let res = match op {
-            UnaryOp::Not => Value::Bool(!self.is_truthy(&val)),
-            UnaryOp::Neg => match val {
-                Value::Int(i) => Value::Int(-i),
-                Value::Float(f) => Value::Float(-f),
-                _ => {
-                    return EvalResult::Err(RuntimeErrorKind::TypeError {
-                        expected: "Number".into(),
-                        found: "Mismatch".into(),
-                    });
-                }
-            },
-        };
-        EvalResult::Ok(res)
+        match ops::unary_op(op, val) {
+            Ok(v) => EvalResult::Ok(v),
+            Err(e) => EvalResult::Err(e),
+        }
     }
 
     fn eval_array(&mut self, elements: &[Expression]) -> EvalResult {
@@ -784,29 +685,24 @@ impl<'a> Interpreter<'a> {
         for e in elements {
             vals.push(require_ok!(self.evaluate(e)));
         }
-        EvalResult::Ok(Value::Array(Rc::new(RefCell::new(vals))))
+        EvalResult::Ok(Value::Array(self.ctx.heap.alloc_array(vals)))
+    }
+
+    fn eval_tuple(&mut self, elements: &[Expression]) -> EvalResult {
+        let mut vals = Vec::with_capacity(elements.len());
+        for e in elements {
+            vals.push(require_ok!(self.evaluate(e)));
+        }
+        EvalResult::Ok(Value::Tuple(vals.into()))
     }
 
     fn eval_index(&mut self, target: &Expression, index: &Expression) -> EvalResult {
         let t_val = require_ok!(self.evaluate(target));
         let i_val = require_ok!(self.evaluate(index));
 
-        match (t_val, i_val) {
-            (Value::Array(arr), Value::Int(idx)) => {
-                let vec = arr.borrow();
-                if idx >= 0 && (idx as usize) < vec.len() {
-                    EvalResult::Ok(vec[idx as usize].clone())
-                } else {
-                    EvalResult::Err(RuntimeErrorKind::IndexOutOfBounds {
-                        index: idx,
-                        len: vec.len(),
-                    })
-                }
-            }
-            _ => EvalResult::Err(RuntimeErrorKind::TypeError {
-                expected: "Indexable".into(),
-                found: "Mismatch".into(),
-            }),
+        match ops::index_get(&mut self.ctx.heap, t_val, i_val) {
+            Ok(v) => EvalResult::Ok(v),
+            Err(e) => EvalResult::Err(e),
         }
     }
 }
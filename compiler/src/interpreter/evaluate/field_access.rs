@@ -1,31 +1,45 @@
 use super::*;
+use crate::interpreter::MethodImpl;
+use crate::interpreter::dispatch::VTable;
 use crate::interpreter::errors::RuntimeErrorKind;
-use crate::interpreter::native::*;
-use crate::interpreter::value::NativeFunc;
 
 use crate::source::FileId;
 
 impl<'a> Interpreter<'a> {
     /// === 核心入口：字段/方法访问 ===
-    pub fn eval_field_access(&mut self, target: &Expression, field: Symbol) -> EvalResult {
+    /// site 是 FieldAccess 节点自身的 ID，用作内联缓存的下标
+    pub fn eval_field_access(
+        &mut self,
+        site: NodeId,
+        target: &Expression,
+        field: Symbol,
+    ) -> EvalResult {
         // 1. 先计算 target 的值
         let target_val = require_ok!(self.evaluate(target));
 
         // 2. 根据值的类型分发处理逻辑
         match target_val {
-            Value::Instance(instance) => self.access_instance_member(instance, field),
+            Value::Instance(instance) => self.access_instance_member(site, instance, field),
 
             // [Modified] 匹配新的 Module 结构 (FileId, Env)
             Value::Module(file_id, _) => self.access_module_member(file_id, field),
 
-            // 将 String 和 Array 统一归类为原生类型处理
-            val @ Value::Str(_) | val @ Value::Array(_) => self.access_native_member(val, field),
+            // [New] Shape.Circle
+            val @ Value::Enum(_) => match ops::enum_member(self.ctx, val, field) {
+                Ok(v) => EvalResult::Ok(v),
+                Err(e) => EvalResult::Err(e),
+            },
+
+            // 将 String、Array 和 Range 统一归类为原生类型处理
+            val @ (Value::Str(_) | Value::Array(_) | Value::Range(_)) => {
+                self.access_native_member(val, field)
+            }
 
             _ => {
                 let _field_name = self.ctx.resolve_symbol(field);
                 // 试图访问比如 1.length，这是类型错误
                 EvalResult::Err(RuntimeErrorKind::TypeError {
-                    expected: "Instance, Module, String or Array".into(),
+                    expected: "Instance, Module, String, Array or Range".into(),
                     found: format!("{:?}", target_val), // 简单描述实际类型
                 })
             }
@@ -33,79 +47,104 @@ impl<'a> Interpreter<'a> {
     }
 
     /// === 辅助函数 1：处理实例成员 (Instance) ===
-    fn access_instance_member(&mut self, instance: Rc<Instance>, field: Symbol) -> EvalResult {
-        // 1. 优先查找实例自身的字段 (Fields)
-        if let Some(val) = instance.fields.borrow().get(&field) {
-            return EvalResult::Ok(val.clone());
+    fn access_instance_member(
+        &mut self,
+        site: NodeId,
+        instance: Rc<Instance>,
+        field: Symbol,
+    ) -> EvalResult {
+        // 1. 优先查找实例自身的字段 (Fields)，有静态下标时直接按下标取
+        if let Some(val) = instance.get_field(field, self.ctx.db.field_slot_of(site)) {
+            return EvalResult::Ok(val);
         }
 
-        // 2. 查找方法 (Methods) - 支持继承链
-        // [Modified] find_method_in_chain 现在返回 (MethodDefinition, Env)
-        if let Some((method_def, def_env)) = self.find_method_in_chain(instance.table_id, field) {
+        // 2. 查找方法 (Methods)：先查调用点的内联缓存，未命中再查虚表
+        let class = instance.table_id;
+        let cached = self
+            .inline_caches
+            .get(site.0 as usize)
+            .and_then(|cache| cache.get(class))
+            .cloned();
+
+        let method = match cached {
+            Some(method) => Some(method),
+            None => {
+                let found = self
+                    .vtable_for(class)
+                    .and_then(|vtable| vtable.lookup(field).cloned());
+                if let Some(method) = &found {
+                    self.cache_method(site, class, method.clone());
+                }
+                found
+            }
+        };
+
+        if let Some((method_def, def_env)) = method {
             // [Fix] 将环境打包进 BoundMethod
-            return EvalResult::Ok(Value::BoundMethod(instance.clone(), method_def, def_env));
+            return EvalResult::Ok(Value::BoundMethod(instance, method_def, def_env));
         }
 
-        let field_name = self.ctx.resolve_symbol(field);
-
         // [New] 结构化错误
-        EvalResult::Err(RuntimeErrorKind::PropertyNotFound {
-            target_type: "Instance".into(),
-            property: field_name.to_string(),
-        })
+        EvalResult::Err(ops::property_not_found(
+            self.ctx,
+            &Value::Instance(instance),
+            field,
+            false,
+        ))
     }
 
-    /// === 辅助函数 2：继承链查找核心逻辑 ===
-    fn find_method_in_chain(
-        &self,
-        start_id: TableId,
-        method_name: Symbol,
-    ) -> Option<(MethodDefinition, Rc<RefCell<Environment>>)> {
-        let mut current_table_id = start_id;
-
-        loop {
-            // 1. 查找定义
-            let table_def = self.table_definitions.get(&current_table_id)?;
-
-            // 2. 查找方法
-            for item in &table_def.data.items {
-                if let TableItem::Method(method_def) = item
-                    && method_def.name == method_name
-                {
-                    // 找到了！获取环境
-                    let file_id = current_table_id.file_id();
-                    if let Some(Value::Module(_, env)) = self.module_cache.get(&file_id) {
-                        return Some((method_def.clone(), env.clone()));
-                    }
-                    return None;
-                }
-            }
+    fn cache_method(&mut self, site: NodeId, class: TableId, method: MethodImpl) {
+        let idx = site.0 as usize;
+        if idx >= self.inline_caches.len() {
+            self.inline_caches.resize_with(idx + 1, Default::default);
+        }
+        self.inline_caches[idx].insert(class, method);
+    }
+
+    /// === 辅助函数 2：获取 (必要时构建) 类的虚表 ===
+    /// 虚表项来自 Analyzer 合并好的 TableInfo.vtable；
+    /// 继承来的方法直接复用父类虚表里的同一个 Rc，方法 AST 只在定义它的类上克隆一次
+    fn vtable_for(&mut self, table_id: TableId) -> Option<Rc<VTable<MethodImpl>>> {
+        if let Some(vtable) = self.vtables.get(&table_id) {
+            return Some(vtable.clone());
+        }
 
-            // 3. [New] 使用公共逻辑查找父类，继续循环
-            if let Some(parent_id) = self.get_parent_table_id(current_table_id) {
-                current_table_id = parent_id;
+        let entries = self.table_info(table_id)?.vtable.clone();
+        let def = self.table_definitions.get(&table_id)?.clone();
+
+        let mut vtable = VTable::default();
+        for entry in entries {
+            let method = if entry.owner == table_id {
+                let method_def = def.data.items.iter().find_map(|item| match item {
+                    TableItem::Method(m) if m.name == entry.name => Some(m),
+                    _ => None,
+                })?;
+                let env = match self.module_cache.get(&table_id.file_id()) {
+                    Some(Value::Module(_, env)) => env.clone(),
+                    _ => return None,
+                };
+                (Rc::new(method_def.clone()), env)
             } else {
-                break;
-            }
+                self.vtable_for(entry.owner)?.lookup(entry.name)?.clone()
+            };
+            vtable.push(entry.name, method);
         }
-        None
+
+        let vtable = Rc::new(vtable);
+        self.vtables.insert(table_id, vtable.clone());
+        Some(vtable)
     }
 
     /// === 辅助函数 3：处理模块导出 ===
     fn access_module_member(&self, file_id: FileId, field: Symbol) -> EvalResult {
         // 查 module_cache 里的 Environment
-        if let Some(Value::Module(_, env)) = self.module_cache.get(&file_id) {
+        if let Some(module @ Value::Module(_, env)) = self.module_cache.get(&file_id) {
             if let Some(val) = env.borrow().get(field) {
                 return EvalResult::Ok(val);
             }
 
-            let field_name = self.ctx.resolve_symbol(field);
-
             // [New] 结构化错误
-            return EvalResult::Err(RuntimeErrorKind::PropertyNotFound {
-                target_type: "Module".into(),
-                property: field_name.to_string(),
-            });
+            return EvalResult::Err(ops::property_not_found(self.ctx, module, field, false));
         }
 
         // [New] 结构化错误
@@ -117,35 +156,9 @@ impl<'a> Interpreter<'a> {
     /// === 辅助函数 4：处理原生类型方法 ===
     fn access_native_member(&mut self, target_val: Value, field: Symbol) -> EvalResult {
         let field_name = self.ctx.resolve_symbol(field);
-
-        match target_val {
-            Value::Str(_) => match field_name {
-                "len" => EvalResult::Ok(Value::BoundNativeMethod(
-                    Box::new(target_val),
-                    NativeFunc::new("len", native_str_len),
-                )),
-                _ => EvalResult::Err(RuntimeErrorKind::PropertyNotFound {
-                    target_type: "String".into(),
-                    property: field_name.to_string(),
-                }),
-            },
-
-            Value::Array(_) => match field_name {
-                "len" => EvalResult::Ok(Value::BoundNativeMethod(
-                    Box::new(target_val),
-                    NativeFunc::new("len", native_array_len),
-                )),
-                "push" => EvalResult::Ok(Value::BoundNativeMethod(
-                    Box::new(target_val),
-                    NativeFunc::new("push", native_array_push),
-                )),
-                _ => EvalResult::Err(RuntimeErrorKind::PropertyNotFound {
-                    target_type: "Array".into(),
-                    property: field_name.to_string(),
-                }),
-            },
-
-            _ => unreachable!("Should only be called for native types"),
+        match ops::native_member(target_val, field_name) {
+            Ok(v) => EvalResult::Ok(v),
+            Err(e) => EvalResult::Err(e),
         }
     }
 }
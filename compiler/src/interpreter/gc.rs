@@ -0,0 +1,236 @@
+// src/interpreter/gc.rs
+//
+// 循环垃圾回收
+//
+// 实例和数组仍然是 Rc，绝大多数对象在引用计数归零时就释放了；
+// 这里只处理计数永远降不到 0 的环 (父子互指、双向链表……)。
+//
+// 算法 (标记-清除，根由引用计数推出)：
+// 1. 所有实例/数组分配时登记在 Heap 里 (Weak，不影响计数)
+// 2. 统计每个对象被其它堆对象引用的次数 (内部引用)
+// 3. strong_count 大于内部引用数的对象必然还被堆外持有：
+//    模块环境、调用帧、VM 值栈、Rust 栈上的临时值……它们就是根
+// 4. 从根出发标记可达对象；剩下的只被垃圾环引用，清空其字段/元素断开环，Rc 随即释放
+//
+// 根不需要解释器显式枚举，所以两个后端在任意分配点触发回收都是安全的。
+// 限制：经过模块环境 (Value::Module / Value::Function 持有的 Environment) 的环不会被回收。
+// [New] 闭包捕获的帧 / upvalue 同理：闭包引用的对象都当作根，经过闭包的环也不会被回收。
+
+use super::value::{Instance, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+pub type ArrayRef = Rc<RefCell<Vec<Value>>>;
+
+/// 首次回收前允许的分配次数
+const INITIAL_THRESHOLD: usize = 1024;
+
+/// 回收统计 (gc_stats() 的数据来源)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GcStats {
+    /// 累计分配的对象数
+    pub allocated: usize,
+    /// 累计被回收器释放的对象数 (不含引用计数自然释放的)
+    pub freed: usize,
+    /// 回收次数
+    pub collections: usize,
+}
+
+/// 登记的堆对象 (回收期间临时持有强引用)
+enum Object {
+    Instance(Rc<Instance>),
+    Array(ArrayRef),
+}
+
+impl Object {
+    fn addr(&self) -> *const () {
+        match self {
+            Object::Instance(rc) => Rc::as_ptr(rc) as *const (),
+            Object::Array(rc) => Rc::as_ptr(rc) as *const (),
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Object::Instance(rc) => Rc::strong_count(rc),
+            Object::Array(rc) => Rc::strong_count(rc),
+        }
+    }
+
+    /// 枚举直接引用的堆对象；内容正被借用时返回 false
+    fn for_each_child(&self, f: &mut impl FnMut(*const ())) -> bool {
+        let values = match self {
+            Object::Instance(rc) => rc.fields.try_borrow(),
+            Object::Array(rc) => rc.try_borrow(),
+        };
+        match values {
+            Ok(values) => {
+                values.iter().for_each(|v| visit_value(v, f));
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// 清空内容以断开环，返回被移出的值 (由调用方统一释放)
+    fn clear(&self) -> Vec<Value> {
+        match self {
+            Object::Instance(rc) => std::mem::take(&mut *rc.fields.borrow_mut()),
+            Object::Array(rc) => std::mem::take(&mut *rc.borrow_mut()),
+        }
+    }
+}
+
+/// 值里直接持有的堆对象
+fn visit_value(val: &Value, f: &mut impl FnMut(*const ())) {
+    match val {
+        Value::Instance(rc) | Value::BoundMethod(rc, ..) | Value::BoundCompiledMethod(rc, _) => {
+            f(Rc::as_ptr(rc) as *const ())
+        }
+        Value::Array(rc) => f(Rc::as_ptr(rc) as *const ()),
+        Value::BoundNativeMethod(receiver, _) => visit_value(receiver, f),
+        // [New] 元组本身不登记；只有它被这一处独占时，里面的引用才算作内部引用。
+        // 共享的元组 (同时还在栈上、别的字段里……) 不往里看，它引用的对象保守地当作根
+        Value::Tuple(items) if Rc::strong_count(items) == 1 => {
+            items.iter().for_each(|v| visit_value(v, f))
+        }
+        // [New] 枚举值同理
+        Value::Variant(value) if Rc::strong_count(value) == 1 => {
+            value.payload.iter().for_each(|v| visit_value(v, f))
+        }
+        _ => {}
+    }
+}
+
+#[derive(Debug)]
+pub struct Heap {
+    instances: Vec<Weak<Instance>>,
+    arrays: Vec<Weak<RefCell<Vec<Value>>>>,
+    /// 距离上次回收的分配次数
+    since_last: usize,
+    threshold: usize,
+    pub stats: GcStats,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self {
+            instances: Vec::new(),
+            arrays: Vec::new(),
+            since_last: 0,
+            threshold: INITIAL_THRESHOLD,
+            stats: GcStats::default(),
+        }
+    }
+
+    pub fn alloc_instance(&mut self, instance: Instance) -> Rc<Instance> {
+        self.before_alloc();
+        let rc = Rc::new(instance);
+        self.instances.push(Rc::downgrade(&rc));
+        rc
+    }
+
+    pub fn alloc_array(&mut self, elements: Vec<Value>) -> ArrayRef {
+        self.before_alloc();
+        let rc = Rc::new(RefCell::new(elements));
+        self.arrays.push(Rc::downgrade(&rc));
+        rc
+    }
+
+    /// 当前仍存活的登记对象数
+    pub fn live(&self) -> usize {
+        let instances = self.instances.iter().filter(|w| w.strong_count() > 0);
+        let arrays = self.arrays.iter().filter(|w| w.strong_count() > 0);
+        instances.count() + arrays.count()
+    }
+
+    fn before_alloc(&mut self) {
+        self.stats.allocated += 1;
+        self.since_last += 1;
+        if self.since_last >= self.threshold {
+            self.collect();
+        }
+    }
+
+    /// 执行一次完整回收，返回释放的对象数
+    pub fn collect(&mut self) -> usize {
+        // 1. 取出所有存活对象 (顺便丢掉已经自然释放的登记项)
+        let objects: Vec<Object> = self
+            .instances
+            .drain(..)
+            .filter_map(|w| w.upgrade().map(Object::Instance))
+            .chain(
+                self.arrays
+                    .drain(..)
+                    .filter_map(|w| w.upgrade().map(Object::Array)),
+            )
+            .collect();
+        let index: HashMap<*const (), usize> = objects
+            .iter()
+            .enumerate()
+            .map(|(i, o)| (o.addr(), i))
+            .collect();
+
+        // 2. 统计内部引用
+        let mut internal = vec![0usize; objects.len()];
+        let mut pinned = vec![false; objects.len()];
+        for (i, obj) in objects.iter().enumerate() {
+            pinned[i] = !obj.for_each_child(&mut |addr| {
+                if let Some(&j) = index.get(&addr) {
+                    internal[j] += 1;
+                }
+            });
+        }
+
+        // 3. 标记：被堆外引用的对象 (减去 objects 自身持有的 1) 作为根
+        let mut marked = vec![false; objects.len()];
+        let mut worklist = Vec::new();
+        for (i, obj) in objects.iter().enumerate() {
+            if pinned[i] || obj.strong_count() - 1 > internal[i] {
+                marked[i] = true;
+                worklist.push(i);
+            }
+        }
+        while let Some(i) = worklist.pop() {
+            objects[i].for_each_child(&mut |addr| {
+                if let Some(&j) = index.get(&addr)
+                    && !marked[j]
+                {
+                    marked[j] = true;
+                    worklist.push(j);
+                }
+            });
+        }
+
+        // 4. 清除：断开不可达对象的引用，存活对象重新登记
+        let mut garbage = Vec::new();
+        let mut freed = 0;
+        for (obj, live) in objects.iter().zip(&marked) {
+            if !*live {
+                garbage.push(obj.clear());
+                freed += 1;
+                continue;
+            }
+            match obj {
+                Object::Instance(rc) => self.instances.push(Rc::downgrade(rc)),
+                Object::Array(rc) => self.arrays.push(Rc::downgrade(rc)),
+            }
+        }
+        drop(garbage);
+        drop(objects);
+
+        let survivors = self.instances.len() + self.arrays.len();
+        self.threshold = INITIAL_THRESHOLD.max(survivors * 2);
+        self.since_last = 0;
+        self.stats.freed += freed;
+        self.stats.collections += 1;
+        freed
+    }
+}
@@ -1,26 +1,31 @@
 // src/interpreter.rs
 
+pub mod dispatch;
 pub mod environment;
 pub mod errors;
 pub mod evaluate;
+pub mod gc;
 pub mod native;
+pub mod ops;
 pub mod value;
+pub mod vm;
 
-use crate::analyzer::TableId;
 use crate::analyzer::resolve_module_path;
+use crate::analyzer::{TableId, TableInfo};
 use crate::ast::*;
 use crate::context::Context;
 use crate::source::FileId;
-use crate::utils::Symbol;
-use environment::Environment;
+use crate::utils::{NodeId, Span, Symbol};
+use dispatch::{InlineCache, VTable};
+use environment::{Environment, Frame};
 
 // [New] 引入具体的错误类型
-use errors::RuntimeErrorKind;
+use errors::{RuntimeError, RuntimeErrorKind, SourceLocation, StackFrame};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::rc::Rc;
-use value::{Instance, Value};
+use value::{EnumDesc, FieldLayout, Instance, Value};
 
 /// 解释器的求值结果
 #[derive(Debug, Clone)]
@@ -48,6 +53,9 @@ impl EvalResult {
     }
 }
 
+/// 树遍历解释器里的方法实现：方法 AST + 定义所在模块的环境
+pub type MethodImpl = (Rc<MethodDefinition>, Rc<RefCell<Environment>>);
+
 pub struct Interpreter<'a> {
     pub ctx: &'a mut Context,
 
@@ -58,8 +66,9 @@ pub struct Interpreter<'a> {
     // 全局环境 -> 指向"当前正在执行的模块的全局环境"
     pub globals: Rc<RefCell<Environment>>,
 
-    // 当前局部环境 (函数调用时会变)
-    pub environment: Rc<RefCell<Environment>>,
+    // 当前调用帧 (函数调用时会变)
+    // 局部变量按 Analyzer 记录的槽位存取，全局变量仍然走 globals
+    pub frame: Rc<RefCell<Frame>>,
 
     // 模块缓存
     // 防止重复加载，解决循环依赖
@@ -74,19 +83,46 @@ pub struct Interpreter<'a> {
     pub table_definitions: HashMap<TableId, Rc<TableDefinition>>,
     pub function_definitions: HashMap<(FileId, Symbol), Rc<MethodDefinition>>,
 
+    // [New] 方法分派：每个类的虚表 (首次使用时构建) + 按 NodeId 索引的调用点内联缓存
+    pub vtables: HashMap<TableId, Rc<VTable<MethodImpl>>>,
+    pub inline_caches: Vec<InlineCache<MethodImpl>>,
+
+    // [New] 每个类的字段布局 (首次实例化时构建，同类实例共享)
+    pub layouts: HashMap<TableId, Rc<FieldLayout>>,
+
     pub current_file_path: PathBuf,
     pub main_file_id: FileId,
     pub current_file_id: FileId, // 当前执行文件 ID
+
+    // [New] 当前的 Loom 调用栈，用于限制递归深度和报错回溯
+    pub call_stack: Vec<CallRecord>,
+    pub max_call_depth: usize,
+
+    // [New] 最内层出错表达式的位置与当时的调用栈 (错误向上传播时由 evaluate 记录一次)
+    error_origin: Option<(SourceLocation, Vec<StackFrame>)>,
 }
 
+/// [New] 调用栈上的一条记录 (函数/方法/类初始化)
+#[derive(Debug, Clone, Copy)]
+pub struct CallRecord {
+    pub name: Symbol,
+    /// 调用表达式 (节点 ID 用于反查文件；宿主直接调用时为 None)
+    pub call_site: Option<(NodeId, Span)>,
+}
+
+/// [New] 默认的最大调用深度 (两个后端共用，可通过 Driver::with_max_call_depth 调整)
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 10_000;
+
 impl<'a> Interpreter<'a> {
     pub fn new(ctx: &'a mut Context, main_file_path: PathBuf, main_file_id: FileId) -> Self {
         // 1. 初始化内置环境
         let builtins = Rc::new(RefCell::new(Environment::new()));
-        builtins.borrow_mut().define(
-            ctx.intern("print"),
-            Value::NativeFunction(value::NativeFunc::new("print", native::native_print)),
-        );
+        for (name, func) in native::GLOBAL_NATIVES {
+            builtins.borrow_mut().define(
+                ctx.intern(name),
+                Value::NativeFunction(value::NativeFunc::new(name, *func)),
+            );
+        }
         // 这里还可以 define("int", Value::Table(primitive_int)) 等
 
         // 2. 初始化 Main 模块的环境
@@ -98,19 +134,25 @@ impl<'a> Interpreter<'a> {
         Self {
             ctx,
             builtins: builtins.clone(),
-            globals: main_env.clone(),     // 初始 globals 是 Main
-            environment: main_env.clone(), // 初始 env 也是 Main
+            globals: main_env.clone(), // 初始 globals 是 Main
+            frame: Rc::new(RefCell::new(Frame::new())),
             module_cache: HashMap::new(),
             table_definitions: HashMap::new(),
             function_definitions: HashMap::new(),
+            vtables: HashMap::new(),
+            inline_caches: Vec::new(),
+            layouts: HashMap::new(),
             module_programs: HashMap::new(),
             current_file_path: abs_main_path,
             current_file_id: main_file_id,
             main_file_id,
+            call_stack: Vec::new(),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            error_origin: None,
         }
     }
 
-    pub fn eval_program(&mut self, program: &Program) -> Result<Value, String> {
+    pub fn eval_program(&mut self, program: &Program) -> Result<Value, RuntimeError> {
         // Step 1: 把 Main 放入缓存
         // 这样 Main 自己 import 自己（虽然少见）或者是循环依赖时也能工作
         let main_module_val = Value::Module(self.main_file_id, self.globals.clone());
@@ -126,7 +168,7 @@ impl<'a> Interpreter<'a> {
 
     // --- Loading / Top Level Phase ---
 
-    fn run_top_level(&mut self, program: &Program) -> Result<(), String> {
+    fn run_top_level(&mut self, program: &Program) -> Result<(), RuntimeError> {
         for item in &program.definitions {
             match item {
                 TopLevelItem::Table(def) => {
@@ -135,6 +177,14 @@ impl<'a> Interpreter<'a> {
                     self.globals.borrow_mut().define(def.name, val);
                 }
 
+                // [New] 枚举：类型对象，成员访问得到变体
+                TopLevelItem::Enum(def) => {
+                    let desc = EnumDesc::new(self.current_file_id, def);
+                    self.globals
+                        .borrow_mut()
+                        .define(def.name, Value::Enum(Rc::new(desc)));
+                }
+
                 TopLevelItem::Function(func_def) => {
                     let val = Value::Function(
                         self.current_file_id, // 确保这是当前文件的 ID
@@ -146,11 +196,21 @@ impl<'a> Interpreter<'a> {
 
                 TopLevelItem::Field(field_def) => {
                     let val = if let Some(expr) = &field_def.value {
-                        match self.evaluate(expr) {
+                        // 初值表达式在独立的帧里求值 (与 Analyzer 的槽位分配一致)
+                        let prev_frame = std::mem::take(&mut self.frame);
+                        let result = self.evaluate(expr);
+                        self.frame = prev_frame;
+
+                        match result {
                             EvalResult::Ok(v) => v,
-                            // [Fix] 错误转换
-                            EvalResult::Err(e) => return Err(e.to_string()),
-                            _ => return Err("Control flow error in global init".into()),
+                            // [Fix] 错误转换 (附带出错位置)
+                            EvalResult::Err(e) => return Err(self.take_runtime_error(e)),
+                            _ => {
+                                return Err(RuntimeErrorKind::Internal(
+                                    "Control flow error in global init".into(),
+                                )
+                                .into());
+                            }
                         }
                     } else {
                         Value::Nil
@@ -165,7 +225,7 @@ impl<'a> Interpreter<'a> {
     }
 
     // [Refactor] 真正的模块加载逻辑
-    fn bind_module(&mut self, stmt: &UseStatement) -> Result<(), String> {
+    fn bind_module(&mut self, stmt: &UseStatement) -> Result<(), RuntimeError> {
         let module_name_sym = stmt.path.last().unwrap();
         let bind_name = stmt.alias.unwrap_or(*module_name_sym);
 
@@ -182,7 +242,9 @@ impl<'a> Interpreter<'a> {
 
         // 1. 解析路径
         let target_path = resolve_module_path(self.ctx, &stmt.anchor, &path_segments, current_dir)
-            .ok_or_else(|| format!("Module not found: {:?}", path_segments))?;
+            .ok_or_else(|| {
+                RuntimeErrorKind::Custom(format!("Module not found: {:?}", path_segments))
+            })?;
         let abs_path = target_path.canonicalize().unwrap_or(target_path);
 
         // 2. 加载文件 (获取 FileId)
@@ -190,7 +252,7 @@ impl<'a> Interpreter<'a> {
             .ctx
             .source_manager
             .load_file(&abs_path)
-            .map_err(|e| format!("IO Error: {}", e))?;
+            .map_err(|e| RuntimeErrorKind::Custom(format!("IO Error: {}", e)))?;
 
         // 3. [Check Cache] 检查是否已加载
         let module_val = if let Some(cached) = self.module_cache.get(&file_id) {
@@ -210,7 +272,7 @@ impl<'a> Interpreter<'a> {
         &mut self,
         file_id: FileId,
         path: PathBuf,
-    ) -> Result<Value, String> {
+    ) -> Result<Value, RuntimeError> {
         // A. 创建新环境
         let module_env = Rc::new(RefCell::new(Environment::with_enclosing(
             self.builtins.clone(),
@@ -220,22 +282,21 @@ impl<'a> Interpreter<'a> {
 
         // B. 保存上下文
         let prev_globals = self.globals.clone();
-        let prev_env = self.environment.clone();
+        let prev_frame = std::mem::take(&mut self.frame);
         let prev_path = self.current_file_path.clone();
         let prev_file_id = self.current_file_id;
 
         // C. 切换上下文
-        self.globals = module_env.clone();
-        self.environment = module_env;
+        self.globals = module_env;
         self.current_file_path = path;
         self.current_file_id = file_id;
 
         // D. 获取完整 AST
         let program = self.module_programs.get(&file_id).cloned().ok_or_else(|| {
-            format!(
+            RuntimeErrorKind::Internal(format!(
                 "Runtime: AST for module {:?} not found (Driver issue)",
                 file_id
-            )
+            ))
         })?;
 
         // E. 执行
@@ -243,7 +304,7 @@ impl<'a> Interpreter<'a> {
 
         // F. 恢复上下文
         self.globals = prev_globals;
-        self.environment = prev_env;
+        self.frame = prev_frame;
         self.current_file_path = prev_path;
         self.current_file_id = prev_file_id;
 
@@ -253,7 +314,7 @@ impl<'a> Interpreter<'a> {
 
     // --- Execution Phase ---
 
-    fn run_main_entry(&mut self) -> Result<Value, String> {
+    fn run_main_entry(&mut self) -> Result<Value, RuntimeError> {
         let main_sym = self.ctx.intern("main");
 
         // 1. 在全局环境 (Globals) 中查找 main 函数
@@ -269,50 +330,50 @@ impl<'a> Interpreter<'a> {
         match self.call_value(main_func, &[], None) {
             EvalResult::Ok(v) => Ok(v),
             EvalResult::Return(v) => Ok(v),
-            EvalResult::Err(e) => Err(e.to_string()),
-            _ => Err("Runtime Error: Control flow escape".into()),
+            EvalResult::Err(e) => Err(self.take_runtime_error(e)),
+            _ => Err(RuntimeErrorKind::Internal("Control flow escape".into()).into()),
         }
     }
 
     /// === 2. 实例化 Table ===
-    fn instantiate_table(&mut self, def: &TableDefinition, file_id: FileId) -> EvalResult {
-        let mut fields_map = HashMap::new();
+    fn instantiate_table(
+        &mut self,
+        def: &TableDefinition,
+        file_id: FileId,
+        call_site: Option<(NodeId, Span)>,
+    ) -> EvalResult {
         let table_id = TableId(file_id, def.name);
 
         // --- Step 1: 准备阶段 (只读) ---
-        let fields_to_init: Vec<(Symbol, Option<Expression>)> = {
-            let file_path = match self.ctx.source_manager.get_file_path(file_id) {
-                Some(p) => p,
-                None => {
-                    return EvalResult::Err(RuntimeErrorKind::Internal("File path missing".into()));
-                }
-            };
-            let mod_info = match self.ctx.modules.get(file_path) {
-                Some(m) => m,
-                None => {
-                    return EvalResult::Err(RuntimeErrorKind::Internal(
-                        "ModuleInfo missing".into(),
-                    ));
-                }
-            };
-            let table_info = match mod_info.tables.get(&table_id) {
+        // 按布局顺序 (父类字段在前) 取出每个字段的初值表达式
+        let (layout, fields_to_init) = {
+            let table_info = match self.table_info(table_id) {
                 Some(t) => t,
                 None => {
                     return EvalResult::Err(RuntimeErrorKind::Internal("TableInfo missing".into()));
                 }
             };
-            table_info
-                .fields
+            let inits: Vec<Option<Expression>> = table_info
+                .field_layout
                 .iter()
-                .map(|(name, info)| (*name, info.value.clone()))
-                .collect()
+                .map(|name| table_info.fields.get(name).and_then(|f| f.value.clone()))
+                .collect();
+            let layout = self
+                .layouts
+                .get(&table_id)
+                .cloned()
+                .unwrap_or_else(|| Rc::new(FieldLayout::new(table_info.field_layout.clone())));
+            (layout, inits)
         };
+        self.layouts
+            .entry(table_id)
+            .or_insert_with(|| layout.clone());
 
         // --- Step 2: 执行阶段 (关键修改) ---
 
         // 1. 保存“案发现场” (Caller's Context)
         // 这是当前正在执行代码的环境 (比如 main.lm)
-        let caller_env = self.environment.clone();
+        let caller_frame = self.frame.clone();
         let caller_globals = self.globals.clone();
 
         // 2. 找到“定义现场” (Definer's Context)
@@ -333,39 +394,48 @@ impl<'a> Interpreter<'a> {
             }
         };
 
+        // 字段初值里可能再调用函数 / 构造实例，所以初始化也算一层调用
+        if let Err(e) = self.enter_call(def.name, call_site) {
+            return EvalResult::Err(e);
+        }
+
         // 3. 切换环境
-        // self.environment 控制局部变量查找 (对于字段初始化，通常没有局部变量，但为了一致性设为 def_env)
         // self.globals 控制全局变量查找 (这最重要，决定了 default_hp 找谁)
-        self.environment = definer_env.clone();
         self.globals = definer_env;
 
         // 4. 执行初始化
         // 此时调用 self.evaluate，它眼中的“世界”变成了 lib.lm
-        for (name, init_expr_opt) in fields_to_init {
+        let mut field_values = Vec::with_capacity(layout.len());
+        for init_expr_opt in fields_to_init {
             let value = if let Some(expr) = &init_expr_opt {
+                // 每个字段初值使用独立的帧 (与 Analyzer 的槽位分配一致)
+                self.frame = Rc::new(RefCell::new(Frame::new()));
                 match self.evaluate(expr) {
                     EvalResult::Ok(v) => v,
                     other => {
                         // 出错如果要提前返回，一定要记得恢复环境！
-                        self.environment = caller_env;
+                        self.frame = caller_frame;
                         self.globals = caller_globals;
+                        self.call_stack.pop();
                         return other;
                     }
                 }
             } else {
                 Value::Nil
             };
-            fields_map.insert(name, value);
+            field_values.push(value);
         }
 
         // 5. 恢复环境
-        self.environment = caller_env;
+        self.frame = caller_frame;
         self.globals = caller_globals;
+        self.call_stack.pop();
 
         // --- Step 3: 构造实例 ---
-        let instance = Rc::new(Instance {
+        let instance = self.ctx.heap.alloc_instance(Instance {
             table_id,
-            fields: RefCell::new(fields_map),
+            layout,
+            fields: RefCell::new(field_values),
         });
 
         EvalResult::Ok(Value::Instance(instance))
@@ -376,7 +446,7 @@ impl<'a> Interpreter<'a> {
         &mut self,
         func: Value,
         args: &[Value],
-        _receiver: Option<Value>,
+        call_site: Option<(NodeId, Span)>,
     ) -> EvalResult {
         match func {
             // 1. 原生函数调用 (print)
@@ -397,9 +467,6 @@ impl<'a> Interpreter<'a> {
                     }
                 };
 
-                // C. 准备环境 (Context Switch)
-                let mut func_env = Environment::with_enclosing(captured_env.clone());
-
                 // 参数检查
                 if args.len() != func_def.params.len() {
                     return EvalResult::Err(RuntimeErrorKind::ArgumentCountMismatch {
@@ -409,15 +476,22 @@ impl<'a> Interpreter<'a> {
                     });
                 }
 
+                if let Err(e) = self.enter_call(func_name, call_site) {
+                    return EvalResult::Err(e);
+                }
+
+                // C. 准备调用帧：参数写入 Analyzer 分配的槽位
+                let mut frame = Frame::new();
                 for (i, param) in func_def.params.iter().enumerate() {
-                    func_env.define(param.name, args[i].clone());
+                    if let Some(addr) = self.ctx.db.slot_of(param.id) {
+                        frame.set(0, addr.slot, args[i].clone());
+                    }
                 }
 
                 // D. 切换上下文并执行
-                let prev_env = self.environment.clone();
+                let prev_frame = std::mem::replace(&mut self.frame, Rc::new(RefCell::new(frame)));
                 let prev_globals = self.globals.clone(); // 保存当前模块环境
 
-                self.environment = Rc::new(RefCell::new(func_env));
                 self.globals = captured_env;
 
                 // E. 执行
@@ -428,8 +502,9 @@ impl<'a> Interpreter<'a> {
                 };
 
                 // F. 恢复上下文
-                self.environment = prev_env;
+                self.frame = prev_frame;
                 self.globals = prev_globals; // 恢复回调用者的模块环境
+                self.call_stack.pop();
 
                 match result {
                     EvalResult::Return(v) => EvalResult::Ok(v),
@@ -437,64 +512,112 @@ impl<'a> Interpreter<'a> {
                 }
             }
 
-            // 3. 错误处理
-            _ => EvalResult::Err(RuntimeErrorKind::NotCallable(
-                func.to_string(&self.ctx.interner),
-            )),
-        }
-    }
-
-    /// [Core Helper] 获取指定 Table 的父类 TableId
-    /// 这一步封装了复杂的跨模块查找逻辑
-    fn get_parent_table_id(&self, table_id: TableId) -> Option<TableId> {
-        let def = self.table_definitions.get(&table_id)?;
-
-        // 1. 获取父类类型引用
-        let parent_ref = def.data.prototype.as_ref()?;
-
-        match &parent_ref.data {
-            // Case 1: 本地/已导入的具名引用 (Animal)
-            TypeRefData::Named(sym) => {
-                // 需要去定义该子类的模块环境中查找父类符号
-                // 1. 找到定义该 Table 的模块环境
-                let file_id = table_id.file_id();
-                if let Some(Value::Module(_, env)) = self.module_cache.get(&file_id) {
-                    // 2. 在该模块环境中查找符号
-                    if let Some(Value::Table(parent_id)) = env.borrow().get(*sym) {
-                        return Some(parent_id);
-                    }
+            // [New] 3. 闭包：新帧的外层是定义时捕获的帧 (depth > 0 的地址沿它向上找)
+            Value::Closure(closure) => {
+                let def = &closure.def;
+                if args.len() != def.params.len() {
+                    return EvalResult::Err(RuntimeErrorKind::ArgumentCountMismatch {
+                        func_name: self.ctx.resolve_symbol(def.name).to_string(),
+                        expected: def.params.len(),
+                        found: args.len(),
+                    });
                 }
-                None
-            }
 
-            // Case 2: 泛型实例 (List<T>) -> 实际上继承自 Base Table (List)
-            TypeRefData::GenericInstance { base, .. } => {
-                // 同上，去模块环境找 base
-                let file_id = table_id.file_id();
-                if let Some(Value::Module(_, env)) = self.module_cache.get(&file_id)
-                    && let Some(Value::Table(parent_id)) = env.borrow().get(*base)
-                {
-                    return Some(parent_id);
+                if let Err(e) = self.enter_call(def.name, call_site) {
+                    return EvalResult::Err(e);
                 }
-                None
-            }
 
-            // Case 3: 显式跨模块引用 (lib.Animal)
-            TypeRefData::Member { module, member } => {
-                let file_id = table_id.file_id();
-                if let Some(Value::Module(_, env)) = self.module_cache.get(&file_id) {
-                    // 1. 找模块对象 (animal_lib)
-                    if let Some(Value::Module(_, mod_env)) = env.borrow().get(*module) {
-                        // 2. 找导出类 (Animal)
-                        if let Some(Value::Table(parent_id)) = mod_env.borrow().get(*member) {
-                            return Some(parent_id);
-                        }
+                let mut frame = Frame::new();
+                frame.enclosing = Some(closure.frame.clone());
+                for (param, arg) in def.params.iter().zip(args) {
+                    if let Some(addr) = self.ctx.db.slot_of(param.id) {
+                        frame.set(0, addr.slot, arg.clone());
                     }
                 }
-                None
+
+                let prev_frame = std::mem::replace(&mut self.frame, Rc::new(RefCell::new(frame)));
+                let prev_globals = std::mem::replace(&mut self.globals, closure.globals.clone());
+
+                let result = match &def.body {
+                    Some(body) => self.execute_block(body),
+                    None => EvalResult::Ok(Value::Nil),
+                };
+
+                self.frame = prev_frame;
+                self.globals = prev_globals;
+                self.call_stack.pop();
+
+                // 闭包可能定义在循环里，但 break / continue 不能跳出闭包 (和字节码后端一致)
+                match result {
+                    EvalResult::Return(v) => EvalResult::Ok(v),
+                    EvalResult::Break | EvalResult::Continue => EvalResult::Err(
+                        RuntimeErrorKind::Internal("'break' or 'continue' outside of loop".into()),
+                    ),
+                    other => other,
+                }
             }
 
-            _ => None,
+            // 4. 错误处理
+            _ => EvalResult::Err(RuntimeErrorKind::NotCallable(
+                func.to_string(&self.ctx.interner),
+            )),
+        }
+    }
+
+    /// [New] 进入一次 Loom 调用；超过最大深度时返回 StackOverflow
+    fn enter_call(
+        &mut self,
+        name: Symbol,
+        call_site: Option<(NodeId, Span)>,
+    ) -> Result<(), RuntimeErrorKind> {
+        if self.call_stack.len() >= self.max_call_depth {
+            return Err(RuntimeErrorKind::StackOverflow {
+                limit: self.max_call_depth,
+            });
+        }
+        self.call_stack.push(CallRecord { name, call_site });
+        Ok(())
+    }
+
+    /// [New] 记录错误发生的位置和调用栈 (只记录最内层的一次)
+    fn record_error_origin(&mut self, node: NodeId, span: Span) {
+        if self.error_origin.is_some() {
+            return;
         }
+        let locate = |id: NodeId, span: Span| {
+            let file_id = self.ctx.file_of_node(id).unwrap_or(self.current_file_id);
+            (file_id, span)
+        };
+        let origin = locate(node, span);
+
+        // 每一帧的位置 = 它调用下一帧的位置；最内层帧的位置就是出错点
+        let mut trace = Vec::with_capacity(self.call_stack.len());
+        let mut location = Some(origin);
+        for record in self.call_stack.iter().rev() {
+            trace.push(StackFrame {
+                name: self.ctx.resolve_symbol(record.name).to_string(),
+                location,
+            });
+            location = record.call_site.map(|(id, span)| locate(id, span));
+        }
+        self.error_origin = Some((origin, trace));
+    }
+
+    /// [New] 把传播到顶层的错误和记录的位置合成 RuntimeError
+    fn take_runtime_error(&mut self, kind: RuntimeErrorKind) -> RuntimeError {
+        match self.error_origin.take() {
+            Some((location, trace)) => RuntimeError {
+                kind,
+                location: Some(location),
+                trace,
+            },
+            None => kind.into(),
+        }
+    }
+
+    /// [Helper] 跨模块获取 Analyzer 产出的 TableInfo
+    fn table_info(&self, table_id: TableId) -> Option<&TableInfo> {
+        let path = self.ctx.source_manager.get_file_path(table_id.file_id())?;
+        self.ctx.modules.get(path)?.tables.get(&table_id)
     }
 }
@@ -1,8 +1,9 @@
 // core/src/interpreter/native.rs
 
 use super::errors::RuntimeErrorKind;
-use super::value::Value;
+use super::value::{NativeFuncPtr, RangeValue, Value};
 use crate::context::Context;
+use std::rc::Rc;
 
 // === 辅助 Helper 函数 (让 Native 代码更干净) ===
 
@@ -40,7 +41,7 @@ fn expect_array(
 }
 
 /// 强制获取第 N 个参数为 String
-fn expect_string(args: &[Value], index: usize) -> Result<&String, RuntimeErrorKind> {
+fn expect_string(args: &[Value], index: usize) -> Result<&str, RuntimeErrorKind> {
     match args.get(index) {
         Some(Value::Str(s)) => Ok(s),
         Some(other) => Err(RuntimeErrorKind::TypeError {
@@ -53,6 +54,34 @@ fn expect_string(args: &[Value], index: usize) -> Result<&String, RuntimeErrorKi
     }
 }
 
+/// [New] 参数检查：范围
+fn expect_range(args: &[Value], index: usize) -> Result<&RangeValue, RuntimeErrorKind> {
+    match args.get(index) {
+        Some(Value::Range(range)) => Ok(range),
+        Some(other) => Err(RuntimeErrorKind::TypeError {
+            expected: "Range".into(),
+            found: other_type_name(other),
+        }),
+        None => Err(RuntimeErrorKind::Internal(
+            "Missing argument in expect_range".into(),
+        )),
+    }
+}
+
+/// [New] 参数检查：整数
+fn expect_int(args: &[Value], index: usize) -> Result<i64, RuntimeErrorKind> {
+    match args.get(index) {
+        Some(Value::Int(i)) => Ok(*i),
+        Some(other) => Err(RuntimeErrorKind::TypeError {
+            expected: "int".into(),
+            found: other_type_name(other),
+        }),
+        None => Err(RuntimeErrorKind::Internal(
+            "Missing argument in expect_int".into(),
+        )),
+    }
+}
+
 /// 获取类型的显示名称 (用于报错)
 fn other_type_name(v: &Value) -> String {
     match v {
@@ -61,7 +90,10 @@ fn other_type_name(v: &Value) -> String {
         Value::Int(_) => "int",
         Value::Float(_) => "float",
         Value::Str(_) => "str",
+        Value::Char(_) => "char",
         Value::Array(_) => "Array",
+        Value::Tuple(_) => "tuple",
+        Value::Range(_) => "Range",
         Value::Instance(_i) => "Instance", // 这里虽然没办法拿 Interner，但这是 Native 层的简略报错
         _ => "unknown",
     }
@@ -70,6 +102,13 @@ fn other_type_name(v: &Value) -> String {
 
 // === Native 实现 ===
 
+/// [New] 全局内置函数表 (两个后端共用，类型声明见 Analyzer::register_builtins)
+pub const GLOBAL_NATIVES: &[(&str, NativeFuncPtr)] = &[
+    ("print", native_print),
+    ("gc_collect", native_gc_collect),
+    ("gc_stats", native_gc_stats),
+];
+
 pub fn native_print(ctx: &mut Context, args: &[Value]) -> Result<Value, RuntimeErrorKind> {
     for (i, arg) in args.iter().enumerate() {
         if i > 0 {
@@ -107,3 +146,52 @@ pub fn native_array_push(_ctx: &mut Context, args: &[Value]) -> Result<Value, Ru
 
     Ok(Value::Unit)
 }
+
+/// [New] range.len() -> int
+pub fn native_range_len(_ctx: &mut Context, args: &[Value]) -> Result<Value, RuntimeErrorKind> {
+    check_arg_count("len", args, 1)?;
+    Ok(Value::Int(expect_range(args, 0)?.len()?))
+}
+
+/// [New] range.contains(x) -> bool
+pub fn native_range_contains(
+    _ctx: &mut Context,
+    args: &[Value],
+) -> Result<Value, RuntimeErrorKind> {
+    check_arg_count("contains", args, 2)?;
+    let range = expect_range(args, 0)?;
+    Ok(Value::Bool(range.contains(expect_int(args, 1)?)?))
+}
+
+/// [New] range.rev() -> Range
+pub fn native_range_rev(_ctx: &mut Context, args: &[Value]) -> Result<Value, RuntimeErrorKind> {
+    check_arg_count("rev", args, 1)?;
+    Ok(Value::Range(Rc::new(expect_range(args, 0)?.rev()?)))
+}
+
+/// [New] range.step(n) -> Range
+pub fn native_range_step(_ctx: &mut Context, args: &[Value]) -> Result<Value, RuntimeErrorKind> {
+    check_arg_count("step", args, 2)?;
+    let range = expect_range(args, 0)?;
+    Ok(Value::Range(Rc::new(
+        range.with_step(expect_int(args, 1)?)?,
+    )))
+}
+
+/// gc_collect() -> int：立即执行一次循环回收，返回释放的对象数
+pub fn native_gc_collect(ctx: &mut Context, args: &[Value]) -> Result<Value, RuntimeErrorKind> {
+    check_arg_count("gc_collect", args, 0)?;
+    Ok(Value::Int(ctx.heap.collect() as i64))
+}
+
+/// gc_stats() -> [int]：[存活对象数, 累计回收数, 回收次数, 累计分配数]
+pub fn native_gc_stats(ctx: &mut Context, args: &[Value]) -> Result<Value, RuntimeErrorKind> {
+    check_arg_count("gc_stats", args, 0)?;
+    let stats = ctx.heap.stats;
+    let live = ctx.heap.live();
+    let values = [live, stats.freed, stats.collections, stats.allocated]
+        .iter()
+        .map(|n| Value::Int(*n as i64))
+        .collect();
+    Ok(Value::Array(ctx.heap.alloc_array(values)))
+}
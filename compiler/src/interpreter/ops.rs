@@ -0,0 +1,595 @@
+// src/interpreter/ops.rs
+//
+// 运算语义的唯一来源
+// 树遍历解释器 (evaluate) 和字节码虚拟机 (vm) 都调用这里，
+// 保证两个后端对同一段代码给出完全一致的结果和报错。
+
+use super::errors::RuntimeErrorKind;
+use super::gc::Heap;
+use super::native::*;
+use super::value::{EnumDesc, EnumValue, NativeFunc, RangeValue, Value};
+use crate::analyzer::{TableId, Type};
+use crate::ast::{BinaryOp, Literal, Pattern, PatternData, UnaryOp, VariantPatternArgs};
+use crate::context::Context;
+use crate::source::FileId;
+use crate::utils::{Interner, Symbol, best_match};
+use std::rc::Rc;
+
+/// 真值判断: nil / false / 0 为假，其余为真
+pub fn is_truthy(val: &Value) -> bool {
+    match val {
+        Value::Nil => false,
+        Value::Bool(b) => *b,
+        Value::Int(i) => *i != 0,
+        _ => true,
+    }
+}
+
+fn mismatch(expected: &str) -> RuntimeErrorKind {
+    RuntimeErrorKind::TypeError {
+        expected: expected.into(),
+        found: "Mismatch".into(),
+    }
+}
+
+/// 拼接两个字符串，结果只分配一次
+fn concat(a: &str, b: &str) -> Rc<str> {
+    let mut s = String::with_capacity(a.len() + b.len());
+    s.push_str(a);
+    s.push_str(b);
+    s.into()
+}
+
+/// 二元运算 (不含 And / Or，它们需要短路，由调用方处理)
+pub fn binary_op(op: BinaryOp, l: Value, r: Value) -> Result<Value, RuntimeErrorKind> {
+    let res = match op {
+        BinaryOp::Add => match (l, r) {
+            (Value::Int(a), Value::Int(b)) => Value::Int(a + b),
+            (Value::Float(a), Value::Float(b)) => Value::Float(a + b),
+            (Value::Str(a), Value::Str(b)) => Value::Str(concat(&a, &b)),
+            (Value::Str(a), other) => Value::Str(format!("{}{}", a, other).into()),
+            (other, Value::Str(b)) => Value::Str(format!("{}{}", other, b).into()),
+            _ => return Err(mismatch("Addable")),
+        },
+
+        BinaryOp::Sub => match (l, r) {
+            (Value::Int(a), Value::Int(b)) => Value::Int(a - b),
+            (Value::Float(a), Value::Float(b)) => Value::Float(a - b),
+            _ => return Err(mismatch("Number")),
+        },
+
+        BinaryOp::Mul => match (l, r) {
+            (Value::Int(a), Value::Int(b)) => Value::Int(a * b),
+            (Value::Float(a), Value::Float(b)) => Value::Float(a * b),
+            _ => return Err(mismatch("Number")),
+        },
+
+        BinaryOp::Div => match (l, r) {
+            (Value::Int(a), Value::Int(b)) => {
+                if b == 0 {
+                    return Err(RuntimeErrorKind::DivisionByZero);
+                }
+                Value::Int(a / b)
+            }
+            (Value::Float(a), Value::Float(b)) => Value::Float(a / b),
+            _ => return Err(mismatch("Number")),
+        },
+
+        BinaryOp::Mod => match (l, r) {
+            (Value::Int(a), Value::Int(b)) => {
+                if b == 0 {
+                    return Err(RuntimeErrorKind::DivisionByZero);
+                }
+                Value::Int(a % b)
+            }
+            (Value::Float(a), Value::Float(b)) => Value::Float(a % b),
+            _ => return Err(mismatch("Number")),
+        },
+
+        BinaryOp::Eq => Value::Bool(l == r),
+        BinaryOp::Neq => Value::Bool(l != r),
+
+        BinaryOp::Lt => match (l, r) {
+            (Value::Int(a), Value::Int(b)) => Value::Bool(a < b),
+            (Value::Float(a), Value::Float(b)) => Value::Bool(a < b),
+            (Value::Int(a), Value::Float(b)) => Value::Bool((a as f64) < b),
+            (Value::Float(a), Value::Int(b)) => Value::Bool(a < (b as f64)),
+            (Value::Char(a), Value::Char(b)) => Value::Bool(a < b),
+            _ => return Err(mismatch("Comparable")),
+        },
+
+        BinaryOp::Lte => match (l, r) {
+            (Value::Int(a), Value::Int(b)) => Value::Bool(a <= b),
+            (Value::Float(a), Value::Float(b)) => Value::Bool(a <= b),
+            (Value::Int(a), Value::Float(b)) => Value::Bool((a as f64) <= b),
+            (Value::Float(a), Value::Int(b)) => Value::Bool(a <= (b as f64)),
+            (Value::Char(a), Value::Char(b)) => Value::Bool(a <= b),
+            _ => return Err(mismatch("Comparable")),
+        },
+
+        BinaryOp::Gt => match (l, r) {
+            (Value::Int(a), Value::Int(b)) => Value::Bool(a > b),
+            (Value::Float(a), Value::Float(b)) => Value::Bool(a > b),
+            (Value::Int(a), Value::Float(b)) => Value::Bool((a as f64) > b),
+            (Value::Float(a), Value::Int(b)) => Value::Bool(a > (b as f64)),
+            (Value::Char(a), Value::Char(b)) => Value::Bool(a > b),
+            _ => return Err(mismatch("Comparable")),
+        },
+
+        BinaryOp::Gte => match (l, r) {
+            (Value::Int(a), Value::Int(b)) => Value::Bool(a >= b),
+            (Value::Float(a), Value::Float(b)) => Value::Bool(a >= b),
+            (Value::Int(a), Value::Float(b)) => Value::Bool((a as f64) >= b),
+            (Value::Float(a), Value::Int(b)) => Value::Bool(a >= (b as f64)),
+            (Value::Char(a), Value::Char(b)) => Value::Bool(a >= b),
+            _ => return Err(mismatch("Comparable")),
+        },
+
+        // And / Or 在调用方短路处理
+        BinaryOp::And | BinaryOp::Or => Value::Unit,
+    };
+
+    Ok(res)
+}
+
+/// 一元运算
+pub fn unary_op(op: UnaryOp, val: Value) -> Result<Value, RuntimeErrorKind> {
+    match op {
+        UnaryOp::Not => Ok(Value::Bool(!is_truthy(&val))),
+        UnaryOp::Neg => match val {
+            Value::Int(i) => Ok(Value::Int(-i)),
+            Value::Float(f) => Ok(Value::Float(-f)),
+            _ => Err(mismatch("Number")),
+        },
+    }
+}
+
+/// [Changed] 负数下标从末尾数起 (-1 是最后一个)，越界返回 None
+fn normalize_index(index: i64, len: usize) -> Option<usize> {
+    let pos = if index < 0 { index + len as i64 } else { index };
+    usize::try_from(pos).ok().filter(|pos| *pos < len)
+}
+
+/// [New] 构造范围值；省略的端点是 nil (只会出现在切片下标里)
+pub fn make_range(start: Value, end: Value, inclusive: bool) -> Result<Value, RuntimeErrorKind> {
+    let bound = |v: Value| match v {
+        Value::Nil => Ok(None),
+        Value::Int(i) => Ok(Some(i)),
+        _ => Err(mismatch("Int")),
+    };
+    Ok(Value::Range(Rc::new(RangeValue {
+        start: bound(start)?,
+        end: bound(end)?,
+        inclusive,
+        step: None,
+    })))
+}
+
+/// [New] 切片范围 (省略的端点取两头，负数从末尾数起) 换算成 [from, to) 和步长
+fn slice_bounds(range: &RangeValue, len: usize) -> Result<(usize, usize, usize), RuntimeErrorKind> {
+    let bound = |b: Option<i64>, default: i64| match b {
+        None => default,
+        Some(i) if i < 0 => i + len as i64,
+        Some(i) => i,
+    };
+    let from = bound(range.start, 0);
+    let mut to = bound(range.end, len as i64);
+    if range.inclusive && range.end.is_some() {
+        // [Fix] 闭区间的终点是 i64::MAX 时 +1 会溢出，这种切片本来就越界
+        to = to
+            .checked_add(1)
+            .ok_or(RuntimeErrorKind::SliceOutOfBounds {
+                start: from,
+                end: to,
+                len,
+            })?;
+    }
+    let step = match range.step {
+        None => 1,
+        Some(step) if step > 0 => step as usize,
+        Some(step) => {
+            return Err(RuntimeErrorKind::InvalidArgument(format!(
+                "slice step must be positive, got {}",
+                step
+            )));
+        }
+    };
+    if 0 <= from && from <= to && to <= len as i64 {
+        Ok((from as usize, to as usize, step))
+    } else {
+        Err(RuntimeErrorKind::SliceOutOfBounds {
+            start: from,
+            end: to,
+            len,
+        })
+    }
+}
+
+/// 下标读取 arr[i] / s[i] (字符串按字符索引)
+/// [Changed] 下标是范围时切片: arr[1..3] / s[2..]，数组切片是新分配的数组
+pub fn index_get(heap: &mut Heap, target: Value, index: Value) -> Result<Value, RuntimeErrorKind> {
+    match (target, index) {
+        (Value::Str(s), Value::Int(idx)) => {
+            let len = s.chars().count();
+            normalize_index(idx, len)
+                .and_then(|i| s.chars().nth(i))
+                .map(Value::Char)
+                .ok_or(RuntimeErrorKind::IndexOutOfBounds { index: idx, len })
+        }
+        (Value::Array(arr), Value::Int(idx)) => {
+            let vec = arr.borrow();
+            normalize_index(idx, vec.len())
+                .map(|i| vec[i].clone())
+                .ok_or(RuntimeErrorKind::IndexOutOfBounds {
+                    index: idx,
+                    len: vec.len(),
+                })
+        }
+        // [New] t.0 / t[0]：分析器已经静态检查过越界，这里只是兜底
+        (Value::Tuple(items), Value::Int(idx)) => normalize_index(idx, items.len())
+            .map(|i| items[i].clone())
+            .ok_or(RuntimeErrorKind::IndexOutOfBounds {
+                index: idx,
+                len: items.len(),
+            }),
+        (Value::Str(s), Value::Range(range)) => {
+            let (from, to, step) = slice_bounds(&range, s.chars().count())?;
+            let slice: String = s.chars().take(to).skip(from).step_by(step).collect();
+            Ok(Value::Str(slice.into()))
+        }
+        (Value::Array(arr), Value::Range(range)) => {
+            let elements = {
+                let vec = arr.borrow();
+                let (from, to, step) = slice_bounds(&range, vec.len())?;
+                vec[from..to].iter().step_by(step).cloned().collect()
+            };
+            Ok(Value::Array(heap.alloc_array(elements)))
+        }
+        _ => Err(mismatch("Indexable")),
+    }
+}
+
+/// [New] 解构：把元组拆成 n 个元素 (两个后端共用，保证报错一致)
+pub fn unpack_tuple(value: Value, n: usize) -> Result<Rc<[Value]>, RuntimeErrorKind> {
+    match value {
+        Value::Tuple(items) if items.len() == n => Ok(items),
+        Value::Tuple(items) => Err(RuntimeErrorKind::TypeError {
+            expected: format!("tuple of {} elements", n),
+            found: format!("tuple of {} elements", items.len()),
+        }),
+        other => Err(RuntimeErrorKind::TypeError {
+            expected: format!("tuple of {} elements", n),
+            found: format!("{:?}", other),
+        }),
+    }
+}
+
+/// 下标写入 arr[i] = v (负数下标从末尾数起)
+pub fn index_set(target: Value, index: Value, value: Value) -> Result<(), RuntimeErrorKind> {
+    if let (Value::Array(vec_rc), Value::Int(i)) = (target, index) {
+        let mut vec = vec_rc.borrow_mut();
+        let len = vec.len();
+        match normalize_index(i, len) {
+            Some(pos) => {
+                vec[pos] = value;
+                Ok(())
+            }
+            None => Err(RuntimeErrorKind::IndexOutOfBounds { index: i, len }),
+        }
+    } else {
+        Err(RuntimeErrorKind::TypeError {
+            expected: "Array and Int".into(),
+            found: "Invalid Types".into(),
+        })
+    }
+}
+
+/// 基础类型转换 (as int / as float / as str / as bool / as char)
+/// 返回 None 表示 target 不是基础类型名，需要调用方做对象类型检查
+pub fn cast_primitive(
+    val: Value,
+    target: &str,
+    interner: &Interner,
+) -> Option<Result<Value, RuntimeErrorKind>> {
+    let res = match target {
+        "int" => match val {
+            Value::Float(f) => Ok(Value::Int(f as i64)),
+            Value::Int(i) => Ok(Value::Int(i)),
+            Value::Char(c) => Ok(Value::Int(c as i64)),
+            Value::Bool(b) => Ok(Value::Int(if b { 1 } else { 0 })),
+            _ => Err(cast_error(&val, "int", interner)),
+        },
+        "float" => match val {
+            Value::Int(i) => Ok(Value::Float(i as f64)),
+            Value::Float(f) => Ok(Value::Float(f)),
+            _ => Err(cast_error(&val, "float", interner)),
+        },
+        // as str: 显式转字符串
+        "str" => Ok(Value::Str(val.to_string(interner).into())),
+        "bool" => match val {
+            Value::Bool(b) => Ok(Value::Bool(b)),
+            _ => Err(cast_error(&val, "bool", interner)),
+        },
+        // [New] 码位超出范围或落在代理区 (0xD800..=0xDFFF) 时报错
+        "char" => match val {
+            Value::Char(c) => Ok(Value::Char(c)),
+            Value::Int(i) => u32::try_from(i)
+                .ok()
+                .and_then(char::from_u32)
+                .map(Value::Char)
+                .ok_or(RuntimeErrorKind::InvalidCharCode(i)),
+            _ => Err(cast_error(&val, "char", interner)),
+        },
+        _ => return None,
+    };
+    Some(res)
+}
+
+/// [New] 对象类型转换的运行时检查 (两个后端共用)：
+/// 实例的类、它的祖先类，或者它们声明实现的 trait 中有没有叫 target 的
+pub fn instance_of(ctx: &Context, table_id: TableId, target: Symbol) -> bool {
+    let mut current = Some(table_id);
+    while let Some(id) = current {
+        if id.symbol() == target {
+            return true;
+        }
+        let Some(info) = ctx.table_info(id) else {
+            break;
+        };
+        let base = |ty: &Type| match ty {
+            Type::Table(id) | Type::GenericInstance { base: id, .. } => Some(*id),
+            _ => None,
+        };
+        if info
+            .traits
+            .iter()
+            .filter_map(base)
+            .any(|id| id.symbol() == target)
+        {
+            return true;
+        }
+        current = info.parent.as_ref().and_then(base);
+    }
+    false
+}
+
+pub fn cast_error(val: &Value, target: &str, interner: &Interner) -> RuntimeErrorKind {
+    RuntimeErrorKind::InvalidCast {
+        src: val.to_string(interner),
+        target: target.to_string(),
+    }
+}
+
+/// [New] 实例 / 模块上找不到成员 (两个后端共用，保证报错一致)
+/// 从 Analyzer 记录的 TableInfo / ModuleInfo 里找近似的名字；
+/// assigning 为 true 时只考虑字段 (方法不能赋值)
+pub fn property_not_found(
+    ctx: &Context,
+    target: &Value,
+    field: Symbol,
+    assigning: bool,
+) -> RuntimeErrorKind {
+    let property = ctx.resolve_symbol(field);
+    let (target_type, candidates): (&str, Vec<Symbol>) = match target {
+        Value::Instance(instance) => (
+            "Instance",
+            ctx.table_info(instance.table_id)
+                .map(|info| {
+                    let methods = info.methods.keys().filter(|_| !assigning);
+                    info.fields.keys().chain(methods).copied().collect()
+                })
+                .unwrap_or_default(),
+        ),
+        Value::Module(file_id, _) => ("Module", module_exports(ctx, *file_id)),
+        Value::Enum(desc) => (
+            "Enum",
+            desc.variants.iter().map(|variant| variant.name).collect(),
+        ),
+        _ => ("Value", Vec::new()),
+    };
+    let suggestion = best_match(
+        property,
+        candidates.iter().map(|name| ctx.resolve_symbol(*name)),
+    );
+    RuntimeErrorKind::PropertyNotFound {
+        target_type: target_type.into(),
+        property: property.to_string(),
+        suggestion: suggestion.map(Box::from),
+    }
+}
+
+fn module_exports(ctx: &Context, file_id: FileId) -> Vec<Symbol> {
+    let Some(module) = ctx
+        .source_manager
+        .get_file_path(file_id)
+        .and_then(|path| ctx.modules.get(path))
+    else {
+        return Vec::new();
+    };
+    module
+        .tables
+        .keys()
+        .chain(module.enums.keys())
+        .map(TableId::symbol)
+        .chain(module.functions.keys().copied())
+        .chain(module.globals.keys().copied())
+        .collect()
+}
+
+/// [New] Shape.Circle：单元变体直接是枚举值，带字段的变体是构造器
+pub fn enum_member(ctx: &Context, target: Value, field: Symbol) -> Result<Value, RuntimeErrorKind> {
+    let Value::Enum(desc) = &target else {
+        unreachable!("Should only be called for enums");
+    };
+    let Some(tag) = desc.tag_of(field) else {
+        return Err(property_not_found(ctx, &target, field, false));
+    };
+    if desc.variants[tag as usize].arity == 0 && desc.variants[tag as usize].field_names.is_none() {
+        construct_variant(ctx, desc.clone(), tag, Vec::new())
+    } else {
+        Ok(Value::VariantCtor(desc.clone(), tag))
+    }
+}
+
+/// [New] Shape.Circle(1.0)
+pub fn construct_variant(
+    ctx: &Context,
+    desc: Rc<EnumDesc>,
+    tag: u32,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeErrorKind> {
+    let variant = &desc.variants[tag as usize];
+    if variant.field_names.is_some() || args.len() != variant.arity {
+        return Err(RuntimeErrorKind::ArgumentCountMismatch {
+            func_name: ctx.resolve_symbol(variant.name).to_string(),
+            expected: variant.arity,
+            found: args.len(),
+        });
+    }
+    Ok(Value::Variant(Rc::new(EnumValue {
+        desc,
+        tag,
+        payload: args,
+    })))
+}
+
+/// [New] Shape.Rect { h: 2.0, w: 1.0 }：字段按声明顺序重新排列
+pub fn construct_struct_variant(
+    ctx: &Context,
+    ctor: Value,
+    fields: Vec<(Symbol, Value)>,
+) -> Result<Value, RuntimeErrorKind> {
+    let Value::VariantCtor(desc, tag) = &ctor else {
+        return Err(mismatch("struct variant"));
+    };
+    let variant = &desc.variants[*tag as usize];
+    let Some(names) = &variant.field_names else {
+        return Err(mismatch("struct variant"));
+    };
+    let mut payload = vec![Value::Nil; names.len()];
+    let mut found = 0;
+    for (field, value) in fields {
+        let Some(i) = names.iter().position(|name| *name == field) else {
+            return Err(property_not_found(ctx, &ctor, field, true));
+        };
+        payload[i] = value;
+        found += 1;
+    }
+    if found != names.len() {
+        return Err(RuntimeErrorKind::ArgumentCountMismatch {
+            func_name: ctx.resolve_symbol(variant.name).to_string(),
+            expected: names.len(),
+            found,
+        });
+    }
+    Ok(Value::Variant(Rc::new(EnumValue {
+        desc: desc.clone(),
+        tag: *tag,
+        payload,
+    })))
+}
+
+/// [New] match 的一个分支是否匹配 value；匹配时按 Pattern::bindings() 的顺序
+/// 把绑定的值追加到 binds 里 (两个后端共用)
+pub fn match_pattern(pattern: &Pattern, value: &Value, binds: &mut Vec<Value>) -> bool {
+    match (&pattern.data, value) {
+        (PatternData::Wildcard, _) => true,
+        (PatternData::Identifier(_), _) => {
+            binds.push(value.clone());
+            true
+        }
+        (PatternData::Literal(lit), _) => *value == literal_value(lit),
+        (PatternData::Tuple(items), Value::Tuple(values)) => {
+            items.len() == values.len()
+                && items
+                    .iter()
+                    .zip(values.iter())
+                    .all(|(item, value)| match_pattern(item, value, binds))
+        }
+        (PatternData::Variant { name, args, .. }, Value::Variant(value)) => {
+            let variant = value.variant();
+            if variant.name != *name {
+                return false;
+            }
+            match args {
+                VariantPatternArgs::None => true,
+                VariantPatternArgs::Tuple(items) => {
+                    items.len() == value.payload.len()
+                        && items
+                            .iter()
+                            .zip(&value.payload)
+                            .all(|(item, value)| match_pattern(item, value, binds))
+                }
+                VariantPatternArgs::Struct(fields) => {
+                    let Some(names) = &variant.field_names else {
+                        return false;
+                    };
+                    fields.iter().all(|(field, item)| {
+                        names
+                            .iter()
+                            .position(|name| name == field)
+                            .is_some_and(|i| match_pattern(item, &value.payload[i], binds))
+                    })
+                }
+            }
+        }
+        _ => false,
+    }
+}
+
+/// 字面量的值 (求值字面量表达式和匹配字面量模式共用)
+pub fn literal_value(lit: &Literal) -> Value {
+    match lit {
+        Literal::Int(i) => Value::Int(*i),
+        Literal::Float(f) => Value::Float(*f),
+        Literal::String(s) => Value::Str(s.clone()),
+        Literal::Bool(b) => Value::Bool(*b),
+        Literal::Char(c) => Value::Char(*c),
+        Literal::Nil => Value::Nil,
+    }
+}
+
+/// 原生类型 (str / Array) 的成员方法
+pub fn native_member(target: Value, field_name: &str) -> Result<Value, RuntimeErrorKind> {
+    let (type_name, members, func): (_, &[&str], _) = match &target {
+        Value::Str(_) => (
+            "String",
+            &["len"],
+            match field_name {
+                "len" => Some(NativeFunc::new("len", native_str_len)),
+                _ => None,
+            },
+        ),
+        Value::Array(_) => (
+            "Array",
+            &["len", "push"],
+            match field_name {
+                "len" => Some(NativeFunc::new("len", native_array_len)),
+                "push" => Some(NativeFunc::new("push", native_array_push)),
+                _ => None,
+            },
+        ),
+        // [New] 范围的方法
+        Value::Range(_) => (
+            "Range",
+            &["contains", "len", "rev", "step"],
+            match field_name {
+                "contains" => Some(NativeFunc::new("contains", native_range_contains)),
+                "len" => Some(NativeFunc::new("len", native_range_len)),
+                "rev" => Some(NativeFunc::new("rev", native_range_rev)),
+                "step" => Some(NativeFunc::new("step", native_range_step)),
+                _ => None,
+            },
+        ),
+        _ => unreachable!("Should only be called for native types"),
+    };
+
+    match func {
+        Some(f) => Ok(Value::BoundNativeMethod(Box::new(target), f)),
+        None => Err(RuntimeErrorKind::PropertyNotFound {
+            target_type: type_name.into(),
+            property: field_name.to_string(),
+            suggestion: best_match(field_name, members.iter().copied()).map(Box::from),
+        }),
+    }
+}
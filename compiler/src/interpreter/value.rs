@@ -1,8 +1,10 @@
 use super::errors::RuntimeErrorKind;
 use crate::analyzer::TableId;
-use crate::ast::MethodDefinition;
+use crate::ast::{EnumDefinition, MethodDefinition, VariantKind};
 use crate::context::Context;
 use crate::interpreter::Environment;
+use crate::interpreter::environment::Frame;
+use crate::interpreter::vm::FunctionProto;
 use crate::source::FileId; // [New] 引入 FileId
 use crate::utils::{Interner, Symbol}; // [New] 引入 TableId (确保它是 pub 的)
 
@@ -19,10 +21,15 @@ pub enum Value {
     Bool(bool),
     Int(i64),
     Float(f64),
-    Str(String),
+    // [Changed] 不可变、引用计数的字符串，读取/传参不再深拷贝
+    Str(Rc<str>),
+    // [New] 单个 Unicode 字符 ('a'、遍历字符串、s[i])
+    Char(char),
 
     // === 复杂类型 ===
     Array(Rc<RefCell<Vec<Value>>>),
+    // [New] 元组：创建后不可修改，所以不需要 RefCell
+    Tuple(Rc<[Value]>),
 
     // [修改] 模块：现在只存 FileId
     // 解释器通过这个 FileId 去 Driver/Context 里找对应的导出表或 AST
@@ -44,11 +51,27 @@ pub enum Value {
 
     // [修改] 绑定方法
     // 同样，Instance 内部已经包含了 TableId
-    BoundMethod(Rc<Instance>, MethodDefinition, Rc<RefCell<Environment>>),
+    BoundMethod(Rc<Instance>, Rc<MethodDefinition>, Rc<RefCell<Environment>>),
 
     BoundNativeMethod(Box<Value>, NativeFunc),
 
-    Range(Box<Value>, Box<Value>),
+    // [New] 字节码后端的函数与绑定方法
+    CompiledFunction(Rc<FunctionProto>),
+    BoundCompiledMethod(Rc<Instance>, Rc<FunctionProto>),
+
+    // [New] 匿名函数 (闭包)：树遍历解释器捕获外层的帧，字节码后端捕获 upvalue
+    Closure(Rc<Closure>),
+    CompiledClosure(Rc<CompiledClosure>),
+
+    // [Changed] 整数范围是一等值：可以保存、传参、调用 len / contains / rev / step
+    Range(Rc<RangeValue>),
+
+    // [New] 枚举类型对象 (`Shape`)：访问它的成员得到变体
+    Enum(Rc<EnumDesc>),
+    // [New] 带字段的变体的构造器 (`Shape.Circle`)，调用或者 `{ ... }` 产生变体值
+    VariantCtor(Rc<EnumDesc>, u32),
+    // [New] 枚举值；和元组一样创建后不可修改
+    Variant(Rc<EnumValue>),
 }
 
 pub type NativeFuncPtr = fn(&mut Context, &[Value]) -> Result<Value, RuntimeErrorKind>;
@@ -93,6 +116,258 @@ impl NativeFunc {
     }
 }
 
+/// [New] 整数范围 a..b / a..=b
+/// 两个后端的 for 循环都按下标 0..len() 调用 get()，所以步长和方向的语义只在这里实现
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeValue {
+    /// None 是切片下标里省略的端点 (arr[2..])
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub inclusive: bool,
+    /// None 表示没有调用过 step()：起点大于终点时递减，否则递增，步长都是 1
+    pub step: Option<i64>,
+}
+
+impl RangeValue {
+    fn bounds(&self) -> Result<(i64, i64), RuntimeErrorKind> {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => Ok((start, end)),
+            _ => Err(RuntimeErrorKind::TypeError {
+                expected: "Range with both bounds".into(),
+                found: format!("{}", self),
+            }),
+        }
+    }
+
+    fn stride(&self, start: i64, end: i64) -> i64 {
+        self.step.unwrap_or(if start <= end { 1 } else { -1 })
+    }
+
+    /// 元素个数
+    pub fn len(&self) -> Result<i64, RuntimeErrorKind> {
+        let (start, end) = self.bounds()?;
+        let step = self.stride(start, end) as i128;
+        // 沿着步长方向的距离 (用 i128 避免 i64 两端相减溢出)
+        let distance = (end as i128 - start as i128) * step.signum();
+        let stride = step.abs();
+        let len = if self.inclusive {
+            if distance < 0 {
+                0
+            } else {
+                distance / stride + 1
+            }
+        } else if distance <= 0 {
+            0
+        } else {
+            (distance + stride - 1) / stride
+        };
+        Ok(len.min(i64::MAX as i128) as i64)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, RuntimeErrorKind> {
+        Ok(self.len()? == 0)
+    }
+
+    /// 第 k 个元素 (调用方保证 k < len)
+    pub fn get(&self, k: i64) -> Result<i64, RuntimeErrorKind> {
+        let (start, end) = self.bounds()?;
+        Ok(start + k * self.stride(start, end))
+    }
+
+    pub fn contains(&self, x: i64) -> Result<bool, RuntimeErrorKind> {
+        let (start, end) = self.bounds()?;
+        let step = self.stride(start, end) as i128;
+        let offset = x as i128 - start as i128;
+        if offset % step != 0 {
+            return Ok(false);
+        }
+        let k = offset / step;
+        Ok(k >= 0 && k < self.len()? as i128)
+    }
+
+    /// 倒序：从最后一个元素走回起点
+    pub fn rev(&self) -> Result<RangeValue, RuntimeErrorKind> {
+        let (start, end) = self.bounds()?;
+        let step = self.stride(start, end);
+        let len = self.len()?;
+        let (start, end, inclusive) = if len == 0 {
+            (start, start, false)
+        } else {
+            (self.get(len - 1)?, start, true)
+        };
+        Ok(RangeValue {
+            start: Some(start),
+            end: Some(end),
+            inclusive,
+            step: Some(-step),
+        })
+    }
+
+    /// 步长乘以 n (n > 0)，方向不变：(0..10).step(2).step(3) 和 (0..10).step(6) 一样
+    pub fn with_step(&self, n: i64) -> Result<RangeValue, RuntimeErrorKind> {
+        if n <= 0 {
+            return Err(RuntimeErrorKind::InvalidArgument(format!(
+                "range step must be positive, got {}",
+                n
+            )));
+        }
+        let (start, end) = self.bounds()?;
+        Ok(RangeValue {
+            step: Some(n.saturating_mul(self.stride(start, end))),
+            ..self.clone()
+        })
+    }
+}
+
+impl fmt::Display for RangeValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bound = |b: Option<i64>| b.map(|b| b.to_string()).unwrap_or_default();
+        let op = if self.inclusive { "..=" } else { ".." };
+        let range = format!("{}{}{}", bound(self.start), op, bound(self.end));
+        match self.step {
+            Some(step) if step.abs() != 1 => write!(f, "({}).step({})", range, step.abs()),
+            _ => write!(f, "{}", range),
+        }
+    }
+}
+
+/// [New] 枚举的运行时描述，两个后端都从 AST 构造
+#[derive(Debug, PartialEq)]
+pub struct EnumDesc {
+    pub id: TableId,
+    pub variants: Vec<VariantDesc>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct VariantDesc {
+    pub name: Symbol,
+    pub arity: usize,
+    /// 结构体变体的字段名 (按声明顺序)，元组 / 单元变体为 None
+    pub field_names: Option<Vec<Symbol>>,
+}
+
+impl EnumDesc {
+    pub fn new(file_id: FileId, def: &EnumDefinition) -> Self {
+        let variants = def
+            .variants
+            .iter()
+            .map(|variant| {
+                let (arity, field_names) = match &variant.kind {
+                    VariantKind::Unit => (0, None),
+                    VariantKind::Tuple(types) => (types.len(), None),
+                    VariantKind::Struct(fields) => (
+                        fields.len(),
+                        Some(fields.iter().map(|(name, _)| *name).collect()),
+                    ),
+                };
+                VariantDesc {
+                    name: variant.name,
+                    arity,
+                    field_names,
+                }
+            })
+            .collect();
+        Self {
+            id: TableId(file_id, def.name),
+            variants,
+        }
+    }
+
+    pub fn tag_of(&self, name: Symbol) -> Option<u32> {
+        self.variants
+            .iter()
+            .position(|variant| variant.name == name)
+            .map(|tag| tag as u32)
+    }
+}
+
+/// [New] 枚举值：变体下标 + 字段 (结构体变体按声明顺序存放)
+#[derive(Debug)]
+pub struct EnumValue {
+    pub desc: Rc<EnumDesc>,
+    pub tag: u32,
+    pub payload: Vec<Value>,
+}
+
+impl EnumValue {
+    pub fn variant(&self) -> &VariantDesc {
+        &self.desc.variants[self.tag as usize]
+    }
+}
+
+// 同一个枚举的同一个变体、字段逐个相等
+impl PartialEq for EnumValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.desc.id == other.desc.id && self.tag == other.tag && self.payload == other.payload
+    }
+}
+
+/// [New] 树遍历解释器的闭包：函数体 + 定义时所在的帧和模块环境
+/// 外层变量的读写都通过捕获的帧进行，所以闭包和外层函数看到的是同一个变量
+#[derive(Debug)]
+pub struct Closure {
+    pub def: Rc<MethodDefinition>,
+    pub frame: Rc<RefCell<Frame>>,
+    pub globals: Rc<RefCell<Environment>>,
+}
+
+/// [New] 字节码后端的闭包：函数原型 + 捕获的 upvalue (顺序同 FunctionProto::upvalues)
+#[derive(Debug)]
+pub struct CompiledClosure {
+    pub proto: Rc<FunctionProto>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+/// [New] 被闭包捕获的局部变量
+/// 外层函数返回之前变量还在值栈上 (Open 存栈下标)，返回时把值搬进 upvalue (Closed)
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+// 同 FunctionProto：闭包按身份比较
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl PartialEq for CompiledClosure {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// [New] 类的字段布局 (来自 TableInfo.field_layout)
+// 父类字段排在前面，所以同一个字段在整条继承链上下标相同
+#[derive(Debug, PartialEq)]
+pub struct FieldLayout {
+    pub names: Vec<Symbol>,
+    index: HashMap<Symbol, usize>,
+}
+
+impl FieldLayout {
+    pub fn new(names: Vec<Symbol>) -> Self {
+        let index = names.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+        Self { names, index }
+    }
+
+    /// 按名字查下标 (没有静态下标时的慢路径)
+    #[inline]
+    pub fn index_of(&self, name: Symbol) -> Option<usize> {
+        self.index.get(&name).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
 // [修改] Table 实例结构
 #[derive(Debug, PartialEq)]
 pub struct Instance {
@@ -100,7 +375,37 @@ pub struct Instance {
     // 这样我们才能区分不同文件里的同名类 (比如 lib.Config 和 main.Config)
     pub table_id: TableId,
 
-    pub fields: RefCell<HashMap<Symbol, Value>>,
+    // [Changed] 字段按布局下标存放，同类的实例共享一份布局
+    pub layout: Rc<FieldLayout>,
+    pub fields: RefCell<Vec<Value>>,
+}
+
+impl Instance {
+    /// 解析字段下标
+    /// hint 是 Analyzer 按静态类型算出的下标，校验名字一致后直接使用
+    #[inline]
+    fn resolve_index(&self, name: Symbol, hint: Option<u32>) -> Option<usize> {
+        match hint {
+            Some(idx) if self.layout.names.get(idx as usize) == Some(&name) => Some(idx as usize),
+            _ => self.layout.index_of(name),
+        }
+    }
+
+    pub fn get_field(&self, name: Symbol, hint: Option<u32>) -> Option<Value> {
+        let idx = self.resolve_index(name, hint)?;
+        Some(self.fields.borrow()[idx].clone())
+    }
+
+    /// 写入字段；布局里没有这个字段时返回 false
+    pub fn set_field(&self, name: Symbol, hint: Option<u32>, val: Value) -> bool {
+        match self.resolve_index(name, hint) {
+            Some(idx) => {
+                self.fields.borrow_mut()[idx] = val;
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 // ModuleEnv 结构体可以删除了，我们现在用 FileId + Context 来管理
@@ -120,7 +425,8 @@ impl Value {
             Value::Bool(b) => b.to_string(),
             Value::Int(i) => i.to_string(),
             Value::Float(f) => f.to_string(),
-            Value::Str(s) => s.clone(),
+            Value::Str(s) => s.to_string(),
+            Value::Char(c) => c.to_string(),
 
             Value::Array(arr) => {
                 let borrowed = arr.borrow();
@@ -134,6 +440,11 @@ impl Value {
                 }
             }
 
+            Value::Tuple(items) => {
+                let elements: Vec<String> = items.iter().map(|v| v.to_string(interner)).collect();
+                format!("({})", elements.join(", "))
+            }
+
             // [修改] 打印实例
             Value::Instance(inst) => {
                 // inst.table_id 是 (FileId, Symbol)
@@ -167,12 +478,61 @@ impl Value {
                 format!("<bound method {}.{}>", class_name, method_name)
             }
 
+            Value::CompiledFunction(proto) => {
+                format!("<fn {}>", interner.resolve(proto.name))
+            }
+
+            Value::BoundCompiledMethod(inst, proto) => {
+                let class_name = interner.resolve(inst.table_id.symbol());
+                let method_name = interner.resolve(proto.name);
+                format!("<bound method {}.{}>", class_name, method_name)
+            }
+
+            // [New] 两个后端的闭包打印成一样的 <fn <lambda>>
+            Value::Closure(closure) => format!("<fn {}>", interner.resolve(closure.def.name)),
+            Value::CompiledClosure(closure) => {
+                format!("<fn {}>", interner.resolve(closure.proto.name))
+            }
+
             Value::BoundNativeMethod(_receiver, _) => {
                 // 递归调用 receiver 的 to_string 有死循环风险，简单处理
                 "<bound native method>".to_string()
             }
 
-            Value::Range(start, end) => format!("{}..{}", start, end),
+            Value::Range(range) => range.to_string(),
+
+            // [New] Shape.Empty / Shape.Circle(1) / Shape.Rect { w: 1, h: 2 }
+            Value::Enum(desc) => format!("<enum {}>", interner.resolve(desc.id.symbol())),
+            Value::VariantCtor(desc, tag) => format!(
+                "<variant {}.{}>",
+                interner.resolve(desc.id.symbol()),
+                interner.resolve(desc.variants[*tag as usize].name)
+            ),
+            Value::Variant(value) => {
+                let variant = value.variant();
+                let name = format!(
+                    "{}.{}",
+                    interner.resolve(value.desc.id.symbol()),
+                    interner.resolve(variant.name)
+                );
+                let items: Vec<String> = value
+                    .payload
+                    .iter()
+                    .map(|v| v.to_string(interner))
+                    .collect();
+                match &variant.field_names {
+                    Some(names) => {
+                        let fields: Vec<String> = names
+                            .iter()
+                            .zip(&items)
+                            .map(|(field, item)| format!("{}: {}", interner.resolve(*field), item))
+                            .collect();
+                        format!("{} {{ {} }}", name, fields.join(", "))
+                    }
+                    None if items.is_empty() => name,
+                    None => format!("{}({})", name, items.join(", ")),
+                }
+            }
         }
     }
 }
@@ -187,11 +547,22 @@ impl fmt::Display for Value {
             Value::Int(i) => write!(f, "{}", i),
             Value::Float(n) => write!(f, "{}", n),
             Value::Str(s) => write!(f, "{}", s),
+            Value::Char(c) => write!(f, "{}", c),
             Value::Array(_) => write!(f, "[...]"),
+            Value::Tuple(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, ")")
+            }
             Value::Instance(_inst) => write!(f, "<instance>"), // 简略
             Value::Table(_) => write!(f, "<class>"),
             Value::Module(..) => write!(f, "<module>"),
-            Value::Range(start, end) => write!(f, "{}..{}", start, end),
+            Value::Range(range) => write!(f, "{}", range),
             _ => write!(f, "<...>"),
         }
     }
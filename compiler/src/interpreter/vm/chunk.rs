@@ -0,0 +1,233 @@
+// src/interpreter/vm/chunk.rs
+
+use crate::analyzer::TableId;
+use crate::ast::{BinaryOp, Pattern};
+use crate::interpreter::dispatch::InlineCache;
+use crate::interpreter::value::{FieldLayout, Value};
+use crate::source::FileId;
+use crate::utils::{NodeId, Span, Symbol};
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+/// 字节码指令
+///
+/// 约定：每条"表达式"指令执行完后恰好在栈顶留下一个值，
+/// 这和树遍历解释器里"一切皆表达式"的语义一一对应。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpCode {
+    // === 常量 ===
+    /// 压入常量池中的第 n 个值
+    Constant(u32),
+    Nil,
+    Unit,
+    True,
+    False,
+
+    // === 栈操作 ===
+    Pop,
+    /// 把栈截断到局部变量区之上 n 个临时值 (break / continue 时清理)
+    Truncate(u32),
+
+    // === 变量 ===
+    GetLocal(u32),
+    /// 弹出栈顶写入局部槽位
+    SetLocal(u32),
+    GetGlobal(Symbol),
+    /// 弹出栈顶，沿模块环境链赋值；找不到则定义在模块环境里
+    SetGlobal(Symbol),
+    /// 弹出栈顶，定义到当前模块环境
+    DefineGlobal(Symbol),
+    /// [New] 读写当前闭包捕获的第 n 个 upvalue
+    GetUpvalue(u32),
+    SetUpvalue(u32),
+
+    // === 成员访问 ===
+    /// index 是 Analyzer 算出的字段布局下标，校验名字一致后直接按下标存取
+    GetField {
+        name: Symbol,
+        index: Option<u32>,
+    },
+    /// 栈: [value, object] -> []
+    SetField {
+        name: Symbol,
+        index: Option<u32>,
+    },
+    /// 栈: [target, index] -> [value]
+    GetIndex,
+    /// 栈: [value, target, index] -> []
+    SetIndex,
+
+    // === 运算 ===
+    Binary(BinaryOp),
+    Neg,
+    Not,
+    Cast(CastTarget),
+
+    // === 控制流 (操作数均为绝对指令地址) ===
+    Jump(u32),
+    /// 弹出条件，为假时跳转
+    JumpIfFalse(u32),
+    /// 条件为假时保留并跳转，否则弹出 (and 短路)
+    JumpIfFalseOrPop(u32),
+    /// 条件为真时保留并跳转，否则弹出 (or 短路)
+    JumpIfTrueOrPop(u32),
+    /// while 条件必须是 Bool
+    AssertBool,
+
+    // === for 循环 ===
+    /// 弹出可迭代对象，初始化隐藏槽位 slot (序列) 和 slot+1 (游标)
+    IterPrep(u32),
+    /// 取下一个元素写入 slot+2 (迭代变量)，迭代结束则跳转到 exit
+    IterNext {
+        slot: u32,
+        exit: u32,
+    },
+
+    // === 调用 ===
+    /// 栈: [callee, arg1..argN]
+    Call(u32),
+    /// 栈: [receiver, arg1..argN]，按名字查找方法后调用
+    /// cache 是本函数内联缓存表的下标
+    Invoke {
+        name: Symbol,
+        argc: u32,
+        cache: u32,
+    },
+    Return,
+    /// [New] 用常量池第 n 个函数原型创建闭包，按原型的 upvalues 描述捕获外层变量
+    Closure(u32),
+
+    // === 构造 ===
+    /// 弹出 n 个元素组成数组
+    Array(u32),
+    /// [New] 弹出 n 个元素组成元组
+    Tuple(u32),
+    /// [New] 解构：弹出一个 n 元组，逆序压入元素 (第 0 个在栈顶)
+    Unpack(u32),
+    /// 栈: [start, end] -> [range]
+    /// [Changed] inclusive 对应 ..=
+    Range {
+        inclusive: bool,
+    },
+    /// 类初始化函数专用：弹出所有字段初值，构造实例
+    NewInstance,
+    /// [New] 栈: [ctor, v1..vN] -> [variant]，字段名是 field_lists 的第 n 项
+    StructVariant(u32),
+
+    // === match ===
+    /// [New] 弹出一个值，和 patterns 的第 n 个模式匹配：
+    /// 匹配时按 Pattern::bindings() 的顺序压入绑定的值，再压入 true；否则只压入 false
+    Match(u32),
+
+    // === 模块 ===
+    /// 加载 (若未加载) 并压入模块对象
+    Import(FileId),
+    /// 模块初始化函数专用：压入当前模块对象
+    ModuleSelf,
+
+    /// 抛出内部错误，消息为常量池第 n 个字符串
+    /// 用于保持与树遍历解释器一致的"不支持"行为
+    Internal(u32),
+}
+
+/// `as` 的目标
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CastTarget {
+    /// 基础类型 / 类名，运行时按名字分派
+    Named(Symbol),
+    /// 泛型、数组等复杂类型：运行时直接放行
+    Noop,
+}
+
+/// 一段可执行的字节码
+#[derive(Debug, Default, Clone)]
+pub struct Chunk {
+    pub code: Vec<OpCode>,
+    pub constants: Vec<Value>,
+    /// [New] 每条指令来自哪个表达式 (NodeId 用于反查文件)，运行时报错定位用
+    pub origins: Vec<(NodeId, Span)>,
+    /// [New] Match 指令的模式
+    pub patterns: Vec<Pattern>,
+    /// [New] StructVariant 指令的字段名 (按书写顺序)
+    pub field_lists: Vec<Vec<Symbol>>,
+}
+
+impl Chunk {
+    pub fn emit(&mut self, op: OpCode, origin: (NodeId, Span)) -> usize {
+        self.code.push(op);
+        self.origins.push(origin);
+        self.code.len() - 1
+    }
+
+    pub fn add_constant(&mut self, val: Value) -> u32 {
+        self.constants.push(val);
+        (self.constants.len() - 1) as u32
+    }
+
+    /// 回填跳转目标
+    pub fn patch_jump(&mut self, at: usize, target: usize) {
+        let target = target as u32;
+        self.code[at] = match self.code[at] {
+            OpCode::Jump(_) => OpCode::Jump(target),
+            OpCode::JumpIfFalse(_) => OpCode::JumpIfFalse(target),
+            OpCode::JumpIfFalseOrPop(_) => OpCode::JumpIfFalseOrPop(target),
+            OpCode::JumpIfTrueOrPop(_) => OpCode::JumpIfTrueOrPop(target),
+            OpCode::IterNext { slot, .. } => OpCode::IterNext { slot, exit: target },
+            other => unreachable!("patch_jump on non-jump instruction {:?}", other),
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FunctionKind {
+    /// 模块顶层代码
+    Script,
+    /// 顶层函数，槽位 0 保存函数自身
+    Function,
+    /// 方法，槽位 0 保存 self
+    Method,
+    /// 类的字段初始化器，槽位 0 保存类对象
+    /// 按布局顺序求值字段初值后执行 NewInstance
+    Initializer {
+        table_id: TableId,
+        layout: Rc<FieldLayout>,
+    },
+}
+
+/// [New] 闭包捕获的一个外层变量
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UpvalueDesc {
+    /// true: 直接外层函数的局部槽位 index；false: 直接外层闭包的第 index 个 upvalue
+    pub is_local: bool,
+    pub index: u32,
+}
+
+/// 编译后的函数原型
+pub struct FunctionProto {
+    pub name: Symbol,
+    pub kind: FunctionKind,
+    pub arity: usize,
+    /// 局部变量区大小 (含槽位 0 和参数)，调用时一次性预留
+    pub max_slots: usize,
+    /// 定义所在的模块 (决定全局变量从哪个模块环境查找)
+    pub file_id: FileId,
+    pub chunk: Chunk,
+    /// 每条 Invoke 指令一个内联缓存 (类 -> 方法)
+    pub inline_caches: RefCell<Vec<InlineCache<Rc<FunctionProto>>>>,
+    /// [New] 匿名函数捕获的外层变量 (其它函数为空)
+    pub upvalues: Vec<UpvalueDesc>,
+}
+
+// 同 NativeFunc：函数原型按身份比较
+impl PartialEq for FunctionProto {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for FunctionProto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<compiled fn {:?}>", self.name)
+    }
+}
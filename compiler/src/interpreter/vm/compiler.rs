@@ -0,0 +1,1055 @@
+// src/interpreter/vm/compiler.rs
+//
+// AST -> 字节码
+// 输入是已经通过语义检查的 Program，输出每个模块的顶层脚本和每个类的原型。
+
+use super::chunk::{CastTarget, Chunk, FunctionKind, FunctionProto, OpCode, UpvalueDesc};
+use crate::analyzer::{TableId, Type, resolve_module_path};
+use crate::ast::*;
+use crate::context::Context;
+use crate::interpreter::dispatch::{InlineCache, VTable};
+use crate::interpreter::value::{EnumDesc, FieldLayout, Value};
+use crate::source::FileId;
+use crate::utils::{NodeId, Span, Symbol};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// 编译后的类
+pub struct ClassProto {
+    pub parent: Option<TableId>,
+    /// 类自身定义的方法
+    pub methods: HashMap<Symbol, Rc<FunctionProto>>,
+    /// 合并了继承方法的虚表 (按 TableInfo.vtable 生成)
+    pub vtable: VTable<Rc<FunctionProto>>,
+    /// 字段初始化器 (调用类对象时执行)
+    pub init: Rc<FunctionProto>,
+}
+
+/// 整个程序 (所有已加载模块) 的编译产物
+pub struct CompiledProgram {
+    /// 每个模块的顶层代码
+    pub scripts: HashMap<FileId, Rc<FunctionProto>>,
+    pub classes: HashMap<TableId, ClassProto>,
+}
+
+struct Local {
+    /// None 表示编译器内部使用的隐藏槽位 (for 循环状态等)
+    name: Option<Symbol>,
+    slot: u32,
+    depth: usize,
+}
+
+struct LoopCtx {
+    /// continue 跳转的目标
+    start: usize,
+    /// 进入循环时栈上的临时值个数
+    temps: u32,
+    /// 待回填的 break 跳转
+    breaks: Vec<usize>,
+}
+
+/// [New] 模式里的名字怎么绑定
+#[derive(Clone, Copy)]
+enum BindMode {
+    /// for 的迭代变量和 let / var 解构：总是定义新变量
+    Define,
+    /// 解构赋值：和 `x = v` 一样，已有的变量赋值
+    Assign,
+}
+
+/// 单个函数的编译状态
+struct FnState {
+    chunk: Chunk,
+    is_script: bool,
+    locals: Vec<Local>,
+    scope_depth: usize,
+    next_slot: u32,
+    max_slots: u32,
+    /// 当前表达式求值过程中压在局部变量区之上的临时值个数
+    temps: u32,
+    loops: Vec<LoopCtx>,
+    /// 已分配的内联缓存个数
+    inline_caches: u32,
+    /// [New] 匿名函数：找不到的名字先去外层函数的局部变量里找
+    is_closure: bool,
+    upvalues: Vec<UpvalueDesc>,
+    /// [New] 被内层闭包捕获的槽位上界，退出作用域时不回收 (同 Analyzer 的 FrameScope)
+    pinned: u32,
+}
+
+impl FnState {
+    fn new(is_script: bool) -> Self {
+        Self {
+            chunk: Chunk::default(),
+            is_script,
+            locals: Vec::new(),
+            scope_depth: 0,
+            // 槽位 0 保留给 callee / self
+            next_slot: 1,
+            max_slots: 1,
+            temps: 0,
+            loops: Vec::new(),
+            inline_caches: 0,
+            is_closure: false,
+            upvalues: Vec::new(),
+            pinned: 0,
+        }
+    }
+}
+
+pub struct Compiler<'c> {
+    ctx: &'c mut Context,
+    file_id: FileId,
+    /// 当前模块的顶层名字 + 内置名字
+    /// 对这些名字赋值会写回模块环境，而不是定义新的局部变量
+    globals: HashSet<Symbol>,
+    builtins: HashSet<Symbol>,
+    state: FnState,
+    /// [New] 外层函数的编译状态 (由外到内)，匿名函数从这里解析捕获的变量
+    enclosing: Vec<FnState>,
+    /// [New] 正在编译的表达式，随指令一起记录到 Chunk::origins
+    origin: (NodeId, Span),
+}
+
+impl<'c> Compiler<'c> {
+    pub fn new(ctx: &'c mut Context, builtins: HashSet<Symbol>) -> Self {
+        Self {
+            ctx,
+            file_id: FileId::BUILTIN,
+            globals: HashSet::new(),
+            builtins,
+            state: FnState::new(true),
+            enclosing: Vec::new(),
+            origin: (NodeId(0), Span::default()),
+        }
+    }
+
+    /// 编译所有模块和所有类
+    pub fn compile_program(
+        mut self,
+        module_programs: &HashMap<FileId, Rc<Program>>,
+        table_definitions: &HashMap<TableId, Rc<TableDefinition>>,
+    ) -> Result<CompiledProgram, String> {
+        let mut scripts = HashMap::new();
+        for (file_id, program) in module_programs {
+            self.enter_module(*file_id, program);
+            let script = self.compile_script(program)?;
+            scripts.insert(*file_id, script);
+        }
+
+        let mut classes = HashMap::new();
+        for (table_id, def) in table_definitions {
+            if let Some(program) = module_programs.get(&table_id.file_id()) {
+                self.enter_module(table_id.file_id(), program);
+            }
+            classes.insert(*table_id, self.compile_class(*table_id, def));
+        }
+
+        // 所有类的方法都编译完之后，再按 Analyzer 合并好的虚表项填充每个类的虚表
+        let table_ids: Vec<TableId> = classes.keys().copied().collect();
+        for table_id in table_ids {
+            let entries = self
+                .ctx
+                .source_manager
+                .get_file_path(table_id.file_id())
+                .and_then(|path| self.ctx.modules.get(path))
+                .and_then(|module| module.tables.get(&table_id))
+                .map(|info| info.vtable.clone())
+                .unwrap_or_default();
+
+            let mut vtable = VTable::default();
+            for entry in entries {
+                let method = classes
+                    .get(&entry.owner)
+                    .and_then(|owner| owner.methods.get(&entry.name));
+                if let Some(proto) = method {
+                    vtable.push(entry.name, proto.clone());
+                }
+            }
+            if let Some(class) = classes.get_mut(&table_id) {
+                class.vtable = vtable;
+            }
+        }
+
+        Ok(CompiledProgram { scripts, classes })
+    }
+
+    fn enter_module(&mut self, file_id: FileId, program: &Program) {
+        self.file_id = file_id;
+        self.globals = self.builtins.clone();
+        for item in &program.definitions {
+            let name = match item {
+                TopLevelItem::Table(def) => def.name,
+                TopLevelItem::Function(def) => def.name,
+                TopLevelItem::Field(def) => def.name,
+                TopLevelItem::Enum(def) => def.name,
+                TopLevelItem::Use(stmt) => stmt.alias.unwrap_or(*stmt.path.last().unwrap()),
+            };
+            self.globals.insert(name);
+        }
+    }
+
+    // ==========================================
+    //          Section 1: Functions
+    // ==========================================
+
+    fn begin_function(&mut self, is_script: bool) {
+        let prev = std::mem::replace(&mut self.state, FnState::new(is_script));
+        self.enclosing.push(prev);
+    }
+
+    fn end_function(
+        &mut self,
+        name: Symbol,
+        kind: FunctionKind,
+        arity: usize,
+    ) -> Rc<FunctionProto> {
+        let prev = self.enclosing.pop().expect("unbalanced begin_function");
+        let state = std::mem::replace(&mut self.state, prev);
+        Rc::new(FunctionProto {
+            name,
+            kind,
+            arity,
+            max_slots: state.max_slots as usize,
+            file_id: self.file_id,
+            chunk: state.chunk,
+            inline_caches: RefCell::new(
+                (0..state.inline_caches)
+                    .map(|_| InlineCache::default())
+                    .collect(),
+            ),
+            upvalues: state.upvalues,
+        })
+    }
+
+    /// 模块顶层代码：按顺序定义类、函数、变量并加载依赖模块
+    fn compile_script(&mut self, program: &Program) -> Result<Rc<FunctionProto>, String> {
+        self.begin_function(true);
+
+        for item in &program.definitions {
+            match item {
+                TopLevelItem::Table(def) => {
+                    let table_id = TableId(self.file_id, def.name);
+                    self.emit_constant(Value::Table(table_id));
+                    self.emit(OpCode::DefineGlobal(def.name));
+                }
+                TopLevelItem::Enum(def) => {
+                    let desc = EnumDesc::new(self.file_id, def);
+                    self.emit_constant(Value::Enum(Rc::new(desc)));
+                    self.emit(OpCode::DefineGlobal(def.name));
+                }
+                TopLevelItem::Function(def) => {
+                    let proto = self.compile_function(def, FunctionKind::Function);
+                    self.emit_constant(Value::CompiledFunction(proto));
+                    self.emit(OpCode::DefineGlobal(def.name));
+                }
+                TopLevelItem::Field(def) => {
+                    match &def.value {
+                        Some(expr) => self.compile_expr(expr),
+                        None => {
+                            self.emit(OpCode::Nil);
+                        }
+                    }
+                    self.emit(OpCode::DefineGlobal(def.name));
+                }
+                TopLevelItem::Use(stmt) => {
+                    let file_id = self.resolve_use(stmt)?;
+                    let bind_name = stmt.alias.unwrap_or(*stmt.path.last().unwrap());
+                    self.emit(OpCode::Import(file_id));
+                    self.emit(OpCode::DefineGlobal(bind_name));
+                }
+            }
+        }
+
+        self.emit(OpCode::ModuleSelf);
+        self.emit(OpCode::Return);
+
+        let name = self.ctx.intern("<script>");
+        Ok(self.end_function(name, FunctionKind::Script, 0))
+    }
+
+    /// 在编译期把 use 路径解析成 FileId (与 Interpreter::bind_module 一致)
+    fn resolve_use(&mut self, stmt: &UseStatement) -> Result<FileId, String> {
+        let path_segments: Vec<String> = stmt
+            .path
+            .iter()
+            .map(|s| self.ctx.resolve_symbol(*s).to_string())
+            .collect();
+
+        let current_path = self
+            .ctx
+            .source_manager
+            .get_file_path(self.file_id)
+            .cloned()
+            .unwrap_or_else(|| self.ctx.root_dir.clone());
+        let current_dir = current_path
+            .parent()
+            .unwrap_or(&self.ctx.root_dir)
+            .to_path_buf();
+
+        let target_path = resolve_module_path(self.ctx, &stmt.anchor, &path_segments, &current_dir)
+            .ok_or_else(|| format!("Module not found: {:?}", path_segments))?;
+        let abs_path = target_path.canonicalize().unwrap_or(target_path);
+
+        self.ctx
+            .source_manager
+            .load_file(&abs_path)
+            .map_err(|e| format!("IO Error: {}", e))
+    }
+
+    fn compile_function(
+        &mut self,
+        def: &MethodDefinition,
+        kind: FunctionKind,
+    ) -> Rc<FunctionProto> {
+        self.begin_function(false);
+        self.compile_function_body(def, kind)
+    }
+
+    /// [New] 匿名函数：和顶层函数一样编译 (槽位 0 是闭包自身)，
+    /// 再用 Closure 指令在运行时捕获外层变量
+    fn compile_lambda(&mut self, def: &MethodDefinition) {
+        self.begin_function(false);
+        self.state.is_closure = true;
+        let proto = self.compile_function_body(def, FunctionKind::Function);
+        let idx = self
+            .state
+            .chunk
+            .add_constant(Value::CompiledFunction(proto));
+        self.emit(OpCode::Closure(idx));
+    }
+
+    /// 已经 begin_function 之后：声明参数、编译函数体并结束函数
+    fn compile_function_body(
+        &mut self,
+        def: &MethodDefinition,
+        kind: FunctionKind,
+    ) -> Rc<FunctionProto> {
+        // 槽位 0: 方法里是 self，顶层函数里是函数自身 (不可见)
+        let slot0_name = if kind == FunctionKind::Method {
+            Some(self.ctx.intern("self"))
+        } else {
+            None
+        };
+        self.state.locals.push(Local {
+            name: slot0_name,
+            slot: 0,
+            depth: 0,
+        });
+        for param in &def.params {
+            self.declare_local(Some(param.name));
+        }
+
+        match &def.body {
+            Some(body) => self.compile_block(body),
+            None if kind == FunctionKind::Method => {
+                self.emit_internal("Cannot call abstract method");
+            }
+            None => {
+                self.emit(OpCode::Nil);
+            }
+        }
+        self.emit(OpCode::Return);
+
+        self.end_function(def.name, kind, def.params.len())
+    }
+
+    fn compile_class(&mut self, table_id: TableId, def: &TableDefinition) -> ClassProto {
+        let table_info = self
+            .ctx
+            .source_manager
+            .get_file_path(table_id.file_id())
+            .and_then(|path| self.ctx.modules.get(path))
+            .and_then(|module| module.tables.get(&table_id))
+            .cloned();
+
+        // 父类在语义分析阶段已经解析成 Type
+        let parent = table_info
+            .as_ref()
+            .and_then(|info| info.parent.as_ref())
+            .and_then(|ty| match ty {
+                Type::Table(id) => Some(*id),
+                Type::GenericInstance { base, .. } => Some(*base),
+                _ => None,
+            });
+
+        let mut methods = HashMap::new();
+        for item in &def.items {
+            if let TableItem::Method(method_def) = item {
+                let proto = self.compile_function(method_def, FunctionKind::Method);
+                methods.insert(method_def.name, proto);
+            }
+        }
+
+        // 字段初始化器：在定义类的模块里求值所有字段 (含继承来的字段)
+        self.begin_function(false);
+        // 按布局顺序 (父类字段在前) 压栈，NewInstance 直接把它们收成字段向量
+        let mut field_names = Vec::new();
+        match &table_info {
+            Some(info) => {
+                for name in &info.field_layout {
+                    match info.fields.get(name).and_then(|f| f.value.as_ref()) {
+                        Some(expr) => self.compile_expr(expr),
+                        None => {
+                            self.emit(OpCode::Nil);
+                        }
+                    }
+                    self.state.temps += 1;
+                    field_names.push(*name);
+                }
+                self.state.temps = 0;
+                self.emit(OpCode::NewInstance);
+            }
+            None => {
+                self.emit_internal("TableInfo missing");
+            }
+        }
+        self.emit(OpCode::Return);
+        let init = self.end_function(
+            def.name,
+            FunctionKind::Initializer {
+                table_id,
+                layout: Rc::new(FieldLayout::new(field_names)),
+            },
+            0,
+        );
+
+        ClassProto {
+            parent,
+            methods,
+            vtable: VTable::default(),
+            init,
+        }
+    }
+
+    // ==========================================
+    //          Section 2: Scopes & Locals
+    // ==========================================
+
+    fn begin_scope(&mut self) {
+        self.state.scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.state.scope_depth -= 1;
+        let depth = self.state.scope_depth;
+        while let Some(local) = self.state.locals.last() {
+            if local.depth <= depth {
+                break;
+            }
+            self.state.next_slot = local.slot.max(self.state.pinned);
+            self.state.locals.pop();
+        }
+    }
+
+    /// 分配一个新的局部槽位 (同名变量直接遮蔽)
+    fn declare_local(&mut self, name: Option<Symbol>) -> u32 {
+        let slot = self.state.next_slot;
+        self.state.next_slot += 1;
+        self.state.max_slots = self.state.max_slots.max(self.state.next_slot);
+        self.state.locals.push(Local {
+            name,
+            slot,
+            depth: self.state.scope_depth,
+        });
+        slot
+    }
+
+    fn resolve_local(&self, name: Symbol) -> Option<u32> {
+        find_local(&self.state, name)
+    }
+
+    /// [New] 在外层函数里查找 name，找到时登记成当前函数的 upvalue
+    fn resolve_upvalue(&mut self, name: Symbol) -> Option<u32> {
+        self.resolve_upvalue_at(self.enclosing.len(), name)
+    }
+
+    /// level 是函数在 enclosing + [state] 里的下标：
+    /// 直接外层的局部变量直接捕获，更外层的通过直接外层的 upvalue 逐层转交
+    fn resolve_upvalue_at(&mut self, level: usize, name: Symbol) -> Option<u32> {
+        if level == 0 || !self.state_at(level).is_closure {
+            return None;
+        }
+        let parent = level - 1;
+        let desc = match find_local(self.state_at(parent), name) {
+            Some(slot) => {
+                let parent_state = self.state_at(parent);
+                parent_state.pinned = parent_state.pinned.max(slot + 1);
+                UpvalueDesc {
+                    is_local: true,
+                    index: slot,
+                }
+            }
+            None => UpvalueDesc {
+                is_local: false,
+                index: self.resolve_upvalue_at(parent, name)?,
+            },
+        };
+
+        let upvalues = &mut self.state_at(level).upvalues;
+        let index = match upvalues.iter().position(|u| *u == desc) {
+            Some(index) => index,
+            None => {
+                upvalues.push(desc);
+                upvalues.len() - 1
+            }
+        };
+        Some(index as u32)
+    }
+
+    fn state_at(&mut self, level: usize) -> &mut FnState {
+        if level == self.enclosing.len() {
+            &mut self.state
+        } else {
+            &mut self.enclosing[level]
+        }
+    }
+
+    // ==========================================
+    //          Section 3: Emit Helpers
+    // ==========================================
+
+    fn emit(&mut self, op: OpCode) -> usize {
+        self.state.chunk.emit(op, self.origin)
+    }
+
+    fn emit_constant(&mut self, val: Value) {
+        let idx = self.state.chunk.add_constant(val);
+        self.emit(OpCode::Constant(idx));
+    }
+
+    fn emit_internal(&mut self, msg: &str) {
+        let idx = self.state.chunk.add_constant(Value::Str(msg.into()));
+        self.emit(OpCode::Internal(idx));
+    }
+
+    fn current_pos(&self) -> usize {
+        self.state.chunk.code.len()
+    }
+
+    fn patch_jump(&mut self, at: usize) {
+        let target = self.current_pos();
+        self.state.chunk.patch_jump(at, target);
+    }
+
+    // ==========================================
+    //          Section 4: Expressions
+    // ==========================================
+
+    /// 编译一个表达式，执行后栈顶恰好多出一个值
+    fn compile_expr(&mut self, expr: &Expression) {
+        let prev = std::mem::replace(&mut self.origin, (expr.id, expr.span));
+        self.compile_expr_data(expr);
+        self.origin = prev;
+    }
+
+    fn compile_expr_data(&mut self, expr: &Expression) {
+        match &expr.data {
+            ExpressionData::Literal(lit) => self.compile_literal(lit),
+            ExpressionData::Identifier(sym) => {
+                if let Some(slot) = self.resolve_local(*sym) {
+                    self.emit(OpCode::GetLocal(slot));
+                } else if let Some(idx) = self.resolve_upvalue(*sym) {
+                    self.emit(OpCode::GetUpvalue(idx));
+                } else {
+                    self.emit(OpCode::GetGlobal(*sym));
+                }
+            }
+
+            ExpressionData::FieldAccess { target, field } => {
+                self.compile_expr(target);
+                self.emit(OpCode::GetField {
+                    name: *field,
+                    index: self.ctx.db.field_slot_of(expr.id),
+                });
+            }
+            ExpressionData::Index { target, index } => {
+                self.compile_expr(target);
+                self.state.temps += 1;
+                self.compile_expr(index);
+                self.state.temps -= 1;
+                self.emit(OpCode::GetIndex);
+            }
+            ExpressionData::Call { callee, args, .. } => self.compile_call(callee, args),
+
+            ExpressionData::Assign { op, target, value } => {
+                self.compile_assignment(*op, target, value)
+            }
+            ExpressionData::Destructure {
+                pattern,
+                value,
+                is_mut,
+            } => {
+                self.compile_expr(value);
+                // [New] let / var 解构总是定义新变量
+                let mode = match is_mut {
+                    Some(_) => BindMode::Define,
+                    None => BindMode::Assign,
+                };
+                self.compile_bind_pattern(pattern, mode);
+                self.emit(OpCode::Unit);
+            }
+
+            ExpressionData::Binary { op, left, right } => self.compile_binary(*op, left, right),
+            ExpressionData::Unary { op, expr } => {
+                self.compile_expr(expr);
+                match op {
+                    UnaryOp::Neg => self.emit(OpCode::Neg),
+                    UnaryOp::Not => self.emit(OpCode::Not),
+                };
+            }
+
+            ExpressionData::Block(blk) => self.compile_block(blk),
+            ExpressionData::If {
+                condition,
+                then_block,
+                else_block,
+            } => self.compile_if(condition, then_block, else_block),
+            ExpressionData::While { condition, body } => self.compile_while(condition, body),
+            ExpressionData::For {
+                pattern,
+                iterable,
+                body,
+            } => self.compile_for(pattern, iterable, body),
+            ExpressionData::Break { .. } => self.compile_break(),
+            ExpressionData::Continue => self.compile_continue(),
+
+            ExpressionData::Array(elements) => {
+                for e in elements {
+                    self.compile_expr(e);
+                    self.state.temps += 1;
+                }
+                self.state.temps -= elements.len() as u32;
+                self.emit(OpCode::Array(elements.len() as u32));
+            }
+            ExpressionData::Tuple(elements) => {
+                for e in elements {
+                    self.compile_expr(e);
+                    self.state.temps += 1;
+                }
+                self.state.temps -= elements.len() as u32;
+                self.emit(OpCode::Tuple(elements.len() as u32));
+            }
+
+            ExpressionData::Return(val) => {
+                match val {
+                    Some(e) => self.compile_expr(e),
+                    None => {
+                        self.emit(OpCode::Unit);
+                    }
+                }
+                self.emit(OpCode::Return);
+            }
+
+            ExpressionData::VariableDefinition { name, init, .. } => {
+                self.compile_expr(init);
+                self.define_variable(*name);
+                self.emit(OpCode::Unit);
+            }
+
+            ExpressionData::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                // [Changed] 省略的端点是 nil (只会出现在切片下标里)
+                self.compile_optional(start);
+                self.state.temps += 1;
+                self.compile_optional(end);
+                self.state.temps -= 1;
+                self.emit(OpCode::Range {
+                    inclusive: *inclusive,
+                });
+            }
+            ExpressionData::Cast { expr, target_type } => {
+                self.compile_expr(expr);
+                let target = match &target_type.data {
+                    TypeRefData::Named(sym) => CastTarget::Named(*sym),
+                    _ => CastTarget::Noop,
+                };
+                self.emit(OpCode::Cast(target));
+            }
+            ExpressionData::Match { scrutinee, arms } => self.compile_match(scrutinee, arms),
+            ExpressionData::VariantLiteral { target, fields } => {
+                self.compile_expr(target);
+                self.state.temps += 1;
+                for (_, value) in fields {
+                    self.compile_expr(value);
+                    self.state.temps += 1;
+                }
+                self.state.temps -= fields.len() as u32 + 1;
+                let names = fields.iter().map(|(name, _)| *name).collect();
+                self.state.chunk.field_lists.push(names);
+                let idx = self.state.chunk.field_lists.len() - 1;
+                self.emit(OpCode::StructVariant(idx as u32));
+            }
+            ExpressionData::Lambda(def) => self.compile_lambda(def),
+        }
+    }
+
+    fn compile_optional(&mut self, expr: &Option<Box<Expression>>) {
+        match expr {
+            Some(expr) => self.compile_expr(expr),
+            None => {
+                self.emit(OpCode::Nil);
+            }
+        }
+    }
+
+    fn compile_literal(&mut self, lit: &Literal) {
+        match lit {
+            Literal::Int(i) => self.emit_constant(Value::Int(*i)),
+            Literal::Float(f) => self.emit_constant(Value::Float(*f)),
+            Literal::String(s) => self.emit_constant(Value::Str(s.clone())),
+            Literal::Bool(true) => {
+                self.emit(OpCode::True);
+            }
+            Literal::Bool(false) => {
+                self.emit(OpCode::False);
+            }
+            Literal::Char(c) => self.emit_constant(Value::Char(*c)),
+            Literal::Nil => {
+                self.emit(OpCode::Nil);
+            }
+        }
+    }
+
+    fn compile_call(&mut self, callee: &Expression, args: &[CallArg]) {
+        // obj.method(args) 直接编译成 Invoke，省掉中间的 BoundMethod
+        let invoke = if let ExpressionData::FieldAccess { target, field } = &callee.data {
+            self.compile_expr(target);
+            Some(*field)
+        } else {
+            self.compile_expr(callee);
+            None
+        };
+        self.state.temps += 1;
+
+        for arg in args {
+            self.compile_expr(&arg.value);
+            self.state.temps += 1;
+        }
+        self.state.temps -= args.len() as u32 + 1;
+
+        let argc = args.len() as u32;
+        match invoke {
+            Some(name) => {
+                let cache = self.state.inline_caches;
+                self.state.inline_caches += 1;
+                self.emit(OpCode::Invoke { name, argc, cache })
+            }
+            None => self.emit(OpCode::Call(argc)),
+        };
+    }
+
+    fn compile_binary(&mut self, op: BinaryOp, left: &Expression, right: &Expression) {
+        self.compile_expr(left);
+
+        match op {
+            BinaryOp::And | BinaryOp::Or => {
+                let jump = if op == BinaryOp::And {
+                    self.emit(OpCode::JumpIfFalseOrPop(0))
+                } else {
+                    self.emit(OpCode::JumpIfTrueOrPop(0))
+                };
+                self.compile_expr(right);
+                self.patch_jump(jump);
+            }
+            _ => {
+                self.state.temps += 1;
+                self.compile_expr(right);
+                self.state.temps -= 1;
+                self.emit(OpCode::Binary(op));
+            }
+        }
+    }
+
+    fn compile_assignment(&mut self, op: AssignOp, target: &Expression, value: &Expression) {
+        self.compile_expr(value);
+
+        if op != AssignOp::Assign {
+            self.emit_internal("Compound assignment not implemented yet");
+            return;
+        }
+
+        match &target.data {
+            ExpressionData::Identifier(name) => {
+                if let Some(slot) = self.resolve_local(*name) {
+                    self.emit(OpCode::SetLocal(slot));
+                } else if let Some(idx) = self.resolve_upvalue(*name) {
+                    self.emit(OpCode::SetUpvalue(idx));
+                } else if self.globals.contains(name) {
+                    self.emit(OpCode::SetGlobal(*name));
+                } else {
+                    // 自动定义
+                    self.define_variable(*name);
+                }
+            }
+
+            ExpressionData::FieldAccess {
+                target: obj_expr,
+                field,
+            } => {
+                self.state.temps += 1;
+                self.compile_expr(obj_expr);
+                self.state.temps -= 1;
+                self.emit(OpCode::SetField {
+                    name: *field,
+                    index: self.ctx.db.field_slot_of(target.id),
+                });
+            }
+
+            ExpressionData::Index {
+                target: arr_expr,
+                index: idx_expr,
+            } => {
+                self.state.temps += 1;
+                self.compile_expr(arr_expr);
+                self.state.temps += 1;
+                self.compile_expr(idx_expr);
+                self.state.temps -= 2;
+                self.emit(OpCode::SetIndex);
+            }
+
+            _ => {
+                self.emit_internal("Invalid assignment target");
+                return;
+            }
+        }
+        self.emit(OpCode::Unit);
+    }
+
+    /// [New] 弹出栈顶的值，绑定到模式里的名字上
+    fn compile_bind_pattern(&mut self, pattern: &Pattern, mode: BindMode) {
+        match &pattern.data {
+            PatternData::Identifier(name) => match mode {
+                BindMode::Define => self.define_variable(*name),
+                // 和 `x = v` 一样：已有的变量赋值，否则自动定义
+                BindMode::Assign => {
+                    if let Some(slot) = self.resolve_local(*name) {
+                        self.emit(OpCode::SetLocal(slot));
+                    } else if let Some(idx) = self.resolve_upvalue(*name) {
+                        self.emit(OpCode::SetUpvalue(idx));
+                    } else if self.globals.contains(name) {
+                        self.emit(OpCode::SetGlobal(*name));
+                    } else {
+                        self.define_variable(*name);
+                    }
+                }
+            },
+            PatternData::Wildcard => {
+                self.emit(OpCode::Pop);
+            }
+            // 可能匹配失败的模式只出现在 match 里 (见 compile_match)
+            PatternData::Literal(_) | PatternData::Variant { .. } => {
+                self.emit_internal("Refutable pattern outside of match");
+            }
+            PatternData::Tuple(items) => {
+                // 拆开后剩下还没绑定的元素都是临时值
+                self.emit(OpCode::Unpack(items.len() as u32));
+                self.state.temps += items.len() as u32;
+                for item in items {
+                    self.state.temps -= 1;
+                    self.compile_bind_pattern(item, mode);
+                }
+            }
+        }
+    }
+
+    /// 把栈顶的值定义为当前作用域的变量
+    fn define_variable(&mut self, name: Symbol) {
+        if self.state.is_script && self.state.scope_depth == 0 {
+            // 模块顶层没有局部作用域，直接写入模块环境
+            self.emit(OpCode::DefineGlobal(name));
+        } else {
+            let slot = self.declare_local(Some(name));
+            self.emit(OpCode::SetLocal(slot));
+        }
+    }
+
+    // ==========================================
+    //          Section 5: Control Flow
+    // ==========================================
+
+    fn compile_block(&mut self, block: &Block) {
+        self.begin_scope();
+
+        if block.statements.is_empty() {
+            self.emit(OpCode::Unit);
+        }
+        let last = block.statements.len().saturating_sub(1);
+        for (i, stmt) in block.statements.iter().enumerate() {
+            self.compile_expr(stmt);
+            if i != last {
+                self.emit(OpCode::Pop);
+            }
+        }
+
+        self.end_scope();
+    }
+
+    fn compile_if(
+        &mut self,
+        condition: &Expression,
+        then_block: &Block,
+        else_block: &Option<Block>,
+    ) {
+        self.compile_expr(condition);
+        let else_jump = self.emit(OpCode::JumpIfFalse(0));
+
+        self.compile_block(then_block);
+        let end_jump = self.emit(OpCode::Jump(0));
+
+        self.patch_jump(else_jump);
+        match else_block {
+            Some(blk) => self.compile_block(blk),
+            None => {
+                self.emit(OpCode::Unit);
+            }
+        }
+        self.patch_jump(end_jump);
+    }
+
+    /// [New] 被匹配的值放在隐藏槽位里，逐个分支执行 Match；
+    /// 匹配成功时绑定的值在栈上，依次写入分支作用域里的局部变量
+    fn compile_match(&mut self, scrutinee: &Expression, arms: &[MatchArm]) {
+        self.compile_expr(scrutinee);
+        self.begin_scope();
+        let value_slot = self.declare_local(None);
+        self.emit(OpCode::SetLocal(value_slot));
+
+        let mut end_jumps = Vec::new();
+        for arm in arms {
+            self.emit(OpCode::GetLocal(value_slot));
+            self.state.chunk.patterns.push(arm.pattern.clone());
+            let idx = self.state.chunk.patterns.len() - 1;
+            self.emit(OpCode::Match(idx as u32));
+            let next_arm = self.emit(OpCode::JumpIfFalse(0));
+
+            self.begin_scope();
+            let slots: Vec<u32> = arm
+                .pattern
+                .bindings()
+                .into_iter()
+                .map(|(_, name)| self.declare_local(Some(name)))
+                .collect();
+            for slot in slots.into_iter().rev() {
+                self.emit(OpCode::SetLocal(slot));
+            }
+            self.compile_block(&arm.body);
+            self.end_scope();
+            end_jumps.push(self.emit(OpCode::Jump(0)));
+
+            self.patch_jump(next_arm);
+        }
+        // Analyzer 保证 match 是完整的
+        self.emit_internal("No match arm matched the value");
+
+        for at in end_jumps {
+            self.patch_jump(at);
+        }
+        self.end_scope();
+    }
+
+    fn compile_while(&mut self, condition: &Expression, body: &Block) {
+        let start = self.current_pos();
+        self.compile_expr(condition);
+        self.emit(OpCode::AssertBool);
+        let exit_jump = self.emit(OpCode::JumpIfFalse(0));
+
+        self.state.loops.push(LoopCtx {
+            start,
+            temps: self.state.temps,
+            breaks: Vec::new(),
+        });
+        self.compile_block(body);
+        self.emit(OpCode::Pop);
+        self.emit(OpCode::Jump(start as u32));
+
+        self.patch_jump(exit_jump);
+        self.finish_loop();
+    }
+
+    fn compile_for(&mut self, iterator: &Pattern, iterable: &Expression, body: &Block) {
+        self.compile_expr(iterable);
+
+        // 隐藏槽位: [序列, 游标]，紧跟着迭代变量
+        self.begin_scope();
+        let state_slot = self.declare_local(None);
+        self.declare_local(None);
+        self.emit(OpCode::IterPrep(state_slot));
+
+        let start = self.current_pos();
+        let next = self.emit(OpCode::IterNext {
+            slot: state_slot,
+            exit: 0,
+        });
+
+        self.state.loops.push(LoopCtx {
+            start,
+            temps: self.state.temps,
+            breaks: Vec::new(),
+        });
+        self.begin_scope();
+        match &iterator.data {
+            PatternData::Identifier(name) => {
+                self.declare_local(Some(*name));
+            }
+            // [New] 元素先放进隐藏槽位，再拆开绑定到各个名字
+            _ => {
+                let item_slot = self.declare_local(None);
+                self.emit(OpCode::GetLocal(item_slot));
+                self.compile_bind_pattern(iterator, BindMode::Define);
+            }
+        }
+        self.compile_block(body);
+        self.emit(OpCode::Pop);
+        self.end_scope();
+        self.emit(OpCode::Jump(start as u32));
+
+        self.patch_jump(next);
+        self.finish_loop();
+        self.end_scope();
+    }
+
+    /// 回填 break 并压入循环表达式的值 (Unit)
+    fn finish_loop(&mut self) {
+        let ctx = self.state.loops.pop().expect("loop context");
+        for at in ctx.breaks {
+            self.patch_jump(at);
+        }
+        self.emit(OpCode::Unit);
+    }
+
+    fn compile_break(&mut self) {
+        let Some(ctx) = self.state.loops.last() else {
+            self.emit_internal("'break' or 'continue' outside of loop");
+            return;
+        };
+        let temps = ctx.temps;
+        self.emit(OpCode::Truncate(temps));
+        let jump = self.emit(OpCode::Jump(0));
+        self.state.loops.last_mut().unwrap().breaks.push(jump);
+    }
+
+    fn compile_continue(&mut self) {
+        let Some(ctx) = self.state.loops.last() else {
+            self.emit_internal("'break' or 'continue' outside of loop");
+            return;
+        };
+        let (temps, start) = (ctx.temps, ctx.start);
+        self.emit(OpCode::Truncate(temps));
+        self.emit(OpCode::Jump(start as u32));
+    }
+}
+
+/// 函数里最内层的同名局部变量
+fn find_local(state: &FnState, name: Symbol) -> Option<u32> {
+    state
+        .locals
+        .iter()
+        .rev()
+        .find(|l| l.name == Some(name))
+        .map(|l| l.slot)
+}
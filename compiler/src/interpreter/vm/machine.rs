@@ -0,0 +1,839 @@
+// src/interpreter/vm/machine.rs
+//
+// 栈式虚拟机
+// 所有 Loom 调用都在 frames 上展开，不占用宿主 (Rust) 调用栈。
+
+use super::chunk::{CastTarget, FunctionKind, FunctionProto, OpCode};
+use super::compiler::{CompiledProgram, Compiler};
+use crate::analyzer::TableId;
+use crate::ast::{Program, TableDefinition};
+use crate::context::Context;
+use crate::interpreter::DEFAULT_MAX_CALL_DEPTH;
+use crate::interpreter::environment::Environment;
+use crate::interpreter::errors::{RuntimeError, RuntimeErrorKind, StackFrame};
+use crate::interpreter::native;
+use crate::interpreter::ops;
+use crate::interpreter::value::{CompiledClosure, Instance, NativeFunc, Upvalue, Value};
+use crate::source::FileId;
+use crate::utils::Symbol;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// 调用帧
+struct CallFrame {
+    proto: Rc<FunctionProto>,
+    ip: usize,
+    /// 槽位 0 在值栈中的位置
+    base: usize,
+    /// 函数定义所在模块的全局环境
+    globals: Rc<RefCell<Environment>>,
+    /// [New] 正在执行的闭包 (GetUpvalue / SetUpvalue 从这里取捕获的变量)
+    closure: Option<Rc<CompiledClosure>>,
+}
+
+pub struct Vm<'a> {
+    pub ctx: &'a mut Context,
+
+    // 内置环境 (包含 print)，所有模块环境的父环境
+    builtins: Rc<RefCell<Environment>>,
+
+    program: CompiledProgram,
+
+    // 模块缓存 Key: FileId -> Value::Module
+    module_cache: HashMap<FileId, Value>,
+
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+    /// [New] 还指向值栈的 upvalue；同一个槽位只有一个，所以闭包之间共享同一个变量
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+
+    // [New] 活跃的函数/方法/初始化器帧数 (不含模块顶层)，与树遍历解释器的计数一致
+    call_depth: usize,
+    pub max_call_depth: usize,
+
+    main_file_id: FileId,
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(ctx: &'a mut Context, main_file_id: FileId) -> Self {
+        let builtins = Rc::new(RefCell::new(Environment::new()));
+        for (name, func) in native::GLOBAL_NATIVES {
+            builtins.borrow_mut().define(
+                ctx.intern(name),
+                Value::NativeFunction(NativeFunc::new(name, *func)),
+            );
+        }
+
+        Self {
+            ctx,
+            builtins,
+            program: CompiledProgram {
+                scripts: HashMap::new(),
+                classes: HashMap::new(),
+            },
+            module_cache: HashMap::new(),
+            stack: Vec::with_capacity(256),
+            frames: Vec::with_capacity(64),
+            open_upvalues: Vec::new(),
+            call_depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            main_file_id,
+        }
+    }
+
+    /// 编译并运行整个程序：先执行 Main 的顶层代码，再调用 main()
+    pub fn run_program(
+        &mut self,
+        module_programs: &HashMap<FileId, Rc<Program>>,
+        table_definitions: &HashMap<TableId, Rc<TableDefinition>>,
+    ) -> Result<Value, RuntimeError> {
+        // Step 1: 编译
+        let builtin_names: HashSet<Symbol> =
+            self.builtins.borrow().values.keys().copied().collect();
+        self.program = Compiler::new(self.ctx, builtin_names)
+            .compile_program(module_programs, table_definitions)
+            .map_err(RuntimeErrorKind::Internal)?;
+
+        // Step 2: 执行顶层代码 (Main 放入缓存，与 Interpreter 一致)
+        self.stack.push(Value::Nil);
+        self.load_module(self.main_file_id)?;
+        self.run(0).map_err(|e| self.locate_error(e))?;
+
+        // Step 3: 执行 Main 入口
+        let main_sym = self.ctx.intern("main");
+        let main_func = match self
+            .module_globals(self.main_file_id)
+            .borrow()
+            .get(main_sym)
+        {
+            Some(v) => v,
+            // 没有 main 函数，对于脚本来说也是合法的
+            None => return Ok(Value::Unit),
+        };
+        self.call_value(main_func, &[])
+            .map_err(|e| self.locate_error(e))
+    }
+
+    /// 从宿主调用一个 Loom 可调用对象
+    pub fn call_value(&mut self, func: Value, args: &[Value]) -> Result<Value, RuntimeErrorKind> {
+        let depth = self.frames.len();
+        self.stack.push(func);
+        self.stack.extend_from_slice(args);
+
+        if self.call(args.len())? {
+            self.run(depth)
+        } else {
+            // 原生函数：结果已经在栈顶
+            Ok(self.stack.pop().unwrap_or(Value::Unit))
+        }
+    }
+
+    // ==========================================
+    //          Section 1: Dispatch Loop
+    // ==========================================
+
+    /// 执行直到帧数回落到 stop_depth，返回最后一个帧的返回值
+    fn run(&mut self, stop_depth: usize) -> Result<Value, RuntimeErrorKind> {
+        loop {
+            let frame = self.frames.last_mut().expect("no active frame");
+            let op = frame.proto.chunk.code[frame.ip];
+            frame.ip += 1;
+            let base = frame.base;
+
+            match op {
+                OpCode::Constant(idx) => {
+                    let val = frame.proto.chunk.constants[idx as usize].clone();
+                    self.stack.push(val);
+                }
+                OpCode::Nil => self.stack.push(Value::Nil),
+                OpCode::Unit => self.stack.push(Value::Unit),
+                OpCode::True => self.stack.push(Value::Bool(true)),
+                OpCode::False => self.stack.push(Value::Bool(false)),
+
+                OpCode::Pop => {
+                    self.stack.pop();
+                }
+                OpCode::Truncate(temps) => {
+                    let len = base + frame.proto.max_slots + temps as usize;
+                    self.stack.truncate(len);
+                }
+
+                OpCode::GetLocal(slot) => {
+                    let val = self.stack[base + slot as usize].clone();
+                    self.stack.push(val);
+                }
+                OpCode::SetLocal(slot) => {
+                    let val = self.pop();
+                    self.stack[base + slot as usize] = val;
+                }
+                OpCode::GetGlobal(sym) => {
+                    let val = frame.globals.borrow().get(sym);
+                    match val {
+                        Some(v) => self.stack.push(v),
+                        None => {
+                            return Err(RuntimeErrorKind::UndefinedVariable(
+                                self.ctx.resolve_symbol(sym).to_string(),
+                            ));
+                        }
+                    }
+                }
+                OpCode::SetGlobal(sym) => {
+                    let globals = frame.globals.clone();
+                    let val = self.pop();
+                    let mut env = globals.borrow_mut();
+                    if !env.assign(sym, val.clone()) {
+                        env.define(sym, val);
+                    }
+                }
+                OpCode::DefineGlobal(sym) => {
+                    let globals = frame.globals.clone();
+                    let val = self.pop();
+                    globals.borrow_mut().define(sym, val);
+                }
+                OpCode::GetUpvalue(idx) => {
+                    let upvalue = frame_upvalue(frame, idx)?;
+                    let val = match &*upvalue.borrow() {
+                        Upvalue::Open(slot) => self.stack[*slot].clone(),
+                        Upvalue::Closed(v) => v.clone(),
+                    };
+                    self.stack.push(val);
+                }
+                OpCode::SetUpvalue(idx) => {
+                    let upvalue = frame_upvalue(frame, idx)?;
+                    let val = self.pop();
+                    match &mut *upvalue.borrow_mut() {
+                        Upvalue::Open(slot) => self.stack[*slot] = val,
+                        Upvalue::Closed(v) => *v = val,
+                    }
+                }
+
+                OpCode::GetField { name, index } => {
+                    let target = self.pop();
+                    let val = self.get_member(target, name, index)?;
+                    self.stack.push(val);
+                }
+                OpCode::SetField { name, index } => {
+                    let obj = self.pop();
+                    let val = self.pop();
+                    if let Value::Instance(instance) = &obj {
+                        if !instance.set_field(name, index, val) {
+                            return Err(ops::property_not_found(self.ctx, &obj, name, true));
+                        }
+                    } else {
+                        return Err(RuntimeErrorKind::TypeError {
+                            expected: "Instance".into(),
+                            found: "Non-Instance".into(),
+                        });
+                    }
+                }
+                OpCode::GetIndex => {
+                    let index = self.pop();
+                    let target = self.pop();
+                    self.stack
+                        .push(ops::index_get(&mut self.ctx.heap, target, index)?);
+                }
+                OpCode::SetIndex => {
+                    let index = self.pop();
+                    let target = self.pop();
+                    let val = self.pop();
+                    ops::index_set(target, index, val)?;
+                }
+
+                OpCode::Binary(bin_op) => {
+                    let r = self.pop();
+                    let l = self.pop();
+                    self.stack.push(ops::binary_op(bin_op, l, r)?);
+                }
+                OpCode::Neg => {
+                    let val = self.pop();
+                    self.stack
+                        .push(ops::unary_op(crate::ast::UnaryOp::Neg, val)?);
+                }
+                OpCode::Not => {
+                    let val = self.pop();
+                    self.stack.push(Value::Bool(!ops::is_truthy(&val)));
+                }
+                OpCode::Cast(target) => {
+                    let val = self.pop();
+                    let res = self.cast(val, target)?;
+                    self.stack.push(res);
+                }
+
+                OpCode::Jump(target) => frame.ip = target as usize,
+                OpCode::JumpIfFalse(target) => {
+                    let cond = self.pop();
+                    if !ops::is_truthy(&cond) {
+                        self.jump(target);
+                    }
+                }
+                OpCode::JumpIfFalseOrPop(target) => {
+                    if ops::is_truthy(self.peek()) {
+                        self.stack.pop();
+                    } else {
+                        self.jump(target);
+                    }
+                }
+                OpCode::JumpIfTrueOrPop(target) => {
+                    if ops::is_truthy(self.peek()) {
+                        self.jump(target);
+                    } else {
+                        self.stack.pop();
+                    }
+                }
+                OpCode::AssertBool => {
+                    if !matches!(self.peek(), Value::Bool(_)) {
+                        return Err(RuntimeErrorKind::TypeError {
+                            expected: "Bool".into(),
+                            found: "Non-Bool".into(),
+                        });
+                    }
+                }
+
+                OpCode::IterPrep(slot) => {
+                    let iterable = self.pop();
+                    let (seq, cursor) = Self::iter_prep(iterable)?;
+                    self.stack[base + slot as usize] = seq;
+                    self.stack[base + slot as usize + 1] = Value::Int(cursor);
+                }
+                OpCode::IterNext { slot, exit } => {
+                    let slot = base + slot as usize;
+                    let cursor = self.stack[slot + 1].as_int().unwrap_or(0);
+                    let item = match &self.stack[slot] {
+                        Value::Array(arr) => arr.borrow().get(cursor as usize).cloned(),
+                        // 范围的游标是元素下标
+                        Value::Range(range) if range.len().is_ok_and(|len| cursor < len) => {
+                            range.get(cursor).ok().map(Value::Int)
+                        }
+                        _ => None,
+                    };
+                    match item {
+                        Some(v) => {
+                            self.stack[slot + 1] = Value::Int(cursor + 1);
+                            self.stack[slot + 2] = v;
+                        }
+                        None => self.jump(exit),
+                    }
+                }
+
+                OpCode::Call(argc) => {
+                    self.call(argc as usize)?;
+                }
+                OpCode::Invoke { name, argc, cache } => {
+                    self.invoke(name, argc as usize, cache as usize)?;
+                }
+                OpCode::Return => {
+                    let result = self.pop();
+                    let frame = self.frames.pop().expect("no active frame");
+                    if is_call_frame(&frame.proto) {
+                        self.call_depth -= 1;
+                    }
+                    self.close_upvalues(frame.base);
+                    self.stack.truncate(frame.base);
+                    if self.frames.len() == stop_depth {
+                        return Ok(result);
+                    }
+                    self.stack.push(result);
+                }
+                OpCode::Closure(idx) => {
+                    let Value::CompiledFunction(proto) =
+                        frame.proto.chunk.constants[idx as usize].clone()
+                    else {
+                        return Err(RuntimeErrorKind::Internal(
+                            "Closure operand is not a function".into(),
+                        ));
+                    };
+                    let enclosing = frame.closure.clone();
+                    let mut upvalues = Vec::with_capacity(proto.upvalues.len());
+                    for desc in &proto.upvalues {
+                        let upvalue = if desc.is_local {
+                            self.capture_upvalue(base + desc.index as usize)
+                        } else {
+                            match enclosing
+                                .as_ref()
+                                .and_then(|c| c.upvalues.get(desc.index as usize))
+                            {
+                                Some(upvalue) => upvalue.clone(),
+                                None => {
+                                    return Err(RuntimeErrorKind::Internal(
+                                        "Upvalue not found".into(),
+                                    ));
+                                }
+                            }
+                        };
+                        upvalues.push(upvalue);
+                    }
+                    self.stack
+                        .push(Value::CompiledClosure(Rc::new(CompiledClosure {
+                            proto,
+                            upvalues,
+                        })));
+                }
+
+                OpCode::Array(n) => {
+                    let start = self.stack.len() - n as usize;
+                    let elements = self.stack.split_off(start);
+                    let array = self.ctx.heap.alloc_array(elements);
+                    self.stack.push(Value::Array(array));
+                }
+                OpCode::Tuple(n) => {
+                    let start = self.stack.len() - n as usize;
+                    let elements = self.stack.split_off(start);
+                    self.stack.push(Value::Tuple(elements.into()));
+                }
+                OpCode::StructVariant(idx) => {
+                    let names = &frame.proto.chunk.field_lists[idx as usize];
+                    let start = self.stack.len() - names.len();
+                    let values = self.stack.split_off(start);
+                    let fields = names.iter().copied().zip(values).collect();
+                    let ctor = self.stack.pop().unwrap_or(Value::Nil);
+                    let variant = ops::construct_struct_variant(self.ctx, ctor, fields)?;
+                    self.stack.push(variant);
+                }
+                OpCode::Match(idx) => {
+                    let value = self.stack.pop().unwrap_or(Value::Nil);
+                    let pattern = &frame.proto.chunk.patterns[idx as usize];
+                    let mut binds = Vec::new();
+                    let matched = ops::match_pattern(pattern, &value, &mut binds);
+                    if matched {
+                        self.stack.extend(binds);
+                    }
+                    self.stack.push(Value::Bool(matched));
+                }
+                OpCode::Unpack(n) => {
+                    let value = self.pop();
+                    let items = ops::unpack_tuple(value, n as usize)?;
+                    self.stack.extend(items.iter().rev().cloned());
+                }
+                OpCode::Range { inclusive } => {
+                    let end = self.pop();
+                    let start = self.pop();
+                    self.stack.push(ops::make_range(start, end, inclusive)?);
+                }
+                OpCode::NewInstance => {
+                    let FunctionKind::Initializer { table_id, layout } = &frame.proto.kind else {
+                        return Err(RuntimeErrorKind::Internal(
+                            "NewInstance outside of initializer".into(),
+                        ));
+                    };
+                    let start = self.stack.len() - layout.len();
+                    let fields: Vec<Value> = self.stack.drain(start..).collect();
+                    let instance = self.ctx.heap.alloc_instance(Instance {
+                        table_id: *table_id,
+                        layout: layout.clone(),
+                        fields: RefCell::new(fields),
+                    });
+                    self.stack.push(Value::Instance(instance));
+                }
+
+                OpCode::Import(file_id) => {
+                    if let Some(module) = self.module_cache.get(&file_id) {
+                        self.stack.push(module.clone());
+                    } else {
+                        // 槽位 0 占位，模块顶层代码返回时换成模块对象
+                        self.stack.push(Value::Nil);
+                        self.load_module(file_id)?;
+                    }
+                }
+                OpCode::ModuleSelf => {
+                    let module = Value::Module(frame.proto.file_id, frame.globals.clone());
+                    self.stack.push(module);
+                }
+
+                OpCode::Internal(idx) => {
+                    let msg =
+                        frame.proto.chunk.constants[idx as usize].to_string(&self.ctx.interner);
+                    return Err(RuntimeErrorKind::Internal(msg));
+                }
+            }
+        }
+    }
+
+    // ==========================================
+    //          Section 2: Calls
+    // ==========================================
+
+    /// 调用栈上 [callee, args..] 处的对象
+    /// 返回 true 表示压入了新的帧，false 表示已经同步得到结果
+    fn call(&mut self, argc: usize) -> Result<bool, RuntimeErrorKind> {
+        let callee_idx = self.stack.len() - argc - 1;
+
+        match self.stack[callee_idx].clone() {
+            Value::NativeFunction(f) => {
+                let args = self.stack.split_off(callee_idx + 1);
+                self.stack.pop();
+                let result = f.call(self.ctx, &args)?;
+                self.stack.push(result);
+                Ok(false)
+            }
+
+            Value::BoundNativeMethod(receiver, f) => {
+                // 把 receiver 插入到参数列表的最前面 (self)
+                let mut full_args = Vec::with_capacity(argc + 1);
+                full_args.push(*receiver);
+                full_args.extend(self.stack.drain(callee_idx + 1..));
+                self.stack.pop();
+                let result = f.call(self.ctx, &full_args)?;
+                self.stack.push(result);
+                Ok(false)
+            }
+
+            Value::CompiledFunction(proto) => {
+                self.push_frame(proto, callee_idx, argc, None)?;
+                Ok(true)
+            }
+
+            Value::BoundCompiledMethod(instance, proto) => {
+                self.stack[callee_idx] = Value::Instance(instance);
+                self.push_frame(proto, callee_idx, argc, None)?;
+                Ok(true)
+            }
+
+            // [New] 闭包：槽位 0 是闭包自身，和顶层函数一样
+            Value::CompiledClosure(closure) => {
+                self.push_frame(closure.proto.clone(), callee_idx, argc, Some(closure))?;
+                Ok(true)
+            }
+
+            // 构造函数调用 (Table)：参数被忽略，只执行字段初始化器
+            Value::Table(table_id) => {
+                let init = match self.program.classes.get(&table_id) {
+                    Some(class) => class.init.clone(),
+                    None => {
+                        return Err(RuntimeErrorKind::Internal(format!(
+                            "Definition for class '{}' not found",
+                            self.ctx.resolve_symbol(table_id.symbol())
+                        )));
+                    }
+                };
+                self.stack.truncate(callee_idx + 1);
+                self.push_frame(init, callee_idx, 0, None)?;
+                Ok(true)
+            }
+
+            // [New] 元组变体的构造器
+            Value::VariantCtor(desc, tag) => {
+                let args = self.stack.split_off(callee_idx + 1);
+                self.stack.pop();
+                let variant = ops::construct_variant(self.ctx, desc, tag, args)?;
+                self.stack.push(variant);
+                Ok(false)
+            }
+
+            other => Err(RuntimeErrorKind::NotCallable(
+                other.to_string(&self.ctx.interner),
+            )),
+        }
+    }
+
+    /// receiver.name(args..)
+    fn invoke(
+        &mut self,
+        name: Symbol,
+        argc: usize,
+        cache: usize,
+    ) -> Result<bool, RuntimeErrorKind> {
+        let recv_idx = self.stack.len() - argc - 1;
+
+        if let Value::Instance(instance) = &self.stack[recv_idx] {
+            // 1. 字段优先 (字段里可能存着一个函数)
+            let field_val = instance.get_field(name, None);
+            if let Some(val) = field_val {
+                self.stack[recv_idx] = val;
+                return self.call(argc);
+            }
+
+            // 2. 方法：先查本条指令的内联缓存，未命中再查虚表
+            let class = instance.table_id;
+            let caller = self.frames.last().expect("no active frame").proto.clone();
+            let cached = caller.inline_caches.borrow()[cache].get(class).cloned();
+            let method = cached.or_else(|| {
+                let found = self.find_method(class, name);
+                if let Some(proto) = &found {
+                    caller.inline_caches.borrow_mut()[cache].insert(class, proto.clone());
+                }
+                found
+            });
+
+            // self 已经在槽位 0
+            if let Some(proto) = method {
+                self.push_frame(proto, recv_idx, argc, None)?;
+                return Ok(true);
+            }
+
+            return Err(ops::property_not_found(
+                self.ctx,
+                &self.stack[recv_idx],
+                name,
+                false,
+            ));
+        }
+
+        let receiver = self.stack[recv_idx].clone();
+        self.stack[recv_idx] = self.get_member(receiver, name, None)?;
+        self.call(argc)
+    }
+
+    fn push_frame(
+        &mut self,
+        proto: Rc<FunctionProto>,
+        base: usize,
+        argc: usize,
+        closure: Option<Rc<CompiledClosure>>,
+    ) -> Result<(), RuntimeErrorKind> {
+        if !matches!(proto.kind, FunctionKind::Initializer { .. }) && argc != proto.arity {
+            return Err(RuntimeErrorKind::ArgumentCountMismatch {
+                func_name: self.ctx.resolve_symbol(proto.name).to_string(),
+                expected: proto.arity,
+                found: argc,
+            });
+        }
+
+        if is_call_frame(&proto) {
+            if self.call_depth >= self.max_call_depth {
+                return Err(RuntimeErrorKind::StackOverflow {
+                    limit: self.max_call_depth,
+                });
+            }
+            self.call_depth += 1;
+        }
+
+        // 同模块调用直接复用调用者的全局环境，省一次查表
+        let globals = match self.frames.last() {
+            Some(caller) if caller.proto.file_id == proto.file_id => caller.globals.clone(),
+            _ => self.module_globals(proto.file_id),
+        };
+
+        // 一次性预留局部变量区
+        self.stack.resize(base + proto.max_slots, Value::Nil);
+        self.frames.push(CallFrame {
+            proto,
+            ip: 0,
+            base,
+            globals,
+            closure,
+        });
+        Ok(())
+    }
+
+    /// [New] 捕获值栈上 slot 处的变量 (已经被捕获过就复用同一个 upvalue)
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        let existing = self
+            .open_upvalues
+            .iter()
+            .find(|u| matches!(*u.borrow(), Upvalue::Open(s) if s == slot));
+        if let Some(upvalue) = existing {
+            return upvalue.clone();
+        }
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        self.open_upvalues.push(upvalue.clone());
+        upvalue
+    }
+
+    /// [New] 帧返回前：把指向 base 及以上槽位的 upvalue 关闭 (值搬出值栈)
+    fn close_upvalues(&mut self, base: usize) {
+        let stack = &self.stack;
+        self.open_upvalues.retain(|upvalue| {
+            let slot = match *upvalue.borrow() {
+                Upvalue::Open(slot) if slot >= base => slot,
+                _ => return true,
+            };
+            *upvalue.borrow_mut() = Upvalue::Closed(stack[slot].clone());
+            false
+        });
+    }
+
+    /// 创建模块环境、写入缓存并压入顶层代码的帧
+    /// 调用前栈顶必须已经有槽位 0 的占位值
+    fn load_module(&mut self, file_id: FileId) -> Result<(), RuntimeErrorKind> {
+        let script = self.program.scripts.get(&file_id).cloned().ok_or_else(|| {
+            RuntimeErrorKind::Internal(format!(
+                "Runtime: AST for module {:?} not found (Driver issue)",
+                file_id
+            ))
+        })?;
+
+        let module_env = Rc::new(RefCell::new(Environment::with_enclosing(
+            self.builtins.clone(),
+        )));
+        self.module_cache
+            .insert(file_id, Value::Module(file_id, module_env.clone()));
+
+        let base = self.stack.len() - 1;
+        self.stack.resize(base + script.max_slots, Value::Nil);
+        self.frames.push(CallFrame {
+            proto: script,
+            ip: 0,
+            base,
+            globals: module_env,
+            closure: None,
+        });
+        Ok(())
+    }
+
+    /// [New] 给错误补上位置和调用栈
+    /// 出错时各帧都还没弹出：最内层帧的 ip - 1 是出错的指令，外层帧的 ip - 1 是调用指令
+    fn locate_error(&self, kind: RuntimeErrorKind) -> RuntimeError {
+        let locate = |frame: &CallFrame| {
+            let (id, span) = *frame.proto.chunk.origins.get(frame.ip.checked_sub(1)?)?;
+            let file_id = self.ctx.file_of_node(id).unwrap_or(frame.proto.file_id);
+            Some((file_id, span))
+        };
+        let trace = self
+            .frames
+            .iter()
+            .rev()
+            .filter(|frame| is_call_frame(&frame.proto))
+            .map(|frame| StackFrame {
+                name: self.ctx.resolve_symbol(frame.proto.name).to_string(),
+                location: locate(frame),
+            })
+            .collect();
+        RuntimeError {
+            kind,
+            location: self.frames.last().and_then(locate),
+            trace,
+        }
+    }
+
+    fn module_globals(&self, file_id: FileId) -> Rc<RefCell<Environment>> {
+        match self.module_cache.get(&file_id) {
+            Some(Value::Module(_, env)) => env.clone(),
+            // 模块总是先于其中的函数被加载；兜底用内置环境
+            _ => self.builtins.clone(),
+        }
+    }
+
+    // ==========================================
+    //          Section 3: Helpers
+    // ==========================================
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("value stack underflow")
+    }
+
+    fn peek(&self) -> &Value {
+        self.stack.last().expect("value stack underflow")
+    }
+
+    fn jump(&mut self, target: u32) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.ip = target as usize;
+        }
+    }
+
+    /// 查虚表 (继承来的方法已经合并进去了)
+    fn find_method(&self, table_id: TableId, name: Symbol) -> Option<Rc<FunctionProto>> {
+        self.program
+            .classes
+            .get(&table_id)?
+            .vtable
+            .lookup(name)
+            .cloned()
+    }
+
+    /// 字段/方法访问 (语义同 Interpreter::eval_field_access)
+    fn get_member(
+        &self,
+        target: Value,
+        field: Symbol,
+        index: Option<u32>,
+    ) -> Result<Value, RuntimeErrorKind> {
+        match &target {
+            Value::Instance(instance) => {
+                if let Some(val) = instance.get_field(field, index) {
+                    return Ok(val);
+                }
+                if let Some(proto) = self.find_method(instance.table_id, field) {
+                    return Ok(Value::BoundCompiledMethod(instance.clone(), proto));
+                }
+                Err(ops::property_not_found(self.ctx, &target, field, false))
+            }
+
+            Value::Module(_, env) => env
+                .borrow()
+                .get(field)
+                .ok_or_else(|| ops::property_not_found(self.ctx, &target, field, false)),
+
+            Value::Str(_) | Value::Array(_) | Value::Range(_) => {
+                ops::native_member(target.clone(), self.ctx.resolve_symbol(field))
+            }
+
+            Value::Enum(_) => ops::enum_member(self.ctx, target.clone(), field),
+
+            _ => Err(RuntimeErrorKind::TypeError {
+                expected: "Instance, Module, String, Array or Range".into(),
+                found: format!("{:?}", target),
+            }),
+        }
+    }
+
+    fn cast(&self, val: Value, target: CastTarget) -> Result<Value, RuntimeErrorKind> {
+        if matches!(val, Value::Nil) {
+            return Ok(Value::Nil);
+        }
+
+        let CastTarget::Named(sym) = target else {
+            return Ok(val);
+        };
+        let type_name = self.ctx.resolve_symbol(sym);
+
+        if let Some(res) = ops::cast_primitive(val.clone(), type_name, &self.ctx.interner) {
+            return res;
+        }
+
+        // 对象类型转换 (RTTI)
+        if let Value::Instance(ref instance) = val {
+            if ops::instance_of(self.ctx, instance.table_id, sym) {
+                return Ok(val);
+            }
+            Err(RuntimeErrorKind::InvalidCast {
+                src: self
+                    .ctx
+                    .resolve_symbol(instance.table_id.symbol())
+                    .to_string(),
+                target: type_name.to_string(),
+            })
+        } else {
+            Err(ops::cast_error(&val, type_name, &self.ctx.interner))
+        }
+    }
+
+    /// for 循环的迭代状态 (序列快照, 起始游标)
+    fn iter_prep(iterable: Value) -> Result<(Value, i64), RuntimeErrorKind> {
+        match iterable {
+            Value::Array(arr) => {
+                let snapshot = arr.borrow().clone();
+                Ok((Value::Array(Rc::new(RefCell::new(snapshot))), 0))
+            }
+            Value::Str(s) => {
+                let chars = s.chars().map(Value::Char).collect();
+                Ok((Value::Array(Rc::new(RefCell::new(chars))), 0))
+            }
+            Value::Range(range) => {
+                // 检查端点 (省略端点的范围不能遍历)
+                range.len()?;
+                Ok((Value::Range(range), 0))
+            }
+            other => Err(RuntimeErrorKind::TypeError {
+                expected: "Iterable (Array, Str, Range)".into(),
+                found: format!("{:?}", other),
+            }),
+        }
+    }
+}
+
+/// 是否计入调用深度和调用栈 (模块顶层代码不算)
+fn is_call_frame(proto: &FunctionProto) -> bool {
+    !matches!(proto.kind, FunctionKind::Script)
+}
+
+/// 当前帧闭包的第 idx 个 upvalue
+fn frame_upvalue(frame: &CallFrame, idx: u32) -> Result<Rc<RefCell<Upvalue>>, RuntimeErrorKind> {
+    frame
+        .closure
+        .as_ref()
+        .and_then(|c| c.upvalues.get(idx as usize))
+        .cloned()
+        .ok_or_else(|| RuntimeErrorKind::Internal("Upvalue not found".into()))
+}
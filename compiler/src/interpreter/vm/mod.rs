@@ -0,0 +1,15 @@
+// src/interpreter/vm/mod.rs
+//
+// 字节码后端
+// 1. compiler: 把通过语义检查的 Program 编译成 FunctionProto (常量池 + 指令)
+// 2. machine:  基于值栈和调用帧执行字节码
+//
+// 运算语义与树遍历解释器共用 interpreter::ops，两者输出保持一致。
+
+pub mod chunk;
+pub mod compiler;
+pub mod machine;
+
+pub use chunk::{Chunk, FunctionProto, OpCode};
+pub use compiler::{CompiledProgram, Compiler};
+pub use machine::Vm;
@@ -114,8 +114,13 @@ impl<'a> Lexer<'a> {
             // Dot (.)
             '.' => {
                 if self.match_char('.') {
-                    // case: .. (Range)
-                    self.make_token(TokenKind::DotDot)
+                    if self.match_char('=') {
+                        // case: ..= (Inclusive Range)
+                        self.make_token(TokenKind::DotDotEq)
+                    } else {
+                        // case: .. (Range)
+                        self.make_token(TokenKind::DotDot)
+                    }
                 } else {
                     // case: . (Access)
                     self.make_token(TokenKind::Dot)
@@ -397,8 +402,15 @@ impl<'a> Lexer<'a> {
             if c == '\'' {
                 return self.make_token(TokenKind::ERROR);
             } // 空 char
-            if c == '\\' {
-                self.advance();
+            // [Fix] \u{4e2d} 形式的转义一直读到 '}'
+            if c == '\\' && self.advance() == Some('u') && self.match_char('{') {
+                while let Some(&c) = self.chars.peek() {
+                    if c == '}' || c == '\'' || c == '\n' {
+                        break;
+                    }
+                    self.advance();
+                }
+                self.match_char('}');
             }
         }
         if self.match_char('\'') {
@@ -409,10 +421,10 @@ impl<'a> Lexer<'a> {
     }
 }
 
-fn is_ident_start(c: char) -> bool {
+pub fn is_ident_start(c: char) -> bool {
     c.is_ascii_alphabetic() || c == '_'
 }
 
-fn is_ident_continue(c: char) -> bool {
+pub fn is_ident_continue(c: char) -> bool {
     c.is_ascii_alphanumeric() || c == '_'
 }
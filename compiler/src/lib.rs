@@ -2,6 +2,7 @@
 
 pub mod ast;
 pub mod context;
+pub mod diagnostic;
 pub mod lexer;
 pub mod parser;
 pub mod source;
@@ -13,4 +14,6 @@ pub mod analyzer;
 pub mod interpreter;
 
 pub mod driver;
-pub use driver::Driver;
+pub mod lsp;
+pub mod refactor;
+pub use driver::{Backend, Driver};
@@ -0,0 +1,283 @@
+// src/lsp/completion.rs
+//
+// 补全：按光标前的文本判断场景，再查 SemanticDB / ModuleInfo
+// - `obj.` / `obj.na`：obj 的字段和方法 (包括继承来的)，类型来自 db.receivers
+// - `lib.`：模块导出的类 / 枚举 / 函数 / 全局变量
+// - 类型标注位置 (`x: `、参数、返回类型、`as`)：内置类型、本文件的类、import 的模块
+
+use crate::analyzer::{FunctionSignature, Type};
+use crate::ast::TopLevelItem;
+use crate::context::Context;
+use crate::lexer::is_ident_continue;
+use crate::source::FileId;
+use crate::utils::Symbol;
+
+/// LSP CompletionItemKind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Method = 2,
+    Function = 3,
+    Field = 5,
+    Variable = 6,
+    Class = 7,
+    Module = 9,
+    Keyword = 14,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompletionItem {
+    pub label: String,
+    pub kind: CompletionKind,
+    /// 类型或签名
+    pub detail: String,
+}
+
+/// 内置类型名 (token.rs 里的类型关键字，加上内置的 Range)
+const BUILTIN_TYPES: [&str; 7] = ["int", "float", "bool", "str", "char", "any", "Range"];
+
+pub fn complete(ctx: &Context, file_id: FileId, offset: usize) -> Vec<CompletionItem> {
+    let src = &ctx.source_manager.get_file(file_id).src;
+    let offset = offset.min(src.len());
+    // 光标前正在输入的标识符 (客户端按它过滤，这里只用来定位)
+    let word_start = src[..offset]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_ident_continue(*c))
+        .last()
+        .map_or(offset, |(i, _)| i);
+    let before = &src[..word_start];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+    let mut items = match before.strip_suffix('.') {
+        Some(qualified) => {
+            let dot = word_start - 1;
+            let qualifier_start = qualified
+                .char_indices()
+                .rev()
+                .take_while(|(_, c)| is_ident_continue(*c))
+                .last()
+                .map_or(dot, |(i, _)| i);
+            let in_type = is_type_position(&src[line_start.min(qualifier_start)..qualifier_start]);
+            match receiver_at(ctx, file_id, dot) {
+                Some(ty) if !in_type => member_items(ctx, &ty),
+                // 类型标注里的 `lib.` 不是表达式，没有 receiver 记录：按 import 名字找模块
+                _ => imports(ctx, file_id)
+                    .into_iter()
+                    .find(|(name, _)| ctx.resolve_symbol(*name) == &src[qualifier_start..dot])
+                    .map(|(_, module)| {
+                        let mut items = member_items(ctx, &Type::Module(module));
+                        if in_type {
+                            items.retain(|item| item.kind == CompletionKind::Class);
+                        }
+                        items
+                    })
+                    .unwrap_or_default(),
+            }
+        }
+        None if is_type_position(&src[line_start..word_start]) => type_items(ctx, file_id),
+        None => Vec::new(),
+    };
+
+    items.sort_by(|a, b| a.label.cmp(&b.label));
+    items.dedup_by(|a, b| a.label == b.label);
+    items
+}
+
+/// 光标前的一行是否处在类型标注的位置
+fn is_type_position(line: &str) -> bool {
+    let head = line.trim_start();
+    let before = line.trim_end();
+    if before.ends_with(" as") {
+        return true;
+    }
+    // fn f(a: |  /  fn f() |  /  class Dog : |
+    if head.starts_with("fn ") || head.starts_with("class ") {
+        return before.ends_with(':') || before.ends_with(')') || before.ends_with(',');
+    }
+    // name: |  (变量 / 字段声明；排除调用里的命名参数 `f(width: `)
+    // [New] 也可能带 let / var / const 关键字
+    before
+        .strip_suffix(':')
+        .map(|decl| {
+            let decl = decl.trim();
+            ["let ", "var ", "const "]
+                .iter()
+                .find_map(|kw| decl.strip_prefix(kw))
+                .unwrap_or(decl)
+                .trim()
+        })
+        .is_some_and(|name| !name.is_empty() && name.chars().all(is_ident_continue))
+}
+
+/// 点号左边表达式的类型：包含这个点号的最内层成员访问节点
+fn receiver_at(ctx: &Context, file_id: FileId, dot: usize) -> Option<Type> {
+    let db = &ctx.db;
+    db.receivers
+        .keys()
+        .filter_map(|id| Some((id, db.node_spans.get(id)?)))
+        .filter(|(_, span)| span.start <= dot && dot < span.end)
+        .filter(|(id, _)| ctx.file_of_node(**id) == Some(file_id))
+        .min_by_key(|(id, span)| (span.len(), **id))
+        .and_then(|(id, _)| db.receivers.get(id).cloned())
+}
+
+/// 文件 import 的模块：(名字, 模块文件)
+fn imports(ctx: &Context, file_id: FileId) -> Vec<(Symbol, FileId)> {
+    let Some(Ok(program)) = ctx.parsed.get(&file_id).map(|p| &p.program) else {
+        return Vec::new();
+    };
+    program
+        .definitions
+        .iter()
+        .filter_map(|item| match item {
+            TopLevelItem::Use(stmt) => {
+                let name = stmt.data.alias.or(stmt.data.path.last().copied())?;
+                match ctx.db.type_map.get(&stmt.id) {
+                    Some(Type::Module(module)) => Some((name, *module)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn member_items(ctx: &Context, ty: &Type) -> Vec<CompletionItem> {
+    let item = |name: Symbol, kind, detail: String| CompletionItem {
+        label: ctx.resolve_symbol(name).to_string(),
+        kind,
+        detail,
+    };
+    match ty {
+        // resolve_hierarchy 之后 TableInfo 里已经合并了父类的字段和方法
+        Type::Table(id) | Type::GenericInstance { base: id, .. } => {
+            let Some(info) = ctx.table_info(*id) else {
+                return Vec::new();
+            };
+            let fields = info.fields.iter().map(|(name, field)| {
+                item(
+                    *name,
+                    CompletionKind::Field,
+                    field.ty.display(ctx).to_string(),
+                )
+            });
+            let methods = info.methods.iter().map(|(name, method)| {
+                item(
+                    *name,
+                    CompletionKind::Method,
+                    signature(ctx, &method.signature),
+                )
+            });
+            fields.chain(methods).collect()
+        }
+        Type::Module(file_id) => {
+            let Some(module) = ctx
+                .source_manager
+                .get_file_path(*file_id)
+                .and_then(|path| ctx.modules.get(path))
+            else {
+                return Vec::new();
+            };
+            let tables = module
+                .tables
+                .iter()
+                .map(|(id, info)| item(id.1, CompletionKind::Class, table_kind(info.is_trait)));
+            let enums = module
+                .enums
+                .keys()
+                .map(|id| item(id.1, CompletionKind::Class, "enum".to_string()));
+            let functions = module.functions.iter().map(|(name, func)| {
+                item(
+                    *name,
+                    CompletionKind::Function,
+                    signature(ctx, &func.signature),
+                )
+            });
+            let globals = module.globals.iter().map(|(name, global)| {
+                item(
+                    *name,
+                    CompletionKind::Variable,
+                    global.ty.display(ctx).to_string(),
+                )
+            });
+            tables
+                .chain(enums)
+                .chain(functions)
+                .chain(globals)
+                .collect()
+        }
+        // 内置成员 (见 Analyzer::check_builtin_member_access)
+        Type::Array(inner) => vec![
+            builtin_method("len", "() -> int".to_string()),
+            builtin_method("push", format!("({}) -> ()", inner.display(ctx))),
+        ],
+        Type::Str => vec![builtin_method("len", "() -> int".to_string())],
+        Type::Range(inner) => vec![
+            builtin_method("len", "() -> int".to_string()),
+            builtin_method("contains", format!("({}) -> bool", inner.display(ctx))),
+            builtin_method("rev", format!("() -> {}", ty.display(ctx))),
+            builtin_method("step", format!("(int) -> {}", ty.display(ctx))),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+fn builtin_method(name: &str, detail: String) -> CompletionItem {
+    CompletionItem {
+        label: name.to_string(),
+        kind: CompletionKind::Method,
+        detail,
+    }
+}
+
+fn type_items(ctx: &Context, file_id: FileId) -> Vec<CompletionItem> {
+    let mut items: Vec<CompletionItem> = BUILTIN_TYPES
+        .iter()
+        .map(|name| CompletionItem {
+            label: name.to_string(),
+            kind: CompletionKind::Keyword,
+            detail: "builtin type".to_string(),
+        })
+        .collect();
+    if let Some(module) = ctx
+        .source_manager
+        .get_file_path(file_id)
+        .and_then(|path| ctx.modules.get(path))
+    {
+        items.extend(module.tables.iter().map(|(id, info)| CompletionItem {
+            label: ctx.resolve_symbol(id.1).to_string(),
+            kind: CompletionKind::Class,
+            detail: table_kind(info.is_trait),
+        }));
+        items.extend(module.enums.keys().map(|id| CompletionItem {
+            label: ctx.resolve_symbol(id.1).to_string(),
+            kind: CompletionKind::Class,
+            detail: "enum".to_string(),
+        }));
+    }
+    items.extend(
+        imports(ctx, file_id)
+            .into_iter()
+            .map(|(name, _)| CompletionItem {
+                label: ctx.resolve_symbol(name).to_string(),
+                kind: CompletionKind::Module,
+                detail: "module".to_string(),
+            }),
+    );
+    items
+}
+
+/// [New] 类和 trait 都按 Class 补全，detail 里区分
+fn table_kind(is_trait: bool) -> String {
+    if is_trait { "trait" } else { "class" }.to_string()
+}
+
+/// `(a: int, b: str) -> bool`
+fn signature(ctx: &Context, sig: &FunctionSignature) -> String {
+    let params: Vec<String> = sig
+        .params
+        .iter()
+        .map(|(name, ty)| format!("{}: {}", ctx.resolve_symbol(*name), ty.display(ctx)))
+        .collect();
+    format!("({}) -> {}", params.join(", "), sig.ret.display(ctx))
+}
@@ -0,0 +1,31 @@
+// src/lsp/mod.rs
+//
+// Loom 语言服务器 (`loom lsp`)，通过 stdio 和编辑器通信
+//
+// 功能都建立在 Analyzer 产出的 SemanticDB 上：
+// - 诊断：解析错误 + 语义错误 (包括被 import 的文件)
+// - Hover：type_map 里光标下表达式的类型；光标在声明的名字上时用 bindings 里绑定的类型
+// - Goto Definition：def_map 里引用指向的定义位置
+// - Find References / Rename：def_map 的反向索引 (见 crate::refactor)
+// - Completion：成员 / 模块导出 / 类型名 (见 completion.rs)
+//
+// 所有文档共用一个 Context：修改一个文件只重新检查它和依赖它的打开文档，
+// 没变的文件复用缓存的 AST (编辑器内容优先于磁盘内容)
+
+mod completion;
+mod server;
+mod transport;
+mod workspace;
+
+pub use server::Server;
+
+use std::io;
+use std::path::PathBuf;
+
+/// 在 stdin/stdout 上运行服务器，返回进程退出码
+pub fn run_stdio(root_dir: PathBuf) -> io::Result<i32> {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut server = Server::new(io::stdout().lock(), root_dir);
+    server.run(&mut input)
+}
@@ -0,0 +1,410 @@
+// src/lsp/server.rs
+
+use super::transport::{Incoming, read_message, write_message};
+use super::workspace::{Workspace, to_offset, to_position};
+use crate::diagnostic::Severity;
+use crate::source::{FileId, SourceFile};
+use crate::utils::{Json, Span};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// JSON-RPC 错误码
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// LSP 3.17 RequestFailed：请求合法但无法完成 (例如改名冲突)
+const REQUEST_FAILED: i64 = -32803;
+
+pub struct Server<W: Write> {
+    out: W,
+    workspace: Workspace,
+    /// 上一轮发布过非空诊断的文件
+    published: HashSet<PathBuf>,
+    shutdown_requested: bool,
+}
+
+impl<W: Write> Server<W> {
+    pub fn new(out: W, root_dir: PathBuf) -> Self {
+        Self {
+            out,
+            workspace: Workspace::new(root_dir),
+            published: HashSet::new(),
+            shutdown_requested: false,
+        }
+    }
+
+    /// 消息循环；收到 exit 或输入结束时返回，返回值是进程退出码
+    pub fn run(&mut self, input: &mut impl BufRead) -> io::Result<i32> {
+        while let Some(incoming) = read_message(input)? {
+            let message = match incoming {
+                Incoming::Message(message) => message,
+                // [Fix] 一条坏消息不结束会话：按 JSON-RPC 回 Parse error (id 为 null)，继续读下一条
+                Incoming::Malformed(reason) => {
+                    let response = error_response(Json::Null, PARSE_ERROR, reason);
+                    write_message(&mut self.out, &response)?;
+                    continue;
+                }
+            };
+            let method = message.get("method").as_str().unwrap_or("").to_string();
+            if method == "exit" {
+                return Ok(if self.shutdown_requested { 0 } else { 1 });
+            }
+            let params = message.get("params");
+            let id = message.get("id");
+
+            if id.is_null() {
+                self.handle_notification(&method, params)?;
+            } else if !method.is_empty() {
+                let response = match self.handle_request(&method, params) {
+                    Ok(result) => Json::object([
+                        ("jsonrpc", "2.0".into()),
+                        ("id", id.clone()),
+                        ("result", result),
+                    ]),
+                    Err((code, msg)) => error_response(id.clone(), code, msg),
+                };
+                write_message(&mut self.out, &response)?;
+            }
+            // 带 id 但没有 method 的是客户端对我们请求的响应，目前不发请求，直接忽略
+        }
+        Ok(if self.shutdown_requested { 0 } else { 1 })
+    }
+
+    fn handle_request(&mut self, method: &str, params: &Json) -> Result<Json, (i64, String)> {
+        match method {
+            "initialize" => {
+                if let Some(root) = params
+                    .get("rootUri")
+                    .as_str()
+                    .and_then(uri_to_path)
+                    .or_else(|| params.get("rootPath").as_str().map(PathBuf::from))
+                {
+                    self.workspace.set_root(root);
+                }
+                Ok(Json::object([
+                    (
+                        "capabilities",
+                        Json::object([
+                            // 1 = Full：每次修改发送完整内容
+                            ("textDocumentSync", Json::Number(1.0)),
+                            ("hoverProvider", true.into()),
+                            ("definitionProvider", true.into()),
+                            ("referencesProvider", true.into()),
+                            ("renameProvider", true.into()),
+                            (
+                                "completionProvider",
+                                Json::object([("triggerCharacters", vec![".".into()].into())]),
+                            ),
+                        ]),
+                    ),
+                    (
+                        "serverInfo",
+                        Json::object([
+                            ("name", "loom".into()),
+                            ("version", env!("CARGO_PKG_VERSION").into()),
+                        ]),
+                    ),
+                ]))
+            }
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Ok(Json::Null)
+            }
+            "textDocument/hover" => {
+                let (file_id, offset) = self.locate(params)?;
+                let ws = &self.workspace;
+                Ok(match ws.hover(file_id, offset) {
+                    Some((ty, span)) => Json::object([
+                        (
+                            "contents",
+                            Json::object([
+                                ("kind", "markdown".into()),
+                                ("value", format!("```loom\n{}\n```", ty).into()),
+                            ]),
+                        ),
+                        ("range", range_json(ws.file(file_id), span)),
+                    ]),
+                    None => Json::Null,
+                })
+            }
+            "textDocument/definition" => {
+                let (file_id, offset) = self.locate(params)?;
+                let ws = &self.workspace;
+                Ok(ws
+                    .definition(file_id, offset)
+                    .and_then(|loc| location_json(ws, loc.file_id, loc.span))
+                    .unwrap_or(Json::Null))
+            }
+            "textDocument/completion" => {
+                let (file_id, offset) = self.locate(params)?;
+                let items = self
+                    .workspace
+                    .completion(file_id, offset)
+                    .into_iter()
+                    .map(|item| {
+                        Json::object([
+                            ("label", item.label.into()),
+                            ("kind", Json::Number(item.kind as u8 as f64)),
+                            ("detail", item.detail.into()),
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                Ok(items.into())
+            }
+            "textDocument/references" => {
+                self.workspace.index_project();
+                let (file_id, offset) = self.locate(params)?;
+                let include_declaration = params
+                    .get("context")
+                    .get("includeDeclaration")
+                    .as_bool()
+                    .unwrap_or(true);
+                let ws = &self.workspace;
+                let locations = ws
+                    .references(file_id, offset, include_declaration)
+                    .into_iter()
+                    .filter_map(|loc| location_json(ws, loc.file_id, loc.span))
+                    .collect::<Vec<_>>();
+                Ok(locations.into())
+            }
+            "textDocument/rename" => {
+                self.workspace.index_project();
+                let (file_id, offset) = self.locate(params)?;
+                let new_name = params
+                    .get("newName")
+                    .as_str()
+                    .ok_or((INVALID_PARAMS, "missing newName".to_string()))?;
+                let ws = &self.workspace;
+                let edits = ws
+                    .rename(file_id, offset, new_name)
+                    .map_err(|msg| (REQUEST_FAILED, msg))?;
+
+                // WorkspaceEdit.changes: uri -> TextEdit[] (按文件第一次出现的顺序)
+                let mut changes: Vec<(String, Json)> = Vec::new();
+                for edit in edits {
+                    let Some(path) = ws.driver.ctx.source_manager.get_file_path(edit.file_id)
+                    else {
+                        continue;
+                    };
+                    let uri = path_to_uri(path);
+                    let text_edit = Json::object([
+                        ("range", range_json(ws.file(edit.file_id), edit.span)),
+                        ("newText", edit.new_text.into()),
+                    ]);
+                    match changes.iter_mut().find(|(u, _)| *u == uri) {
+                        Some((_, Json::Array(list))) => list.push(text_edit),
+                        _ => changes.push((uri, vec![text_edit].into())),
+                    }
+                }
+                Ok(Json::object([("changes", Json::Object(changes))]))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("method not found: {}", method))),
+        }
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &Json) -> io::Result<()> {
+        let doc = params.get("textDocument");
+        let path = doc.get("uri").as_str().and_then(uri_to_path);
+        match (method, path) {
+            ("textDocument/didOpen", Some(path)) => {
+                let text = doc.get("text").as_str().unwrap_or("").to_string();
+                self.workspace.update_document(path, text);
+            }
+            ("textDocument/didChange", Some(path)) => {
+                // Full 同步：最后一个 change 就是完整内容
+                let changes = params.get("contentChanges").as_array().unwrap_or(&[]);
+                if let Some(text) = changes.last().and_then(|c| c.get("text").as_str()) {
+                    self.workspace.update_document(path, text.to_string());
+                }
+            }
+            ("textDocument/didClose", Some(path)) => self.workspace.close_document(&path),
+            ("workspace/didChangeWatchedFiles", _) => {
+                let changes = params.get("changes").as_array().unwrap_or(&[]);
+                for change in changes {
+                    if let Some(path) = change.get("uri").as_str().and_then(uri_to_path) {
+                        self.workspace.reload_from_disk(&path);
+                    }
+                }
+            }
+            // didSave：打开的文档以编辑器内容为准，保存不改变分析结果
+            _ => return Ok(()),
+        }
+        self.workspace.check();
+        self.publish_diagnostics()
+    }
+
+    fn publish_diagnostics(&mut self) -> io::Result<()> {
+        // 同一个被 import 的文件可能从多个入口文档报出相同的错误，按 (区间, 消息) 去重
+        let mut by_file: HashMap<PathBuf, Vec<Json>> = HashMap::new();
+        let mut seen = HashSet::new();
+        let ws = &self.workspace;
+        for errors in ws.errors.values() {
+            for diag in errors {
+                let Some(primary) = &diag.primary else {
+                    continue;
+                };
+                let Some(path) = ws.driver.ctx.source_manager.get_file_path(primary.file_id) else {
+                    continue;
+                };
+                if !seen.insert((path.clone(), primary.span, diag.message.clone())) {
+                    continue;
+                }
+                // help 接在消息后面，编辑器里悬停可见 (note 是命令行相关的说明，不发)
+                let mut message = diag.message.clone();
+                if let Some(help) = &diag.help {
+                    message.push_str(&format!("\nhelp: {}", help));
+                }
+                let related: Vec<Json> = diag
+                    .secondary
+                    .iter()
+                    .filter_map(|label| {
+                        Some(Json::object([
+                            ("location", location_json(ws, label.file_id, label.span)?),
+                            ("message", label.message.clone().into()),
+                        ]))
+                    })
+                    .collect();
+                let severity = match diag.severity {
+                    Severity::Error => 1.0,
+                    Severity::Warning => 2.0,
+                };
+                by_file.entry(path.clone()).or_default().push(Json::object([
+                    ("range", range_json(ws.file(primary.file_id), primary.span)),
+                    ("severity", Json::Number(severity)),
+                    ("code", diag.code.into()),
+                    ("source", "loom".into()),
+                    ("message", message.into()),
+                    ("relatedInformation", related.into()),
+                ]));
+            }
+        }
+
+        // 打开的文档总是发布 (可能是空列表)；上一轮报过错、这一轮干净的文件发空列表清掉
+        for path in ws.errors.keys().chain(&self.published) {
+            by_file.entry(path.clone()).or_default();
+        }
+
+        self.published.clear();
+        for (path, diagnostics) in by_file {
+            if !diagnostics.is_empty() {
+                self.published.insert(path.clone());
+            }
+            let notification = Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("method", "textDocument/publishDiagnostics".into()),
+                (
+                    "params",
+                    Json::object([
+                        ("uri", path_to_uri(&path).into()),
+                        ("diagnostics", diagnostics.into()),
+                    ]),
+                ),
+            ]);
+            write_message(&mut self.out, &notification)?;
+        }
+        Ok(())
+    }
+
+    /// 解析 TextDocumentPositionParams：文件和字节偏移
+    fn locate(&self, params: &Json) -> Result<(FileId, usize), (i64, String)> {
+        let path = params
+            .get("textDocument")
+            .get("uri")
+            .as_str()
+            .and_then(uri_to_path)
+            .ok_or((INVALID_PARAMS, "missing textDocument.uri".to_string()))?;
+        let position = params.get("position");
+        let line = position.get("line").as_u64().unwrap_or(0) as usize;
+        let character = position.get("character").as_u64().unwrap_or(0) as usize;
+
+        // 没打开的文件 (例如跳转过去的库) 只要被某个文档 import 过也能查询
+        let file_id = self.workspace.file_id(&path).ok_or((
+            INVALID_PARAMS,
+            format!("document not analyzed: {}", path.display()),
+        ))?;
+        let offset = to_offset(self.workspace.file(file_id), line, character);
+        Ok((file_id, offset))
+    }
+}
+
+fn position_json(file: &SourceFile, offset: usize) -> Json {
+    let (line, character) = to_position(file, offset);
+    Json::object([("line", line.into()), ("character", character.into())])
+}
+
+fn range_json(file: &SourceFile, span: Span) -> Json {
+    Json::object([
+        ("start", position_json(file, span.start)),
+        ("end", position_json(file, span.end)),
+    ])
+}
+
+fn location_json(ws: &Workspace, file_id: FileId, span: Span) -> Option<Json> {
+    let path = ws.driver.ctx.source_manager.get_file_path(file_id)?;
+    Some(Json::object([
+        ("uri", path_to_uri(path).into()),
+        ("range", range_json(ws.file(file_id), span)),
+    ]))
+}
+
+/// file:// URI -> 绝对路径 (和 SourceManager 一样做 canonicalize，文件不存在时保持原样)
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let raw = uri.strip_prefix("file://")?;
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(b) = raw
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+        {
+            decoded.push(b);
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    let mut path = String::from_utf8(decoded).ok()?;
+    // Windows: file:///C:/x -> C:/x
+    if path.len() > 2 && path.as_bytes()[2] == b':' {
+        path.remove(0);
+    }
+    let path = PathBuf::from(path);
+    Some(path.canonicalize().unwrap_or(path))
+}
+
+/// 绝对路径 -> file:// URI
+pub fn path_to_uri(path: &Path) -> String {
+    let text = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !text.starts_with('/') {
+        uri.push('/');
+    }
+    for b in text.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' | b':' => {
+                uri.push(b as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    uri
+}
+
+/// JSON-RPC 错误响应
+fn error_response(id: Json, code: i64, message: String) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id),
+        (
+            "error",
+            Json::object([
+                ("code", Json::Number(code as f64)),
+                ("message", message.into()),
+            ]),
+        ),
+    ])
+}
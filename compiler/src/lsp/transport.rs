@@ -0,0 +1,55 @@
+// src/lsp/transport.rs
+//
+// LSP 的 stdio 传输层：每条消息是 "Content-Length: N\r\n\r\n" + N 字节 JSON
+
+use crate::utils::Json;
+use std::io::{self, BufRead, Write};
+
+/// [New] 读到的一条消息
+pub enum Incoming {
+    Message(Json),
+    /// 消息体不是合法的 JSON (或不是 UTF-8)：回一个 Parse error，会话继续
+    Malformed(String),
+}
+
+/// 读取一条消息；输入结束时返回 Ok(None)，只有真正的 I/O 错误才返回 Err
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Incoming>> {
+    let mut content_length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            // 空行：头部结束 (容忍消息之间多余的空行)
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            content_length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0; content_length.unwrap_or(0)];
+    input.read_exact(&mut body)?;
+    let message = match String::from_utf8(body) {
+        Ok(text) => match Json::parse(&text) {
+            Ok(json) => Incoming::Message(json),
+            Err(e) => Incoming::Malformed(e),
+        },
+        Err(e) => Incoming::Malformed(e.to_string()),
+    };
+    Ok(Some(message))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
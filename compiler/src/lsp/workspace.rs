@@ -0,0 +1,214 @@
+// src/lsp/workspace.rs
+//
+// 编辑器会话的增量分析状态，以及基于 SemanticDB 的查询
+
+use super::completion::{self, CompletionItem};
+use crate::analyzer::Location;
+use crate::diagnostic::Diagnostic;
+use crate::driver::Driver;
+use crate::refactor::{self, TextEdit};
+use crate::source::{FileId, SourceFile};
+use crate::utils::Span;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// 一个编辑器会话的分析状态
+/// 所有打开的文档共用一个 Driver (Context)：解析结果、模块信息和 SemanticDB 在多次分析之间保留，
+/// 文档修改后只重新检查被作废的模块 (自身 + 依赖它的模块)
+pub struct Workspace {
+    pub driver: Driver,
+    root_dir: PathBuf,
+    /// 编辑器里打开的文档：绝对路径 -> 当前内容 (可能未保存)
+    documents: HashMap<PathBuf, String>,
+    /// 每个打开的文档作为入口检查出的错误和警告
+    pub errors: HashMap<PathBuf, Vec<Diagnostic>>,
+    /// 需要重新检查的打开文档
+    dirty: HashSet<PathBuf>,
+}
+
+/// 作废节点超过这个数 (且超过总数一半) 时整体重建 Context，回收 SemanticDB 的空间
+const COMPACT_THRESHOLD: usize = 1 << 20;
+
+impl Workspace {
+    pub fn new(root_dir: PathBuf) -> Self {
+        Self {
+            driver: Driver::new(root_dir.clone()),
+            root_dir,
+            documents: HashMap::new(),
+            errors: HashMap::new(),
+            dirty: HashSet::new(),
+        }
+    }
+
+    pub fn set_root(&mut self, root_dir: PathBuf) {
+        self.root_dir = root_dir;
+        self.reset();
+    }
+
+    pub fn is_open(&self, path: &Path) -> bool {
+        self.documents.contains_key(path)
+    }
+
+    /// 打开或修改文档
+    pub fn update_document(&mut self, path: PathBuf, text: String) {
+        self.documents.insert(path.clone(), text.clone());
+        self.set_source(&path, text);
+        self.dirty.insert(path);
+    }
+
+    /// 关闭文档：恢复成磁盘上的内容 (未保存的修改被丢弃)
+    pub fn close_document(&mut self, path: &Path) {
+        self.documents.remove(path);
+        self.errors.remove(path);
+        self.dirty.remove(path);
+        self.reload_from_disk(path);
+    }
+
+    /// 磁盘上的文件变了 (打开的文档以编辑器内容为准，不受影响)
+    pub fn reload_from_disk(&mut self, path: &Path) {
+        if self.is_open(path) || self.driver.ctx.source_manager.find_file(path).is_none() {
+            return;
+        }
+        if let Ok(text) = std::fs::read_to_string(path) {
+            self.set_source(path, text);
+        }
+    }
+
+    /// 替换文件内容，被作废的打开文档标记为需要重新检查
+    fn set_source(&mut self, path: &Path, text: String) {
+        let ctx = &mut self.driver.ctx;
+        let invalidated = match ctx.source_manager.find_file(path) {
+            Some(file_id) => ctx.update_file(file_id, text),
+            None => {
+                ctx.source_manager.open_file(path.to_path_buf(), text);
+                vec![path.to_path_buf()]
+            }
+        };
+        for module in invalidated {
+            if self.documents.contains_key(&module) {
+                self.dirty.insert(module);
+            }
+        }
+    }
+
+    /// 丢弃所有缓存，从头开始 (所有打开的文档都要重新检查)
+    fn reset(&mut self) {
+        self.driver = Driver::new(self.root_dir.clone());
+        for (path, text) in &self.documents {
+            self.driver
+                .ctx
+                .source_manager
+                .open_file(path.clone(), text.clone());
+        }
+        self.dirty = self.documents.keys().cloned().collect();
+    }
+
+    /// 重新检查所有被标记的文档
+    pub fn check(&mut self) {
+        let ctx = &self.driver.ctx;
+        if ctx.retired_nodes > COMPACT_THRESHOLD
+            && ctx.retired_nodes > ctx.next_node_id as usize / 2
+        {
+            self.reset();
+        }
+
+        let dirty: Vec<PathBuf> = self.dirty.drain().collect();
+        for path in dirty {
+            let Some(file_id) = self.driver.ctx.source_manager.find_file(&path) else {
+                continue;
+            };
+            let diagnostics = match self.driver.analyze(file_id, path.clone()) {
+                Ok(_) => std::mem::take(&mut self.driver.warnings),
+                Err(errors) => errors,
+            };
+            self.errors.insert(path, diagnostics);
+        }
+    }
+
+    /// [New] 把项目里没打开的文件也分析一遍 (Find References / Rename 前调用)
+    /// 引用可能在没打开的文件里；已经分析过且没被作废的文件直接跳过，错误不发布
+    pub fn index_project(&mut self) {
+        self.driver.analyze_project();
+    }
+
+    pub fn file_id(&self, path: &Path) -> Option<FileId> {
+        self.driver.ctx.source_manager.find_file(path)
+    }
+
+    pub fn file(&self, file_id: FileId) -> &SourceFile {
+        self.driver.ctx.source_manager.get_file(file_id)
+    }
+
+    /// Hover：光标下表达式的类型
+    pub fn hover(&self, file_id: FileId, offset: usize) -> Option<(String, Span)> {
+        let ctx = &self.driver.ctx;
+        // [Fix] 声明处的名字显示绑定的类型 (否则命中的是整条声明语句)
+        if let Some((ty, span)) = refactor::binding_at(ctx, file_id, offset) {
+            return Some((ty.display(ctx).to_string(), span));
+        }
+        let (id, span) = refactor::node_at(ctx, file_id, offset, |_| true)?;
+        let ty = ctx.db.type_map.get(&id)?;
+        Some((ty.display(ctx).to_string(), span))
+    }
+
+    /// Goto Definition：光标下引用的定义位置
+    pub fn definition(&self, file_id: FileId, offset: usize) -> Option<Location> {
+        let ctx = &self.driver.ctx;
+        let (id, _) =
+            refactor::node_at(ctx, file_id, offset, |id| ctx.db.def_map.contains_key(&id))?;
+        ctx.db.def_map.get(&id).copied()
+    }
+
+    /// [New] 补全：光标处可用的成员 / 模块导出 / 类型名
+    pub fn completion(&self, file_id: FileId, offset: usize) -> Vec<CompletionItem> {
+        completion::complete(&self.driver.ctx, file_id, offset)
+    }
+
+    /// [New] Find References：光标下符号的所有引用 (跨文件)
+    pub fn references(
+        &self,
+        file_id: FileId,
+        offset: usize,
+        include_declaration: bool,
+    ) -> Vec<Location> {
+        refactor::find_references(&self.driver.ctx, file_id, offset, include_declaration)
+    }
+
+    /// [New] Rename：光标下符号改名需要的所有修改
+    pub fn rename(
+        &self,
+        file_id: FileId,
+        offset: usize,
+        new_name: &str,
+    ) -> Result<Vec<TextEdit>, String> {
+        refactor::rename(&self.driver.ctx, file_id, offset, new_name)
+    }
+}
+
+/// LSP 位置 (0-based 行, UTF-16 列) -> 字节偏移，越界时夹到行尾
+pub fn to_offset(file: &SourceFile, line: usize, character: usize) -> usize {
+    let Some(&line_start) = file.line_starts.get(line) else {
+        return file.src.len();
+    };
+    let line_text = file.src[line_start..].split('\n').next().unwrap_or("");
+    let mut units = 0;
+    for (i, c) in line_text.char_indices() {
+        if units >= character {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    line_start + line_text.len()
+}
+
+/// 字节偏移 -> LSP 位置 (0-based 行, UTF-16 列)
+pub fn to_position(file: &SourceFile, offset: usize) -> (usize, usize) {
+    let offset = offset.min(file.src.len());
+    let line = file.lookup_line(offset) - 1;
+    let line_start = file.line_starts[line];
+    let character = file.src[line_start..offset]
+        .chars()
+        .map(char::len_utf16)
+        .sum();
+    (line, character)
+}
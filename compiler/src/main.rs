@@ -1,6 +1,9 @@
 // src/main.rs
 
-use loom::Driver;
+use loom::analyzer::{Lint, LintConfig, LintLevel};
+use loom::diagnostic::MessageFormat;
+use loom::interpreter::DEFAULT_MAX_CALL_DEPTH;
+use loom::{Backend, Driver};
 use std::env;
 use std::path::PathBuf;
 
@@ -8,13 +11,106 @@ fn main() {
     // 1. 获取命令行参数
     let args: Vec<String> = env::args().collect();
 
-    if args.len() < 2 {
-        println!("Usage: loom <script.lm>");
-        return;
+    // [New] loom lsp: 以 stdio 语言服务器模式运行
+    if args.get(1).map(String::as_str) == Some("lsp") {
+        let root_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        // 分析器同样是递归的，沿用大栈线程
+        let code = std::thread::Builder::new()
+            .name("loom-lsp".into())
+            .stack_size(Driver::stack_size_for(
+                Backend::TreeWalk,
+                DEFAULT_MAX_CALL_DEPTH,
+            ))
+            .spawn(move || loom::lsp::run_stdio(root_dir))
+            .unwrap_or_else(|e| spawn_failed(e))
+            .join();
+        match code {
+            Ok(Ok(code)) => std::process::exit(code),
+            Ok(Err(e)) => {
+                eprintln!("Error: language server I/O failed: {}", e);
+                std::process::exit(1);
+            }
+            Err(_) => std::process::exit(101),
+        }
+    }
+
+    // [New] loom rename <file>:<line>:<col> <new_name>: 跨模块重命名符号 (直接修改文件)
+    if args.get(1).map(String::as_str) == Some("rename") {
+        std::process::exit(run_rename(&args[2..]));
+    }
+
+    // --vm: 使用字节码虚拟机执行
+    // --max-call-depth=N: 最大调用深度 (默认 10000)
+    // --message-format=human|json: 诊断输出格式 (json 每行一条，输出到 stderr)
+    // --allow=L / --warn=L / --deny=L: 设置 lint 级别 (逗号分隔)，--deny-warnings: 警告视为错误
+    let mut backend = Backend::TreeWalk;
+    let mut max_call_depth = None;
+    let mut message_format = MessageFormat::Human;
+    let mut lints = LintConfig::default();
+    let mut filename = None;
+    for arg in &args[1..] {
+        let level_arg = [
+            ("--allow=", LintLevel::Allow),
+            ("--warn=", LintLevel::Warn),
+            ("--deny=", LintLevel::Deny),
+        ]
+        .into_iter()
+        .find_map(|(prefix, level)| Some((arg.strip_prefix(prefix)?, level)));
+        if let Some((names, level)) = level_arg {
+            for name in names.split(',') {
+                match Lint::from_name(name) {
+                    Some(lint) => lints.set(lint, level),
+                    None => {
+                        let known: Vec<&str> = Lint::ALL.iter().map(|l| l.name()).collect();
+                        eprintln!(
+                            "Error: unknown lint '{}' (known lints: {})",
+                            name,
+                            known.join(", ")
+                        );
+                        std::process::exit(1);
+                    }
+                }
+            }
+            continue;
+        }
+        if let Some(format) = arg.strip_prefix("--message-format=") {
+            message_format = match format {
+                "human" => MessageFormat::Human,
+                "json" => MessageFormat::Json,
+                _ => {
+                    eprintln!("Error: invalid value for --message-format: '{}'", format);
+                    std::process::exit(1);
+                }
+            };
+            continue;
+        }
+        if let Some(n) = arg.strip_prefix("--max-call-depth=") {
+            match n.parse::<usize>() {
+                Ok(n) if n > 0 => max_call_depth = Some(n),
+                _ => {
+                    eprintln!("Error: invalid value for --max-call-depth: '{}'", n);
+                    std::process::exit(1);
+                }
+            }
+            continue;
+        }
+        match arg.as_str() {
+            "--vm" => backend = Backend::Bytecode,
+            "--deny-warnings" => lints.deny_warnings = true,
+            _ => filename = Some(arg.clone()),
+        }
     }
 
-    let filename = &args[1];
-    let path = PathBuf::from(filename);
+    let Some(filename) = filename else {
+        println!(
+            "Usage: loom [--vm] [--max-call-depth=N] [--message-format=human|json] <script.lm>"
+        );
+        println!("            [--allow=<lint>] [--warn=<lint>] [--deny=<lint>] [--deny-warnings]");
+        println!("       loom rename <file>:<line>:<col> <new_name>");
+        println!("       loom lsp");
+        return;
+    };
+    let path = PathBuf::from(&filename);
 
     if !path.exists() {
         eprintln!("Error: File '{}' not found.", filename);
@@ -25,16 +121,107 @@ fn main() {
     // 假设当前目录是项目根目录，或者你可以把 path 的父目录作为 root
     let root_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
 
-    let mut driver = Driver::new(root_dir);
+    let max_call_depth = max_call_depth.unwrap_or(DEFAULT_MAX_CALL_DEPTH);
+    // [New] 树遍历解释器的调用深度受宿主栈上限约束，更深的递归要用 VM
+    if backend == Backend::TreeWalk && max_call_depth > Driver::MAX_TREE_WALK_CALL_DEPTH {
+        eprintln!(
+            "Error: --max-call-depth={} exceeds the tree-walking interpreter's limit of {}; use --vm for deeper recursion",
+            max_call_depth,
+            Driver::MAX_TREE_WALK_CALL_DEPTH
+        );
+        std::process::exit(1);
+    }
 
     // 3. 运行
-    match driver.run_file(&path) {
-        Ok(_) => {
+    // 树遍历解释器在 Rust 栈上递归，所以放到栈足够大的线程里执行，
+    // 让调用深度由 max_call_depth 控制 (超出时报 StackOverflow) 而不是宿主栈大小。
+    // Driver 内部大量使用 Rc，只能在线程里创建
+    let result = std::thread::Builder::new()
+        .name("loom-main".into())
+        .stack_size(Driver::stack_size_for(backend, max_call_depth))
+        .spawn(move || {
+            let mut driver = Driver::new(root_dir)
+                .with_backend(backend)
+                .with_max_call_depth(max_call_depth)
+                .with_message_format(message_format)
+                .with_lints(lints);
+            driver
+                .run_file(&path)
+                .map(|_| ())
+                .map_err(|diagnostics| driver.render(&diagnostics))
+        })
+        .unwrap_or_else(|e| spawn_failed(e))
+        .join();
+
+    match result {
+        Ok(Ok(())) => {
             // 程序正常结束
         }
-        Err(e) => {
+        Ok(Err(e)) => {
             eprintln!("{}", e);
             std::process::exit(1);
         }
+        Err(_) => std::process::exit(101),
+    }
+}
+
+/// `loom rename` 子命令，返回进程退出码
+fn run_rename(args: &[String]) -> i32 {
+    let [position, new_name] = args else {
+        eprintln!("Usage: loom rename <file>:<line>:<col> <new_name>");
+        return 1;
+    };
+    // 从右边拆，文件名里可以有冒号 (例如 Windows 盘符)
+    let mut parts = position.rsplitn(3, ':');
+    let (Some(col), Some(line), Some(file)) = (parts.next(), parts.next(), parts.next()) else {
+        eprintln!("Error: expected <file>:<line>:<col>, got '{}'", position);
+        return 1;
+    };
+    let (Ok(line), Ok(col)) = (line.parse::<usize>(), col.parse::<usize>()) else {
+        eprintln!("Error: invalid position '{}'", position);
+        return 1;
+    };
+
+    let path = PathBuf::from(file);
+    let new_name = new_name.clone();
+    let root_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let result = std::thread::Builder::new()
+        .name("loom-rename".into())
+        .stack_size(Driver::stack_size_for(
+            Backend::TreeWalk,
+            DEFAULT_MAX_CALL_DEPTH,
+        ))
+        .spawn(move || {
+            Driver::new(root_dir)
+                .rename(&path, line, col, &new_name)
+                .map(|changed| {
+                    let edits: usize = changed.iter().map(|(_, n)| n).sum();
+                    for (path, n) in &changed {
+                        println!("{}: {} edit(s)", path.display(), n);
+                    }
+                    println!(
+                        "Renamed to '{}': {} edit(s) in {} file(s)",
+                        new_name,
+                        edits,
+                        changed.len()
+                    );
+                })
+        })
+        .unwrap_or_else(|e| spawn_failed(e))
+        .join();
+
+    match result {
+        Ok(Ok(())) => 0,
+        Ok(Err(e)) => {
+            eprintln!("{}", e);
+            1
+        }
+        Err(_) => 101,
     }
 }
+
+/// [New] 工作线程创建失败 (例如栈太大，系统拒绝分配) 时按普通错误退出
+fn spawn_failed(e: std::io::Error) -> ! {
+    eprintln!("Error: failed to start the interpreter thread: {}", e);
+    std::process::exit(1);
+}
@@ -2,7 +2,7 @@ mod postfix;
 mod prefix;
 
 use crate::ast::*;
-use crate::parser::{ParseResult, Parser};
+use crate::parser::{ParseError, ParseResult, Parser};
 use crate::token::TokenKind;
 
 impl<'a> Parser<'a> {
@@ -34,7 +34,7 @@ impl<'a> Parser<'a> {
             TokenKind::Star | TokenKind::Slash | TokenKind::Percent => Some(60),
 
             // --- 范围 (70) ---
-            TokenKind::DotDot => Some(70),
+            TokenKind::DotDot | TokenKind::DotDotEq => Some(70),
 
             TokenKind::As => Some(80),
 
@@ -53,6 +53,21 @@ impl<'a> Parser<'a> {
         // 1. Prefix
         let mut lhs = self.parse_prefix()?;
 
+        // [Fix] if/while/for 以缩进块结尾，后面的 token 已经是下一条语句了
+        // (否则块后面一行的 `(a, b) = ...` 会被当成对 for 表达式的调用)
+        // [Changed] match 的分支也是缩进块
+        // [Changed] 匿名函数的函数体 (`=> expr` 已经吃掉了后面的整个表达式)
+        if matches!(
+            lhs.data,
+            ExpressionData::If { .. }
+                | ExpressionData::While { .. }
+                | ExpressionData::For { .. }
+                | ExpressionData::Match { .. }
+                | ExpressionData::Lambda(_)
+        ) {
+            return Ok(lhs);
+        }
+
         // 2. Infix / Postfix
         loop {
             let next_token = self.peek();
@@ -68,6 +83,14 @@ impl<'a> Parser<'a> {
             }
             // 否则，它是小于号，继续下面的 op_bp 逻辑
 
+            // [New] 只有 `类型.变体` 后面的 `{` 是结构体变体字面量: Shape.Rect { w: 1.0, h: 2.0 }
+            if next_token.kind == TokenKind::LeftBrace
+                && matches!(lhs.data, ExpressionData::FieldAccess { .. })
+            {
+                lhs = self.parse_postfix(lhs)?;
+                continue;
+            }
+
             let op_bp = match self.get_binding_power(next_token.kind) {
                 Some(bp) if bp >= min_bp => bp,
                 _ => break,
@@ -81,20 +104,29 @@ impl<'a> Parser<'a> {
                 lhs = self.parse_postfix(lhs)?;
             }
             // [Fix] 专门处理 Range (..)
-            else if next_token.kind == TokenKind::DotDot {
-                self.advance(); // 吃掉 ..
+            else if matches!(next_token.kind, TokenKind::DotDot | TokenKind::DotDotEq) {
+                self.advance(); // 吃掉 .. / ..=
+                let inclusive = next_token.kind == TokenKind::DotDotEq;
 
                 // Range 是左结合还是右结合？通常无所谓，这里用 op_bp
-                let rhs = self.parse_expression_bp(op_bp)?;
-
-                let span = lhs.span.to(rhs.span);
+                // [New] arr[2..]：后面直接是 `]` 等结束符时省略终点
+                let rhs = if self.range_end_omitted() {
+                    None
+                } else {
+                    Some(self.parse_expression_bp(op_bp)?)
+                };
+
+                let span = match &rhs {
+                    Some(rhs) => lhs.span.to(rhs.span),
+                    None => lhs.span.to(self.previous_span()),
+                };
 
                 // 生成专门的 Range 节点
                 lhs = self.make_node(
                     ExpressionData::Range {
-                        start: Box::new(lhs),
-                        end: Box::new(rhs),
-                        inclusive: false, // Loom 默认为 0..5 (不包含5)
+                        start: Some(Box::new(lhs)),
+                        end: rhs.map(Box::new),
+                        inclusive, // 0..5 不包含 5，0..=5 包含
                     },
                     span,
                 );
@@ -108,14 +140,22 @@ impl<'a> Parser<'a> {
                 let rhs = self.parse_expression_bp(r_bp)?;
 
                 let span = lhs.span.to(rhs.span);
-                lhs = self.make_node(
-                    ExpressionData::Assign {
+                let data = match &lhs.data {
+                    // [New] (a, b) = pair：左边是元组时按模式解构
+                    ExpressionData::Tuple(_) if assign_op == AssignOp::Assign => {
+                        ExpressionData::Destructure {
+                            pattern: self.expression_to_pattern(&lhs)?,
+                            value: Box::new(rhs),
+                            is_mut: None,
+                        }
+                    }
+                    _ => ExpressionData::Assign {
                         op: assign_op,
                         target: Box::new(lhs),
                         value: Box::new(rhs),
                     },
-                    span,
-                );
+                };
+                lhs = self.make_node(data, span);
             }
             // [New] 处理 as 类型转换
             else if next_token.kind == TokenKind::As {
@@ -158,6 +198,45 @@ impl<'a> Parser<'a> {
         Ok(lhs)
     }
 
+    /// [New] 解构赋值的左边先被当成元组表达式解析，这里再转成模式
+    fn expression_to_pattern(&mut self, expr: &Expression) -> ParseResult<Pattern> {
+        let data = match &expr.data {
+            ExpressionData::Identifier(name) if self.interner.resolve(*name) == "_" => {
+                PatternData::Wildcard
+            }
+            ExpressionData::Identifier(name) => PatternData::Identifier(*name),
+            ExpressionData::Tuple(items) => PatternData::Tuple(
+                items
+                    .iter()
+                    .map(|item| self.expression_to_pattern(item))
+                    .collect::<ParseResult<_>>()?,
+            ),
+            _ => {
+                return Err(ParseError {
+                    expected: "pattern".into(),
+                    found: TokenKind::Assign,
+                    span: expr.span,
+                    message: "Only names, '_' and tuples can be destructured".into(),
+                });
+            }
+        };
+        Ok(self.make_node(data, expr.span))
+    }
+
+    /// [New] `..` 后面没有终点 (arr[2..] / arr[..])
+    fn range_end_omitted(&mut self) -> bool {
+        matches!(
+            self.peek().kind,
+            TokenKind::RightBracket
+                | TokenKind::RightParen
+                | TokenKind::Comma
+                | TokenKind::Newline
+                | TokenKind::Indent
+                | TokenKind::Dedent
+                | TokenKind::EOF
+        )
+    }
+
     // Helper: Map Token to AssignOp
     fn map_assign_op(&self, kind: TokenKind) -> Option<AssignOp> {
         match kind {
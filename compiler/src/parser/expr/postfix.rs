@@ -24,16 +24,49 @@ impl<'a> Parser<'a> {
 
             // Member: obj.prop
             TokenKind::Dot => {
-                self.advance(); // eat .
-                let name_token = self.expect(TokenKind::Identifier)?;
-                let name = self.intern_token(name_token);
+                let dot = self.advance(); // eat .
+
+                // [New] 元组下标: pair.0 等价于 pair[0]
+                // 词法上 t.0.1 的 "0.1" 是一个 Float，拆成两次下标
+                if self.check(TokenKind::Integer) || self.check(TokenKind::Float) {
+                    let token = self.advance();
+                    let mut expr = base;
+                    for part in self.text(token).split('.') {
+                        let index = part.parse::<i64>().map_err(|_| ParseError {
+                            expected: "tuple index".into(),
+                            found: token.kind,
+                            span: token.span,
+                            message: format!("Invalid tuple index '{}'", self.text(token)),
+                        })?;
+                        let index = self
+                            .make_node(ExpressionData::Literal(Literal::Int(index)), token.span);
+                        let span = expr.span.to(token.span);
+                        expr = self.make_node(
+                            ExpressionData::Index {
+                                target: Box::new(expr),
+                                index: Box::new(index),
+                            },
+                            span,
+                        );
+                    }
+                    return Ok(expr);
+                }
+                // [New] 容忍缺少成员名 (编辑器里刚输入 `obj.`)：记下错误，成员名留空，
+                // 这样 obj 仍然会被分析，补全能拿到它的类型
+                let (name, end_span) = match self.expect(TokenKind::Identifier) {
+                    Ok(name_token) => (self.intern_token(name_token), name_token.span),
+                    Err(e) => {
+                        self.errors.push(e);
+                        (self.interner.intern(""), dot.span)
+                    }
+                };
 
                 Ok(self.make_node(
                     ExpressionData::FieldAccess {
                         target: Box::new(base.clone()),
                         field: name,
                     },
-                    base.span.to(name_token.span),
+                    base.span.to(end_span),
                 ))
             }
 
@@ -56,6 +89,34 @@ impl<'a> Parser<'a> {
                 ))
             }
 
+            // [New] 结构体变体: Shape.Rect { w: 1.0, h } (`h` 是 `h: h` 的简写)
+            TokenKind::LeftBrace => {
+                self.advance(); // 吃掉 '{'
+                let mut fields = Vec::new();
+                while !self.check(TokenKind::RightBrace) && !self.is_at_end() {
+                    let name_token = self.expect(TokenKind::Identifier)?;
+                    let name = self.intern_token(name_token);
+                    let value = if self.match_token(&[TokenKind::Colon]) {
+                        self.parse_expression()?
+                    } else {
+                        self.make_node(ExpressionData::Identifier(name), name_token.span)
+                    };
+                    fields.push((name, value));
+                    if !self.match_token(&[TokenKind::Comma]) {
+                        break;
+                    }
+                }
+                let end_token = self.expect(TokenKind::RightBrace)?;
+                let span = base.span.to(end_token.span);
+                Ok(self.make_node(
+                    ExpressionData::VariantLiteral {
+                        target: Box::new(base),
+                        fields,
+                    },
+                    span,
+                ))
+            }
+
             _ => Ok(base),
         }
     }
@@ -88,7 +149,19 @@ impl<'a> Parser<'a> {
             }
         }
 
-        self.expect(TokenKind::RightParen)?;
+        // [New] 行尾还没闭合的调用 (正在输入 `print(obj.`) 记为错误但保留已解析的参数
+        match self.expect(TokenKind::RightParen) {
+            Ok(_) => {}
+            Err(e)
+                if matches!(
+                    e.found,
+                    TokenKind::Newline | TokenKind::Dedent | TokenKind::EOF
+                ) =>
+            {
+                self.errors.push(e)
+            }
+            Err(e) => return Err(e),
+        }
         Ok(args)
     }
 
@@ -2,6 +2,8 @@
 use crate::ast::*;
 use crate::parser::{ParseError, ParseResult, Parser};
 use crate::token::TokenKind;
+use crate::utils::Span;
+use std::rc::Rc;
 
 impl<'a> Parser<'a> {
     pub fn parse_prefix(&mut self) -> ParseResult<Expression> {
@@ -12,6 +14,7 @@ impl<'a> Parser<'a> {
             TokenKind::Integer => self.parse_int_literal(),
             TokenKind::Float => self.parse_float_literal(),
             TokenKind::StringLiteral => self.parse_string_literal(),
+            TokenKind::CharLiteral => self.parse_char_literal(),
             TokenKind::True => {
                 self.advance();
                 Ok(self.make_node(ExpressionData::Literal(Literal::Bool(true)), token.span))
@@ -35,6 +38,27 @@ impl<'a> Parser<'a> {
             // === 前缀运算 ===
             TokenKind::Minus | TokenKind::Bang => self.parse_unary(),
 
+            // [New] 省略起点的范围: arr[..3] / arr[..]
+            TokenKind::DotDot | TokenKind::DotDotEq => {
+                self.advance();
+                let inclusive = token.kind == TokenKind::DotDotEq;
+                let end = if self.range_end_omitted() {
+                    None
+                } else {
+                    // 和中缀 `..` 的右边使用同样的结合力
+                    Some(Box::new(self.parse_expression_bp(70)?))
+                };
+                let span = token.span.to(self.previous_span());
+                Ok(self.make_node(
+                    ExpressionData::Range {
+                        start: None,
+                        end,
+                        inclusive,
+                    },
+                    span,
+                ))
+            }
+
             // === 分组/元组 ===
             TokenKind::LeftParen => self.parse_group_or_tuple(), // 确保你有这个函数，或者用简单的分组解析
 
@@ -57,6 +81,8 @@ impl<'a> Parser<'a> {
             TokenKind::Continue => self.parse_continue(),
             TokenKind::For => self.parse_for(),
             TokenKind::While => self.parse_while(),
+            TokenKind::Match => self.parse_match(),
+            TokenKind::Fn => self.parse_lambda(),
             _ => Err(ParseError {
                 expected: "Expression".into(),
                 found: token.kind,
@@ -149,10 +175,28 @@ impl<'a> Parser<'a> {
         let text = self.text(token);
         // 去掉引号
         let content = &text[1..text.len() - 1];
-        let val = self.unescape_string(content);
+        let val = self.intern_string(content);
         Ok(self.make_node(ExpressionData::Literal(Literal::String(val)), token.span))
     }
 
+    /// [New] 'a' / '\n' / '\u{4e2d}'
+    fn parse_char_literal(&mut self) -> ParseResult<Expression> {
+        let token = self.advance();
+        let text = self.unescape_string(self.text(token));
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => {
+                Ok(self.make_node(ExpressionData::Literal(Literal::Char(c)), token.span))
+            }
+            _ => Err(ParseError {
+                expected: "char literal".into(),
+                found: token.kind,
+                span: token.span,
+                message: "Char literal must contain exactly one character".into(),
+            }),
+        }
+    }
+
     fn parse_float_literal(&mut self) -> ParseResult<Expression> {
         let token = self.advance();
         // 移除数字中的下划线 (e.g. 1_000.00)
@@ -301,12 +345,12 @@ impl<'a> Parser<'a> {
     /// 解析 For 循环
     /// 语法: for i in 0..10
     ///       for item in list
+    ///       for (k, v) in pairs
     fn parse_for(&mut self) -> ParseResult<Expression> {
         let start_span = self.expect(TokenKind::For)?.span;
 
-        // Iterator (i)
-        let iter_token = self.expect(TokenKind::Identifier)?;
-        let iterator = self.intern_token(iter_token);
+        // Iterator (i / (k, v))
+        let pattern = self.parse_pattern()?;
 
         // Keyword 'in'
         self.expect(TokenKind::In)?;
@@ -326,7 +370,7 @@ impl<'a> Parser<'a> {
 
         Ok(self.make_node(
             ExpressionData::For {
-                iterator,
+                pattern,
                 iterable: Box::new(iterable),
                 body,
             },
@@ -334,6 +378,299 @@ impl<'a> Parser<'a> {
         ))
     }
 
+    /// [New] 解析绑定模式
+    /// 语法: name / _ / (p1, p2, ...)
+    pub(crate) fn parse_pattern(&mut self) -> ParseResult<Pattern> {
+        if self.check(TokenKind::LeftParen) {
+            return self.parse_tuple_pattern(Self::parse_pattern);
+        }
+
+        let token = self.expect(TokenKind::Identifier)?;
+        let data = match self.text(token) {
+            "_" => PatternData::Wildcard,
+            _ => PatternData::Identifier(self.intern_token(token)),
+        };
+        Ok(self.make_node(data, token.span))
+    }
+
+    /// (p1, p2, ...)，元素由 item 解析；(p) 只是分组
+    fn parse_tuple_pattern(
+        &mut self,
+        item: fn(&mut Self) -> ParseResult<Pattern>,
+    ) -> ParseResult<Pattern> {
+        let start_span = self.expect(TokenKind::LeftParen)?.span;
+        let mut items = Vec::new();
+        let mut has_comma = false;
+        while !self.check(TokenKind::RightParen) && !self.is_at_end() {
+            items.push(item(self)?);
+            if !self.match_token(&[TokenKind::Comma]) {
+                break;
+            }
+            has_comma = true;
+        }
+        let end_token = self.expect(TokenKind::RightParen)?;
+        if items.len() == 1 && !has_comma {
+            return Ok(items.pop().unwrap());
+        }
+        Ok(self.make_node(PatternData::Tuple(items), start_span.to(end_token.span)))
+    }
+
+    /// [New] 解析匿名函数
+    /// 语法: fn(x: int) int => x * 2
+    ///       fn(x: int) int
+    ///           block
+    fn parse_lambda(&mut self) -> ParseResult<Expression> {
+        let start_span = self.expect(TokenKind::Fn)?.span;
+        let params = self.parse_param_list()?;
+
+        // 和具名函数一样：`=>` / 换行之前的是返回类型
+        let return_type = if !self.check(TokenKind::Newline)
+            && !self.check(TokenKind::Indent)
+            && !self.check(TokenKind::FatArrow)
+        {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        let body = if self.match_token(&[TokenKind::FatArrow]) {
+            let expr = self.parse_expression()?;
+            let span = expr.span;
+            self.make_node(
+                BlockData {
+                    statements: vec![expr],
+                },
+                span,
+            )
+        } else {
+            if self.check(TokenKind::Newline) {
+                self.advance();
+            }
+            if !self.check(TokenKind::Indent) {
+                return Err(ParseError {
+                    expected: "Function Body (Indent or =>)".into(),
+                    found: self.peek().kind,
+                    span: self.peek().span,
+                    message: "Function must have a body".into(),
+                });
+            }
+            self.parse_block()?
+        };
+
+        let span = start_span.to(body.span);
+        let name = self.interner.intern("<lambda>");
+        let def = self.make_node(
+            MethodDefinitionData {
+                name,
+                generics: Vec::new(),
+                params,
+                return_type,
+                body: Some(body),
+            },
+            span,
+        );
+        Ok(self.make_node(ExpressionData::Lambda(Rc::new(def)), span))
+    }
+
+    /// [New] 解析 match 表达式
+    /// 语法: match value
+    ///           .Circle(r) => r * r
+    ///           _ =>
+    ///               block
+    fn parse_match(&mut self) -> ParseResult<Expression> {
+        let start_span = self.expect(TokenKind::Match)?.span;
+        let scrutinee = self.parse_expression()?;
+
+        if self.check(TokenKind::Newline) {
+            self.advance();
+        }
+        self.expect(TokenKind::Indent)?;
+
+        let mut arms = Vec::new();
+        loop {
+            while self.match_token(&[TokenKind::Newline]) {}
+            if self.check(TokenKind::Dedent) || self.is_at_end() {
+                break;
+            }
+
+            let pattern = self.parse_match_pattern()?;
+            self.expect(TokenKind::FatArrow)?;
+
+            // `=> expr` 或者 `=>` 后面换行接缩进块
+            if self.check(TokenKind::Newline) {
+                self.advance();
+            }
+            let body = if self.check(TokenKind::Indent) {
+                self.parse_block()?
+            } else {
+                let expr = self.parse_expression()?;
+                let span = expr.span;
+                self.make_node(
+                    BlockData {
+                        statements: vec![expr],
+                    },
+                    span,
+                )
+            };
+
+            let span = pattern.span.to(body.span);
+            arms.push(self.make_node(MatchArmData { pattern, body }, span));
+
+            // 分支之间用换行分隔；以缩进块结尾的分支已经吃掉了 Dedent
+            if !self.check(TokenKind::Dedent)
+                && !self.is_at_end()
+                && self.previous_kind != TokenKind::Dedent
+            {
+                self.expect(TokenKind::Newline)?;
+            }
+        }
+        self.expect(TokenKind::Dedent)?;
+
+        Ok(self.make_node(
+            ExpressionData::Match {
+                scrutinee: Box::new(scrutinee),
+                arms,
+            },
+            start_span.to(self.previous_span()),
+        ))
+    }
+
+    /// [New] 解析 match 分支的模式 (可能匹配失败)
+    /// 语法: 字面量 / _ / else / name / (p1, p2) /
+    ///       .V / .V(p1, p2) / .V { f: p, g } / Enum.V... / lib.Enum.V...
+    fn parse_match_pattern(&mut self) -> ParseResult<Pattern> {
+        let token = self.peek();
+        match token.kind {
+            TokenKind::LeftParen => self.parse_tuple_pattern(Self::parse_match_pattern),
+            TokenKind::Else => {
+                self.advance();
+                Ok(self.make_node(PatternData::Wildcard, token.span))
+            }
+            // 字面量 (包括负数) 直接复用表达式的解析
+            TokenKind::Integer
+            | TokenKind::Float
+            | TokenKind::StringLiteral
+            | TokenKind::CharLiteral
+            | TokenKind::True
+            | TokenKind::False
+            | TokenKind::Nil
+            | TokenKind::Minus => {
+                let expr = self.parse_prefix()?;
+                let literal = match expr.data {
+                    ExpressionData::Literal(literal) => literal,
+                    ExpressionData::Unary {
+                        op: UnaryOp::Neg,
+                        expr: inner,
+                    } => match inner.data {
+                        ExpressionData::Literal(Literal::Int(n)) => Literal::Int(-n),
+                        ExpressionData::Literal(Literal::Float(f)) => Literal::Float(-f),
+                        _ => return Err(self.pattern_error(expr.span)),
+                    },
+                    _ => return Err(self.pattern_error(expr.span)),
+                };
+                Ok(self.make_node(PatternData::Literal(literal), expr.span))
+            }
+            TokenKind::Dot => {
+                self.advance();
+                self.parse_variant_pattern(None, token.span)
+            }
+            TokenKind::Identifier if self.text(token) == "_" => {
+                self.advance();
+                Ok(self.make_node(PatternData::Wildcard, token.span))
+            }
+            TokenKind::Identifier if !self.check_nth(1, TokenKind::Dot) => {
+                self.advance();
+                let name = self.intern_token(token);
+                Ok(self.make_node(PatternData::Identifier(name), token.span))
+            }
+            // Enum.V / lib.Enum.V
+            TokenKind::Identifier => {
+                self.advance();
+                let first = self.intern_token(token);
+                self.expect(TokenKind::Dot)?;
+                let qualifier =
+                    if self.check(TokenKind::Identifier) && self.check_nth(1, TokenKind::Dot) {
+                        let member_token = self.advance();
+                        let member = self.intern_token(member_token);
+                        self.advance(); // eat .
+                        self.make_node(
+                            TypeRefData::Member {
+                                module: first,
+                                member,
+                            },
+                            token.span.to(member_token.span),
+                        )
+                    } else {
+                        self.make_node(TypeRefData::Named(first), token.span)
+                    };
+                self.parse_variant_pattern(Some(qualifier), token.span)
+            }
+            _ => Err(self.pattern_error(token.span)),
+        }
+    }
+
+    /// `.` 之后的部分: V / V(p1, p2) / V { f: p, g }
+    fn parse_variant_pattern(
+        &mut self,
+        qualifier: Option<TypeRef>,
+        start_span: Span,
+    ) -> ParseResult<Pattern> {
+        let name_token = self.expect(TokenKind::Identifier)?;
+        let name = self.intern_token(name_token);
+
+        let args = if self.check(TokenKind::LeftParen) {
+            self.advance();
+            let mut items = Vec::new();
+            while !self.check(TokenKind::RightParen) && !self.is_at_end() {
+                items.push(self.parse_match_pattern()?);
+                if !self.match_token(&[TokenKind::Comma]) {
+                    break;
+                }
+            }
+            self.expect(TokenKind::RightParen)?;
+            VariantPatternArgs::Tuple(items)
+        } else if self.check(TokenKind::LeftBrace) {
+            self.advance();
+            let mut fields = Vec::new();
+            while !self.check(TokenKind::RightBrace) && !self.is_at_end() {
+                let field_token = self.expect(TokenKind::Identifier)?;
+                let field = self.intern_token(field_token);
+                // { w } 是 { w: w } 的简写
+                let pattern = if self.match_token(&[TokenKind::Colon]) {
+                    self.parse_match_pattern()?
+                } else {
+                    self.make_node(PatternData::Identifier(field), field_token.span)
+                };
+                fields.push((field, pattern));
+                if !self.match_token(&[TokenKind::Comma]) {
+                    break;
+                }
+            }
+            self.expect(TokenKind::RightBrace)?;
+            VariantPatternArgs::Struct(fields)
+        } else {
+            VariantPatternArgs::None
+        };
+
+        Ok(self.make_node(
+            PatternData::Variant {
+                qualifier,
+                name,
+                args,
+            },
+            start_span.to(self.previous_span()),
+        ))
+    }
+
+    fn pattern_error(&mut self, span: Span) -> ParseError {
+        ParseError {
+            expected: "pattern".into(),
+            found: self.peek().kind,
+            span,
+            message: "Expected a literal, name, '_', tuple or enum variant pattern".into(),
+        }
+    }
+
     /// 解析 While 循环
     /// 语法: while cond
     ///           block
@@ -6,10 +6,13 @@ mod ty;
 
 use crate::ast::*;
 use crate::context::Context;
+use crate::diagnostic::Diagnostic;
 use crate::lexer::Lexer;
 use crate::source::FileId;
 use crate::token::{Token, TokenKind};
 use crate::utils::{Interner, Node, NodeId, Span, Symbol};
+use std::collections::HashMap;
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub struct ParseError {
@@ -19,6 +22,13 @@ pub struct ParseError {
     pub message: String,
 }
 
+impl ParseError {
+    /// [New] 转换成诊断 (解析错误统一用 E0001)
+    pub fn to_diagnostic(&self, file_id: FileId) -> Diagnostic {
+        Diagnostic::error("E0001", self.message.clone()).with_primary(file_id, self.span)
+    }
+}
+
 pub type ParseResult<T> = Result<T, ParseError>;
 
 pub struct Parser<'a> {
@@ -32,6 +42,9 @@ pub struct Parser<'a> {
     pub file_id: FileId,
 
     interner: &'a mut Interner,
+
+    // [New] 字符串字面量驻留：同一文件里相同内容的字面量共享一份 Rc<str>
+    string_literals: HashMap<String, Rc<str>>,
 }
 
 impl<'a> Parser<'a> {
@@ -50,9 +63,22 @@ impl<'a> Parser<'a> {
             node_id_counter: 0,
             file_id,
             interner,
+            string_literals: HashMap::new(),
         }
     }
 
+    /// [New] 指定 NodeId 的起始编号
+    /// 同一个 Context 里解析多个文件时，由调用方传入 ctx.next_node_id 避免 ID 冲突
+    pub fn with_first_node_id(mut self, first: u32) -> Self {
+        self.node_id_counter = first;
+        self
+    }
+
+    /// [New] 下一个未分配的 NodeId 编号 (解析结束后写回 ctx.next_node_id)
+    pub fn next_node_id(&self) -> u32 {
+        self.node_id_counter
+    }
+
     /// 创建 AST 节点 (自动分配 ID)
     pub fn make_node<T>(&mut self, data: T, span: Span) -> Node<T> {
         let id = self.next_id();
@@ -173,7 +199,11 @@ impl<'a> Parser<'a> {
         while !self.is_at_end() {
             // 遇到这些关键字，说明可能是一个新定义的开始，可以在这里恢复解析
             match self.peek().kind {
-                TokenKind::Class | TokenKind::Fn | TokenKind::Use => return,
+                TokenKind::Class
+                | TokenKind::Trait
+                | TokenKind::Enum
+                | TokenKind::Fn
+                | TokenKind::Use => return,
                 _ => {}
             }
 
@@ -181,6 +211,17 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// [New] 转义并驻留字符串字面量
+    pub fn intern_string(&mut self, raw: &str) -> Rc<str> {
+        let text = self.unescape_string(raw);
+        if let Some(s) = self.string_literals.get(&text) {
+            return s.clone();
+        }
+        let s: Rc<str> = Rc::from(text.as_str());
+        self.string_literals.insert(text, s.clone());
+        s
+    }
+
     // --- 字符串 Unescape ---
     pub fn unescape_string(&self, raw: &str) -> String {
         let mut result = String::new();
@@ -254,11 +295,22 @@ impl<'a> Parser<'a> {
                 // 处理类定义: class Dog ...
                 let item = self.parse_class_definition()?;
                 definitions.push(item);
+            } else if self.check(TokenKind::Trait) {
+                // [New] trait 定义: trait Drawable ...
+                let item = self.parse_trait_definition()?;
+                definitions.push(item);
+            } else if self.check(TokenKind::Enum) {
+                // [New] 枚举定义: enum Shape ...
+                let item = self.parse_enum_definition()?;
+                definitions.push(item);
             } else if self.check(TokenKind::Fn) {
                 // 处理顶层函数: fn main() ...
                 let item = self.parse_function_definition()?;
                 definitions.push(item);
-            } else if self.check(TokenKind::Identifier) {
+            } else if matches!(
+                self.peek().kind,
+                TokenKind::Identifier | TokenKind::Let | TokenKind::Var | TokenKind::Const
+            ) {
                 let field = self.parse_top_level_field()?;
                 definitions.push(TopLevelItem::Field(field));
             } else {
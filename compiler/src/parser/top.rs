@@ -25,12 +25,15 @@ impl<'a> Parser<'a> {
             Vec::new()
         };
 
-        // 3. Inheritance (: Parent)
-        let prototype = if self.match_token(&[TokenKind::Colon]) {
-            Some(self.parse_type()?)
-        } else {
-            None
-        };
+        // 3. Inheritance (: Parent, Trait1, Trait2)
+        let mut prototype = None;
+        let mut traits = Vec::new();
+        if self.match_token(&[TokenKind::Colon]) {
+            prototype = Some(self.parse_type()?);
+            while self.match_token(&[TokenKind::Comma]) {
+                traits.push(self.parse_type()?);
+            }
+        }
 
         // 4. Body
         // 允许头部后换行
@@ -68,6 +71,67 @@ impl<'a> Parser<'a> {
             TableDefinitionData {
                 name,
                 prototype,
+                traits,
+                is_trait: false,
+                generics,
+                items,
+            },
+            start_span.to(end_span),
+        )))
+    }
+
+    // ==========================================
+    // [New] Trait Definition
+    // 语法:
+    // trait Drawable
+    //     fn draw() str
+    //     fn area() float
+    // ==========================================
+    pub fn parse_trait_definition(&mut self) -> ParseResult<TopLevelItem> {
+        let start_span = self.expect(TokenKind::Trait)?.span;
+
+        let name_token = self.expect(TokenKind::Identifier)?;
+        let name = self.intern_token(name_token);
+
+        let generics = if self.check(TokenKind::LessThan) {
+            self.parse_generic_params()?
+        } else {
+            Vec::new()
+        };
+
+        if self.check(TokenKind::Newline) {
+            self.advance();
+        }
+
+        // trait 里只有方法签名
+        let mut items = Vec::new();
+        self.expect(TokenKind::Indent)?;
+        loop {
+            while self.match_token(&[TokenKind::Newline]) {}
+            if self.check(TokenKind::Dedent) || self.is_at_end() {
+                break;
+            }
+            if !self.check(TokenKind::Fn) {
+                let token = self.peek();
+                return Err(ParseError {
+                    expected: "fn".into(),
+                    found: token.kind,
+                    span: token.span,
+                    message: "Traits can only declare method signatures".into(),
+                });
+            }
+            let method = self.parse_function_definition_internal(true, true)?;
+            items.push(TableItem::Method(method));
+        }
+        self.expect(TokenKind::Dedent)?;
+
+        let end_span = self.previous_span();
+        Ok(TopLevelItem::Table(self.make_node(
+            TableDefinitionData {
+                name,
+                prototype: None,
+                traits: Vec::new(),
+                is_trait: true,
                 generics,
                 items,
             },
@@ -75,19 +139,109 @@ impl<'a> Parser<'a> {
         )))
     }
 
+    // ==========================================
+    // [New] Enum Definition
+    // 语法:
+    // enum Shape
+    //     Circle(float)
+    //     Rect { w: float, h: float }
+    //     Empty
+    // ==========================================
+    pub fn parse_enum_definition(&mut self) -> ParseResult<TopLevelItem> {
+        let start_span = self.expect(TokenKind::Enum)?.span;
+
+        let name_token = self.expect(TokenKind::Identifier)?;
+        let name = self.intern_token(name_token);
+
+        if self.check(TokenKind::Newline) {
+            self.advance();
+        }
+
+        let mut variants = Vec::new();
+        self.expect(TokenKind::Indent)?;
+        loop {
+            while self.match_token(&[TokenKind::Newline]) {}
+            if self.check(TokenKind::Dedent) || self.is_at_end() {
+                break;
+            }
+            variants.push(self.parse_variant_definition()?);
+            if !self.check(TokenKind::Dedent) && !self.is_at_end() {
+                self.expect(TokenKind::Newline)?;
+            }
+        }
+        self.expect(TokenKind::Dedent)?;
+
+        let end_span = self.previous_span();
+        Ok(TopLevelItem::Enum(self.make_node(
+            EnumDefinitionData { name, variants },
+            start_span.to(end_span),
+        )))
+    }
+
+    /// 变体: Name / Name(T1, T2) / Name { a: T1, b: T2 }
+    fn parse_variant_definition(&mut self) -> ParseResult<VariantDefinition> {
+        let name_token = self.expect(TokenKind::Identifier)?;
+        let name = self.intern_token(name_token);
+
+        let kind = if self.match_token(&[TokenKind::LeftParen]) {
+            let mut types = Vec::new();
+            while !self.check(TokenKind::RightParen) && !self.is_at_end() {
+                types.push(self.parse_type()?);
+                if !self.match_token(&[TokenKind::Comma]) {
+                    break;
+                }
+            }
+            self.expect(TokenKind::RightParen)?;
+            // Name() 和 Name 是一回事
+            if types.is_empty() {
+                VariantKind::Unit
+            } else {
+                VariantKind::Tuple(types)
+            }
+        } else if self.match_token(&[TokenKind::LeftBrace]) {
+            let mut fields = Vec::new();
+            while !self.check(TokenKind::RightBrace) && !self.is_at_end() {
+                let field_token = self.expect(TokenKind::Identifier)?;
+                let field = self.intern_token(field_token);
+                self.expect(TokenKind::Colon)?;
+                fields.push((field, self.parse_type()?));
+                if !self.match_token(&[TokenKind::Comma]) {
+                    break;
+                }
+            }
+            self.expect(TokenKind::RightBrace)?;
+            VariantKind::Struct(fields)
+        } else {
+            VariantKind::Unit
+        };
+
+        let span = name_token.span.to(self.previous_span());
+        Ok(self.make_node(VariantDefinitionData { name, kind }, span))
+    }
+
     /// 解析类成员 (Field 或 Method)
     fn parse_class_member(&mut self) -> ParseResult<TableItem> {
         // Case A: 方法 (fn method_name ...)
         if self.check(TokenKind::Fn) {
-            let method = self.parse_function_definition_internal(true)?; // true = implies method
+            let method = self.parse_function_definition_internal(true, false)?; // true = implies method
             return Ok(TableItem::Method(method));
         }
 
-        // Case B: 字段 (name: Type = val)
-        // 必须以 Identifier 开头
+        // Case B: 字段 ([let|var] name: Type = val)
+        let start_span = self.peek().span;
+        let (is_mut, is_const) = self.parse_binding_keyword();
+        if is_const {
+            return Err(ParseError {
+                expected: "field".into(),
+                found: TokenKind::Const,
+                span: start_span,
+                message: "Fields cannot be 'const'; use 'let' for a read-only field".into(),
+            });
+        }
+
+        // 关键字后面必须是 Identifier
         let name_token = self.expect(TokenKind::Identifier)?;
         let name = self.intern_token(name_token);
-        let start_span = name_token.span;
 
         // 字段必须有类型标注 (Loom 强类型)
         let type_annotation = if self.match_token(&[TokenKind::Colon]) {
@@ -125,6 +279,8 @@ impl<'a> Parser<'a> {
                 name,
                 type_annotation,
                 value,
+                is_mut,
+                is_const,
             },
             start_span.to(end_span),
         )))
@@ -138,14 +294,16 @@ impl<'a> Parser<'a> {
 
     // 公共入口：顶层调用
     pub fn parse_function_definition(&mut self) -> ParseResult<TopLevelItem> {
-        let func = self.parse_function_definition_internal(false)?;
+        let func = self.parse_function_definition_internal(false, false)?;
         Ok(TopLevelItem::Function(func))
     }
 
     // 内部实现：供 TopLevel 和 ClassMethod 复用
+    // [New] is_signature: trait 里的方法只有签名，不能有方法体
     fn parse_function_definition_internal(
         &mut self,
         is_method: bool,
+        is_signature: bool,
     ) -> ParseResult<MethodDefinition> {
         let start_span = self.expect(TokenKind::Fn)?.span;
 
@@ -191,7 +349,16 @@ impl<'a> Parser<'a> {
             // just consume
         }
 
-        if self.match_token(&[TokenKind::FatArrow]) {
+        if is_signature {
+            if self.check(TokenKind::FatArrow) || self.check(TokenKind::Indent) {
+                return Err(ParseError {
+                    expected: "newline".into(),
+                    found: self.peek().kind,
+                    span: self.peek().span,
+                    message: "Trait methods cannot have a body".into(),
+                });
+            }
+        } else if self.match_token(&[TokenKind::FatArrow]) {
             let expr = self.parse_expression()?;
             end_span = expr.span;
             body = Some(self.make_node(
@@ -205,7 +372,7 @@ impl<'a> Parser<'a> {
             end_span = block.span;
             body = Some(block);
         } else {
-            // 如果既没有 => 也没有 Indent，对于 fn 来说是语法错误 (除非是 trait 定义，目前 Loom 没有 interface 关键字)
+            // 如果既没有 => 也没有 Indent，对于 fn 来说是语法错误 (trait 里的签名在上面处理)
             return Err(ParseError {
                 expected: "Function Body (Indent or =>)".into(),
                 found: self.peek().kind,
@@ -319,7 +486,7 @@ impl<'a> Parser<'a> {
     }
 
     /// 解析参数列表 (a: int, b: str)
-    fn parse_param_list(&mut self) -> ParseResult<Vec<Param>> {
+    pub fn parse_param_list(&mut self) -> ParseResult<Vec<Param>> {
         self.expect(TokenKind::LeftParen)?;
         let mut params = Vec::new();
 
@@ -372,15 +539,29 @@ impl<'a> Parser<'a> {
             let is_explicit_decl =
                 self.check(TokenKind::Identifier) && self.check_nth(1, TokenKind::Colon);
 
-            let stmt = if is_explicit_decl {
-                self.parse_variable_definition_without_keyword()?
+            let stmt = if matches!(
+                self.peek().kind,
+                TokenKind::Let | TokenKind::Var | TokenKind::Const
+            ) {
+                self.parse_variable_declaration()
+            } else if is_explicit_decl {
+                self.parse_variable_definition_without_keyword()
             } else {
                 // 否则，按普通表达式解析
                 // 如果是 a = 1，会被解析成 Binary Expression (Assign)
-                self.parse_expression()?
+                self.parse_expression()
             };
 
-            statements.push(stmt);
+            // [New] 语句级错误恢复：记下错误，跳过这条语句，继续解析后面的语句
+            // (编辑器里总有写了一半的代码，不能因为一行就丢掉整个文件的分析结果)
+            match stmt {
+                Ok(stmt) => statements.push(stmt),
+                Err(e) => {
+                    self.errors.push(e);
+                    self.skip_statement();
+                    continue;
+                }
+            }
 
             // === 3. 语句分隔逻辑 (Core Fix) ===
             // 逻辑：语句之间必须由 Newline 分隔，除非：
@@ -405,12 +586,13 @@ impl<'a> Parser<'a> {
                 } else {
                     // 错误情况: 两个语句挤在一行，且中间没有块结束符
                     let current = self.peek();
-                    return Err(ParseError {
+                    self.errors.push(ParseError {
                         expected: "Newline or Dedent".to_string(),
                         found: current.kind,
                         span: current.span,
                         message: "Expected a newline or end of block after statement".to_string(),
                     });
+                    self.skip_statement();
                 }
             }
         }
@@ -423,6 +605,36 @@ impl<'a> Parser<'a> {
         ))
     }
 
+    /// [New] 跳过当前语句剩下的部分：到本层的换行为止 (连同后面缩进的子块)，
+    /// 遇到本层的 Dedent 时停下 (留给 parse_block 结束代码块)
+    fn skip_statement(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.peek().kind {
+                TokenKind::EOF => return,
+                TokenKind::Indent => depth += 1,
+                TokenKind::Dedent if depth == 0 => return,
+                TokenKind::Dedent => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.advance();
+                        return;
+                    }
+                }
+                TokenKind::Newline if depth == 0 => {
+                    self.advance();
+                    // 出错的是 if/while 的头部：它的代码块也一起跳过
+                    if !self.check(TokenKind::Indent) {
+                        return;
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
     /// 新增 helper：解析没有 var/let 的定义
     /// 语法: name : type = value
     fn parse_variable_definition_without_keyword(&mut self) -> ParseResult<Expression> {
@@ -444,7 +656,8 @@ impl<'a> Parser<'a> {
 
         Ok(self.make_node(
             ExpressionData::VariableDefinition {
-                is_mut: true, // 默认可变，或者根据需求
+                is_mut: true, // 不带关键字的定义保持可变 (兼容旧代码)
+                is_const: false,
                 name,
                 ty: Some(ty),
                 init: Box::new(init),
@@ -453,6 +666,78 @@ impl<'a> Parser<'a> {
         ))
     }
 
+    /// [New] 带关键字的变量定义
+    /// 语法: (let | var | const) name (: type)? = value
+    ///       (let | var) (p1, p2, ...) = value
+    fn parse_variable_declaration(&mut self) -> ParseResult<Expression> {
+        let start_span = self.peek().span;
+        let (is_mut, is_const) = self.parse_binding_keyword();
+
+        // [New] let (a, b) = pair：按元组模式解构，每个名字都是新变量
+        if self.check(TokenKind::LeftParen) {
+            if is_const {
+                let token = self.peek();
+                return Err(ParseError {
+                    expected: "Identifier".into(),
+                    found: token.kind,
+                    span: token.span,
+                    message: "Tuple patterns can only be declared with 'let' or 'var'".into(),
+                });
+            }
+            let pattern = self.parse_pattern()?;
+            self.expect(TokenKind::Assign)?;
+            let value = self.parse_expression()?;
+            let end_span = value.span;
+            return Ok(self.make_node(
+                ExpressionData::Destructure {
+                    pattern,
+                    value: Box::new(value),
+                    is_mut: Some(is_mut),
+                },
+                start_span.to(end_span),
+            ));
+        }
+
+        let name_token = self.expect(TokenKind::Identifier)?;
+        let name = self.intern_token(name_token);
+
+        let ty = if self.match_token(&[TokenKind::Colon]) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        // 不可变的绑定以后没法再赋值，所以必须在这里初始化
+        self.expect(TokenKind::Assign)?;
+
+        let init = self.parse_expression()?;
+        let end_span = init.span;
+
+        Ok(self.make_node(
+            ExpressionData::VariableDefinition {
+                is_mut,
+                is_const,
+                name,
+                ty,
+                init: Box::new(init),
+            },
+            start_span.to(end_span),
+        ))
+    }
+
+    /// [New] 吃掉可选的 let / var / const，返回 (is_mut, is_const)
+    /// 没有关键字时按 `var` 处理
+    fn parse_binding_keyword(&mut self) -> (bool, bool) {
+        if self.match_token(&[TokenKind::Let]) {
+            (false, false)
+        } else if self.match_token(&[TokenKind::Const]) {
+            (false, true)
+        } else {
+            self.match_token(&[TokenKind::Var]);
+            (true, false)
+        }
+    }
+
     // --- Lookahead Helpers ---
 
     /// 简单的 Lookahead 判断是否像参数列表
@@ -550,13 +835,14 @@ impl<'a> Parser<'a> {
     }
 
     /// [New] 解析顶层变量定义
-    /// 语法: name (: type)? = value
+    /// 语法: [let | var | const] name (: type)? = value
     /// 或者: name : type
     pub fn parse_top_level_field(&mut self) -> ParseResult<FieldDefinition> {
-        // 1. 解析名字
+        // 1. 解析关键字和名字
+        let start_span = self.peek().span;
+        let (is_mut, is_const) = self.parse_binding_keyword();
         let name_token = self.expect(TokenKind::Identifier)?;
         let name = self.intern_token(name_token);
-        let start_span = name_token.span;
 
         // 2. 解析可选类型 (: Type)
         let type_annotation = if self.match_token(&[TokenKind::Colon]) {
@@ -577,6 +863,15 @@ impl<'a> Parser<'a> {
         // 4. 结尾检查
         // 必须以换行符结束（或者是 EOF）
         // 如果没有值也没有类型，那是没有意义的单独 Identifier，应该在前面就报错
+        // [New] 只读的全局变量以后没法再赋值，必须有初值
+        if !is_mut && value.is_none() {
+            return Err(ParseError {
+                expected: "'='".into(),
+                found: self.peek().kind,
+                span: start_span.to(self.previous_span()),
+                message: "Immutable top-level variable must have an initial value".into(),
+            });
+        }
         if type_annotation.is_none() && value.is_none() {
             return Err(ParseError {
                 expected: "'=' or ':'".into(),
@@ -595,6 +890,8 @@ impl<'a> Parser<'a> {
                 name,
                 type_annotation,
                 value,
+                is_mut,
+                is_const,
             },
             start_span.to(end_span),
         ))
@@ -10,9 +10,43 @@ impl<'a> Parser<'a> {
     /// - lib.MyClass  (New!)
     /// - List<String>
     /// - { name: str, age: int }
+    /// - fn(int, str) bool
     pub fn parse_type(&mut self) -> ParseResult<TypeRef> {
         let start_span = self.peek().span;
 
+        // [New] 函数类型: fn(int) int；后面紧跟的不是类型时没有返回值 (fn(str))
+        if self.match_token(&[TokenKind::Fn]) {
+            self.expect(TokenKind::LeftParen)?;
+            let mut params = Vec::new();
+            while !self.check(TokenKind::RightParen) && !self.is_at_end() {
+                params.push(self.parse_type()?);
+                if !self.match_token(&[TokenKind::Comma]) {
+                    break;
+                }
+            }
+            self.expect(TokenKind::RightParen)?;
+
+            let next = self.peek().kind;
+            let ret = if matches!(
+                next,
+                TokenKind::LeftBracket
+                    | TokenKind::LeftParen
+                    | TokenKind::LeftBrace
+                    | TokenKind::Identifier
+                    | TokenKind::Fn
+            ) || self.get_basic_type_name(next).is_some()
+            {
+                Some(Box::new(self.parse_type()?))
+            } else {
+                None
+            };
+            let end_span = self.previous_span();
+            return Ok(self.make_node(
+                TypeRefData::Function { params, ret },
+                start_span.to(end_span),
+            ));
+        }
+
         // [NEW] 0. 数组类型 (Array Type): [int] or [[str]]
         // 递归解析，天然支持多维数组
         if self.check(TokenKind::LeftBracket) {
@@ -31,6 +65,27 @@ impl<'a> Parser<'a> {
             ));
         }
 
+        // [New] 元组类型: (int, str)；只有一个类型且没有逗号时只是分组
+        if self.check(TokenKind::LeftParen) {
+            self.advance(); // consume '('
+            let mut items = Vec::new();
+            let mut has_comma = false;
+            while !self.check(TokenKind::RightParen) && !self.is_at_end() {
+                items.push(self.parse_type()?);
+                if !self.match_token(&[TokenKind::Comma]) {
+                    break;
+                }
+                has_comma = true;
+            }
+            self.expect(TokenKind::RightParen)?;
+
+            if items.len() == 1 && !has_comma {
+                return Ok(items.pop().unwrap());
+            }
+            let end_span = self.previous_span();
+            return Ok(self.make_node(TypeRefData::Tuple(items), start_span.to(end_span)));
+        }
+
         // 1. 结构化类型 (Structural Type): { name: str }
         if self.check(TokenKind::LeftBrace) {
             return self.parse_structural_type();
@@ -176,6 +231,7 @@ impl<'a> Parser<'a> {
             TokenKind::FloatType => Some("float"),
             TokenKind::BoolType => Some("bool"),
             TokenKind::StrType => Some("str"),
+            TokenKind::CharType => Some("char"),
             TokenKind::AnyType => Some("any"),
             TokenKind::Nil => Some("nil"), // Nil 也可以作为一种类型
             _ => None,
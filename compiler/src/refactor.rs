@@ -0,0 +1,521 @@
+// src/refactor.rs
+//
+// 基于 SemanticDB 的跨模块查询：Find References / Rename
+// CLI (`loom rename`) 和 LSP 共用
+//
+// def_map 记录的是 "引用 -> 定义"，这里把它反过来建索引 (定义 -> 所有引用)。
+// 类成员按覆写链合并：重命名父类的方法时，子类的覆写和通过子类调用的地方一起改
+// [New] trait 的方法和它的各个实现也算同一条链
+
+use crate::analyzer::{Location, TableId, TableInfo, Type};
+use crate::context::Context;
+use crate::driver::Driver;
+use crate::lexer::{is_ident_continue, is_ident_start};
+use crate::source::FileId;
+use crate::token::TokenKind;
+use crate::utils::{NodeId, Span, Symbol};
+use std::collections::{BTreeSet, HashSet};
+use std::path::PathBuf;
+
+/// 一处文本替换
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub file_id: FileId,
+    pub span: Span,
+    pub new_text: String,
+}
+
+/// 光标下的符号：名字 + 所有声明 (类成员包括覆写链上每个类自己的声明)
+struct Target {
+    name: Symbol,
+    defs: Vec<Location>,
+    /// 声明它的类 (类成员才有)，用于检查重名
+    owners: Vec<TableId>,
+}
+
+/// 光标下最内层的、满足 filter 的表达式节点
+pub fn node_at(
+    ctx: &Context,
+    file_id: FileId,
+    offset: usize,
+    filter: impl Fn(NodeId) -> bool,
+) -> Option<(NodeId, Span)> {
+    ctx.db
+        .node_spans
+        .iter()
+        // 光标在标识符末尾 (offset == end) 时也算命中
+        .filter(|(_, span)| span.start <= offset && offset <= span.end)
+        .filter(|(id, _)| filter(**id) && ctx.file_of_node(**id) == Some(file_id))
+        .min_by_key(|(id, span)| (span.len(), **id))
+        .map(|(id, span)| (*id, *span))
+}
+
+/// [New] 光标在声明的名字上 (let x / 参数 / 全局变量 / 字段) 时，返回绑定的类型和名字的区间
+/// 声明语句本身的类型是 ()，Hover 要显示的是名字绑定的类型
+pub fn binding_at(ctx: &Context, file_id: FileId, offset: usize) -> Option<(&Type, Span)> {
+    ctx.db
+        .bindings
+        .iter()
+        .filter(|(id, (_, span, _))| {
+            span.start <= offset && offset <= span.end && ctx.file_of_node(**id) == Some(file_id)
+        })
+        .filter_map(|(_, (name, span, ty))| {
+            let name_span = declaration_name_span(ctx, location(file_id, *span), *name)?;
+            (name_span.start <= offset && offset <= name_span.end).then_some((ty, name_span, *span))
+        })
+        .min_by_key(|(_, _, span)| span.len())
+        .map(|(ty, name_span, _)| (ty, name_span))
+}
+
+/// Find References：光标下符号的所有引用位置 (只含名字本身的区间)
+/// include_declaration 为 true 时把声明处也算进去
+pub fn find_references(
+    ctx: &Context,
+    file_id: FileId,
+    offset: usize,
+    include_declaration: bool,
+) -> Vec<Location> {
+    let Some(target) = target_at(ctx, file_id, offset) else {
+        return Vec::new();
+    };
+    collect_locations(ctx, &target, include_declaration)
+}
+
+/// Rename：把光标下的符号改名为 new_name，返回所有文件上的修改
+pub fn rename(
+    ctx: &Context,
+    file_id: FileId,
+    offset: usize,
+    new_name: &str,
+) -> Result<Vec<TextEdit>, String> {
+    if !is_valid_identifier(new_name) {
+        return Err(format!("'{}' is not a valid identifier", new_name));
+    }
+    let target = target_at(ctx, file_id, offset).ok_or("no renamable symbol at this position")?;
+    let old_name = ctx.resolve_symbol(target.name);
+    if old_name == "self" {
+        return Err("cannot rename 'self'".into());
+    }
+    // `use lib` 引入的模块名对应的是文件名，改名要改文件，这里不处理
+    if target.defs.iter().any(|loc| {
+        source_text(ctx, loc.file_id, loc.span)
+            .split_whitespace()
+            .next()
+            == Some("use")
+    }) {
+        return Err(format!("cannot rename module import '{}'", old_name));
+    }
+    if old_name == new_name {
+        return Ok(Vec::new());
+    }
+    check_conflicts(ctx, &target, new_name)?;
+
+    let edits: Vec<TextEdit> = collect_locations(ctx, &target, true)
+        .into_iter()
+        .map(|loc| TextEdit {
+            file_id: loc.file_id,
+            span: loc.span,
+            new_text: new_name.to_string(),
+        })
+        .collect();
+    // [New] 新名字可能遮蔽外层的同名符号，或者抢走别处对同名符号的引用 (局部作用域里也一样)
+    check_resolutions(ctx, &edits)?;
+    Ok(edits)
+}
+
+/// [New] 把 edits 应用到文件内容上 (从后往前替换，前面的偏移不受影响)
+pub fn apply_edits(src: &str, file_id: FileId, edits: &[TextEdit]) -> String {
+    let mut spans: Vec<&TextEdit> = edits.iter().filter(|e| e.file_id == file_id).collect();
+    spans.sort_by_key(|e| std::cmp::Reverse(e.span.start));
+    let mut src = src.to_string();
+    for edit in spans {
+        src.replace_range(edit.span.start..edit.span.end, &edit.new_text);
+    }
+    src
+}
+
+/// 标识符规则和 Lexer 一致，且不能是关键字
+fn is_valid_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(is_ident_start)
+        && chars.all(is_ident_continue)
+        && TokenKind::lookup_keyword(name).is_none()
+}
+
+fn source_text(ctx: &Context, file_id: FileId, span: Span) -> &str {
+    ctx.source_manager
+        .get_file(file_id)
+        .src
+        .get(span.start..span.end)
+        .unwrap_or("")
+}
+
+/// 找出光标下的符号：先看引用 (表达式 / 类型标注)，再看声明
+fn target_at(ctx: &Context, file_id: FileId, offset: usize) -> Option<Target> {
+    let db = &ctx.db;
+    let (name, def) = match node_at(ctx, file_id, offset, |id| db.ref_names.contains_key(&id)) {
+        Some((id, _)) => (*db.ref_names.get(&id)?, *db.def_map.get(&id)?),
+        None => declaration_at(ctx, file_id, offset)?,
+    };
+
+    match member_family(ctx, def, name) {
+        Some((defs, owners)) => Some(Target { name, defs, owners }),
+        None => Some(Target {
+            name,
+            defs: vec![def],
+            owners: Vec::new(),
+        }),
+    }
+}
+
+/// 光标落在某个声明的名字上 (没有被引用过的声明也能找到)
+fn declaration_at(ctx: &Context, file_id: FileId, offset: usize) -> Option<(Symbol, Location)> {
+    let db = &ctx.db;
+    // 候选：被引用过的定义 (包括局部变量和参数) + 所有模块级声明
+    let mut candidates: Vec<(Symbol, Location)> = Vec::new();
+    for (id, loc) in &db.def_map {
+        if loc.file_id == file_id
+            && let Some(name) = db.ref_names.get(id)
+        {
+            candidates.push((*name, *loc));
+        }
+    }
+    for module in ctx.modules.values().filter(|m| m.file_id == file_id) {
+        for (id, info) in &module.tables {
+            candidates.push((id.1, location(info.file_id, info.defined_span)));
+            for (name, field) in &info.fields {
+                candidates.push((*name, location(info.file_id, field.span)));
+            }
+            for (name, method) in &info.methods {
+                candidates.push((*name, location(info.file_id, method.span)));
+            }
+        }
+        for (id, info) in &module.enums {
+            candidates.push((id.1, location(info.file_id, info.defined_span)));
+        }
+        for (name, info) in &module.functions {
+            candidates.push((*name, location(info.file_id, info.span)));
+        }
+        for (name, info) in &module.globals {
+            candidates.push((*name, location(info.file_id, info.span)));
+        }
+    }
+
+    candidates
+        .into_iter()
+        .filter(|(_, loc)| loc.file_id == file_id)
+        .filter_map(|(name, loc)| {
+            let span = declaration_name_span(ctx, loc, name)?;
+            (span.start <= offset && offset <= span.end).then_some((span, name, loc))
+        })
+        .min_by_key(|(span, _, loc)| (span.start, loc.span.len()))
+        .map(|(_, name, loc)| (name, loc))
+}
+
+fn location(file_id: FileId, span: Span) -> Location {
+    Location { file_id, span }
+}
+
+/// 声明区间里名字本身的位置：第一个完整匹配的单词
+/// (`class Dog ...` / `fn speak() ...` / `name: str = ...` 名字都在关键字之后最先出现)
+fn declaration_name_span(ctx: &Context, loc: Location, name: Symbol) -> Option<Span> {
+    let text = source_text(ctx, loc.file_id, loc.span);
+    let name = ctx.resolve_symbol(name);
+    let bytes = text.as_bytes();
+    let is_word = |b: Option<&u8>| b.is_some_and(|b| is_ident_continue(*b as char));
+    let mut from = 0;
+    while let Some(pos) = text[from..].find(name) {
+        let start = from + pos;
+        let end = start + name.len();
+        if !is_word(start.checked_sub(1).and_then(|i| bytes.get(i))) && !is_word(bytes.get(end)) {
+            return Some(Span::new(loc.span.start + start, loc.span.start + end));
+        }
+        from = end;
+    }
+    None
+}
+
+/// 引用区间里名字本身的位置
+/// `obj.field` / `lib.Animal` 名字在末尾；`Box<int>` 这种泛型标注名字在开头
+fn usage_name_span(ctx: &Context, file_id: FileId, span: Span, name: Symbol) -> Option<Span> {
+    let text = source_text(ctx, file_id, span);
+    let name = ctx.resolve_symbol(name);
+    if text.ends_with(name) {
+        Some(Span::new(span.end - name.len(), span.end))
+    } else if text.starts_with(name) {
+        Some(Span::new(span.start, span.start + name.len()))
+    } else {
+        None
+    }
+}
+
+/// 所有引用 (以及声明) 的名字区间，按文件和位置排序、去重
+fn collect_locations(ctx: &Context, target: &Target, include_declaration: bool) -> Vec<Location> {
+    let db = &ctx.db;
+    let index = db.reference_index();
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+
+    for def in &target.defs {
+        if include_declaration
+            && let Some(span) = declaration_name_span(ctx, *def, target.name)
+            && seen.insert((def.file_id, span))
+        {
+            out.push(location(def.file_id, span));
+        }
+        for id in index.get(def).map(Vec::as_slice).unwrap_or(&[]) {
+            // 被重新解析替换掉的旧节点不算
+            let (Some(file_id), Some(span)) = (ctx.file_of_node(*id), db.node_spans.get(id)) else {
+                continue;
+            };
+            if let Some(span) = usage_name_span(ctx, file_id, *span, target.name)
+                && seen.insert((file_id, span))
+            {
+                out.push(location(file_id, span));
+            }
+        }
+    }
+
+    out.sort_by_key(|loc| (loc.file_id, loc.span));
+    out
+}
+
+fn parent_of(info: &TableInfo) -> Option<TableId> {
+    match &info.parent {
+        Some(Type::Table(id)) | Some(Type::GenericInstance { base: id, .. }) => Some(*id),
+        _ => None,
+    }
+}
+
+fn member_span(info: &TableInfo, name: Symbol) -> Option<Span> {
+    info.fields
+        .get(&name)
+        .map(|f| f.span)
+        .or_else(|| info.methods.get(&name).map(|m| m.span))
+}
+
+/// [New] 父类和实现的 trait
+fn supertypes_of(info: &TableInfo) -> impl Iterator<Item = TableId> + '_ {
+    info.parent
+        .iter()
+        .chain(&info.traits)
+        .filter_map(|ty| match ty {
+            Type::Table(id) | Type::GenericInstance { base: id, .. } => Some(*id),
+            _ => None,
+        })
+}
+
+/// 类成员的覆写链：声明它的祖先 (父类或 trait)，以及这些祖先的所有子孙类
+/// [Changed] 有了 trait 之后一个类可以有多个上级，所以反复向上、向下扩展直到不再变化：
+/// 改 trait 的方法会一起改所有实现，改某个实现也会改 trait 和其它实现
+/// def 不是类成员时返回 None
+fn member_family(
+    ctx: &Context,
+    def: Location,
+    name: Symbol,
+) -> Option<(Vec<Location>, Vec<TableId>)> {
+    let tables: Vec<(&TableId, &TableInfo)> =
+        ctx.modules.values().flat_map(|m| m.tables.iter()).collect();
+
+    let start = *tables
+        .iter()
+        .find(|(_, info)| info.file_id == def.file_id && member_span(info, name) == Some(def.span))?
+        .0;
+
+    let mut family = vec![start];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        // 向上：声明了同名成员的上级
+        let ups: Vec<TableId> = family
+            .iter()
+            .filter_map(|id| ctx.table_info(*id))
+            .flat_map(supertypes_of)
+            .filter(|sup| {
+                ctx.table_info(*sup)
+                    .and_then(|p| member_span(p, name))
+                    .is_some()
+            })
+            .collect();
+        // 向下：所有直接子类型
+        let downs = tables
+            .iter()
+            .filter(|(_, info)| supertypes_of(info).any(|sup| family.contains(&sup)))
+            .map(|(id, _)| **id);
+        for id in ups.into_iter().chain(downs).collect::<Vec<_>>() {
+            if !family.contains(&id) {
+                family.push(id);
+                changed = true;
+            }
+        }
+    }
+
+    let mut defs = Vec::new();
+    for id in &family {
+        let Some(info) = ctx.table_info(*id) else {
+            continue;
+        };
+        let Some(span) = member_span(info, name) else {
+            continue;
+        };
+        // 和父类的声明区间相同：继承来的副本，不是自己的声明
+        let inherited = parent_of(info)
+            .and_then(|pid| ctx.table_info(pid))
+            .and_then(|p| member_span(p, name))
+            == Some(span);
+        if !inherited && !defs.contains(&location(info.file_id, span)) {
+            defs.push(location(info.file_id, span));
+        }
+    }
+    // 祖先也不能已有同名成员 (改名后会变成覆写)
+    let mut owners = family.clone();
+    let mut pending = family;
+    while let Some(id) = pending.pop() {
+        for sup in ctx.table_info(id).into_iter().flat_map(supertypes_of) {
+            if !owners.contains(&sup) {
+                owners.push(sup);
+                pending.push(sup);
+            }
+        }
+    }
+    Some((defs, owners))
+}
+
+/// 改名后不能和已有的名字冲突
+/// - 类成员：覆写链涉及的类里不能已有同名字段 / 方法
+/// - 模块级声明 (类 / 枚举 / 函数 / 全局变量)：所在模块不能已有同名导出
+///
+/// 局部作用域里的遮蔽 / 捕获由 check_resolutions 检查
+fn check_conflicts(ctx: &Context, target: &Target, new_name: &str) -> Result<(), String> {
+    for id in &target.owners {
+        let Some(info) = ctx.table_info(*id) else {
+            continue;
+        };
+        let clash = info
+            .fields
+            .keys()
+            .chain(info.methods.keys())
+            .any(|sym| ctx.resolve_symbol(*sym) == new_name);
+        if clash {
+            return Err(format!(
+                "class '{}' already has a member named '{}'",
+                ctx.resolve_symbol(info.name),
+                new_name
+            ));
+        }
+    }
+
+    for def in &target.defs {
+        let Some(module) = ctx
+            .source_manager
+            .get_file_path(def.file_id)
+            .and_then(|path| ctx.modules.get(path))
+        else {
+            continue;
+        };
+        let is_export = module.tables.values().any(|t| t.defined_span == def.span)
+            || module.enums.values().any(|e| e.defined_span == def.span)
+            || module.functions.values().any(|f| f.span == def.span)
+            || module.globals.values().any(|g| g.span == def.span);
+        let clash = module
+            .tables
+            .keys()
+            .chain(module.enums.keys())
+            .map(|id| id.1)
+            .chain(module.functions.keys().copied())
+            .chain(module.globals.keys().copied())
+            .any(|sym| ctx.resolve_symbol(sym) == new_name);
+        if is_export && clash {
+            return Err(format!(
+                "module '{}' already defines '{}'",
+                ctx.source_manager
+                    .get_file_name(def.file_id)
+                    .unwrap_or("<unknown>"),
+                new_name
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 一个引用和它解析到的定义：((引用所在文件, 引用区间), (定义所在文件, 定义区间))
+type Resolution = ((PathBuf, Span), (PathBuf, Span));
+
+/// [New] 在临时的 Driver 里按改名后的源码把项目重新分析一遍，
+/// 每个引用解析到的定义都必须和改名前一样 (区间换算到改名后的坐标再比较)
+fn check_resolutions(ctx: &Context, edits: &[TextEdit]) -> Result<(), String> {
+    let mut paths: Vec<&PathBuf> = ctx.modules.keys().collect();
+    paths.sort();
+
+    let mut driver = Driver::new(ctx.root_dir.clone());
+    for path in &paths {
+        if let Some(file_id) = ctx.source_manager.find_file(path) {
+            let src = apply_edits(&ctx.source_manager.get_file(file_id).src, file_id, edits);
+            driver.ctx.source_manager.open_file(path.to_path_buf(), src);
+        }
+    }
+    let mut broken = None;
+    for path in &paths {
+        let Some(file_id) = driver.ctx.source_manager.find_file(path) else {
+            continue;
+        };
+        // 作为依赖已经分析过的模块不用重复分析
+        if driver.ctx.modules.get(*path).is_some_and(|m| !m.has_errors) {
+            continue;
+        }
+        if driver.analyze(file_id, path.to_path_buf()).is_err() && !ctx.modules[*path].has_errors {
+            broken.get_or_insert(*path);
+        }
+    }
+
+    let before = resolutions(ctx, |file_id, span| {
+        Span::new(
+            shifted(edits, file_id, span.start),
+            shifted(edits, file_id, span.end),
+        )
+    });
+    let after = resolutions(&driver.ctx, |_, span| span);
+    let changed = before
+        .symmetric_difference(&after)
+        .map(|((path, span), _)| (path, span.start))
+        .min();
+    if let Some((path, offset)) = changed
+        && let Some(file_id) = driver.ctx.source_manager.find_file(path)
+    {
+        let file = driver.ctx.source_manager.get_file(file_id);
+        let (line, col, _) = file.lookup_location(offset);
+        return Err(format!(
+            "the name at {}:{}:{} would refer to a different definition",
+            file.name, line, col
+        ));
+    }
+    if let Some(path) = broken {
+        return Err(format!("'{}' would no longer compile", path.display()));
+    }
+    Ok(())
+}
+
+/// 项目模块里所有记录下来的引用 (区间先经过 map 换算)
+fn resolutions(ctx: &Context, map: impl Fn(FileId, Span) -> Span) -> BTreeSet<Resolution> {
+    let path_of = |file_id| ctx.source_manager.get_file_path(file_id).cloned();
+    ctx.db
+        .def_map
+        .iter()
+        .filter_map(|(id, def)| {
+            let file_id = ctx.file_of_node(*id)?;
+            let path = path_of(file_id).filter(|p| ctx.modules.contains_key(p))?;
+            let span = map(file_id, *ctx.db.node_spans.get(id)?);
+            let def_path = path_of(def.file_id)?;
+            Some(((path, span), (def_path, map(def.file_id, def.span))))
+        })
+        .collect()
+}
+
+/// 改名前的偏移 -> 改名后的偏移
+/// 替换的都是完整的名字，节点区间的端点不会落在被替换的名字中间
+fn shifted(edits: &[TextEdit], file_id: FileId, offset: usize) -> usize {
+    edits
+        .iter()
+        .filter(|e| e.file_id == file_id && e.span.end <= offset)
+        .fold(offset, |acc, e| acc + e.new_text.len() - e.span.len())
+}
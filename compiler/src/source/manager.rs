@@ -37,6 +37,18 @@ impl SourceManager {
         Ok(id)
     }
 
+    /// [New] 用给定内容注册一个文件 (编辑器里尚未保存的缓冲区)
+    /// path 必须是绝对路径；之后对同一路径的 load_file 直接命中，不再读磁盘
+    pub fn open_file(&mut self, abs_path: PathBuf, src: String) -> FileId {
+        if let Some(id) = self.files.iter().position(|f| f.path == abs_path) {
+            self.files[id] = SourceFile::new(abs_path, src);
+            return FileId::new(id);
+        }
+        let id = FileId::new(self.files.len());
+        self.files.push(SourceFile::new(abs_path, src));
+        id
+    }
+
     pub fn add_file(&mut self, name: String, src: String) -> io::Result<FileId> {
         // [修改] 直接创建
         let file = SourceFile::new(PathBuf::from(name), src);
@@ -59,6 +71,14 @@ impl SourceManager {
         Some(file.lookup_location(offset))
     }
 
+    /// [New] 按绝对路径查找已加载的文件
+    pub fn find_file(&self, abs_path: &Path) -> Option<FileId> {
+        self.files
+            .iter()
+            .position(|f| f.path == abs_path)
+            .map(FileId::new)
+    }
+
     /// 根据 ID 获取文件的完整路径
     pub fn get_file_path(&self, id: FileId) -> Option<&PathBuf> {
         self.files.get(id.get()).map(|f| &f.path)
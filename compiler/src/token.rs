@@ -78,6 +78,7 @@ define_tokens! {
         "float"   => FloatType,  // 对应 f64
         "bool"    => BoolType,
         "str"     => StrType,
+        "char"    => CharType,   // [New] Unicode 标量值
         "any"     => AnyType,    // 类似于 TypeScript 的结构化通配符
 
         // --- 字面量关键字 ---
@@ -92,7 +93,12 @@ define_tokens! {
         "self"    => SmallSelf,  // 实例访问：self.host
         "Self"    => BigSelf,    // 约束/类型引用：[T: Self]
         "class"   => Class,      // 替代原有的 [ClassName]
+        "enum"    => Enum,       // [New] 代数数据类型
+        "trait"   => Trait,      // [New] 只有方法签名的接口
         "fn"      => Fn,
+        "let"     => Let,        // [New] 不可变绑定
+        "var"     => Var,        // [New] 可变绑定
+        "const"   => Const,      // [New] 编译期常量
 
         // --- 控制流 ---
         "if"       => If,
@@ -100,6 +106,7 @@ define_tokens! {
         "for"      => For,
         "in"       => In,
         "while"    => While,
+        "match"    => Match,     // [New] 模式匹配
         "break"    => Break,
         "continue" => Continue,
         "return"   => Return,
@@ -148,6 +155,7 @@ define_tokens! {
         ","   => Comma,           // 分隔符
         ":"   => Colon,           // 类型约束 var: int 或 [Prod: Base]
         ".."  => DotDot,          // 范围 0..limit
+        "..=" => DotDotEq,        // 闭区间 1..=limit
         "=>"  => FatArrow,        // 单行函数 add = (a, b) => a + b
     }
 }
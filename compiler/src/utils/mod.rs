@@ -1,7 +1,11 @@
+mod json;
 mod node;
 mod span;
+mod suggest;
 mod symbol;
 
+pub use json::Json;
 pub use node::{Node, NodeId};
 pub use span::Span;
+pub use suggest::{best_match, edit_distance};
 pub use symbol::{Interner, Symbol};
@@ -0,0 +1,53 @@
+// src/utils/suggest.rs
+//
+// "did you mean" 用的近似匹配：分析器 (未定义的名字 / 成员) 和解释器 (运行时找不到属性) 共用
+
+/// 两个字符串的编辑距离 (按字符计算)
+/// 相邻两个字符交换算一次编辑 (coutn -> count)，这是最常见的手误
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // d[i][j] = a 的前 i 个字符和 b 的前 j 个字符之间的距离
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (d[i - 1][j - 1] + cost)
+                .min(d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(d[i - 2][j - 2] + 1);
+            }
+            d[i][j] = best;
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// 从候选里找出和 name 最接近的一个
+/// 距离超过名字长度的三分之一 (至少允许 1) 的不算；只差大小写的优先；
+/// 距离相同时取字典序最小的，保证结果稳定
+pub fn best_match<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .filter_map(|candidate| {
+            if candidate.eq_ignore_ascii_case(name) {
+                return Some((0, candidate));
+            }
+            let distance = edit_distance(name, candidate);
+            (distance <= max_distance).then_some((distance, candidate))
+        })
+        .min()
+        .map(|(_, candidate)| candidate)
+}
@@ -0,0 +1,106 @@
+// tests/golden.rs
+//
+// [New] 黄金输出测试：每个 tests/golden/*.lm 分别用树遍历解释器和 VM 运行，
+// 两个后端的输出必须一致，并且和旁边的 .out 文件一致。
+// example/ 下的示例没有 .out 文件，只要求正常退出且两个后端输出一致。
+//
+// 更新期望输出: LOOM_BLESS=1 cargo test --test golden
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// 运行一次 loom，返回 stdout + stderr (+ 非零退出码)
+/// 当前目录就是项目根目录，诊断里的绝对路径换成相对路径，保证输出和机器无关
+fn run(dir: &Path, file: &Path, vm: bool) -> (String, bool) {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_loom"));
+    if vm {
+        cmd.arg("--vm");
+    }
+    let output = cmd
+        .arg(file)
+        .current_dir(dir)
+        .output()
+        .expect("failed to run loom");
+
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.is_empty() {
+        text.push_str("--- stderr ---\n");
+        text.push_str(&stderr);
+    }
+    if let Some(code) = output.status.code().filter(|&c| c != 0) {
+        text.push_str(&format!("--- exit {} ---\n", code));
+    }
+    let prefix = format!("{}/", dir.display());
+    (text.replace(&prefix, ""), output.status.success())
+}
+
+/// 目录下的 .lm 文件 (不进子目录：子目录里是被 import 的模块)，按名字排序
+fn scripts(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lm"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .canonicalize()
+        .unwrap()
+}
+
+#[test]
+fn golden_outputs_match_on_both_backends() {
+    let dir = root().join("tests/golden");
+    let bless = std::env::var_os("LOOM_BLESS").is_some();
+    let mut failures = Vec::new();
+
+    for script in scripts(&dir) {
+        let name = script.file_name().unwrap();
+        let (tree_walk, _) = run(&dir, Path::new(name), false);
+        let (vm, _) = run(&dir, Path::new(name), true);
+        let expected_path = script.with_extension("out");
+
+        if tree_walk != vm {
+            failures.push(format!(
+                "{}: backends disagree\n--- tree-walker ---\n{}--- vm ---\n{}",
+                script.display(),
+                tree_walk,
+                vm
+            ));
+            continue;
+        }
+        if bless {
+            fs::write(&expected_path, &tree_walk).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+        if tree_walk != expected {
+            failures.push(format!(
+                "{}: output differs from {}\n--- expected ---\n{}--- actual ---\n{}",
+                script.display(),
+                expected_path.display(),
+                expected,
+                tree_walk
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn examples_run_the_same_on_both_backends() {
+    let dir = root().join("../example");
+    for script in scripts(&dir) {
+        let name = script.file_name().unwrap();
+        let (tree_walk, ok) = run(&dir, Path::new(name), false);
+        let (vm, _) = run(&dir, Path::new(name), true);
+        assert!(ok, "{} failed:\n{}", script.display(), tree_walk);
+        assert_eq!(tree_walk, vm, "{}: backends disagree", script.display());
+    }
+}
@@ -1 +1,30 @@
-console.log("Hello via Bun!");
\ No newline at end of file
+import { workspace, type ExtensionContext } from "vscode";
+import {
+  LanguageClient,
+  type LanguageClientOptions,
+  type ServerOptions,
+} from "vscode-languageclient/node";
+
+let client: LanguageClient | undefined;
+
+// Starts `loom lsp` for .lm files: diagnostics, hover, go-to-definition,
+// find references, rename and completion.
+export function activate(context: ExtensionContext) {
+  const command = workspace.getConfiguration("loom").get<string>("serverPath") || "loom";
+
+  const serverOptions: ServerOptions = {
+    run: { command, args: ["lsp"] },
+    debug: { command, args: ["lsp"] },
+  };
+  const clientOptions: LanguageClientOptions = {
+    documentSelector: [{ scheme: "file", language: "loom" }],
+  };
+
+  client = new LanguageClient("loom", "Loom Language Server", serverOptions, clientOptions);
+  client.start();
+  context.subscriptions.push(client);
+}
+
+export function deactivate(): Thenable<void> | undefined {
+  return client?.stop();
+}